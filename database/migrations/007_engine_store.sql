@@ -0,0 +1,44 @@
+-- ALICE Risk: core engine persistence (RISK_DATABASE_URL); the engine also creates these on startup
+CREATE TABLE IF NOT EXISTS engine_checks (
+    check_id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    account TEXT NOT NULL,
+    approved BOOLEAN NOT NULL,
+    reasons TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    inputs TEXT NOT NULL,
+    limits_evaluated TEXT NOT NULL,
+    elapsed_us BIGINT NOT NULL,
+    at_ms BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS engine_margins (
+    id TEXT PRIMARY KEY,
+    account TEXT NOT NULL,
+    source TEXT NOT NULL,
+    initial_margin DOUBLE PRECISION NOT NULL,
+    maintenance_margin DOUBLE PRECISION NOT NULL,
+    at_ms BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS engine_alerts (
+    id BIGINT PRIMARY KEY,
+    at_ms BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    account TEXT,
+    instrument TEXT,
+    message TEXT NOT NULL,
+    tags TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS engine_counters (
+    id INTEGER PRIMARY KEY,
+    total_checks BIGINT NOT NULL,
+    total_margin_calcs BIGINT NOT NULL,
+    total_alerts BIGINT NOT NULL,
+    trades_blocked BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_engine_checks_account ON engine_checks(account, at_ms);
+CREATE INDEX IF NOT EXISTS idx_engine_checks_at ON engine_checks(at_ms);
@@ -0,0 +1,6 @@
+-- ALICE Risk: core engine warm-start snapshot, one row per section; the engine also creates this on startup
+CREATE TABLE IF NOT EXISTS engine_snapshots (
+    section TEXT PRIMARY KEY,
+    body TEXT NOT NULL,
+    at_ms BIGINT NOT NULL
+);
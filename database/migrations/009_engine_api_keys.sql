@@ -0,0 +1,7 @@
+-- ALICE Risk: core engine API keys, stored as the hex SHA-256 of the secret; scope is read_only, trading or risk_admin
+CREATE TABLE IF NOT EXISTS engine_api_keys (
+    id TEXT PRIMARY KEY,
+    key_sha256 TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    requests_per_minute BIGINT
+);
@@ -150,10 +150,15 @@ async fn forward(url: &str, req: Request) -> Result<Response, (StatusCode, Json<
     let q = req.uri().query().map(|q| format!("?{q}")).unwrap_or_default();
     let method = req.method().clone();
     let hdrs = req.headers().clone();
+    let claims = req.extensions().get::<Claims>().cloned();
     let body = axum::body::to_bytes(req.into_body(), 5 * 1024 * 1024).await
         .map_err(|e| (StatusCode::BAD_REQUEST, Json(Err { error: "Body read fail".into(), details: Some(e.to_string()) })))?;
     let mut r = client.request(method, format!("{url}{path}{q}"));
-    for (k, v) in hdrs.iter() { if k != "host" { r = r.header(k, v); } }
+    for (k, v) in hdrs.iter() { if k != "host" && k != "x-user-id" && k != "x-user-role" { r = r.header(k, v); } }
+    if let Some(c) = claims {
+        r = r.header("X-User-Id", c.sub);
+        if let Some(role) = c.role { r = r.header("X-User-Role", role); }
+    }
     let resp = r.body(body).send().await
         .map_err(|e| (StatusCode::BAD_GATEWAY, Json(Err { error: "Upstream unavailable".into(), details: Some(e.to_string()) })))?;
     let st = StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
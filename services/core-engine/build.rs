@@ -0,0 +1,7 @@
+// Compiles the gRPC API with protox, so building needs no protoc installed.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto");
+    let fds = protox::compile(["proto/risk.proto"], ["proto"])?;
+    tonic_build::configure().build_client(false).compile_fds(fds)?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::greeks::GreekLimits;
+use crate::margin::MarginModel;
+use crate::velocity::VelocityLimits;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus { Active, ReduceOnly, Suspended, Closed }
+
+/// The `max_basket_*` limits apply to a basket as a whole: its gross notional and the absolute change it makes to
+/// any one sector's net exposure or to beta-weighted exposure. `greeks` caps net option Greeks per underlier and
+/// across the account. `max_beta_exposure` caps the account's absolute beta-adjusted net exposure.
+/// `max_position_notional` caps the absolute net notional held in any one instrument once an order fills.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct DefaultLimits {
+    pub max_order_notional: Option<f64>, pub max_order_quantity: Option<f64>, pub daily_notional: Option<f64>, pub velocity: Option<VelocityLimits>,
+    pub max_basket_notional: Option<f64>, pub max_basket_sector_change: Option<f64>, pub max_basket_beta_change: Option<f64>,
+    pub greeks: Option<GreekLimits>, pub max_beta_exposure: Option<f64>, pub max_position_notional: Option<f64>,
+}
+
+/// `entity` groups the accounts of one legal entity for cross-account analysis; `desk` the accounts run by one
+/// trading desk for desk-level limits. `version` goes up with every update.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Account {
+    pub id: String, pub entity: Option<String>, pub desk: Option<String>, pub base_currency: String, pub margin_model: MarginModel, pub default_limits: DefaultLimits, pub status: AccountStatus,
+    #[serde(default)] pub version: u64, pub created_at_ms: u64, pub updated_at_ms: u64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateAccount { pub id: String, pub entity: Option<String>, pub desk: Option<String>, pub base_currency: Option<String>, pub margin_model: Option<MarginModel>, #[serde(default)] pub default_limits: DefaultLimits, pub status: Option<AccountStatus> }
+
+#[derive(Deserialize)]
+pub struct UpdateAccount { pub entity: Option<String>, pub desk: Option<String>, pub base_currency: Option<String>, pub margin_model: Option<MarginModel>, pub default_limits: Option<DefaultLimits>, pub status: Option<AccountStatus>, pub reason: Option<String> }
+
+pub enum AccountError { NotFound, Exists, Invalid(String) }
+
+fn validate(currency: Option<&str>) -> Result<(), AccountError> {
+    if currency.is_some_and(|c| c.len() != 3 || !c.chars().all(|c| c.is_ascii_alphabetic())) { return Err(AccountError::Invalid("base_currency must be an ISO 4217 code".into())); }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct AccountBook { accounts: HashMap<String, Account> }
+
+impl AccountBook {
+    pub fn get(&self, id: &str) -> Option<&Account> { self.accounts.get(id) }
+    pub fn by_entity(&self, entity: &str) -> Vec<Account> { self.list().into_iter().filter(|a| a.entity.as_deref() == Some(entity)).collect() }
+    pub fn by_desk(&self, desk: &str) -> Vec<Account> { self.list().into_iter().filter(|a| a.desk.as_deref() == Some(desk)).collect() }
+    pub fn list(&self) -> Vec<Account> { let mut v: Vec<_> = self.accounts.values().cloned().collect(); v.sort_by(|a, b| a.id.cmp(&b.id)); v }
+
+    /// Adds an account exactly as it stands elsewhere, keeping its id and timestamps.
+    pub fn insert(&mut self, a: Account) { self.accounts.insert(a.id.clone(), a); }
+
+    pub fn create(&mut self, req: CreateAccount, now_ms: u64) -> Result<Account, AccountError> {
+        if req.id.trim().is_empty() { return Err(AccountError::Invalid("id must not be empty".into())); }
+        if self.accounts.contains_key(&req.id) { return Err(AccountError::Exists); }
+        validate(req.base_currency.as_deref())?;
+        let a = Account {
+            id: req.id, entity: req.entity.filter(|e| !e.is_empty()), desk: req.desk.filter(|d| !d.is_empty()), base_currency: req.base_currency.unwrap_or_else(|| "USD".into()).to_ascii_uppercase(), margin_model: req.margin_model.unwrap_or_default(),
+            default_limits: req.default_limits, status: req.status.unwrap_or(AccountStatus::Active), version: 1, created_at_ms: now_ms, updated_at_ms: now_ms,
+        };
+        self.accounts.insert(a.id.clone(), a.clone());
+        Ok(a)
+    }
+
+    pub fn update(&mut self, id: &str, req: UpdateAccount, now_ms: u64) -> Result<Account, AccountError> {
+        validate(req.base_currency.as_deref())?;
+        let a = self.accounts.get_mut(id).ok_or(AccountError::NotFound)?;
+        if a.status == AccountStatus::Closed && req.status.is_some_and(|st| st != AccountStatus::Closed) { return Err(AccountError::Invalid("closed accounts cannot be reopened".into())); }
+        if let Some(e) = req.entity { a.entity = Some(e).filter(|e| !e.is_empty()); }
+        if let Some(d) = req.desk { a.desk = Some(d).filter(|d| !d.is_empty()); }
+        if let Some(c) = req.base_currency { a.base_currency = c.to_ascii_uppercase(); }
+        if let Some(m) = req.margin_model { a.margin_model = m; }
+        if let Some(l) = req.default_limits { a.default_limits = l; }
+        if let Some(st) = req.status { a.status = st; }
+        a.version += 1;
+        a.updated_at_ms = now_ms;
+        Ok(a.clone())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::locale::Locale;
+use crate::tags::Tags;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity { Info, Warning, Critical }
+
+/// `tags` are those of the order that raised the alert, if any.
+#[derive(Serialize, Clone)]
+pub struct Alert {
+    pub id: u64, pub at_ms: u64, pub kind: String, pub severity: Severity, pub account: Option<String>, pub instrument: Option<String>, pub message: String,
+    #[serde(skip_serializing_if = "Tags::is_empty")] pub tags: Tags,
+}
+
+/// What each kind of alert is called, in `Locale` order.
+const KINDS: [(&str, [&str; 3]); 11] = [
+    ("circuit_breaker", ["Circuit breaker halt", "サーキットブレーカー発動", "熔断暂停"]),
+    ("collateral_breach", ["Collateral breach", "担保不足", "抵押品不足"]),
+    ("kill_switch", ["Kill switch", "キルスイッチ", "紧急停止开关"]),
+    ("limit_breach", ["Limit breach", "限度超過", "突破限额"]),
+    ("liquidation", ["Liquidation", "強制決済", "强制平仓"]),
+    ("margin_call", ["Margin call", "追加証拠金", "追加保证金"]),
+    ("margin_cycle", ["Margin cycle", "証拠金サイクル", "保证金周期"]),
+    ("override_issued", ["Override issued", "オーバーライド発行", "已签发越权许可"]),
+    ("override_used", ["Override used", "オーバーライド使用", "已使用越权许可"]),
+    ("stress_breach", ["Stress breach", "ストレス限度超過", "突破压力限额"]),
+    ("trade_blocked", ["Trade blocked", "取引拒否", "交易被拒绝"]),
+];
+
+/// An alert as a client sees it: `text` names its kind in the client's language, while `message` keeps the
+/// figures as the engine wrote them.
+#[derive(Serialize, Clone)]
+pub struct Localized { #[serde(flatten)] pub alert: Alert, pub text: String, pub locale: Locale }
+
+impl Alert {
+    pub fn localized(self, locale: Locale) -> Localized {
+        let text = KINDS.iter().find(|k| k.0 == self.kind).map_or_else(|| self.kind.clone(), |k| k.1[locale as usize].into());
+        Localized { alert: self, text, locale }
+    }
+}
+
+/// `strategy`, `algo` and `trader` narrow to alerts raised by orders carrying those tags.
+#[derive(Deserialize)]
+pub struct AlertQuery { pub account: Option<String>, pub kind: Option<String>, pub limit: Option<usize>, pub strategy: Option<String>, pub algo: Option<String>, pub trader: Option<String> }
+
+/// What one streaming connection is sent: alerts on `account` (every account when unset) at `min_severity` or above.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Subscription { pub account: Option<String>, pub min_severity: Option<Severity> }
+
+impl Subscription {
+    pub fn matches(&self, a: &Alert) -> bool { self.account.as_ref().is_none_or(|x| a.account.as_ref() == Some(x)) && self.min_severity.is_none_or(|m| a.severity >= m) }
+}
+
+/// Messages on the alert stream. `subscribed` confirms the filter in force; `lagged` counts alerts dropped because
+/// the connection fell too far behind.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent { Alert(Localized), Subscribed(Subscription), Lagged { missed: u64 }, Error { message: String } }
+
+#[derive(Default)]
+pub struct AlertLog { alerts: Vec<Alert> }
+
+impl AlertLog {
+    /// Alerts must be oldest first, as the store returns them, so new ids carry on from the last.
+    pub fn restore(alerts: Vec<Alert>) -> Self { Self { alerts } }
+
+    /// The new alert is returned for the caller to tag.
+    pub fn push(&mut self, kind: &str, severity: Severity, account: Option<&str>, instrument: Option<&str>, message: String, now_ms: u64) -> &mut Alert {
+        let a = Alert { id: self.alerts.len() as u64 + 1, at_ms: now_ms, kind: kind.into(), severity, account: account.map(Into::into), instrument: instrument.map(Into::into), message, tags: Tags::default() };
+        self.alerts.push(a);
+        self.alerts.last_mut().unwrap()
+    }
+
+    /// Newest first.
+    pub fn query(&self, q: &AlertQuery) -> Vec<Alert> {
+        let tags = Tags { strategy: q.strategy.clone(), algo: q.algo.clone(), trader: q.trader.clone() };
+        self.alerts.iter().rev()
+            .filter(|a| q.account.as_ref().is_none_or(|x| a.account.as_ref() == Some(x)) && q.kind.as_ref().is_none_or(|k| &a.kind == k) && a.tags.within(&tags))
+            .take(q.limit.unwrap_or(100)).cloned().collect()
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+/// Length of a regular trading session; ADV is spread over it to project the market volume during a slice.
+const SESSION_SECS: f64 = 23_400.0;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy { Twap, Vwap }
+
+/// Parameters of an algorithmic parent order. VWAP slices follow `volume_profile` (relative weights, one per slice,
+/// resampled if the count differs); without a profile a VWAP is sliced like a TWAP.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AlgoParams {
+    pub strategy: Strategy, pub duration_secs: u64, pub slice_interval_secs: Option<u64>, pub participation_rate: Option<f64>,
+    #[serde(default)] pub volume_profile: Vec<f64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct AlgoLimits { pub max_participation: f64, pub max_duration_secs: u64, pub max_slice_quantity: Option<f64>, pub default_slice_interval_secs: u64 }
+
+/// Slicing projected at parent acceptance. `projected_participation` is the largest slice quantity over the market
+/// volume expected during that slice; it is absent without ADV for the instrument.
+#[derive(Serialize)]
+pub struct AlgoProfile {
+    pub strategy: Strategy, pub slices: usize, pub slice_interval_secs: u64, pub max_slice_quantity: f64, pub min_slice_quantity: f64,
+    pub projected_participation: Option<f64>, pub reasons: Vec<String>,
+}
+
+pub fn validate(p: &AlgoParams) -> Result<(), String> {
+    if p.duration_secs == 0 { return Err("duration_secs must be positive".into()); }
+    if p.slice_interval_secs == Some(0) { return Err("slice_interval_secs must be positive".into()); }
+    if p.participation_rate.is_some_and(|r| !(r > 0.0 && r <= 1.0)) { return Err("participation_rate must be in (0, 1]".into()); }
+    if p.volume_profile.iter().any(|w| *w < 0.0) || (!p.volume_profile.is_empty() && p.volume_profile.iter().sum::<f64>() <= 0.0) { return Err("volume_profile weights must be non-negative and not all zero".into()); }
+    Ok(())
+}
+
+impl AlgoLimits {
+    /// Slices the parent and checks the requested cap, the duration and every projected slice against the limits.
+    pub fn evaluate(&self, p: &AlgoParams, quantity: f64, adv: Option<f64>) -> AlgoProfile {
+        let interval = p.slice_interval_secs.unwrap_or(self.default_slice_interval_secs).min(p.duration_secs);
+        let n = p.duration_secs.div_ceil(interval) as usize;
+        let weights: Vec<f64> = match p.strategy {
+            Strategy::Vwap if !p.volume_profile.is_empty() => (0..n).map(|i| p.volume_profile[i * p.volume_profile.len() / n]).collect(),
+            _ => vec![1.0; n],
+        };
+        let total: f64 = weights.iter().sum();
+        let slices: Vec<f64> = weights.iter().map(|w| quantity * w / total).collect();
+        let max_slice = slices.iter().cloned().fold(0.0, f64::max);
+        // A TWAP trades evenly against flat volume; a VWAP is assumed to meet volume shaped like its own profile.
+        let projected_participation = adv.filter(|a| *a > 0.0).map(|adv| {
+            let session_volume = adv * p.duration_secs as f64 / SESSION_SECS;
+            match p.strategy {
+                Strategy::Twap => max_slice / (session_volume / n as f64),
+                Strategy::Vwap => quantity / session_volume,
+            }
+        });
+        let mut reasons = Vec::new();
+        if let Some(r) = p.participation_rate.filter(|r| *r > self.max_participation) { reasons.push(format!("Algo max participation {:.2} exceeded: requested {r:.2}", self.max_participation)); }
+        if let Some(x) = projected_participation.filter(|x| *x > self.max_participation) { reasons.push(format!("Algo max participation {:.2} exceeded: projected {x:.2}", self.max_participation)); }
+        if p.duration_secs > self.max_duration_secs { reasons.push(format!("Algo max duration {}s exceeded", self.max_duration_secs)); }
+        if let Some(q) = self.max_slice_quantity.filter(|q| max_slice > *q) { reasons.push(format!("Algo max slice quantity {q} exceeded: projected {max_slice:.2}")); }
+        AlgoProfile {
+            strategy: p.strategy, slices: n, slice_interval_secs: interval, max_slice_quantity: max_slice, min_slice_quantity: slices.iter().cloned().fold(f64::INFINITY, f64::min),
+            projected_participation, reasons,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Clone)]
+pub struct AuditEntry { pub id: u64, pub at_ms: u64, pub actor: String, pub action: String, pub target: String, pub details: serde_json::Value }
+
+#[derive(Deserialize)]
+pub struct AuditQuery { pub target: Option<String>, pub action: Option<String>, pub limit: Option<usize> }
+
+#[derive(Default)]
+pub struct AuditLog { entries: Vec<AuditEntry> }
+
+impl AuditLog {
+    pub fn record(&mut self, actor: &str, action: &str, target: &str, details: serde_json::Value, now_ms: u64) {
+        let id = self.entries.len() as u64 + 1;
+        tracing::info!(actor, action, target, "audit");
+        self.entries.push(AuditEntry { id, at_ms: now_ms, actor: actor.into(), action: action.into(), target: target.into(), details });
+    }
+
+    /// Newest first.
+    pub fn query(&self, q: &AuditQuery) -> Vec<AuditEntry> {
+        self.entries.iter().rev()
+            .filter(|e| q.target.as_ref().is_none_or(|t| &e.target == t) && q.action.as_ref().is_none_or(|a| &e.action == a))
+            .take(q.limit.unwrap_or(100)).cloned().collect()
+    }
+}
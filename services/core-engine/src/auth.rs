@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+
+const MINUTE_MS: u64 = 60_000;
+
+/// What a key may do: read-only keys make safe requests only; trading keys may also write but take no role;
+/// risk-admin keys may act as any engine role.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyScope { ReadOnly, Trading, RiskAdmin }
+
+/// Keys are held as the hex SHA-256 of the secret, so neither config nor database carries the secret itself.
+/// Without `requests_per_minute` the engine-wide default applies.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ApiKey { pub id: String, pub key_sha256: String, pub scope: KeyScope, pub requests_per_minute: Option<u32> }
+
+impl ApiKey {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty() || !self.id.bytes().all(|b| b.is_ascii_graphic()) { return Err(format!("api key id {:?} must be printable with no spaces", self.id)); }
+        if !(self.key_sha256.len() == 64 && self.key_sha256.bytes().all(|b| b.is_ascii_hexdigit())) { return Err(format!("api key {}: key_sha256 must be 64 hex digits", self.id)); }
+        if self.requests_per_minute == Some(0) { return Err(format!("api key {}: requests_per_minute must be positive", self.id)); }
+        Ok(())
+    }
+}
+
+/// Who a request came from once its key checks out, and the role it acts as.
+pub struct Caller { pub key_id: String, pub role: Option<String> }
+
+pub enum Denied { Missing, Unknown, ReadOnly, Role(String), RateLimited { retry_after_secs: u64 } }
+
+/// Keys from config and from the database by digest, and each key's requests in the last minute. With no keys at
+/// all authentication is off and every request passes as before.
+pub struct Auth { config: Vec<ApiKey>, stored: Vec<ApiKey>, by_digest: HashMap<String, ApiKey>, default_rpm: u32, recent: HashMap<String, VecDeque<u64>> }
+
+pub fn digest(secret: &str) -> String { Sha256::digest(secret.as_bytes()).iter().map(|b| format!("{b:02x}")).collect() }
+
+impl Auth {
+    pub fn new(config: Vec<ApiKey>, default_rpm: u32) -> Self {
+        let mut a = Self { config, stored: Vec::new(), by_digest: HashMap::new(), default_rpm: default_rpm.max(1), recent: HashMap::new() };
+        a.index();
+        a
+    }
+
+    pub fn enabled(&self) -> bool { !self.by_digest.is_empty() }
+    pub fn key_count(&self) -> usize { self.by_digest.len() }
+
+    /// Replaces the keys read from the database; config keys stay. A key id in both keeps its config entry.
+    pub fn set_stored(&mut self, keys: Vec<ApiKey>) { self.stored = keys; self.index(); }
+
+    fn index(&mut self) {
+        self.by_digest = self.stored.iter().filter(|k| !self.config.iter().any(|c| c.id == k.id)).chain(&self.config).map(|k| (k.key_sha256.to_ascii_lowercase(), k.clone())).collect();
+        let live: Vec<&String> = self.by_digest.values().map(|k| &k.id).collect();
+        self.recent.retain(|id, _| live.contains(&id));
+    }
+
+    /// Checks `secret` may make a request with `safe` method as `role`, and counts it against the key's rate limit.
+    /// Risk-admin keys act as `admin` unless they ask for another role.
+    pub fn admit(&mut self, secret: Option<&str>, safe: bool, role: Option<&str>, now_ms: u64) -> Result<Caller, Denied> {
+        let k = self.by_digest.get(&digest(secret.ok_or(Denied::Missing)?)).ok_or(Denied::Unknown)?;
+        if k.scope == KeyScope::ReadOnly && !safe { return Err(Denied::ReadOnly); }
+        let role = match (k.scope, role) {
+            (KeyScope::RiskAdmin, r) => Some(r.unwrap_or("admin").to_string()),
+            (_, Some(r)) => return Err(Denied::Role(r.into())),
+            (_, None) => None,
+        };
+        let max = k.requests_per_minute.unwrap_or(self.default_rpm) as usize;
+        let q = self.recent.entry(k.id.clone()).or_default();
+        while q.front().is_some_and(|t| t + MINUTE_MS <= now_ms) { q.pop_front(); }
+        if q.len() >= max { return Err(Denied::RateLimited { retry_after_secs: q.front().map_or(1, |t| (t + MINUTE_MS - now_ms).div_ceil(1000).max(1)) }); }
+        q.push_back(now_ms);
+        Ok(Caller { key_id: k.id.clone(), role })
+    }
+}
@@ -0,0 +1,148 @@
+use crate::positions::Side;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Rows one batch may carry; a year of daily closes for a few hundred instruments fits in a handful of batches.
+pub const MAX_BATCH_ROWS: usize = 50_000;
+/// Row errors kept per job; later ones are counted but not listed.
+const MAX_ERRORS: usize = 100;
+
+/// An execution from before the engine was running, kept for backtests. It does not move current positions.
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct HistoricalTrade { pub trade_id: String, pub account: String, pub instrument: String, pub side: String, pub quantity: f64, pub price: f64, pub executed_at: DateTime<Utc> }
+
+/// One batch of a backfill job. Batches of a job may arrive in any order and any batch may be sent again: it is
+/// applied again and replaces its earlier counts, so a re-run converges on the same history. Without `job_id`
+/// the batch is a job of its own; without `batches` a job is complete after its first batch.
+#[derive(Deserialize)]
+pub struct Batch<T> { pub job_id: Option<String>, #[serde(default)] pub batch: u32, pub batches: Option<u32>, pub rows: Vec<T> }
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind { Prices, Trades }
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Status { InProgress, Complete }
+
+impl Kind {
+    pub fn name(self) -> &'static str { match self { Kind::Prices => "prices", Kind::Trades => "trades" } }
+}
+
+impl Status {
+    pub fn name(self) -> &'static str { match self { Status::InProgress => "in_progress", Status::Complete => "complete" } }
+}
+
+/// `loaded` rows were new or changed what was held; `unchanged` rows matched it exactly, as on a re-run.
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct Counts { pub rows: usize, pub loaded: usize, pub unchanged: usize, pub rejected: usize }
+
+#[derive(Serialize, Clone)]
+pub struct RowError { pub batch: u32, pub row: usize, pub error: String }
+
+/// `from` and `to` span the dates of the rows loaded so far.
+#[derive(Serialize, Clone)]
+pub struct Job {
+    pub job_id: String, pub kind: Kind, pub status: Status, pub batches_received: usize, pub batches_expected: u32, pub progress_pct: f64, #[serde(flatten)] pub counts: Counts,
+    pub from: Option<NaiveDate>, pub to: Option<NaiveDate>, pub errors: Vec<RowError>, pub started_at_ms: u64, pub updated_at_ms: u64, pub completed_at_ms: Option<u64>,
+    #[serde(skip)] by_batch: BTreeMap<u32, BatchRun>,
+}
+
+/// The last run of one batch.
+#[derive(Clone)]
+struct BatchRun { counts: Counts, errors: Vec<RowError>, span: Option<(NaiveDate, NaiveDate)> }
+
+#[derive(Deserialize)]
+pub struct JobQuery { pub kind: Option<String>, pub status: Option<String> }
+
+#[derive(Deserialize)]
+pub struct TradeQuery { pub account: Option<String>, pub instrument: Option<String>, pub from: Option<NaiveDate>, pub to: Option<NaiveDate>, pub limit: Option<usize> }
+
+/// What one batch did, row by row: validation failures by row index and the dates of the rows that passed.
+pub struct Outcome { pub counts: Counts, pub errors: Vec<(usize, String)>, pub span: Option<(NaiveDate, NaiveDate)> }
+
+impl Outcome {
+    pub fn new(rows: usize) -> Self { Self { counts: Counts { rows, ..Default::default() }, errors: Vec::new(), span: None } }
+
+    pub fn reject(&mut self, row: usize, error: String) { self.counts.rejected += 1; self.errors.push((row, error)); }
+
+    pub fn accept(&mut self, date: NaiveDate, changed: bool) {
+        if changed { self.counts.loaded += 1 } else { self.counts.unchanged += 1 }
+        self.span = Some(self.span.map_or((date, date), |(a, b)| (a.min(date), b.max(date))));
+    }
+}
+
+/// Historical trades by id, so loading the same trade twice keeps one copy.
+#[derive(Default, Clone)]
+pub struct TradeHistory { trades: HashMap<String, HistoricalTrade> }
+
+impl TradeHistory {
+    /// Returns whether the trade was new or differed from the copy held.
+    pub fn upsert(&mut self, t: HistoricalTrade) -> bool {
+        if self.trades.get(&t.trade_id) == Some(&t) { return false; }
+        self.trades.insert(t.trade_id.clone(), t);
+        true
+    }
+
+    /// Oldest first.
+    pub fn query(&self, q: &TradeQuery) -> Vec<HistoricalTrade> {
+        let mut v: Vec<HistoricalTrade> = self.trades.values().filter(|t| {
+            let day = t.executed_at.date_naive();
+            q.account.as_ref().is_none_or(|a| &t.account == a) && q.instrument.as_ref().is_none_or(|i| &t.instrument == i)
+                && q.from.is_none_or(|f| day >= f) && q.to.is_none_or(|to| day <= to)
+        }).cloned().collect();
+        v.sort_by(|a, b| (a.executed_at, &a.trade_id).cmp(&(b.executed_at, &b.trade_id)));
+        v.truncate(q.limit.unwrap_or(10_000));
+        v
+    }
+}
+
+/// Checks the fields of a trade that need no engine state.
+pub fn validate_trade(t: &HistoricalTrade, now: DateTime<Utc>) -> Result<(), String> {
+    if t.trade_id.trim().is_empty() || t.instrument.trim().is_empty() { return Err("trade_id and instrument are required".into()); }
+    Side::try_from(t.side.clone())?;
+    if !(t.quantity.is_finite() && t.quantity > 0.0 && t.price.is_finite() && t.price > 0.0) { return Err("quantity and price must be positive".into()); }
+    if t.executed_at > now { return Err("executed_at is in the future".into()); }
+    Ok(())
+}
+
+/// Backfill jobs by id, oldest first when listed.
+#[derive(Default)]
+pub struct Jobs { jobs: BTreeMap<String, Job> }
+
+impl Jobs {
+    pub fn get(&self, job_id: &str) -> Option<Job> { self.jobs.get(job_id).cloned() }
+
+    pub fn list(&self, q: &JobQuery) -> Vec<Job> {
+        let mut v: Vec<Job> = self.jobs.values().filter(|j| q.kind.as_deref().is_none_or(|k| j.kind.name() == k) && q.status.as_deref().is_none_or(|s| j.status.name() == s)).cloned().collect();
+        v.sort_by_key(|j| j.started_at_ms);
+        v
+    }
+
+    /// A job's kind is fixed by its first batch.
+    pub fn check_kind(&self, job_id: &str, kind: Kind) -> Result<(), String> {
+        match self.jobs.get(job_id) { Some(j) if j.kind != kind => Err(format!("job {job_id} is a {} backfill", j.kind.name())), _ => Ok(()) }
+    }
+
+    /// Records a batch, replacing what an earlier run of the same batch recorded, and returns the job.
+    pub fn record(&mut self, job_id: &str, kind: Kind, batch: u32, batches: Option<u32>, o: Outcome, now_ms: u64) -> Job {
+        let j = self.jobs.entry(job_id.into()).or_insert_with(|| Job {
+            job_id: job_id.into(), kind, status: Status::InProgress, batches_received: 0, batches_expected: 1, progress_pct: 0.0, counts: Counts::default(),
+            from: None, to: None, errors: Vec::new(), started_at_ms: now_ms, updated_at_ms: now_ms, completed_at_ms: None, by_batch: BTreeMap::new(),
+        });
+        let errors = o.errors.into_iter().map(|(row, error)| RowError { batch, row, error }).collect();
+        j.by_batch.insert(batch, BatchRun { counts: o.counts, errors, span: o.span });
+        if let Some(n) = batches { j.batches_expected = n; }
+        j.batches_expected = j.batches_expected.max(j.by_batch.keys().next_back().map_or(0, |b| b + 1));
+        j.counts = j.by_batch.values().fold(Counts::default(), |a, BatchRun { counts: c, .. }| Counts { rows: a.rows + c.rows, loaded: a.loaded + c.loaded, unchanged: a.unchanged + c.unchanged, rejected: a.rejected + c.rejected });
+        j.errors = j.by_batch.values().flat_map(|r| r.errors.iter().cloned()).take(MAX_ERRORS).collect();
+        let spans = j.by_batch.values().filter_map(|r| r.span);
+        (j.from, j.to) = spans.fold((None, None), |(f, t): (Option<NaiveDate>, Option<NaiveDate>), (a, b)| (Some(f.map_or(a, |f| f.min(a))), Some(t.map_or(b, |t| t.max(b)))));
+        j.batches_received = j.by_batch.len();
+        j.progress_pct = (j.batches_received as f64 / j.batches_expected as f64 * 100.0).min(100.0);
+        j.updated_at_ms = now_ms;
+        if j.status == Status::InProgress && (0..j.batches_expected).all(|b| j.by_batch.contains_key(&b)) { (j.status, j.completed_at_ms) = (Status::Complete, Some(now_ms)); }
+        j.clone()
+    }
+}
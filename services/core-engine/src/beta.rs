@@ -0,0 +1,59 @@
+use crate::scenarios::{AssetClass, InstrumentFactors};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Cap on the absolute beta-adjusted net exposure summed over every account tagged with the desk.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct DeskLimit { pub max_beta_exposure: f64 }
+
+#[derive(Serialize)]
+pub struct BetaExposure { pub net_notional: f64, pub beta_exposure: f64, #[serde(skip_serializing_if = "Vec::is_empty")] pub unclassified: Vec<String> }
+
+#[derive(Serialize)]
+pub struct AccountExposure { pub account: String, #[serde(flatten)] pub exposure: BetaExposure }
+
+#[derive(Serialize)]
+pub struct DeskExposure { pub desk: String, pub max_beta_exposure: Option<f64>, pub net_notional: f64, pub beta_exposure: f64, pub accounts: Vec<AccountExposure> }
+
+/// What an order does to the beta-adjusted exposure of its account and, when it has one, its desk.
+#[derive(Serialize)]
+pub struct BetaImpact {
+    pub order: f64, pub account_before: f64, pub account_after: f64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub desk: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub desk_before: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub desk_after: Option<f64>,
+}
+
+#[derive(Default, Clone)]
+pub struct DeskLimits { limits: BTreeMap<String, DeskLimit> }
+
+impl DeskLimits {
+    pub fn get(&self, desk: &str) -> Option<DeskLimit> { self.limits.get(desk).copied() }
+    pub fn list(&self) -> BTreeMap<String, DeskLimit> { self.limits.clone() }
+    pub fn set(&mut self, desk: &str, l: DeskLimit) { self.limits.insert(desk.into(), l); }
+    pub fn remove(&mut self, desk: &str) -> bool { self.limits.remove(desk).is_some() }
+}
+
+/// An instrument's own beta, or 1.0 for equities that have none. Other asset classes carry no equity beta.
+pub fn beta(f: Option<&InstrumentFactors>) -> Option<f64> {
+    let f = f?;
+    f.beta.or((f.asset_class == AssetClass::Equity).then_some(1.0))
+}
+
+/// Net and beta-weighted notional of `(instrument, signed quantity, price)` lines. Instruments with no factor
+/// mapping are listed as unclassified and count in net notional only.
+pub fn exposure<'a>(lines: &[(String, f64, f64)], factor: impl Fn(&str) -> Option<&'a InstrumentFactors>) -> BetaExposure {
+    let mut e = BetaExposure { net_notional: 0.0, beta_exposure: 0.0, unclassified: Vec::new() };
+    for (instrument, q, price) in lines {
+        e.net_notional += q * price;
+        match factor(instrument) {
+            None => e.unclassified.push(instrument.clone()),
+            Some(f) => e.beta_exposure += beta(Some(f)).unwrap_or(0.0) * q * price,
+        }
+    }
+    e
+}
+
+/// Breach text if `after` is over `cap` and further from flat than `before`; orders that cut exposure always pass.
+pub fn breach(scope: &str, on: &str, cap: f64, before: f64, after: f64) -> Option<String> {
+    (after.abs() > cap && after.abs() > before.abs() + 1e-9).then(|| format!("{scope} max beta exposure {cap:.2} exceeded{on}: {after:.2}"))
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BreachKind { Limit, Var, Stress, Margin }
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BreachStatus { Open, Acknowledged, Resolved }
+
+#[derive(Serialize, Clone)]
+pub struct Note { pub at_ms: u64, pub author: String, pub text: String }
+
+/// `key` identifies the threshold that was crossed; repeats against an unresolved breach with the same
+/// kind, account, instrument and key bump `occurrences` instead of opening a new record.
+#[derive(Serialize, Clone)]
+pub struct Breach {
+    pub id: String, pub kind: BreachKind, pub account: Option<String>, pub instrument: Option<String>, pub key: String, pub message: String,
+    pub detected_at_ms: u64, pub last_seen_ms: u64, pub occurrences: u32, pub owner: Option<String>, pub status: BreachStatus,
+    pub notes: Vec<Note>, pub resolved_at_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct BreachQuery { pub status: Option<BreachStatus>, pub kind: Option<BreachKind>, pub account: Option<String>, pub owner: Option<String>, pub limit: Option<usize> }
+
+#[derive(Deserialize)]
+pub struct UpdateBreach { pub owner: Option<String>, pub status: Option<BreachStatus>, pub note: Option<String> }
+
+#[derive(Serialize, Default)]
+pub struct AgeBuckets { pub under_1h: usize, pub h1_to_24h: usize, pub d1_to_7d: usize, pub over_7d: usize }
+
+#[derive(Serialize)]
+pub struct AgingReport { pub unresolved: usize, pub unowned: usize, pub oldest_unresolved_age_ms: Option<u64>, pub by_status: BTreeMap<BreachStatus, AgeBuckets>, pub by_kind: BTreeMap<BreachKind, AgeBuckets> }
+
+#[derive(Default)]
+pub struct BreachBook { breaches: Vec<Breach>, seq: u64 }
+
+const HOUR_MS: u64 = 3_600_000;
+
+impl AgeBuckets {
+    fn add(&mut self, age_ms: u64) {
+        match age_ms {
+            a if a < HOUR_MS => self.under_1h += 1,
+            a if a < 24 * HOUR_MS => self.h1_to_24h += 1,
+            a if a < 7 * 24 * HOUR_MS => self.d1_to_7d += 1,
+            _ => self.over_7d += 1,
+        }
+    }
+}
+
+impl BreachBook {
+    /// Opens a breach or counts a repeat of an unresolved one. Returns the record and whether it is new.
+    pub fn record(&mut self, kind: BreachKind, account: Option<&str>, instrument: Option<&str>, key: &str, message: String, now_ms: u64) -> (Breach, bool) {
+        if let Some(b) = self.breaches.iter_mut().find(|b| b.status != BreachStatus::Resolved && b.kind == kind && b.account.as_deref() == account && b.instrument.as_deref() == instrument && b.key == key) {
+            b.occurrences += 1;
+            b.last_seen_ms = now_ms;
+            b.message = message;
+            return (b.clone(), false);
+        }
+        self.seq += 1;
+        let b = Breach {
+            id: format!("BR-{:06}", self.seq), kind, account: account.map(Into::into), instrument: instrument.map(Into::into), key: key.into(), message,
+            detected_at_ms: now_ms, last_seen_ms: now_ms, occurrences: 1, owner: None, status: BreachStatus::Open, notes: Vec::new(), resolved_at_ms: None,
+        };
+        self.breaches.push(b.clone());
+        (b, true)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Breach> { self.breaches.iter().find(|b| b.id == id) }
+
+    /// Newest first.
+    pub fn query(&self, q: &BreachQuery) -> Vec<Breach> {
+        self.breaches.iter().rev()
+            .filter(|b| q.status.is_none_or(|s| b.status == s) && q.kind.is_none_or(|k| b.kind == k))
+            .filter(|b| q.account.as_ref().is_none_or(|a| b.account.as_ref() == Some(a)) && q.owner.as_ref().is_none_or(|o| b.owner.as_ref() == Some(o)))
+            .take(q.limit.unwrap_or(100)).cloned().collect()
+    }
+
+    /// Resolved breaches are closed for good, and resolving needs a resolution note on record.
+    pub fn update(&mut self, id: &str, req: UpdateBreach, author: &str, now_ms: u64) -> Result<Breach, String> {
+        let b = self.breaches.iter_mut().find(|b| b.id == id).ok_or("Breach not found")?;
+        if b.status == BreachStatus::Resolved { return Err("resolved breaches cannot be changed".into()); }
+        if req.status == Some(BreachStatus::Open) && b.status != BreachStatus::Open { return Err("acknowledged breaches cannot be reopened".into()); }
+        let note = req.note.filter(|n| !n.trim().is_empty());
+        if req.status == Some(BreachStatus::Resolved) && note.is_none() && b.notes.is_empty() { return Err("resolving a breach requires a resolution note".into()); }
+        if let Some(o) = req.owner { b.owner = Some(o).filter(|o| !o.is_empty()); }
+        if let Some(text) = note { b.notes.push(Note { at_ms: now_ms, author: author.into(), text }); }
+        if let Some(st) = req.status {
+            b.status = st;
+            if st == BreachStatus::Resolved { b.resolved_at_ms = Some(now_ms); }
+        }
+        Ok(b.clone())
+    }
+
+    /// Age of every unresolved breach since first detection, bucketed by status and by kind.
+    pub fn aging(&self, now_ms: u64) -> AgingReport {
+        let open: Vec<&Breach> = self.breaches.iter().filter(|b| b.status != BreachStatus::Resolved).collect();
+        let (mut by_status, mut by_kind) = (BTreeMap::<BreachStatus, AgeBuckets>::new(), BTreeMap::<BreachKind, AgeBuckets>::new());
+        for b in &open {
+            let age = now_ms.saturating_sub(b.detected_at_ms);
+            by_status.entry(b.status).or_default().add(age);
+            by_kind.entry(b.kind).or_default().add(age);
+        }
+        AgingReport {
+            unresolved: open.len(), unowned: open.iter().filter(|b| b.owner.is_none()).count(),
+            oldest_unresolved_age_ms: open.iter().map(|b| now_ms.saturating_sub(b.detected_at_ms)).max(), by_status, by_kind,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+/// What a check decides when its dependencies do not answer within the budget: refuse outright, or let through
+/// orders up to `max_notional` that pass every local check.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Fallback { Reject, NotionalCap { max_notional: f64 } }
+
+/// Without `budget_ms` checks wait on their dependencies for as long as they take. A request may set its own budget.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct LatencyBudget { pub budget_ms: Option<u64>, pub fallback: Fallback }
+
+/// The stage a check cut short by the client's deadline reports.
+pub const DEADLINE: &str = "deadline";
+
+/// Set on a response decided on the fast path; `stage` names what was cut short. A check cut short by the client's
+/// deadline rather than the budget is refused whatever the fallback, since no answer would arrive in time.
+#[derive(Serialize, Clone)]
+pub struct Degraded { pub budget_ms: u64, pub stage: &'static str, pub fallback: Fallback }
+
+impl Default for LatencyBudget {
+    fn default() -> Self { Self { budget_ms: None, fallback: Fallback::Reject } }
+}
+
+impl LatencyBudget {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.budget_ms == Some(0) { return Err("budget_ms must be positive".into()); }
+        if let Fallback::NotionalCap { max_notional } = self.fallback { if !(max_notional.is_finite() && max_notional > 0.0) { return Err("max_notional must be positive".into()); } }
+        Ok(())
+    }
+}
+
+impl Degraded {
+    pub fn timed_out(&self) -> bool { self.stage == DEADLINE }
+
+    pub fn reason(&self, notional: f64) -> Option<String> {
+        if self.timed_out() { return Some(format!("Deadline exceeded: the client's deadline passed after {}ms waiting on dependencies", self.budget_ms)); }
+        self.fallback.reason(self.budget_ms, notional)
+    }
+}
+
+impl Fallback {
+    /// The reason a degraded check is refused, if it is.
+    pub fn reason(&self, budget_ms: u64, notional: f64) -> Option<String> {
+        match self {
+            Fallback::Reject => Some(format!("Degraded check: dependencies did not answer within the {budget_ms}ms latency budget")),
+            Fallback::NotionalCap { max_notional } if notional.abs() > *max_notional => Some(format!("Degraded check: notional {:.2} exceeds fast-path cap {max_notional:.2}", notional.abs())),
+            Fallback::NotionalCap { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind { Fomc, Nfp, Cpi, Earnings, Other }
+
+/// How orders are treated from `before_mins` ahead of an event to `after_mins` after it: limits are multiplied by
+/// `limit_multiplier`, and with `block` new orders are refused outright.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct WindowPolicy { pub before_mins: u64, pub after_mins: u64, pub limit_multiplier: f64, #[serde(default)] pub block: bool }
+
+impl WindowPolicy {
+    fn validate(&self) -> Result<(), String> {
+        if !(self.limit_multiplier > 0.0 && self.limit_multiplier <= 1.0) { return Err("limit_multiplier must be within (0, 1]".into()); }
+        if self.before_mins + self.after_mins == 0 { return Err("a window must span some time around the event".into()); }
+        Ok(())
+    }
+}
+
+/// The window each kind of event gets unless the event sets its own.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct CalendarConfig { pub policies: BTreeMap<EventKind, WindowPolicy> }
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        let p = |before_mins, after_mins| WindowPolicy { before_mins, after_mins, limit_multiplier: 0.5, block: false };
+        Self { policies: BTreeMap::from([(EventKind::Fomc, p(30, 60)), (EventKind::Nfp, p(15, 30)), (EventKind::Cpi, p(15, 30)), (EventKind::Earnings, p(60, 60)), (EventKind::Other, p(15, 15))]) }
+    }
+}
+
+impl CalendarConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        self.policies.iter().try_for_each(|(k, p)| p.validate().map_err(|e| format!("{k:?}: {e}")))
+    }
+}
+
+/// A scheduled release. Macro events without `instruments` cover every instrument; earnings name the symbols
+/// reporting.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct EventSpec {
+    pub kind: EventKind, pub name: String, pub at_ms: u64, #[serde(default)] pub instruments: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub window: Option<WindowPolicy>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Event { pub id: String, #[serde(flatten)] pub spec: EventSpec, pub created_at_ms: u64, pub updated_at_ms: u64 }
+
+/// An event whose window an order falls in, with the window as it applies.
+#[derive(Serialize, Clone)]
+pub struct ActiveEvent { pub id: String, pub kind: EventKind, pub name: String, pub at_ms: u64, pub window_start_ms: u64, pub window_end_ms: u64, pub limit_multiplier: f64, pub block: bool }
+
+/// `instrument` narrows to events covering it; `from_ms` and `to_ms` to events scheduled in that range.
+#[derive(Deserialize)]
+pub struct EventQuery { pub instrument: Option<String>, pub from_ms: Option<u64>, pub to_ms: Option<u64> }
+
+#[derive(Default, Clone)]
+pub struct Calendar { pub config: CalendarConfig, events: BTreeMap<String, Event> }
+
+fn validate(spec: &EventSpec) -> Result<(), String> {
+    if spec.name.trim().is_empty() { return Err("event name is required".into()); }
+    if spec.kind == EventKind::Earnings && spec.instruments.is_empty() { return Err("earnings events must name the instruments reporting".into()); }
+    if spec.instruments.iter().any(|i| i.trim().is_empty()) { return Err("instruments must not be empty".into()); }
+    if let Some(w) = &spec.window { w.validate()?; }
+    Ok(())
+}
+
+impl Event {
+    fn covers(&self, instrument: &str) -> bool { self.spec.instruments.is_empty() || self.spec.instruments.iter().any(|i| i == instrument) }
+}
+
+impl Calendar {
+    /// Soonest first.
+    pub fn list(&self, q: &EventQuery) -> Vec<Event> {
+        let mut v: Vec<Event> = self.events.values().filter(|e| {
+            q.instrument.as_deref().is_none_or(|i| e.covers(i)) && q.from_ms.is_none_or(|f| e.spec.at_ms >= f) && q.to_ms.is_none_or(|t| e.spec.at_ms <= t)
+        }).cloned().collect();
+        v.sort_by_key(|e| e.spec.at_ms);
+        v
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Event> { self.events.get(id) }
+
+    pub fn create(&mut self, spec: EventSpec, now_ms: u64) -> Result<Event, String> {
+        validate(&spec)?;
+        let e = Event { id: uuid::Uuid::new_v4().to_string(), spec, created_at_ms: now_ms, updated_at_ms: now_ms };
+        self.events.insert(e.id.clone(), e.clone());
+        Ok(e)
+    }
+
+    pub fn update(&mut self, id: &str, spec: EventSpec, now_ms: u64) -> Result<Option<Event>, String> {
+        validate(&spec)?;
+        Ok(self.events.get_mut(id).map(|e| { e.spec = spec; e.updated_at_ms = now_ms; e.clone() }))
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool { self.events.remove(id).is_some() }
+
+    /// Events covering `instrument` whose window is open at `now_ms`.
+    pub fn active(&self, instrument: &str, now_ms: u64) -> Vec<ActiveEvent> {
+        self.events.values().filter(|e| e.covers(instrument)).filter_map(|e| {
+            let p = e.spec.window.or_else(|| self.config.policies.get(&e.spec.kind).copied())?;
+            let (start, end) = (e.spec.at_ms.saturating_sub(p.before_mins * 60_000), e.spec.at_ms + p.after_mins * 60_000);
+            (start..end).contains(&now_ms).then(|| ActiveEvent {
+                id: e.id.clone(), kind: e.spec.kind, name: e.spec.name.clone(), at_ms: e.spec.at_ms, window_start_ms: start, window_end_ms: end, limit_multiplier: p.limit_multiplier, block: p.block,
+            })
+        }).collect()
+    }
+}
+
+/// The tightest multiplier among `events`, 1 outside any window.
+pub fn multiplier(events: &[ActiveEvent]) -> f64 { events.iter().map(|e| e.limit_multiplier).fold(1.0, f64::min) }
@@ -0,0 +1,61 @@
+use crate::tags::Tags;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// One pre-trade or basket decision as it was made: `kind` is `pretrade` or `basket`, `inputs` the request as
+/// received, `limits_evaluated` the ids of the configured limits the order fell under.
+#[derive(Serialize, Clone)]
+pub struct CheckRecord {
+    pub check_id: String, pub kind: String, pub account: String, pub approved: bool, pub reasons: Vec<String>,
+    #[serde(skip_serializing_if = "Tags::is_empty")] pub tags: Tags, pub inputs: serde_json::Value, pub limits_evaluated: Vec<String>, pub elapsed_us: u64, pub at_ms: u64,
+}
+
+/// `from` and `to` bound the decision time inclusively; pages are newest first and `offset` skips that many matches.
+#[derive(Deserialize)]
+pub struct CheckQuery { pub account: Option<String>, pub kind: Option<String>, pub approved: Option<bool>, pub from: Option<DateTime<Utc>>, pub to: Option<DateTime<Utc>>, pub limit: Option<usize>, pub offset: Option<usize> }
+
+/// `next_offset` is set while more matches remain.
+#[derive(Serialize)]
+pub struct CheckPage { pub checks: Vec<CheckRecord>, pub total: usize, pub offset: usize, pub next_offset: Option<usize> }
+
+const MAX_PAGE: usize = 1000;
+
+impl CheckQuery {
+    pub fn page_size(&self) -> usize { self.limit.unwrap_or(100).clamp(1, MAX_PAGE) }
+    /// `from` and `to` in epoch milliseconds.
+    pub fn window_ms(&self) -> (Option<u64>, Option<u64>) { let ms = |t: DateTime<Utc>| t.timestamp_millis().max(0) as u64; (self.from.map(ms), self.to.map(ms)) }
+
+    fn matches(&self, c: &CheckRecord) -> bool {
+        let (from, to) = self.window_ms();
+        self.account.as_ref().is_none_or(|a| &c.account == a) && self.kind.as_ref().is_none_or(|k| &c.kind == k) && self.approved.is_none_or(|a| c.approved == a)
+            && from.is_none_or(|f| c.at_ms >= f) && to.is_none_or(|t| c.at_ms <= t)
+    }
+}
+
+impl CheckPage {
+    pub fn new(checks: Vec<CheckRecord>, total: usize, offset: usize) -> Self {
+        let next = offset + checks.len();
+        Self { checks, total, offset, next_offset: (next < total).then_some(next) }
+    }
+}
+
+/// The most recent decisions in memory; with a store configured the full history is kept there.
+pub struct CheckLog { checks: VecDeque<CheckRecord>, max_checks: usize }
+
+impl CheckLog {
+    pub fn new(max_checks: usize) -> Self { Self { checks: VecDeque::new(), max_checks: max_checks.max(1) } }
+
+    pub fn record(&mut self, c: CheckRecord) {
+        self.checks.push_back(c);
+        while self.checks.len() > self.max_checks { self.checks.pop_front(); }
+    }
+
+    pub fn get(&self, check_id: &str) -> Option<CheckRecord> { self.checks.iter().find(|c| c.check_id == check_id).cloned() }
+
+    pub fn query(&self, q: &CheckQuery) -> CheckPage {
+        let matching: Vec<&CheckRecord> = self.checks.iter().rev().filter(|c| q.matches(c)).collect();
+        let offset = q.offset.unwrap_or(0);
+        CheckPage::new(matching.iter().skip(offset).take(q.page_size()).map(|c| (*c).clone()).collect(), matching.len(), offset)
+    }
+}
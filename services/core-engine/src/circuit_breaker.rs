@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Level { pub name: String, pub threshold_pct: f64, pub halt_secs: u64 }
+
+/// Levels trip on the absolute move from the reference price: the earliest tick seen within the last
+/// `reference_window_secs`.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct BreakerConfig { pub levels: Vec<Level>, pub reference_window_secs: u64 }
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        let levels = [("L1", 7.0, 300), ("L2", 13.0, 900), ("L3", 20.0, 3600)].into_iter().map(|(n, t, h)| Level { name: n.into(), threshold_pct: t, halt_secs: h }).collect();
+        Self { levels, reference_window_secs: 300 }
+    }
+}
+
+/// Levels must be listed from the smallest move up, with distinct names and halts that do not get shorter.
+pub fn validate_levels(levels: &[Level]) -> Result<(), String> {
+    if levels.is_empty() { return Err("at least one level is required".into()); }
+    for (i, l) in levels.iter().enumerate() {
+        if l.name.trim().is_empty() { return Err(format!("level {} needs a name", i + 1)); }
+        if !(l.threshold_pct.is_finite() && l.threshold_pct > 0.0) { return Err(format!("level {} threshold_pct must be positive", l.name)); }
+        if l.halt_secs == 0 { return Err(format!("level {} halt_secs must be positive", l.name)); }
+        if levels[..i].iter().any(|p| p.name == l.name) { return Err(format!("level name {} is repeated", l.name)); }
+        if let Some(p) = i.checked_sub(1).map(|j| &levels[j]) {
+            if l.threshold_pct <= p.threshold_pct { return Err(format!("level {} must trip on a larger move than {}", l.name, p.name)); }
+            if l.halt_secs < p.halt_secs { return Err(format!("level {} cannot halt for less than {}", l.name, p.name)); }
+        }
+    }
+    Ok(())
+}
+
+/// The most severe of `levels` the move reaches.
+pub fn trip(levels: &[Level], change_pct: f64) -> Option<&Level> { levels.iter().rfind(|l| change_pct.abs() >= l.threshold_pct) }
+
+impl BreakerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reference_window_secs == 0 { return Err("reference_window_secs must be positive".into()); }
+        validate_levels(&self.levels)
+    }
+}
+
+/// Trading stopped in one instrument until `expires_at_ms`, after `change_pct` tripped `level`.
+#[derive(Serialize, Clone)]
+pub struct Halt { pub instrument: String, pub level: String, pub change_pct: f64, pub started_at_ms: u64, pub expires_at_ms: u64 }
+
+/// Recent prices per instrument, kept for as long as the reference window reaches back, and the halts in force.
+#[derive(Default)]
+pub struct CircuitBreakers { pub config: BreakerConfig, prices: HashMap<String, VecDeque<(u64, f64)>>, halts: HashMap<String, Halt> }
+
+impl CircuitBreakers {
+    pub fn observe(&mut self, instrument: &str, price: f64, at_ms: u64) {
+        let window_ms = self.config.reference_window_secs * 1000;
+        let q = self.prices.entry(instrument.into()).or_default();
+        q.push_back((at_ms, price));
+        while q.front().is_some_and(|(t, _)| *t + window_ms < at_ms) { q.pop_front(); }
+    }
+
+    pub fn reference(&self, instrument: &str, now_ms: u64) -> Option<f64> {
+        let window_ms = self.config.reference_window_secs * 1000;
+        self.prices.get(instrument)?.iter().find(|(t, _)| *t + window_ms >= now_ms).map(|(_, p)| *p)
+    }
+
+    /// Halts the instrument for the level's duration. A trip while already halted can extend the halt but never
+    /// shortens it.
+    pub fn halt(&mut self, instrument: &str, level: &Level, change_pct: f64, now_ms: u64) -> Halt {
+        let expires_at_ms = now_ms + level.halt_secs * 1000;
+        let h = match self.halts.get(instrument).filter(|h| h.expires_at_ms > now_ms) {
+            Some(h) if h.expires_at_ms >= expires_at_ms => h.clone(),
+            _ => Halt { instrument: instrument.into(), level: level.name.clone(), change_pct, started_at_ms: now_ms, expires_at_ms },
+        };
+        self.halts.insert(instrument.into(), h.clone());
+        h
+    }
+
+    pub fn active(&self, instrument: &str, now_ms: u64) -> Option<&Halt> { self.halts.get(instrument).filter(|h| h.expires_at_ms > now_ms) }
+
+    /// Halts still in force, soonest to expire first; expired ones are dropped.
+    pub fn halts(&mut self, now_ms: u64) -> Vec<Halt> {
+        self.halts.retain(|_, h| h.expires_at_ms > now_ms);
+        let mut v: Vec<Halt> = self.halts.values().cloned().collect();
+        v.sort_by(|a, b| (a.expires_at_ms, &a.instrument).cmp(&(b.expires_at_ms, &b.instrument)));
+        v
+    }
+
+    /// Ends a halt early, returning it if one was in force.
+    pub fn lift(&mut self, instrument: &str, now_ms: u64) -> Option<Halt> { self.halts.remove(instrument).filter(|h| h.expires_at_ms > now_ms) }
+}
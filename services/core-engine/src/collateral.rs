@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// `None` on a rule field matches anything; the most specific matching rule wins.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct HaircutRule { pub asset_class: String, pub rating: Option<String>, pub min_maturity_years: Option<f64>, pub max_maturity_years: Option<f64>, pub haircut_pct: f64 }
+
+#[derive(Deserialize)]
+pub struct ScheduleInput { pub name: String, pub effective_from_ms: u64, pub effective_to_ms: Option<u64>, pub rules: Vec<HaircutRule> }
+
+#[derive(Serialize, Clone)]
+pub struct HaircutSchedule { pub id: String, pub name: String, pub version: u64, pub effective_from_ms: u64, pub effective_to_ms: Option<u64>, pub rules: Vec<HaircutRule>, pub updated_at_ms: u64 }
+
+/// `price` is in `currency`, the account's base currency when unset.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CollateralItem {
+    pub asset: String, pub asset_class: String, pub rating: Option<String>, pub maturity_years: Option<f64>, pub quantity: f64, pub price: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub currency: Option<String>,
+}
+
+/// `market_value` and `collateral_value` are in the item's currency; `base_value` is its collateral value in the
+/// account's base currency, nothing when there is no rate.
+#[derive(Serialize)]
+pub struct ValuedItem { #[serde(flatten)] pub item: CollateralItem, pub market_value: f64, pub haircut_pct: f64, pub collateral_value: f64, pub base_value: f64, pub schedule_id: Option<String> }
+
+/// Totals in the account's base currency; `by_currency` is collateral value in each currency of the holdings.
+#[derive(Serialize)]
+pub struct Valuation {
+    pub items: Vec<ValuedItem>, pub market_value: f64, pub collateral_value: f64, pub by_currency: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub unconverted: Vec<String>,
+}
+
+impl HaircutRule {
+    fn matches(&self, c: &CollateralItem) -> Option<usize> {
+        if !self.asset_class.eq_ignore_ascii_case(&c.asset_class) { return None; }
+        if self.rating.as_ref().is_some_and(|r| c.rating.as_ref().is_none_or(|cr| !cr.eq_ignore_ascii_case(r))) { return None; }
+        let m = c.maturity_years;
+        if self.min_maturity_years.is_some_and(|lo| m.is_none_or(|m| m < lo)) || self.max_maturity_years.is_some_and(|hi| m.is_none_or(|m| m >= hi)) { return None; }
+        Some([self.rating.is_some(), self.min_maturity_years.is_some() || self.max_maturity_years.is_some()].iter().filter(|x| **x).count())
+    }
+}
+
+fn validate(s: &ScheduleInput) -> Result<(), String> {
+    if s.effective_to_ms.is_some_and(|to| to <= s.effective_from_ms) { return Err("effective_to_ms must be after effective_from_ms".into()); }
+    if let Some(r) = s.rules.iter().find(|r| !(0.0..=100.0).contains(&r.haircut_pct)) { return Err(format!("haircut_pct {} out of range 0..=100", r.haircut_pct)); }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct CollateralBook { schedules: Vec<HaircutSchedule>, holdings: HashMap<String, Vec<CollateralItem>> }
+
+impl CollateralBook {
+    pub fn schedules(&self) -> Vec<HaircutSchedule> { self.schedules.clone() }
+
+    /// The haircut schedules alone, with no account holding anything.
+    pub fn config_copy(&self) -> Self { Self { schedules: self.schedules.clone(), holdings: HashMap::new() } }
+    pub fn schedule(&self, id: &str) -> Option<&HaircutSchedule> { self.schedules.iter().find(|s| s.id == id) }
+
+    pub fn create_schedule(&mut self, input: ScheduleInput, now_ms: u64) -> Result<HaircutSchedule, String> {
+        validate(&input)?;
+        let s = HaircutSchedule { id: uuid::Uuid::new_v4().to_string(), name: input.name, version: 1, effective_from_ms: input.effective_from_ms, effective_to_ms: input.effective_to_ms, rules: input.rules, updated_at_ms: now_ms };
+        self.schedules.push(s.clone());
+        Ok(s)
+    }
+
+    pub fn update_schedule(&mut self, id: &str, input: ScheduleInput, now_ms: u64) -> Result<Option<HaircutSchedule>, String> {
+        validate(&input)?;
+        let Some(s) = self.schedules.iter_mut().find(|s| s.id == id) else { return Ok(None) };
+        *s = HaircutSchedule { id: s.id.clone(), name: input.name, version: s.version + 1, effective_from_ms: input.effective_from_ms, effective_to_ms: input.effective_to_ms, rules: input.rules, updated_at_ms: now_ms };
+        Ok(Some(s.clone()))
+    }
+
+    pub fn delete_schedule(&mut self, id: &str) -> bool { let n = self.schedules.len(); self.schedules.retain(|s| s.id != id); n != self.schedules.len() }
+
+    /// The schedule in force at `at_ms`: the latest effective start that has not yet ended.
+    pub fn active_schedule(&self, at_ms: u64) -> Option<&HaircutSchedule> {
+        self.schedules.iter().filter(|s| s.effective_from_ms <= at_ms && s.effective_to_ms.is_none_or(|to| at_ms < to)).max_by_key(|s| s.effective_from_ms)
+    }
+
+    /// Collateral with no matching rule is ineligible and takes a 100% haircut.
+    pub fn haircut(&self, item: &CollateralItem, at_ms: u64) -> (f64, Option<String>) {
+        let Some(s) = self.active_schedule(at_ms) else { return (100.0, None) };
+        let best = s.rules.iter().filter_map(|r| r.matches(item).map(|spec| (spec, r.haircut_pct))).max_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        (best.map(|b| b.1).unwrap_or(100.0), Some(s.id.clone()))
+    }
+
+    pub fn holdings(&self, account: &str) -> Vec<CollateralItem> { self.holdings.get(account).cloned().unwrap_or_default() }
+    pub fn set_holdings(&mut self, account: &str, items: Vec<CollateralItem>) { self.holdings.insert(account.into(), items); }
+
+    pub fn has_holdings(&self, account: &str) -> bool { self.holdings.get(account).is_some_and(|h| !h.is_empty()) }
+
+    /// Marks every holding of `asset` to `price`, returning the accounts whose collateral moved.
+    pub fn reprice(&mut self, asset: &str, price: f64) -> Vec<String> {
+        self.holdings.iter_mut().filter_map(|(account, items)| {
+            let mut moved = false;
+            for i in items.iter_mut().filter(|i| i.asset == asset && i.price != price) { i.price = price; moved = true; }
+            moved.then(|| account.clone())
+        }).collect()
+    }
+
+    /// Values the account's holdings in `base_currency`, converting other currencies at `rate`.
+    pub fn value(&self, account: &str, base_currency: &str, at_ms: u64, rate: impl Fn(&str) -> Option<f64>) -> Valuation {
+        let (mut market_value, mut by_currency, mut unconverted) = (0.0, BTreeMap::new(), Vec::new());
+        let items: Vec<ValuedItem> = self.holdings(account).into_iter().map(|item| {
+            let mv = item.quantity * item.price;
+            let (h, schedule_id) = self.haircut(&item, at_ms);
+            let ccy = item.currency.as_deref().unwrap_or(base_currency).to_ascii_uppercase();
+            let r = rate(&ccy);
+            if r.is_none() && !unconverted.contains(&ccy) { unconverted.push(ccy.clone()); }
+            market_value += mv * r.unwrap_or(0.0);
+            let cv = mv * (1.0 - h / 100.0);
+            *by_currency.entry(ccy).or_default() += cv;
+            ValuedItem { market_value: mv, haircut_pct: h, collateral_value: cv, base_value: cv * r.unwrap_or(0.0), schedule_id, item }
+        }).collect();
+        Valuation { market_value, collateral_value: items.iter().map(|i| i.base_value).sum(), by_currency, unconverted, items }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::otc::{OtcTrade, TradeStatus};
+use crate::positions::Side;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Exactly one scope: every account of `entity`, a named set of `accounts`, or the live OTC trades facing
+/// `counterparty` (optionally narrowed by `entity` or `accounts`).
+#[derive(Deserialize)]
+pub struct CompressionRequest { pub entity: Option<String>, pub accounts: Option<Vec<String>>, pub counterparty: Option<String> }
+
+/// One leg of a compression: an offsetting internal cross for account positions, or a full or partial tear-up
+/// of an OTC trade.
+#[derive(Serialize, Clone)]
+pub struct CompressionTrade { pub account: String, pub side: Side, pub quantity: f64, #[serde(skip_serializing_if = "Option::is_none")] pub trade_id: Option<String> }
+
+#[derive(Serialize)]
+pub struct Proposal { pub instrument: String, pub compressed: f64, pub trades: Vec<CompressionTrade>, pub lines_removed: usize }
+
+#[derive(Serialize)]
+pub struct AccountMargin { pub account: String, pub before: f64, pub after: f64 }
+
+#[derive(Serialize)]
+pub struct CompressionReport {
+    pub accounts: Vec<String>, pub counterparty: Option<String>, pub proposals: Vec<Proposal>, pub line_items_before: usize, pub line_items_after: usize,
+    pub margin_before: f64, pub margin_after: f64, pub margin_reduction: f64, pub by_account: Vec<AccountMargin>, pub unpriced: Vec<String>,
+}
+
+fn side(q: f64) -> Side { if q > 0.0 { Side::Buy } else { Side::Sell } }
+
+/// Pairs long and short holdings of the same instrument in different accounts, largest first, and crosses them
+/// down. `books` holds each account's signed positions.
+pub fn offsets(books: &[(String, Vec<(String, f64)>)]) -> Vec<Proposal> {
+    let mut by_instrument: BTreeMap<&str, Vec<(&str, f64)>> = BTreeMap::new();
+    for (a, ps) in books { for (i, q) in ps { by_instrument.entry(i).or_default().push((a, *q)); } }
+    let mut out = Vec::new();
+    for (instrument, holders) in by_instrument {
+        let mut longs: Vec<(&str, f64)> = holders.iter().filter(|h| h.1 > 0.0).copied().collect();
+        let mut shorts: Vec<(&str, f64)> = holders.iter().filter(|h| h.1 < 0.0).map(|(a, q)| (*a, -q)).collect();
+        longs.sort_by(|x, y| y.1.total_cmp(&x.1));
+        shorts.sort_by(|x, y| y.1.total_cmp(&x.1));
+        let (mut trades, mut compressed, mut flat) = (BTreeMap::<&str, f64>::new(), 0.0, 0);
+        let (mut i, mut j) = (0, 0);
+        while i < longs.len() && j < shorts.len() {
+            let q = longs[i].1.min(shorts[j].1);
+            *trades.entry(longs[i].0).or_default() -= q;
+            *trades.entry(shorts[j].0).or_default() += q;
+            compressed += q;
+            longs[i].1 -= q;
+            shorts[j].1 -= q;
+            if longs[i].1 <= 1e-9 { i += 1; flat += 1; }
+            if shorts[j].1 <= 1e-9 { j += 1; flat += 1; }
+        }
+        if compressed > 0.0 {
+            out.push(Proposal { instrument: instrument.into(), compressed, lines_removed: flat, trades: trades.into_iter().map(|(a, q)| CompressionTrade { account: a.into(), side: side(q), quantity: q.abs(), trade_id: None }).collect() });
+        }
+    }
+    out
+}
+
+/// Tears up offsetting live trades in the same instrument: receivers against payers, largest first, terminating
+/// each trade in full where it is covered and partially otherwise.
+pub fn tear_ups(trades: &[OtcTrade]) -> Vec<Proposal> {
+    let mut by_instrument: BTreeMap<&str, Vec<&OtcTrade>> = BTreeMap::new();
+    for t in trades.iter().filter(|t| t.status == TradeStatus::Live) { by_instrument.entry(&t.instrument).or_default().push(t); }
+    let mut out = Vec::new();
+    for (instrument, ts) in by_instrument {
+        let mut rec: Vec<(&OtcTrade, f64)> = ts.iter().filter(|t| t.notional > 0.0).map(|t| (*t, t.notional)).collect();
+        let mut pay: Vec<(&OtcTrade, f64)> = ts.iter().filter(|t| t.notional < 0.0).map(|t| (*t, -t.notional)).collect();
+        rec.sort_by(|x, y| y.1.total_cmp(&x.1));
+        pay.sort_by(|x, y| y.1.total_cmp(&x.1));
+        let (mut cut, mut compressed) = (BTreeMap::<&str, (&OtcTrade, f64)>::new(), 0.0);
+        let (mut i, mut j) = (0, 0);
+        while i < rec.len() && j < pay.len() {
+            let q = rec[i].1.min(pay[j].1);
+            cut.entry(&rec[i].0.trade_id).or_insert((rec[i].0, 0.0)).1 -= q;
+            cut.entry(&pay[j].0.trade_id).or_insert((pay[j].0, 0.0)).1 += q;
+            compressed += q;
+            rec[i].1 -= q;
+            pay[j].1 -= q;
+            if rec[i].1 <= 1e-9 { i += 1; }
+            if pay[j].1 <= 1e-9 { j += 1; }
+        }
+        if compressed > 0.0 {
+            let lines_removed = cut.values().filter(|(t, q)| (t.notional + q).abs() <= 1e-9).count();
+            let trades = cut.into_values().map(|(t, q)| CompressionTrade { account: t.account.clone(), side: side(q), quantity: q.abs(), trade_id: Some(t.trade_id.clone()) }).collect();
+            out.push(Proposal { instrument: instrument.into(), compressed, trades, lines_removed });
+        }
+    }
+    out
+}
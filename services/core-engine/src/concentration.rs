@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What a concentration is measured over.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension { Instrument, Issuer, Sector }
+
+impl Dimension {
+    pub const ALL: [Dimension; 3] = [Dimension::Instrument, Dimension::Issuer, Dimension::Sector];
+    fn label(self) -> &'static str { match self { Dimension::Instrument => "instrument", Dimension::Issuer => "issuer", Dimension::Sector => "sector" } }
+}
+
+/// `Warn` caps flag an order instead of refusing it.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode { #[default] Reject, Warn }
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct Cap { pub max_pct: f64, #[serde(default)] pub mode: Mode }
+
+/// Caps on the share of an account's gross portfolio value in one instrument, issuer or sector. Per-account caps
+/// replace the firm-wide cap for that dimension. Portfolios worth less than `min_portfolio_value` are not held to
+/// them, since the first few positions of any book are most of it.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct ConcentrationLimits {
+    #[serde(default)] pub caps: BTreeMap<Dimension, Cap>, #[serde(default)] pub accounts: BTreeMap<String, BTreeMap<Dimension, Cap>>, #[serde(default)] pub min_portfolio_value: f64,
+}
+
+impl ConcentrationLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.min_portfolio_value.is_finite() && self.min_portfolio_value >= 0.0) { return Err("min_portfolio_value must not be negative".into()); }
+        let caps = self.caps.iter().chain(self.accounts.values().flatten());
+        match caps.map(|(d, c)| (d, c.max_pct)).find(|(_, p)| !(p.is_finite() && *p > 0.0 && *p <= 100.0)) {
+            Some((d, _)) => Err(format!("{} max_pct must be within (0, 100]", d.label())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn cap(&self, account: &str, d: Dimension) -> Option<Cap> {
+        self.accounts.get(account).and_then(|m| m.get(&d)).or_else(|| self.caps.get(&d)).copied()
+    }
+
+    pub fn any(&self, account: &str) -> bool { Dimension::ALL.iter().any(|d| self.cap(account, *d).is_some()) }
+}
+
+/// An instrument's issuer and sector. An instrument without a sector here takes the one in its factor mapping.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct Classification { #[serde(default, skip_serializing_if = "Option::is_none")] pub issuer: Option<String>, #[serde(default, skip_serializing_if = "Option::is_none")] pub sector: Option<String> }
+
+/// One bucket's share of the portfolio. `max_pct` is the cap that applies to it, if any.
+#[derive(Serialize, Clone)]
+pub struct Bucket {
+    pub dimension: Dimension, pub name: String, pub value: f64, pub pct: f64, #[serde(skip_serializing_if = "Option::is_none")] pub max_pct: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub mode: Option<Mode>, pub breached: bool,
+}
+
+/// `unclassified` instruments have no issuer or no sector and count only towards the buckets they have.
+#[derive(Serialize)]
+pub struct Report {
+    pub account: String, pub base_currency: String, pub gross_value: f64, pub exempt: bool, pub buckets: Vec<Bucket>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub unclassified: Vec<String>, #[serde(skip_serializing_if = "Vec::is_empty")] pub unpriced: Vec<String>,
+}
+
+/// Gross value of each bucket, and of the whole portfolio, for `(instrument, value)` holdings. Longs and shorts in
+/// one bucket both add to it.
+pub fn buckets(holdings: &BTreeMap<String, f64>, key: impl Fn(&str, Dimension) -> Option<String>) -> (BTreeMap<(Dimension, String), f64>, f64) {
+    let mut out = BTreeMap::new();
+    for (i, v) in holdings {
+        for d in Dimension::ALL {
+            if let Some(k) = key(i, d) { *out.entry((d, k)).or_default() += v.abs(); }
+        }
+    }
+    (out, holdings.values().map(|v| v.abs()).sum())
+}
+
+fn pct(value: f64, gross: f64) -> f64 { if gross > 0.0 { value / gross * 100.0 } else { 0.0 } }
+
+/// Breaches (`Reject` caps) and flags (`Warn` caps) for the buckets the order moves; an order that lowers a
+/// bucket's share is never held back by it.
+pub fn check(limits: &ConcentrationLimits, account: &str, before: &BTreeMap<String, f64>, after: &BTreeMap<String, f64>, touched: &[String], key: impl Fn(&str, Dimension) -> Option<String>) -> (Vec<String>, Vec<String>) {
+    let (mut reasons, mut flags) = (Vec::new(), Vec::new());
+    let ((b, b_gross), (a, a_gross)) = (buckets(before, &key), buckets(after, &key));
+    if a_gross < limits.min_portfolio_value { return (reasons, flags); }
+    let mut seen = Vec::new();
+    for d in Dimension::ALL {
+        let Some(cap) = limits.cap(account, d) else { continue };
+        for k in touched.iter().filter_map(|i| key(i, d)) {
+            if seen.contains(&(d, k.clone())) { continue; }
+            let (was, now) = (pct(b.get(&(d, k.clone())).copied().unwrap_or(0.0), b_gross), pct(a.get(&(d, k.clone())).copied().unwrap_or(0.0), a_gross));
+            if now > cap.max_pct && now > was + 1e-9 {
+                match cap.mode {
+                    Mode::Reject => reasons.push(format!("Concentration max {} {:.2}% exceeded on {k}: {now:.2}% of the portfolio after the order, {was:.2}% before", d.label(), cap.max_pct)),
+                    Mode::Warn => flags.push(format!("Concentration warning: {} {k} at {now:.2}% of the portfolio after the order, over {:.2}%", d.label(), cap.max_pct)),
+                }
+            }
+            seen.push((d, k));
+        }
+    }
+    (reasons, flags)
+}
+
+/// Every bucket of the portfolio, largest share first within each dimension.
+pub fn report(limits: &ConcentrationLimits, account: &str, holdings: &BTreeMap<String, f64>, key: impl Fn(&str, Dimension) -> Option<String>) -> (Vec<Bucket>, f64, bool) {
+    let (b, gross) = buckets(holdings, key);
+    let exempt = gross < limits.min_portfolio_value;
+    let mut out: Vec<Bucket> = b.into_iter().map(|((dimension, name), value)| {
+        let cap = limits.cap(account, dimension);
+        let pct = pct(value, gross);
+        Bucket { dimension, name, value, pct, max_pct: cap.map(|c| c.max_pct), mode: cap.map(|c| c.mode), breached: !exempt && cap.is_some_and(|c| pct > c.max_pct) }
+    }).collect();
+    out.sort_by(|x, y| x.dimension.cmp(&y.dimension).then(y.pct.total_cmp(&x.pct)));
+    (out, gross, exempt)
+}
@@ -0,0 +1,84 @@
+use crate::calendar::CalendarConfig;
+use crate::circuit_breaker::BreakerConfig;
+use crate::concentration::ConcentrationLimits;
+use crate::erroneous::ErroneousConfig;
+use crate::fat_finger::FatFingerConfig;
+use crate::groups::GroupSpec;
+use crate::hedge::HedgePolicy;
+use crate::limits::Limit;
+use crate::margin_calls::Thresholds;
+use crate::scenarios::Scenario;
+use crate::schedule::GroupSchedule;
+use crate::wash::WashConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Bumped whenever a section changes shape; documents in any other format are refused.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The settings behind the admin config endpoints.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Settings {
+    pub fat_finger: FatFingerConfig, pub circuit_breaker: BreakerConfig, pub erroneous_orders: ErroneousConfig, pub event_windows: CalendarConfig, pub margin_call_thresholds: Thresholds,
+    #[serde(default)] pub concentration_limits: ConcentrationLimits, #[serde(default)] pub wash_trades: WashConfig,
+    #[serde(default)] pub hedging: HedgePolicy,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct NamedGroup { pub name: String, #[serde(flatten)] pub spec: GroupSpec }
+
+/// One environment's risk configuration as a single document, for promoting it to another. On import each section
+/// present replaces that section wholesale, so whatever it leaves out is removed; a section left out of the document
+/// is left as it is. Ids, names and versions travel with the document.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ConfigBundle {
+    pub format_version: u32, #[serde(default)] pub exported_at_ms: u64, #[serde(default, skip_serializing_if = "Option::is_none")] pub source: Option<String>,
+    pub limits: Option<Vec<Limit>>, pub schedules: Option<Vec<GroupSchedule>>, pub scenarios: Option<Vec<Scenario>>, pub instrument_groups: Option<Vec<NamedGroup>>,
+    pub settings: Option<Settings>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery { #[serde(default)] pub dry_run: bool }
+
+/// What importing a section does, by key. Versions and timestamps are not compared.
+#[derive(Serialize, Default)]
+pub struct SectionDiff { pub added: Vec<String>, pub changed: Vec<String>, pub removed: Vec<String>, pub unchanged: usize }
+
+#[derive(Serialize)]
+pub struct ImportReport { pub dry_run: bool, pub applied: bool, #[serde(skip_serializing_if = "Option::is_none")] pub source: Option<String>, pub sections: BTreeMap<&'static str, SectionDiff> }
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.fat_finger.validate().map_err(|e| format!("fat_finger: {e}"))?;
+        self.circuit_breaker.validate().map_err(|e| format!("circuit_breaker: {e}"))?;
+        self.erroneous_orders.validate().map_err(|e| format!("erroneous_orders: {e}"))?;
+        self.event_windows.validate().map_err(|e| format!("event_windows: {e}"))?;
+        self.margin_call_thresholds.validate().map_err(|e| format!("margin_call_thresholds: {e}"))?;
+        self.concentration_limits.validate().map_err(|e| format!("concentration_limits: {e}"))?;
+        self.wash_trades.validate().map_err(|e| format!("wash_trades: {e}"))?;
+        self.hedging.validate().map_err(|e| format!("hedging: {e}"))
+    }
+
+    /// Each setting by name, so settings diff like any other section.
+    pub fn entries(&self) -> Vec<(String, Value)> {
+        match serde_json::to_value(self) { Ok(Value::Object(m)) => m.into_iter().collect(), _ => Vec::new() }
+    }
+}
+
+fn stable(v: Value) -> Value {
+    match v {
+        Value::Object(mut m) => { for k in ["version", "created_at_ms", "updated_at_ms", "packaged"] { m.remove(k); } Value::Object(m) }
+        v => v,
+    }
+}
+
+pub fn diff<T: Serialize>(current: &[T], incoming: &[T], key: impl Fn(&T) -> String) -> SectionDiff {
+    let index = |items: &[T]| -> BTreeMap<String, Value> { items.iter().map(|i| (key(i), stable(serde_json::to_value(i).unwrap_or_default()))).collect() };
+    let (cur, inc) = (index(current), index(incoming));
+    let mut d = SectionDiff { removed: cur.keys().filter(|k| !inc.contains_key(*k)).cloned().collect(), ..Default::default() };
+    for (k, v) in inc {
+        match cur.get(&k) { None => d.added.push(k), Some(c) if *c != v => d.changed.push(k), Some(_) => d.unchanged += 1 }
+    }
+    d
+}
@@ -0,0 +1,56 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Dividend { pub symbol: String, pub ex_date: NaiveDate, pub amount: f64, pub pay_date: Option<NaiveDate> }
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action { Off, Flag, Block }
+
+/// How orders are treated when an ex-dividend date falls within `window_days` of today. Short stock is checked
+/// when the resulting short is at least `short_stock_min_notional`; short calls when the dividend exceeds the
+/// premium's time value, which makes early exercise likely.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct ExDatePolicy { pub window_days: u32, pub short_stock: Action, pub short_stock_min_notional: f64, pub short_calls: Action }
+
+impl Default for ExDatePolicy {
+    fn default() -> Self { Self { window_days: 2, short_stock: Action::Flag, short_stock_min_notional: 100_000.0, short_calls: Action::Flag } }
+}
+
+#[derive(Deserialize)]
+pub struct DividendQuery { pub symbol: Option<String>, pub from: Option<NaiveDate> }
+
+#[derive(Default, Clone)]
+pub struct CorporateActions { dividends: HashMap<String, Vec<Dividend>>, pub policy: ExDatePolicy }
+
+/// Prefix shared by every ex-dividend finding, so they can be told apart from other rejections.
+pub const REASON: &str = "Ex-dividend risk";
+
+impl CorporateActions {
+    /// Replaces any dividend already on file for the same symbol and ex-date.
+    pub fn load(&mut self, rows: Vec<Dividend>) -> usize {
+        let n = rows.len();
+        for d in rows {
+            let v = self.dividends.entry(d.symbol.clone()).or_default();
+            v.retain(|x| x.ex_date != d.ex_date);
+            v.push(d);
+            v.sort_by_key(|x| x.ex_date);
+        }
+        n
+    }
+
+    pub fn list(&self, q: &DividendQuery) -> Vec<Dividend> {
+        let mut v: Vec<Dividend> = self.dividends.iter().filter(|(s, _)| q.symbol.as_ref().is_none_or(|x| x == *s))
+            .flat_map(|(_, v)| v.iter().filter(|d| q.from.is_none_or(|f| d.ex_date >= f)).cloned()).collect();
+        v.sort_by(|a, b| (a.ex_date, &a.symbol).cmp(&(b.ex_date, &b.symbol)));
+        v
+    }
+
+    /// The next dividend on `symbol` going ex within the policy window from `today`.
+    pub fn upcoming(&self, symbol: &str, today: NaiveDate) -> Option<&Dividend> {
+        let until = today + chrono::Days::new(self.policy.window_days as u64);
+        self.dividends.get(symbol)?.iter().find(|d| d.ex_date >= today && d.ex_date <= until)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::simm::{Measure, RiskClass, Sensitivity};
+use serde::Serialize;
+
+#[derive(Serialize, Clone)]
+pub struct RowError { pub line: usize, pub error: String }
+
+#[derive(Serialize, Clone)]
+pub struct CrifUpload { pub uploaded_at_ms: u64, pub rows: usize, pub sensitivities: Vec<Sensitivity>, pub skipped: Vec<RowError> }
+
+/// Maps a CRIF RiskType onto the SIMM risk class and measure; base correlation and unknown types are skipped.
+fn risk_type(t: &str) -> Option<(RiskClass, Measure)> {
+    Some(match t {
+        "Risk_IRCurve" | "Risk_Inflation" | "Risk_XCcyBasis" => (RiskClass::InterestRate, Measure::Delta),
+        "Risk_IRVol" | "Risk_InflationVol" => (RiskClass::InterestRate, Measure::Vega),
+        "Risk_CreditQ" => (RiskClass::CreditQualifying, Measure::Delta),
+        "Risk_CreditVol" => (RiskClass::CreditQualifying, Measure::Vega),
+        "Risk_CreditNonQ" => (RiskClass::CreditNonQualifying, Measure::Delta),
+        "Risk_CreditVolNonQ" => (RiskClass::CreditNonQualifying, Measure::Vega),
+        "Risk_Equity" => (RiskClass::Equity, Measure::Delta),
+        "Risk_EquityVol" => (RiskClass::Equity, Measure::Vega),
+        "Risk_Commodity" => (RiskClass::Commodity, Measure::Delta),
+        "Risk_CommodityVol" => (RiskClass::Commodity, Measure::Vega),
+        "Risk_FX" => (RiskClass::Fx, Measure::Delta),
+        "Risk_FXVol" => (RiskClass::Fx, Measure::Vega),
+        _ => return None,
+    })
+}
+
+/// Vega rows also feed curvature; CRIF carries no curvature, so this stands in for SIMM's expiry scaling function.
+const CURVATURE_SCALING: f64 = 0.5;
+
+/// Parses a tab- or comma-separated CRIF file with a header row. Prefers AmountUSD over Amount.
+pub fn parse(text: &str, now_ms: u64) -> Result<CrifUpload, String> {
+    let mut lines = text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let (_, header) = lines.next().ok_or("empty CRIF file")?;
+    let delim = if header.contains('\t') { '\t' } else { ',' };
+    let cols: Vec<String> = header.split(delim).map(|c| c.trim().to_ascii_lowercase()).collect();
+    let col = |name: &str| cols.iter().position(|c| c == name);
+    let risk_col = col("risktype").ok_or("missing RiskType column")?;
+    let amount_col = col("amountusd").or_else(|| col("amount")).ok_or("missing Amount/AmountUSD column")?;
+    let (qual_col, bucket_col, label1_col) = (col("qualifier"), col("bucket"), col("label1"));
+    let (mut sensitivities, mut skipped, mut rows) = (Vec::new(), Vec::new(), 0);
+    for (i, line) in lines {
+        rows += 1;
+        let f: Vec<&str> = line.split(delim).map(str::trim).collect();
+        let get = |c: Option<usize>| c.and_then(|c| f.get(c)).copied().unwrap_or("");
+        let Some((risk_class, measure)) = risk_type(get(Some(risk_col))) else { skipped.push(RowError { line: i + 1, error: format!("unsupported RiskType '{}'", get(Some(risk_col))) }); continue };
+        let Ok(amount) = get(Some(amount_col)).parse::<f64>() else { skipped.push(RowError { line: i + 1, error: "amount is not a number".into() }); continue };
+        let qualifier = get(qual_col);
+        // Rates and FX are bucketed by currency; the CRIF Bucket column is only populated for the other classes.
+        let bucket = match risk_class { RiskClass::InterestRate | RiskClass::Fx => qualifier, _ => get(bucket_col) };
+        let factor = [qualifier, get(label1_col)].iter().filter(|x| !x.is_empty()).copied().collect::<Vec<_>>().join(":");
+        sensitivities.push(Sensitivity { risk_class, measure, bucket: bucket.into(), qualifier: factor.clone(), amount });
+        if measure == Measure::Vega { sensitivities.push(Sensitivity { risk_class, measure: Measure::Curvature, bucket: bucket.into(), qualifier: factor, amount: amount * CURVATURE_SCALING }); }
+    }
+    Ok(CrifUpload { uploaded_at_ms: now_ms, rows, sensitivities, skipped })
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const HOUR_MS: u64 = 3_600_000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+
+#[derive(Deserialize, Serialize)]
+pub struct DailyLimitUpdate { pub account: Option<String>, pub instrument: Option<String>, pub limit: Option<f64> }
+
+impl DailyLimitUpdate {
+    /// A limit of `None` clears it; one that is set must be a positive notional.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.limit.is_some_and(|l| !(l.is_finite() && l > 0.0)) { return Err("limit must be a positive notional".into()); }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct Headroom { pub account_remaining: Option<f64>, pub instrument_remaining: Option<f64> }
+
+#[derive(Serialize)]
+pub struct DailySnapshot {
+    pub session: u64, pub rollover_utc_hour: u64, pub account_limits: HashMap<String, f64>, pub instrument_limits: HashMap<String, f64>,
+    pub account_used: HashMap<String, f64>, pub instrument_used: HashMap<String, f64>, pub account_reserved: HashMap<String, f64>, pub instrument_reserved: HashMap<String, f64>,
+}
+
+/// Traded-notional allowances that reset when the trading session rolls over. Reservations held by in-flight
+/// orders count against headroom until committed or released and survive a rollover.
+pub struct DailyBook {
+    rollover_utc_hour: u64,
+    session: u64,
+    account_limits: HashMap<String, f64>,
+    instrument_limits: HashMap<String, f64>,
+    account_used: HashMap<String, f64>,
+    instrument_used: HashMap<String, f64>,
+    account_reserved: HashMap<String, f64>,
+    instrument_reserved: HashMap<String, f64>,
+}
+
+impl DailyBook {
+    pub fn new(rollover_utc_hour: u64) -> Self {
+        Self { rollover_utc_hour: rollover_utc_hour % 24, session: 0, account_limits: HashMap::new(), instrument_limits: HashMap::new(), account_used: HashMap::new(), instrument_used: HashMap::new(), account_reserved: HashMap::new(), instrument_reserved: HashMap::new() }
+    }
+
+    /// Sessions start at the rollover hour, so anything after it counts towards the next trade date.
+    pub fn session_of(&self, now_ms: u64) -> u64 { (now_ms + (24 - self.rollover_utc_hour) * HOUR_MS) / DAY_MS }
+
+    /// When the session after the one `now_ms` falls in opens.
+    pub fn next_session_start(&self, now_ms: u64) -> u64 { (self.session_of(now_ms) + 1) * DAY_MS - (24 - self.rollover_utc_hour) * HOUR_MS }
+
+    fn roll(&mut self, now_ms: u64) {
+        let session = self.session_of(now_ms);
+        if session != self.session { self.session = session; self.account_used.clear(); self.instrument_used.clear(); }
+    }
+
+    pub fn update(&mut self, u: DailyLimitUpdate) {
+        let apply = |m: &mut HashMap<String, f64>, k: Option<String>| if let Some(k) = k { match u.limit { Some(l) => { m.insert(k, l); } None => { m.remove(&k); } } };
+        apply(&mut self.account_limits, u.account.clone());
+        apply(&mut self.instrument_limits, u.instrument.clone());
+    }
+
+    pub fn headroom(&mut self, account: &str, instrument: &str, now_ms: u64) -> Headroom {
+        self.roll(now_ms);
+        let get = |m: &HashMap<String, f64>, k: &str| m.get(k).copied().unwrap_or(0.0);
+        let rem = |l: &HashMap<String, f64>, u: &HashMap<String, f64>, r: &HashMap<String, f64>, k: &str| l.get(k).map(|l| (l - get(u, k) - get(r, k)).max(0.0));
+        Headroom {
+            account_remaining: rem(&self.account_limits, &self.account_used, &self.account_reserved, account),
+            instrument_remaining: rem(&self.instrument_limits, &self.instrument_used, &self.instrument_reserved, instrument),
+        }
+    }
+
+    /// Account usage counts `account_notional`; each instrument counts its own leg notional, so a netted package
+    /// uses less account allowance than the sum of its legs.
+    pub fn evaluate(&mut self, account: &str, account_notional: f64, legs: &[(&str, f64)], now_ms: u64) -> Vec<String> {
+        let mut r = Vec::new();
+        let Some((first, _)) = legs.first() else { return r };
+        if let Some(x) = self.headroom(account, first, now_ms).account_remaining.filter(|x| account_notional.abs() > *x) { r.push(format!("Daily account notional limit exceeded: {x:.2} remaining")); }
+        for (instrument, notional) in legs {
+            if let Some(x) = self.headroom(account, instrument, now_ms).instrument_remaining.filter(|x| notional.abs() > *x) { r.push(format!("Daily instrument notional limit exceeded: {x:.2} remaining")); }
+        }
+        r
+    }
+
+    pub fn record(&mut self, account: &str, account_notional: f64, legs: &[(&str, f64)], now_ms: u64) {
+        self.roll(now_ms);
+        *self.account_used.entry(account.into()).or_default() += account_notional.abs();
+        for (instrument, notional) in legs { *self.instrument_used.entry((*instrument).into()).or_default() += notional.abs(); }
+    }
+
+    pub fn reserve(&mut self, account: &str, account_notional: f64, legs: &[(&str, f64)]) {
+        *self.account_reserved.entry(account.into()).or_default() += account_notional.abs();
+        for (instrument, notional) in legs { *self.instrument_reserved.entry((*instrument).into()).or_default() += notional.abs(); }
+    }
+
+    pub fn release(&mut self, account: &str, account_notional: f64, legs: &[(&str, f64)]) {
+        let take = |m: &mut HashMap<String, f64>, k: &str, x: f64| if let Some(r) = m.get_mut(k) { *r -= x; if *r <= 1e-9 { m.remove(k); } };
+        take(&mut self.account_reserved, account, account_notional.abs());
+        for (instrument, notional) in legs { take(&mut self.instrument_reserved, instrument, notional.abs()); }
+    }
+
+    pub fn snapshot(&mut self, now_ms: u64) -> DailySnapshot {
+        self.roll(now_ms);
+        DailySnapshot {
+            session: self.session, rollover_utc_hour: self.rollover_utc_hour, account_limits: self.account_limits.clone(), instrument_limits: self.instrument_limits.clone(), account_used: self.account_used.clone(), instrument_used: self.instrument_used.clone(),
+            account_reserved: self.account_reserved.clone(), instrument_reserved: self.instrument_reserved.clone(),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use axum::http::HeaderMap;
+
+/// The moment, in epoch milliseconds, after which the client no longer wants an answer: `x-request-deadline` in
+/// epoch milliseconds, `x-request-timeout-ms` counted from when the check arrived, or gRPC's `grpc-timeout`. The
+/// earliest wins when several are sent.
+pub fn parse(h: &HeaderMap, now_ms: u64) -> Result<Option<u64>, String> {
+    let header = |name: &str| h.get(name).map(|v| v.to_str().map(str::trim).map_err(|_| format!("{name} is not valid text")));
+    let mut out: Option<u64> = None;
+    let mut earliest = |d: u64| out = Some(out.map_or(d, |o| o.min(d)));
+    if let Some(v) = header("x-request-deadline").transpose()? { earliest(v.parse().map_err(|_| "x-request-deadline must be epoch milliseconds".to_string())?); }
+    if let Some(v) = header("x-request-timeout-ms").transpose()? { earliest(now_ms.saturating_add(v.parse().map_err(|_| "x-request-timeout-ms must be a whole number of milliseconds".to_string())?)); }
+    if let Some(v) = header("grpc-timeout").transpose()? { earliest(now_ms.saturating_add(grpc_timeout_ms(v).ok_or("grpc-timeout is malformed")?)); }
+    Ok(out)
+}
+
+/// Up to eight digits and a unit, rounded up to whole milliseconds.
+fn grpc_timeout_ms(v: &str) -> Option<u64> {
+    let (digits, unit) = v.split_at(v.len().checked_sub(1)?);
+    if digits.is_empty() || digits.len() > 8 { return None; }
+    let n: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "H" => n * 3_600_000, "M" => n * 60_000, "S" => n * 1_000, "m" => n,
+        "u" => n.div_ceil(1_000), "n" => n.div_ceil(1_000_000),
+        _ => return None,
+    })
+}
+
+/// The refusal of a check whose deadline had passed, or was too close to meet, before it was evaluated.
+pub fn reason(deadline_ms: u64, now_ms: u64) -> String {
+    match now_ms.checked_sub(deadline_ms) {
+        Some(late) => format!("Deadline exceeded: the client's deadline passed {late}ms before the check started"),
+        None => format!("Deadline exceeded: {}ms left of the client's deadline is too little to run the check", deadline_ms - now_ms),
+    }
+}
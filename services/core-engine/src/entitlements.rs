@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const VIOLATION: &str = "ENTITLEMENT_VIOLATION";
+
+/// An empty list in `allow` leaves that dimension unrestricted; any match in `deny` rejects.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Rules { #[serde(default)] pub symbols: Vec<String>, #[serde(default)] pub asset_classes: Vec<String>, #[serde(default)] pub venues: Vec<String> }
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Entitlements { #[serde(default)] pub allow: Rules, #[serde(default)] pub deny: Rules }
+
+pub struct OrderScope<'a> { pub symbol: &'a str, pub asset_class: Option<&'a str>, pub venue: Option<&'a str> }
+
+#[derive(Default)]
+pub struct EntitlementBook { accounts: HashMap<String, Entitlements> }
+
+impl EntitlementBook {
+    pub fn get(&self, account: &str) -> Entitlements { self.accounts.get(account).cloned().unwrap_or_default() }
+    pub fn set(&mut self, account: &str, e: Entitlements) { self.accounts.insert(account.into(), e); }
+    pub fn remove(&mut self, account: &str) -> bool { self.accounts.remove(account).is_some() }
+
+    pub fn evaluate(&self, account: &str, o: &OrderScope) -> Vec<String> {
+        let Some(e) = self.accounts.get(account) else { return Vec::new() };
+        let dims = [("symbol", Some(o.symbol), &e.allow.symbols, &e.deny.symbols), ("asset class", o.asset_class, &e.allow.asset_classes, &e.deny.asset_classes), ("venue", o.venue, &e.allow.venues, &e.deny.venues)];
+        dims.into_iter().filter_map(|(dim, v, allow, deny)| {
+            let has = |l: &Vec<String>| v.is_some_and(|v| l.iter().any(|x| x.eq_ignore_ascii_case(v)));
+            if has(deny) { Some(format!("{VIOLATION}: {dim} {} is blacklisted for {account}", v.unwrap_or_default())) }
+            else if !allow.is_empty() && !has(allow) { Some(format!("{VIOLATION}: {dim} {} is not whitelisted for {account}", v.unwrap_or("(unspecified)"))) }
+            else { None }
+        }).collect()
+    }
+}
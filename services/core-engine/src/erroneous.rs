@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Reference prices up to `up_to` (inclusive) allow moves of `band_pct`; the last tier has no upper bound.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Tier { pub up_to: Option<f64>, pub band_pct: f64 }
+
+/// Orders priced outside the band around the median of the last `prints` trade prints are clearly erroneous.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct ErroneousConfig { pub prints: usize, pub tiers: Vec<Tier> }
+
+impl Default for ErroneousConfig {
+    fn default() -> Self {
+        let tiers = [(Some(25.0), 10.0), (Some(50.0), 5.0), (None, 3.0)].into_iter().map(|(up_to, band_pct)| Tier { up_to, band_pct }).collect();
+        Self { prints: 5, tiers }
+    }
+}
+
+impl ErroneousConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.prints == 0 { return Err("prints must be at least 1".into()); }
+        if self.tiers.last().is_none_or(|t| t.up_to.is_some()) { return Err("the last tier must have no up_to".into()); }
+        for (i, t) in self.tiers.iter().enumerate() {
+            if !(t.band_pct.is_finite() && t.band_pct > 0.0) { return Err(format!("tier {} band_pct must be positive", i + 1)); }
+            if i + 1 < self.tiers.len() && t.up_to.is_none_or(|u| !(u.is_finite() && u > 0.0)) { return Err(format!("tier {} needs a positive up_to", i + 1)); }
+            if let (Some(p), Some(u)) = (i.checked_sub(1).and_then(|j| self.tiers[j].up_to), t.up_to) {
+                if u <= p { return Err(format!("tier {} up_to must be above tier {}", i + 1, i)); }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn band_pct(&self, reference: f64) -> f64 {
+        self.tiers.iter().find(|t| t.up_to.is_none_or(|u| reference <= u)).map_or(0.0, |t| t.band_pct)
+    }
+}
+
+#[derive(Serialize)]
+pub struct Finding { pub reference_price: f64, pub prints: usize, pub deviation_pct: f64, pub band_pct: f64 }
+
+impl Finding {
+    pub fn erroneous(&self) -> bool { self.deviation_pct.abs() > self.band_pct }
+}
+
+/// The most recent trade prints per instrument, as many as the config looks back over.
+#[derive(Default)]
+pub struct ErroneousOrders { pub config: ErroneousConfig, prints: HashMap<String, VecDeque<f64>> }
+
+impl ErroneousOrders {
+    pub fn observe(&mut self, instrument: &str, price: f64) {
+        let q = self.prints.entry(instrument.into()).or_default();
+        q.push_back(price);
+        while q.len() > self.config.prints { q.pop_front(); }
+    }
+
+    /// Where `price` sits against the recent prints, or `None` when the instrument has not printed.
+    pub fn assess(&self, instrument: &str, price: f64) -> Option<Finding> {
+        let q = self.prints.get(instrument)?;
+        let mut last: Vec<f64> = q.iter().rev().take(self.config.prints).copied().collect();
+        if last.is_empty() { return None; }
+        last.sort_by(f64::total_cmp);
+        let n = last.len();
+        let reference = if n % 2 == 1 { last[n / 2] } else { (last[n / 2 - 1] + last[n / 2]) / 2.0 };
+        Some(Finding { reference_price: reference, prints: n, deviation_pct: (price / reference - 1.0) * 100.0, band_pct: self.config.band_pct(reference) })
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+
+pub type BusFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// What the engine publishes; each kind goes to its own topic.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind { Decision, Margin, Alert, CircuitBreaker }
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self { Kind::Decision => "decision", Kind::Margin => "margin", Kind::Alert => "alert", Kind::CircuitBreaker => "circuit_breaker" }
+    }
+}
+
+/// `key` is the account or instrument the event is about, so a partitioned topic keeps each one's events in order.
+pub struct Event { pub kind: Kind, pub key: Option<String>, pub at_ms: u64, pub payload: serde_json::Value }
+
+/// An encoded event addressed to a topic.
+pub struct Message { pub topic: String, pub key: Option<String>, pub value: Vec<u8>, pub headers: Vec<(&'static str, Vec<u8>)>, pub at_ms: u64 }
+
+/// Somewhere to send events. Batches arrive from one publisher, in order.
+pub trait EventBus: Send + Sync {
+    fn publish<'a>(&'a self, batch: &'a [Message]) -> BusFuture<'a, ()>;
+}
+
+/// `Avro` values use Avro single-object encoding of [`AVRO_SCHEMA`], the payload carried as its JSON text.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Format { Json, Avro }
+
+#[derive(Serialize, Clone)]
+pub struct Topics { pub decisions: String, pub margin: String, pub alerts: String, pub circuit_breaker: String }
+
+/// Read from `RISK_KAFKA_*`. `acks` is 0, 1 or -1 (all in-sync replicas).
+#[derive(Serialize, Clone)]
+pub struct BusConfig { pub brokers: Vec<String>, pub client_id: String, pub acks: i16, pub timeout_ms: u64, pub format: Format, pub topics: Topics }
+
+/// Events published, and dropped with a batch the bus refused, since the engine started.
+#[derive(Serialize, Clone, Default)]
+pub struct BusStats { pub published: u64, pub failed: u64, #[serde(skip_serializing_if = "Option::is_none")] pub last_error: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub last_published_ms: Option<u64> }
+
+#[derive(Serialize)]
+pub struct BusStatus { pub enabled: bool, #[serde(skip_serializing_if = "Option::is_none")] pub config: Option<BusConfig>, pub stats: BusStats, #[serde(skip_serializing_if = "Option::is_none")] pub avro_schema: Option<&'static str> }
+
+/// The envelope every Avro value is written in, in Parsing Canonical Form so its fingerprint is the one registries compute.
+pub const AVRO_SCHEMA: &str = r#"{"name":"alice.risk.RiskEvent","type":"record","fields":[{"name":"type","type":"string"},{"name":"key","type":["null","string"]},{"name":"at_ms","type":"long"},{"name":"payload","type":"string"}]}"#;
+
+fn env(name: &str, default: &str) -> String { std::env::var(name).ok().filter(|v| !v.trim().is_empty()).unwrap_or_else(|| default.into()) }
+
+impl BusConfig {
+    /// None without `RISK_KAFKA_BROKERS`. The engine refuses to start on settings it cannot read rather than drop
+    /// events it was asked to publish.
+    pub fn from_env() -> Option<Self> {
+        let brokers: Vec<String> = std::env::var("RISK_KAFKA_BROKERS").ok()?.split(',').map(str::trim).filter(|b| !b.is_empty()).map(Into::into).collect();
+        if brokers.is_empty() { return None; }
+        if let Some(b) = brokers.iter().find(|b| b.rsplit_once(':').is_none_or(|(h, p)| h.is_empty() || p.parse::<u16>().is_err())) { panic!("RISK_KAFKA_BROKERS: {b} is not host:port"); }
+        let acks = match env("RISK_KAFKA_ACKS", "1").as_str() { "0" => 0, "1" => 1, "all" | "-1" => -1, v => panic!("RISK_KAFKA_ACKS: {v} is not 0, 1 or all") };
+        let format = match env("RISK_KAFKA_FORMAT", "json").as_str() { "json" => Format::Json, "avro" => Format::Avro, v => panic!("RISK_KAFKA_FORMAT: {v} is not json or avro") };
+        let timeout_ms = env("RISK_KAFKA_TIMEOUT_MS", "5000").parse().ok().filter(|t| *t > 0).unwrap_or_else(|| panic!("RISK_KAFKA_TIMEOUT_MS must be a positive number of milliseconds"));
+        let topics = Topics {
+            decisions: env("RISK_KAFKA_TOPIC_DECISIONS", "risk.decisions"), margin: env("RISK_KAFKA_TOPIC_MARGIN", "risk.margin"),
+            alerts: env("RISK_KAFKA_TOPIC_ALERTS", "risk.alerts"), circuit_breaker: env("RISK_KAFKA_TOPIC_CIRCUIT_BREAKER", "risk.circuit-breaker"),
+        };
+        Some(Self { brokers, client_id: env("RISK_KAFKA_CLIENT_ID", "risk-engine"), acks, timeout_ms, format, topics })
+    }
+
+    fn topic(&self, kind: Kind) -> &str {
+        match kind { Kind::Decision => &self.topics.decisions, Kind::Margin => &self.topics.margin, Kind::Alert => &self.topics.alerts, Kind::CircuitBreaker => &self.topics.circuit_breaker }
+    }
+
+    pub fn message(&self, e: Event) -> Message {
+        let (value, content_type): (Vec<u8>, &[u8]) = match self.format {
+            Format::Json => (serde_json::to_vec(&serde_json::json!({ "type": e.kind, "key": e.key, "at_ms": e.at_ms, "payload": e.payload })).unwrap_or_default(), b"application/json"),
+            Format::Avro => (avro(&e), b"application/vnd.apache.avro+binary"),
+        };
+        let headers = vec![("event-type", e.kind.label().as_bytes().to_vec()), ("content-type", content_type.to_vec())];
+        Message { topic: self.topic(e.kind).into(), key: e.key, value, headers, at_ms: e.at_ms }
+    }
+}
+
+fn zigzag(out: &mut Vec<u8>, n: i64) {
+    let mut v = ((n << 1) ^ (n >> 63)) as u64;
+    while v >= 0x80 { out.push(v as u8 | 0x80); v >>= 7; }
+    out.push(v as u8);
+}
+
+fn avro_string(out: &mut Vec<u8>, s: &str) {
+    zigzag(out, s.len() as i64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// The CRC-64-AVRO fingerprint of a schema's canonical form.
+fn fingerprint(schema: &str) -> u64 {
+    const EMPTY: u64 = 0xc15d_213a_a4d7_a795;
+    let table: Vec<u64> = (0..256u64).map(|i| (0..8).fold(i, |fp, _| (fp >> 1) ^ (EMPTY & 0u64.wrapping_sub(fp & 1)))).collect();
+    schema.bytes().fold(EMPTY, |fp, b| (fp >> 8) ^ table[((fp ^ b as u64) & 0xff) as usize])
+}
+
+fn avro(e: &Event) -> Vec<u8> {
+    let mut out = vec![0xc3, 0x01];
+    out.extend_from_slice(&fingerprint(AVRO_SCHEMA).to_le_bytes());
+    avro_string(&mut out, e.kind.label());
+    match &e.key { Some(k) => { zigzag(&mut out, 1); avro_string(&mut out, k); } None => zigzag(&mut out, 0) }
+    zigzag(&mut out, e.at_ms as i64);
+    avro_string(&mut out, &e.payload.to_string());
+    out
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct FactorCorrelation { pub a: String, pub b: String, pub correlation: f64 }
+
+/// Daily factor vols, their correlations (zero unless listed) and each instrument's loadings on them.
+/// Whatever part of an instrument's own daily vol the factors do not explain is treated as specific risk.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct FactorModel { pub factors: BTreeMap<String, f64>, #[serde(default)] pub correlations: Vec<FactorCorrelation>, #[serde(default)] pub loadings: BTreeMap<String, BTreeMap<String, f64>> }
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Method { #[default] Loadings, Pca }
+
+#[derive(Deserialize)]
+pub struct FactorQuery { #[serde(default)] pub method: Method, pub components: Option<usize>, pub from: Option<chrono::NaiveDate>, pub to: Option<chrono::NaiveDate> }
+
+/// `vol_contribution` is the factor's Euler share of portfolio daily vol: its share of variance times `total_vol`.
+#[derive(Serialize)]
+pub struct FactorContribution { pub factor: String, pub exposure: f64, pub vol_contribution: f64, pub pct_of_variance: f64 }
+
+#[derive(Serialize)]
+pub struct FactorReport {
+    pub account: String, pub method: Method, pub total_vol: f64, pub systematic_vol: f64, pub specific_vol: f64, pub specific_pct_of_variance: f64,
+    pub factors: Vec<FactorContribution>, #[serde(skip_serializing_if = "Vec::is_empty")] pub unmodelled: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub loadings: Option<BTreeMap<String, BTreeMap<String, f64>>>,
+}
+
+impl Default for FactorModel {
+    fn default() -> Self {
+        let factors = [("market", 0.01), ("size", 0.005), ("value", 0.005), ("momentum", 0.006), ("rates", 0.004)].into_iter().map(|(f, v)| (f.to_string(), v)).collect();
+        Self { factors, correlations: Vec::new(), loadings: BTreeMap::new() }
+    }
+}
+
+impl FactorModel {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some((f, _)) = self.factors.iter().find(|(_, v)| v.is_nan() || **v < 0.0) { return Err(format!("vol of factor {f} must be non-negative")); }
+        for c in &self.correlations {
+            if !crate::correlation::valid(c.correlation) { return Err(format!("correlation {}/{} must be within [-1, 1]", c.a, c.b)); }
+            if let Some(f) = [&c.a, &c.b].into_iter().find(|f| !self.factors.contains_key(*f)) { return Err(format!("correlation names unknown factor {f}")); }
+        }
+        if let Some((i, f)) = self.loadings.iter().find_map(|(i, l)| l.keys().find(|f| !self.factors.contains_key(*f)).map(|f| (i, f))) { return Err(format!("{i} loads on unknown factor {f}")); }
+        Ok(())
+    }
+
+    fn covariance(&self, names: &[&String]) -> Vec<Vec<f64>> {
+        let rho = |a: &str, b: &str| if a == b { 1.0 } else { self.correlations.iter().find(|c| (c.a == a && c.b == b) || (c.a == b && c.b == a)).map_or(0.0, |c| c.correlation) };
+        names.iter().map(|a| names.iter().map(|b| self.factors[*a] * self.factors[*b] * rho(a, b)).collect()).collect()
+    }
+
+    /// Decomposes `(instrument, quantity, price)` holdings. `vol` is each instrument's own daily vol.
+    pub fn decompose(&self, account: &str, positions: &[(String, f64, f64)], vol: impl Fn(&str) -> f64) -> FactorReport {
+        let names: Vec<&String> = self.factors.keys().collect();
+        let cov = self.covariance(&names);
+        let (mut exposure, mut specific_var, mut unmodelled) = (vec![0.0; names.len()], 0.0, Vec::new());
+        for (instrument, q, price) in positions {
+            let w = q * price;
+            let b: Vec<f64> = match self.loadings.get(instrument) {
+                Some(l) => names.iter().map(|f| l.get(*f).copied().unwrap_or(0.0)).collect(),
+                None => { unmodelled.push(instrument.clone()); vec![0.0; names.len()] }
+            };
+            let systematic: f64 = (0..names.len()).map(|i| (0..names.len()).map(|j| b[i] * cov[i][j] * b[j]).sum::<f64>()).sum();
+            specific_var += w * w * (vol(instrument).powi(2) - systematic).max(0.0);
+            for (e, b) in exposure.iter_mut().zip(&b) { *e += w * b; }
+        }
+        let marginal: Vec<f64> = cov.iter().map(|row| row.iter().zip(&exposure).map(|(c, e)| c * e).sum()).collect();
+        let parts: Vec<(String, f64, f64)> = names.iter().zip(&exposure).zip(&marginal).map(|((f, e), m)| ((*f).clone(), *e, e * m)).collect();
+        build(account, Method::Loadings, parts, specific_var, unmodelled, None)
+    }
+}
+
+fn build(account: &str, method: Method, parts: Vec<(String, f64, f64)>, specific_var: f64, unmodelled: Vec<String>, loadings: Option<BTreeMap<String, BTreeMap<String, f64>>>) -> FactorReport {
+    let systematic_var: f64 = parts.iter().map(|p| p.2).fold(0.0, |a, b| a + b).max(0.0);
+    let total_var = systematic_var + specific_var;
+    let total_vol = total_var.sqrt();
+    let share = |v: f64| if total_var > 0.0 { v / total_var } else { 0.0 };
+    let factors = parts.into_iter().map(|(factor, exposure, var)| FactorContribution { factor, exposure, vol_contribution: share(var) * total_vol, pct_of_variance: share(var) * 100.0 }).collect();
+    FactorReport {
+        account: account.into(), method, total_vol, systematic_vol: systematic_var.sqrt(), specific_vol: specific_var.sqrt(), specific_pct_of_variance: share(specific_var) * 100.0,
+        factors, unmodelled, loadings,
+    }
+}
+
+/// Eigenvalues and eigenvectors (as columns) of a symmetric matrix by cyclic Jacobi rotations.
+fn jacobi(mut a: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    for _ in 0..100 {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j))).map(|(i, j)| a[i][j] * a[i][j]).sum();
+        if off < 1e-30 { break; }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-300 { continue; }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() { let (x, y) = (row[p], row[q]); row[p] = c * x - s * y; row[q] = s * x + c * y; }
+                let (lo, hi) = a.split_at_mut(q);
+                for (x, y) in lo[p].iter_mut().zip(hi[0].iter_mut()) { (*x, *y) = (c * *x - s * *y, s * *x + c * *y); }
+                for row in v.iter_mut() { let (x, y) = (row[p], row[q]); row[p] = c * x - s * y; row[q] = s * x + c * y; }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i][i]).collect(), v)
+}
+
+/// Statistical factors: the leading principal components of the holdings' daily returns over the dates every
+/// holding printed. `history(instrument)` gives dated closes; components are signed so their largest loading is
+/// positive.
+pub fn pca(account: &str, positions: &[(String, f64, f64)], history: impl Fn(&str) -> Vec<(chrono::NaiveDate, f64)>, components: usize) -> Result<FactorReport, String> {
+    if positions.is_empty() { return Err("account has no priced positions".into()); }
+    let series: Vec<BTreeMap<chrono::NaiveDate, f64>> = positions.iter().map(|(i, _, _)| history(i).into_iter().collect()).collect();
+    let dates: Vec<chrono::NaiveDate> = series[0].keys().filter(|d| series.iter().all(|s| s.contains_key(d))).copied().collect();
+    if dates.len() < 3 { return Err("need at least three dates of common price history".into()); }
+    let returns: Vec<Vec<f64>> = series.iter().map(|s| dates.windows(2).map(|w| s[&w[1]] / s[&w[0]] - 1.0).collect()).collect();
+    let (n, t) = (positions.len(), dates.len() - 1);
+    let means: Vec<f64> = returns.iter().map(|r| r.iter().sum::<f64>() / t as f64).collect();
+    let cov: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| (0..t).map(|k| (returns[i][k] - means[i]) * (returns[j][k] - means[j])).sum::<f64>() / (t - 1) as f64).collect()).collect();
+    let w: Vec<f64> = positions.iter().map(|(_, q, p)| q * p).collect();
+    let total_var: f64 = (0..n).map(|i| (0..n).map(|j| w[i] * cov[i][j] * w[j]).sum::<f64>()).sum();
+    let (values, vectors) = jacobi(cov);
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|a, b| values[*b].total_cmp(&values[*a]));
+    let (mut parts, mut loadings) = (Vec::new(), BTreeMap::new());
+    for (k, &c) in order.iter().take(components.clamp(1, n)).enumerate() {
+        let col: Vec<f64> = (0..n).map(|i| vectors[i][c]).collect();
+        let sign = if col.iter().copied().fold(0.0, |m: f64, x| if x.abs() > m.abs() { x } else { m }) < 0.0 { -1.0 } else { 1.0 };
+        let name = format!("pc{}", k + 1);
+        let exposure: f64 = col.iter().zip(&w).map(|(v, w)| sign * v * w).sum();
+        loadings.insert(name.clone(), positions.iter().zip(&col).map(|((i, _, _), v)| (i.clone(), sign * v)).collect());
+        parts.push((name, exposure, exposure * exposure * values[c].max(0.0)));
+    }
+    let systematic: f64 = parts.iter().map(|p| p.2).sum();
+    Ok(build(account, Method::Pca, parts, (total_var - systematic).max(0.0), Vec::new(), Some(loadings)))
+}
@@ -0,0 +1,50 @@
+use crate::corporate_actions::Action;
+use crate::marketdata::Quote;
+use serde::{Deserialize, Serialize};
+
+/// The price an order is measured against. `Mid` falls back to the last trade when either side is unquoted.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Reference { Last, Mid }
+
+impl Reference {
+    pub fn name(self) -> &'static str { match self { Reference::Last => "last", Reference::Mid => "mid" } }
+}
+
+/// Orders priced more than `warn_pct` from the reference are flagged and more than `reject_pct` refused; either
+/// may be unset. Quotes older than `max_quote_age_ms` are too stale to judge against and the check is skipped.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct FatFingerConfig { pub reference: Reference, pub warn_pct: Option<f64>, pub reject_pct: Option<f64>, pub max_quote_age_ms: Option<u64> }
+
+impl Default for FatFingerConfig {
+    fn default() -> Self { Self { reference: Reference::Mid, warn_pct: Some(5.0), reject_pct: Some(10.0), max_quote_age_ms: None } }
+}
+
+#[derive(Serialize)]
+pub struct Finding { pub reference: Reference, pub reference_price: f64, pub deviation_pct: f64, pub action: Action }
+
+impl FatFingerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, v) in [("warn_pct", self.warn_pct), ("reject_pct", self.reject_pct)] {
+            if v.is_some_and(|v| !(v.is_finite() && v > 0.0)) { return Err(format!("{name} must be positive")); }
+        }
+        if let (Some(w), Some(r)) = (self.warn_pct, self.reject_pct) {
+            if w > r { return Err("warn_pct must not exceed reject_pct".into()); }
+        }
+        if self.max_quote_age_ms == Some(0) { return Err("max_quote_age_ms must be positive".into()); }
+        Ok(())
+    }
+
+    /// Where `price` sits against the quote, or `None` without a usable quote.
+    pub fn assess(&self, quote: Option<Quote>, price: f64, now_ms: u64) -> Option<Finding> {
+        let q = quote.filter(|q| self.max_quote_age_ms.is_none_or(|m| now_ms.saturating_sub(q.at_ms) <= m))?;
+        let (reference, reference_price) = match (self.reference, q.mid) {
+            (Reference::Mid, Some(m)) => (Reference::Mid, m),
+            _ => (Reference::Last, q.price),
+        };
+        if !(reference_price.is_finite() && reference_price > 0.0) { return None; }
+        let deviation_pct = (price / reference_price - 1.0) * 100.0;
+        let action = if self.reject_pct.is_some_and(|r| deviation_pct.abs() > r) { Action::Block } else if self.warn_pct.is_some_and(|w| deviation_pct.abs() > w) { Action::Flag } else { Action::Off };
+        Some(Finding { reference, reference_price, deviation_pct, action })
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Request paths and the stores they read. Storage errors apply to stores, market-data gaps to `market_data`.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Target { Pretrade, Margin, Stress, Accounts, Positions, Reservations, MarketData }
+
+impl Target {
+    pub fn name(self) -> &'static str {
+        match self { Target::Pretrade => "pretrade", Target::Margin => "margin", Target::Stress => "stress", Target::Accounts => "accounts", Target::Positions => "positions", Target::Reservations => "reservations", Target::MarketData => "market_data" }
+    }
+}
+
+/// A gap with no `instruments` hides every quote.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FaultKind { Latency { ms: u64 }, StorageError, MarketDataGap { #[serde(default)] instruments: Vec<String> } }
+
+#[derive(Deserialize)]
+pub struct FaultSpec { pub target: Target, #[serde(flatten)] pub kind: FaultKind, pub duration_secs: Option<u64> }
+
+#[derive(Serialize, Clone)]
+pub struct Fault { pub id: String, pub target: Target, #[serde(flatten)] pub kind: FaultKind, pub created_at_ms: u64, pub expires_at_ms: Option<u64> }
+
+/// Test-only: with `enabled` off nothing can be injected and [`Faults::probe`] is a no-op.
+pub struct Faults { pub enabled: bool, active: Vec<Fault> }
+
+/// What a request path should suffer: a delay to sleep off and failures to fail safe on.
+#[derive(Default)]
+pub struct Probe { pub delay_ms: u64, pub failures: Vec<String> }
+
+impl Faults {
+    pub fn new(enabled: bool) -> Self { Self { enabled, active: Vec::new() } }
+
+    pub fn list(&mut self, now_ms: u64) -> Vec<Fault> { self.expire(now_ms); self.active.clone() }
+
+    pub fn inject(&mut self, spec: FaultSpec, now_ms: u64) -> Result<Fault, String> {
+        let store = matches!(spec.target, Target::Accounts | Target::Positions | Target::Reservations);
+        match spec.kind {
+            FaultKind::StorageError if !store => return Err("storage errors target accounts, positions or reservations".into()),
+            FaultKind::MarketDataGap { .. } if spec.target != Target::MarketData => return Err("market-data gaps target market_data".into()),
+            _ => {}
+        }
+        let f = Fault { id: uuid::Uuid::new_v4().to_string(), target: spec.target, kind: spec.kind, created_at_ms: now_ms, expires_at_ms: spec.duration_secs.map(|d| now_ms + d * 1000) };
+        self.active.push(f.clone());
+        Ok(f)
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool { let n = self.active.len(); self.active.retain(|f| f.id != id); n != self.active.len() }
+    pub fn clear(&mut self) -> usize { std::mem::take(&mut self.active).len() }
+
+    fn expire(&mut self, now_ms: u64) { self.active.retain(|f| f.expires_at_ms.is_none_or(|e| e > now_ms)); }
+
+    /// Faults hitting a request on any of `targets` that prices `instruments`.
+    pub fn probe(&mut self, targets: &[Target], instruments: &[&str], now_ms: u64) -> Probe {
+        if !self.enabled { return Probe::default(); }
+        self.expire(now_ms);
+        let mut p = Probe::default();
+        for f in &self.active {
+            match &f.kind {
+                FaultKind::Latency { ms } if targets.contains(&f.target) => p.delay_ms += ms,
+                FaultKind::StorageError if targets.contains(&f.target) => p.failures.push(format!("Risk data unavailable: {} store error (injected)", f.target.name())),
+                FaultKind::MarketDataGap { instruments: gap } => {
+                    for i in instruments.iter().filter(|i| gap.is_empty() || gap.iter().any(|g| g == *i)) { p.failures.push(format!("Market data unavailable for {i} (injected)")); }
+                }
+                _ => {}
+            }
+        }
+        p
+    }
+}
@@ -0,0 +1,81 @@
+use crate::marketdata::Tick;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use tokio_tungstenite::tungstenite::Message;
+
+type Socket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Somewhere ticks come from outside the engine. The engine connects, reads until the feed fails, then reconnects.
+pub trait MarketDataSource: Send {
+    /// Opens the feed and subscribes to what it is configured for.
+    fn connect(&mut self) -> impl Future<Output = Result<(), String>> + Send;
+    /// The ticks in the next message; heartbeats and other messages carry none. An error ends the connection.
+    fn next(&mut self) -> impl Future<Output = Result<Vec<Tick>, String>> + Send;
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedKind { Websocket }
+
+/// `subscribe` is sent as a text message on every connect, before any ticks are read.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FeedConfig { pub name: String, pub kind: FeedKind, pub url: String, pub subscribe: Option<serde_json::Value> }
+
+impl FeedConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() { return Err("feed name is required".into()); }
+        if !(self.url.starts_with("ws://") || self.url.starts_with("wss://")) { return Err(format!("feed {}: url must be ws:// or wss://", self.name)); }
+        Ok(())
+    }
+}
+
+/// How one feed is doing. `ignored` counts messages that held no ticks, such as heartbeats.
+#[derive(Serialize, Clone)]
+pub struct FeedStatus {
+    pub name: String, pub kind: FeedKind, pub url: String, pub connected: bool, pub connects: u64, pub messages: u64, pub ignored: u64, pub ticks: u64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub last_tick_at_ms: Option<u64>, #[serde(skip_serializing_if = "Option::is_none")] pub last_error: Option<String>,
+}
+
+impl FeedStatus {
+    pub fn new(c: &FeedConfig) -> Self {
+        Self { name: c.name.clone(), kind: c.kind, url: c.url.clone(), connected: false, connects: 0, messages: 0, ignored: 0, ticks: 0, last_tick_at_ms: None, last_error: None }
+    }
+}
+
+/// A feed sending JSON text messages holding one tick, an array of ticks or an object with a `ticks` array.
+pub struct WebSocketSource { url: String, subscribe: Option<String>, socket: Option<Socket> }
+
+impl WebSocketSource {
+    pub fn new(c: &FeedConfig) -> Self { Self { url: c.url.clone(), subscribe: c.subscribe.as_ref().map(|v| v.to_string()), socket: None } }
+}
+
+impl MarketDataSource for WebSocketSource {
+    async fn connect(&mut self) -> Result<(), String> {
+        self.socket = None;
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.url.as_str()).await.map_err(|e| format!("connect: {e}"))?;
+        if let Some(m) = &self.subscribe { socket.send(Message::Text(m.clone())).await.map_err(|e| format!("subscribe: {e}"))?; }
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Vec<Tick>, String> {
+        let socket = self.socket.as_mut().ok_or("not connected")?;
+        match socket.next().await {
+            Some(Ok(Message::Text(t))) => Ok(decode(&t)),
+            Some(Ok(Message::Binary(b))) => Ok(std::str::from_utf8(&b).map(decode).unwrap_or_default()),
+            Some(Ok(Message::Close(f))) => Err(format!("closed by feed{}", f.map(|f| format!(": {}", f.reason)).unwrap_or_default())),
+            Some(Ok(_)) => Ok(Vec::new()),
+            Some(Err(e)) => Err(e.to_string()),
+            None => Err("connection ended".into()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Payload { One(Tick), Many(Vec<Tick>), Wrapped { ticks: Vec<Tick> } }
+
+fn decode(text: &str) -> Vec<Tick> {
+    match serde_json::from_str(text) { Ok(Payload::One(t)) => vec![t], Ok(Payload::Many(v) | Payload::Wrapped { ticks: v }) => v, Err(_) => Vec::new() }
+}
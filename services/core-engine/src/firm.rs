@@ -0,0 +1,118 @@
+use crate::margin::MarginSnapshot;
+use crate::var::{self, HistoricalVar};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// `entity` narrows the summary to one tenant's accounts; `currency` is what it is reported in (default the firm's base currency);
+/// `top` bounds the concentration and stress lists.
+#[derive(Deserialize)]
+pub struct FirmQuery { pub entity: Option<String>, pub currency: Option<String>, pub top: Option<usize> }
+
+/// One account's share of the firm figures in its base currency: signed notional by instrument and the dated P&L
+/// its holdings would have made. Kept until the holdings, their prices or the price history change.
+pub struct Contribution { pub fingerprint: u64, pub exposures: Vec<(String, f64)>, pub pnl: BTreeMap<NaiveDate, f64>, pub missing_history: Vec<String> }
+
+/// Contributions by account, so a summary only revalues the accounts that moved since the last one.
+#[derive(Default)]
+pub struct Contributions { by_account: HashMap<String, Contribution> }
+
+impl Contributions {
+    pub fn is_current(&self, account: &str, fingerprint: u64) -> bool { self.by_account.get(account).is_some_and(|c| c.fingerprint == fingerprint) }
+    pub fn insert(&mut self, account: &str, c: Contribution) { self.by_account.insert(account.into(), c); }
+    pub fn get(&self, account: &str) -> Option<&Contribution> { self.by_account.get(account) }
+}
+
+#[derive(Serialize, Default)]
+pub struct Exposure { pub gross: f64, pub net: f64, pub long: f64, pub short: f64 }
+
+/// Net notional held in one instrument across the accounts summarized; `pct_of_gross` is its absolute share of firm gross.
+#[derive(Serialize)]
+pub struct Concentration { pub instrument: String, pub net_notional: f64, pub gross_notional: f64, pub pct_of_gross: f64, pub accounts: usize }
+
+/// Accounts linked as one party, with what they hold between them; `pct_of_gross` is the group's share of firm gross.
+#[derive(Serialize)]
+pub struct RelatedGroup { pub accounts: Vec<String>, pub gross_notional: f64, pub net_notional: f64, pub pct_of_gross: f64 }
+
+/// Margin snapshots summed over the accounts that have one; `accounts` counts them.
+#[derive(Serialize, Default)]
+pub struct MarginTotals { pub accounts: usize, pub initial_margin: f64, pub maintenance_margin: f64, pub held_margin: f64, pub funds: f64, pub available_margin: f64, pub margin_utilization_pct: f64 }
+
+/// Firm VaR over the dates every contributing account has P&L for. `standalone_*` sums each account's own VaR on
+/// those dates; the diversification benefit is how much netting across accounts takes off that sum.
+#[derive(Serialize)]
+pub struct FirmVar { #[serde(flatten)] pub var: HistoricalVar, pub standalone_var_95: f64, pub standalone_var_99: f64, pub diversification_95: f64, pub diversification_99: f64 }
+
+/// The latest run of one stress scenario, its P&L summed over the accounts summarized.
+#[derive(Serialize)]
+pub struct StressResult { pub scenario: String, pub run_id: String, pub at_ms: u64, pub pnl: f64, pub accounts: usize }
+
+/// `unconverted` currencies had no rate into the reporting currency and their accounts are left out of the totals;
+/// `recomputed_accounts` is how many account contributions this summary had to rebuild.
+#[derive(Serialize)]
+pub struct FirmSummary {
+    pub entity: Option<String>, pub currency: String, pub accounts: usize, pub exposure: Exposure, pub margin: MarginTotals, pub var: FirmVar,
+    pub top_concentrations: Vec<Concentration>, #[serde(skip_serializing_if = "Vec::is_empty")] pub related_groups: Vec<RelatedGroup>, pub worst_stress: Vec<StressResult>, #[serde(skip_serializing_if = "Vec::is_empty")] pub unconverted: Vec<String>,
+    pub recomputed_accounts: usize, pub as_of_ms: u64,
+}
+
+/// Adds `m`, converted at `rate`, to the totals.
+pub fn add_margin(t: &mut MarginTotals, m: &MarginSnapshot, rate: f64) {
+    t.accounts += 1;
+    t.initial_margin += m.initial_margin * rate;
+    t.maintenance_margin += m.maintenance_margin * rate;
+    t.held_margin += m.held_margin * rate;
+    t.funds += m.funds * rate;
+    t.available_margin += m.available_margin * rate;
+    t.margin_utilization_pct = if t.funds > 0.0 { (t.initial_margin + t.held_margin) / t.funds * 100.0 } else { 0.0 };
+}
+
+/// Exposure, the `top` largest concentrations and firm VaR from contributions paired with their rate into the
+/// reporting currency.
+pub fn aggregate(parts: &[(&Contribution, f64)], top: usize, lookback: usize) -> (Exposure, Vec<Concentration>, FirmVar) {
+    let mut e = Exposure::default();
+    let mut by_instrument: BTreeMap<&str, (f64, f64, usize)> = BTreeMap::new();
+    for (c, rate) in parts {
+        for (i, n) in &c.exposures {
+            let n = n * rate;
+            (e.gross, e.net) = (e.gross + n.abs(), e.net + n);
+            if n > 0.0 { e.long += n } else { e.short -= n }
+            let x = by_instrument.entry(i).or_default();
+            (x.0, x.1, x.2) = (x.0 + n, x.1 + n.abs(), x.2 + 1);
+        }
+    }
+    let mut top_concentrations: Vec<Concentration> = by_instrument.into_iter().map(|(i, (net, gross, accounts))| Concentration {
+        instrument: i.into(), net_notional: net, gross_notional: gross, pct_of_gross: if e.gross > 0.0 { net.abs() / e.gross * 100.0 } else { 0.0 }, accounts,
+    }).collect();
+    top_concentrations.sort_by(|a, b| b.net_notional.abs().total_cmp(&a.net_notional.abs()).then_with(|| a.instrument.cmp(&b.instrument)));
+    top_concentrations.truncate(top);
+    (e, top_concentrations, firm_var(parts, lookback))
+}
+
+/// A related group's accounts and their contributions, each paired with its rate into the reporting currency.
+pub type Members<'a> = (Vec<String>, Vec<(&'a Contribution, f64)>);
+
+/// The `top` related groups by gross notional.
+pub fn related_groups(groups: Vec<Members>, firm_gross: f64, top: usize) -> Vec<RelatedGroup> {
+    let mut out: Vec<RelatedGroup> = groups.into_iter().map(|(accounts, parts)| {
+        let (gross, net) = parts.iter().flat_map(|(c, r)| c.exposures.iter().map(move |(_, n)| n * r)).fold((0.0, 0.0), |(g, n), x| (g + x.abs(), n + x));
+        RelatedGroup { accounts, gross_notional: gross, net_notional: net, pct_of_gross: if firm_gross > 0.0 { gross / firm_gross * 100.0 } else { 0.0 } }
+    }).collect();
+    out.sort_by(|a, b| b.gross_notional.total_cmp(&a.gross_notional).then_with(|| a.accounts.cmp(&b.accounts)));
+    out.truncate(top);
+    out
+}
+
+fn firm_var(parts: &[(&Contribution, f64)], lookback: usize) -> FirmVar {
+    let books: Vec<&(&Contribution, f64)> = parts.iter().filter(|(c, _)| !c.pnl.is_empty()).collect();
+    let mut dates: Vec<NaiveDate> = books.first().map_or_else(Vec::new, |(c, _)| c.pnl.keys().filter(|d| books.iter().all(|(b, _)| b.pnl.contains_key(d))).copied().collect());
+    dates.drain(..dates.len().saturating_sub(lookback));
+    let window = dates.first().zip(dates.last()).map(|(a, b)| (*a, *b));
+    let series = |c: &Contribution, rate: f64| dates.iter().map(|d| c.pnl[d] * rate).collect::<Vec<f64>>();
+    let standalone: Vec<HistoricalVar> = books.iter().map(|(c, r)| var::summarize(lookback, series(c, *r), window, Vec::new())).collect();
+    let total: Vec<f64> = (0..dates.len()).map(|k| books.iter().map(|(c, r)| c.pnl[&dates[k]] * r).sum()).collect();
+    let missing: BTreeSet<String> = parts.iter().flat_map(|(c, _)| c.missing_history.iter().cloned()).collect();
+    let v = var::summarize(lookback, total, window, missing.into_iter().collect());
+    let (standalone_var_95, standalone_var_99) = (standalone.iter().map(|s| s.var_95).sum::<f64>(), standalone.iter().map(|s| s.var_99).sum::<f64>());
+    FirmVar { diversification_95: standalone_var_95 - v.var_95, diversification_99: standalone_var_99 - v.var_99, standalone_var_95, standalone_var_99, var: v }
+}
@@ -0,0 +1,178 @@
+use crate::positions::{Fill, Side};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const SOH: u8 = 0x01;
+const BEGIN_STRING: &str = "FIX.4.4";
+/// Larger than any execution report; a claimed body longer than this is garbage, not a message to wait for.
+const MAX_BODY: usize = 64 * 1024;
+
+/// One tag=value message, fields in wire order.
+pub struct Message { fields: Vec<(u32, String)> }
+
+impl Message {
+    pub fn get(&self, tag: u32) -> Option<&str> { self.fields.iter().find(|f| f.0 == tag).map(|f| f.1.as_str()) }
+    pub fn msg_type(&self) -> &str { self.get(35).unwrap_or_default() }
+    fn seq(&self) -> Option<u64> { self.get(34)?.parse().ok() }
+}
+
+fn checksum(bytes: &[u8]) -> u8 { bytes.iter().fold(0u8, |a, b| a.wrapping_add(*b)) }
+
+/// Splits a byte stream into messages by BodyLength, checking each CheckSum.
+#[derive(Default)]
+pub struct Decoder { buf: Vec<u8> }
+
+impl Decoder {
+    pub fn push(&mut self, bytes: &[u8]) { self.buf.extend_from_slice(bytes); }
+
+    /// The next whole message, or `None` until more bytes arrive. A garbled message comes back as an error and is
+    /// skipped, with the stream picking up at the next BeginString.
+    pub fn next(&mut self) -> Option<Result<Message, String>> {
+        let start = b"8=FIX";
+        match self.buf.windows(start.len()).position(|w| w == start) {
+            Some(0) => {}
+            Some(i) => { self.buf.drain(..i); return Some(Err(format!("skipped {i} bytes before BeginString"))); }
+            None => { let keep = self.buf.len().min(start.len() - 1); self.buf.drain(..self.buf.len() - keep); return None; }
+        }
+        let end_of = |from: usize| self.buf[from..].iter().position(|b| *b == SOH).map(|p| from + p);
+        let begin_end = end_of(0)?;
+        let len_end = end_of(begin_end + 1)?;
+        let body_len = std::str::from_utf8(&self.buf[begin_end + 1..len_end]).ok().and_then(|f| f.strip_prefix("9=")).and_then(|n| n.parse::<usize>().ok());
+        let Some(body_len) = body_len.filter(|n| *n <= MAX_BODY) else { self.buf.drain(..len_end + 1); return Some(Err("missing or oversized BodyLength".into())) };
+        let body_end = len_end + 1 + body_len;
+        let total = body_end + 7;
+        if self.buf.len() < total { return None; }
+        let raw: Vec<u8> = self.buf.drain(..total).collect();
+        let trailer = std::str::from_utf8(&raw[body_end..]).ok().and_then(|t| t.strip_prefix("10=")).and_then(|t| t.strip_suffix('\u{1}')).and_then(|t| t.parse::<u8>().ok());
+        if trailer != Some(checksum(&raw[..body_end])) { return Some(Err("bad CheckSum".into())); }
+        let text = String::from_utf8_lossy(&raw[..body_end]);
+        let fields = text.split('\u{1}').filter(|f| !f.is_empty()).map(|f| {
+            let (t, v) = f.split_once('=').ok_or_else(|| format!("field '{f}' has no '='"))?;
+            Ok((t.parse().map_err(|_| format!("bad tag '{t}'"))?, v.to_string()))
+        }).collect::<Result<_, String>>();
+        Some(fields.map(|fields| Message { fields }))
+    }
+}
+
+/// What an execution report does to positions. A correction reverses the fill it names and books itself in its place.
+pub enum Execution { Fill(Fill), Cancel { exec_id: String, cancels: String }, Correct { fill: Fill, corrects: String }, Ignored }
+
+fn side(code: &str) -> Result<Side, String> {
+    match code { "1" => Ok(Side::Buy), "2" => Ok(Side::Sell), "5" | "6" => Ok(Side::SellShort), s => Err(format!("unsupported Side {s}")) }
+}
+
+/// Reads an ExecutionReport (35=8). Trades are ExecType F; G corrects and H cancels the trade named in ExecRefID.
+/// Order-status reports (new, replaced, cancelled and so on) move no position and are ignored.
+pub fn execution(m: &Message) -> Result<Execution, String> {
+    let field = |tag: u32, name: &str| m.get(tag).filter(|v| !v.is_empty()).ok_or_else(|| format!("ExecutionReport without {name} ({tag})"));
+    let exec_type = field(150, "ExecType")?;
+    let exec_id = field(17, "ExecID")?.to_string();
+    let fill = || -> Result<Fill, String> {
+        let number = |tag: u32, name: &str| field(tag, name)?.parse::<f64>().ok().filter(|x| x.is_finite() && *x > 0.0).ok_or_else(|| format!("{name} ({tag}) must be a positive number"));
+        Ok(Fill { fill_id: Some(exec_id.clone()), account: field(1, "Account")?.into(), instrument: field(55, "Symbol")?.into(), side: side(field(54, "Side")?)?, quantity: number(32, "LastQty")?, price: number(31, "LastPx")? })
+    };
+    match exec_type {
+        "F" => Ok(Execution::Fill(fill()?)),
+        "G" => Ok(Execution::Correct { corrects: field(19, "ExecRefID")?.into(), fill: fill()? }),
+        "H" => Ok(Execution::Cancel { cancels: field(19, "ExecRefID")?.into(), exec_id }),
+        _ => Ok(Execution::Ignored),
+    }
+}
+
+/// The counterparty side of one connection: sequence numbers and replies to session messages.
+pub struct Session { comp_id: String, pub counterparty: Option<String>, out_seq: u64, in_seq: u64, pub heartbeat_secs: u64, pub gaps: u64 }
+
+/// What the connection should do with one inbound message.
+#[derive(Default)]
+pub struct Step { pub replies: Vec<Vec<u8>>, pub application: bool, pub close: bool, pub error: Option<String> }
+
+impl Session {
+    pub fn new(comp_id: &str) -> Self { Self { comp_id: comp_id.into(), counterparty: None, out_seq: 0, in_seq: 0, heartbeat_secs: 30, gaps: 0 } }
+
+    pub fn logged_on(&self) -> bool { self.counterparty.is_some() }
+    pub fn in_seq(&self) -> u64 { self.in_seq }
+
+    pub fn encode(&mut self, msg_type: &str, body: &[(u32, String)]) -> Vec<u8> {
+        self.out_seq += 1;
+        let sending_time = chrono::Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string();
+        let mut fields = vec![(35, msg_type.to_string()), (49, self.comp_id.clone()), (56, self.counterparty.clone().unwrap_or_default()), (34, self.out_seq.to_string()), (52, sending_time)];
+        fields.extend_from_slice(body);
+        let body: String = fields.iter().map(|(t, v)| format!("{t}={v}\u{1}")).collect();
+        let mut out = format!("8={BEGIN_STRING}\u{1}9={}\u{1}{body}", body.len()).into_bytes();
+        let sum = checksum(&out);
+        out.extend_from_slice(format!("10={sum:03}\u{1}").as_bytes());
+        out
+    }
+
+    pub fn heartbeat(&mut self) -> Vec<u8> { self.encode("0", &[]) }
+
+    fn logout(&mut self, text: String) -> Step {
+        Step { replies: vec![self.encode("5", &[(58, text.clone())])], close: true, error: Some(text), ..Default::default() }
+    }
+
+    /// Answers session messages. A gap in the inbound sequence asks for a resend; the resent reports arrive as
+    /// possible duplicates and are told apart by ExecID.
+    pub fn on_message(&mut self, m: &Message) -> Step {
+        let Some(seq) = m.seq() else { return self.logout("MsgSeqNum (34) missing".into()) };
+        if m.get(56) != Some(self.comp_id.as_str()) { return self.logout(format!("TargetCompID must be {}", self.comp_id)); }
+        if !self.logged_on() && m.msg_type() != "A" { return self.logout("first message must be Logon".into()); }
+        let mut step = Step::default();
+        if seq > self.in_seq + 1 && m.msg_type() != "4" {
+            self.gaps += seq - self.in_seq - 1;
+            step.replies.push(self.encode("2", &[(7, (self.in_seq + 1).to_string()), (16, "0".into())]));
+        }
+        self.in_seq = self.in_seq.max(seq);
+        match m.msg_type() {
+            "A" => {
+                let Some(sender) = m.get(49).filter(|s| !s.is_empty()) else { return self.logout("SenderCompID (49) missing".into()) };
+                self.counterparty = Some(sender.into());
+                self.heartbeat_secs = m.get(108).and_then(|h| h.parse().ok()).filter(|h| *h > 0).unwrap_or(30);
+                step.replies.push(self.encode("A", &[(98, "0".into()), (108, self.heartbeat_secs.to_string())]));
+            }
+            "1" => { let id = m.get(112).unwrap_or_default().to_string(); step.replies.push(self.encode("0", &[(112, id)])); }
+            // Nothing but session messages was sent, so a resend is answered with a gap fill up to the next number.
+            "2" => { let next = (self.out_seq + 2).to_string(); step.replies.push(self.encode("4", &[(123, "Y".into()), (36, next)])); }
+            "4" => { if let Some(n) = m.get(36).and_then(|n| n.parse::<u64>().ok()) { self.in_seq = n.saturating_sub(1); } }
+            "5" => { step.replies.push(self.encode("5", &[])); step.close = true; }
+            "0" | "3" => {}
+            _ => step.application = true,
+        }
+        step
+    }
+}
+
+/// How one counterparty's drop copy is doing, kept across its reconnects.
+#[derive(Serialize, Clone, Default)]
+pub struct SessionStatus {
+    pub counterparty: String, pub connected: bool, pub connects: u64, pub messages: u64, pub fills: u64, pub duplicates: u64, pub cancels: u64, pub corrections: u64,
+    pub ignored: u64, pub rejected: u64, pub gaps: u64, pub in_seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub last_fill_at_ms: Option<u64>, #[serde(skip_serializing_if = "Option::is_none")] pub last_error: Option<String>,
+}
+
+/// The drop-copy listener's state: session statuses, and the fills it booked so later cancels and corrections can
+/// reverse them.
+#[derive(Default)]
+pub struct DropCopy { pub comp_id: String, sessions: Vec<SessionStatus>, booked: HashMap<String, Fill> }
+
+impl DropCopy {
+    pub fn new(comp_id: &str) -> Self { Self { comp_id: comp_id.into(), ..Default::default() } }
+
+    pub fn sessions(&self) -> Vec<SessionStatus> { self.sessions.clone() }
+
+    pub fn status(&mut self, counterparty: &str) -> &mut SessionStatus {
+        let i = self.sessions.iter().position(|s| s.counterparty == counterparty).unwrap_or_else(|| {
+            self.sessions.push(SessionStatus { counterparty: counterparty.into(), ..Default::default() });
+            self.sessions.len() - 1
+        });
+        &mut self.sessions[i]
+    }
+
+    pub fn booked(&mut self, f: &Fill) { if let Some(id) = &f.fill_id { self.booked.insert(id.clone(), f.clone()); } }
+
+    /// The fill that undoes booked fill `exec_id`, under its own id `reversal_id`. Each fill is reversed once.
+    pub fn reversal(&mut self, exec_id: &str, reversal_id: String) -> Option<Fill> {
+        let f = self.booked.remove(exec_id)?;
+        let side = if f.side == Side::Buy { Side::Sell } else { Side::Buy };
+        Some(Fill { fill_id: Some(reversal_id), side, ..f })
+    }
+}
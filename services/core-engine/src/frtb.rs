@@ -0,0 +1,112 @@
+use crate::simm::Measure;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskClass { Girr, CsrNonSec, Equity, Commodity, Fx }
+
+/// Delta/vega amounts are sensitivities; curvature amounts are the worse of the up/down CVRs.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Sensitivity { pub risk_class: RiskClass, pub measure: Measure, pub bucket: String, pub factor: String, pub amount: f64 }
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Scenario { Low, Medium, High }
+
+#[derive(Serialize)]
+pub struct ClassCharge { pub risk_class: RiskClass, pub delta: f64, pub vega: f64, pub curvature: f64, pub total: f64 }
+
+#[derive(Serialize)]
+pub struct ScenarioCharge { pub scenario: Scenario, pub total: f64, pub by_risk_class: Vec<ClassCharge> }
+
+#[derive(Serialize)]
+pub struct FrtbResult { pub capital: f64, pub binding_scenario: Scenario, pub scenarios: Vec<ScenarioCharge> }
+
+struct Params { delta_rw: f64, vega_rw: f64, rho: f64, gamma: f64 }
+
+fn params(rc: RiskClass) -> Params {
+    match rc {
+        RiskClass::Girr => Params { delta_rw: 0.017, vega_rw: 0.55, rho: 0.96, gamma: 0.50 },
+        RiskClass::CsrNonSec => Params { delta_rw: 0.03, vega_rw: 0.55, rho: 0.65, gamma: 0.15 },
+        RiskClass::Equity => Params { delta_rw: 0.40, vega_rw: 0.7778, rho: 0.15, gamma: 0.15 },
+        RiskClass::Commodity => Params { delta_rw: 0.30, vega_rw: 1.0, rho: 0.55, gamma: 0.20 },
+        RiskClass::Fx => Params { delta_rw: 0.15, vega_rw: 1.0, rho: 1.0, gamma: 0.60 },
+    }
+}
+
+/// Correlations are stressed up and down around the prescribed (medium) values.
+fn scale(c: f64, sc: Scenario) -> f64 {
+    match sc { Scenario::Low => (2.0 * c - 1.0).max(0.75 * c), Scenario::Medium => c, Scenario::High => (1.25 * c).min(1.0) }
+}
+
+fn weighted(sens: &[&Sensitivity], rw: f64) -> BTreeMap<String, BTreeMap<String, f64>> {
+    let mut out: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    for s in sens { *out.entry(s.bucket.clone()).or_default().entry(s.factor.clone()).or_default() += s.amount * rw; }
+    out
+}
+
+/// Delta/vega aggregation. Falls back to the alternative S_b when the sum under the radical goes negative.
+fn aggregate(buckets: &BTreeMap<String, BTreeMap<String, f64>>, rho: f64, gamma: f64) -> f64 {
+    let ks: Vec<(f64, f64)> = buckets.values().map(|ws| {
+        let w: Vec<f64> = ws.values().copied().collect();
+        let mut k2 = 0.0;
+        for (i, a) in w.iter().enumerate() { for (j, b) in w.iter().enumerate() { k2 += if i == j { a * a } else { rho * a * b }; } }
+        let k = k2.max(0.0).sqrt();
+        (k, w.iter().sum())
+    }).collect();
+    let total = |alt: bool| {
+        let mut t = 0.0;
+        for (i, (ki, si)) in ks.iter().enumerate() {
+            for (j, (kj, sj)) in ks.iter().enumerate() {
+                let (si, sj) = if alt { (si.clamp(-ki, *ki), sj.clamp(-kj, *kj)) } else { (*si, *sj) };
+                t += if i == j { ki * ki } else { gamma * si * sj };
+            }
+        }
+        t
+    };
+    let t = total(false);
+    (if t < 0.0 { total(true) } else { t }).max(0.0).sqrt()
+}
+
+/// Curvature uses squared correlations and ignores cross terms where both sides are negative.
+fn curvature(buckets: &BTreeMap<String, BTreeMap<String, f64>>, rho: f64, gamma: f64) -> f64 {
+    let ks: Vec<(f64, f64)> = buckets.values().map(|cvr| {
+        let w: Vec<f64> = cvr.values().copied().collect();
+        let mut k2 = 0.0;
+        for (i, a) in w.iter().enumerate() {
+            for (j, b) in w.iter().enumerate() {
+                k2 += if i == j { a.max(0.0).powi(2) } else if *a < 0.0 && *b < 0.0 { 0.0 } else { rho * rho * a * b };
+            }
+        }
+        let k = k2.max(0.0).sqrt();
+        (k, w.iter().sum::<f64>().clamp(-k, k))
+    }).collect();
+    let mut t = 0.0;
+    for (i, (ki, si)) in ks.iter().enumerate() {
+        for (j, (_, sj)) in ks.iter().enumerate() {
+            t += if i == j { ki * ki } else if *si < 0.0 && *sj < 0.0 { 0.0 } else { gamma * gamma * si * sj };
+        }
+    }
+    t.max(0.0).sqrt()
+}
+
+pub fn compute(sens: &[Sensitivity]) -> FrtbResult {
+    let mut classes: Vec<RiskClass> = sens.iter().map(|s| s.risk_class).collect();
+    classes.sort();
+    classes.dedup();
+    let scenarios: Vec<ScenarioCharge> = [Scenario::Low, Scenario::Medium, Scenario::High].into_iter().map(|sc| {
+        let by_risk_class: Vec<ClassCharge> = classes.iter().map(|&rc| {
+            let p = params(rc);
+            let pick = |m: Measure| sens.iter().filter(|s| s.risk_class == rc && s.measure == m).collect::<Vec<_>>();
+            let (rho, gamma) = (scale(p.rho, sc), scale(p.gamma, sc));
+            let delta = aggregate(&weighted(&pick(Measure::Delta), p.delta_rw), rho, gamma);
+            let vega = aggregate(&weighted(&pick(Measure::Vega), p.vega_rw), rho, gamma);
+            let curv = curvature(&weighted(&pick(Measure::Curvature), 1.0), rho, gamma);
+            ClassCharge { risk_class: rc, delta, vega, curvature: curv, total: delta + vega + curv }
+        }).collect();
+        ScenarioCharge { scenario: sc, total: by_risk_class.iter().map(|c| c.total).sum(), by_risk_class }
+    }).collect();
+    let binding = scenarios.iter().max_by(|a, b| a.total.total_cmp(&b.total)).map(|s| (s.scenario, s.total)).unwrap_or((Scenario::Medium, 0.0));
+    FrtbResult { capital: binding.1, binding_scenario: binding.0, scenarios }
+}
@@ -0,0 +1,69 @@
+use crate::correlation::{Z_95, Z_99};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How a holding moves the currency book: an FX pair is long its base currency and short its quote currency by
+/// the traded amount; anything else priced in a foreign currency is long that currency by its value.
+pub enum Kind { Pair(String, String), Priced(String) }
+
+/// Signed amounts of each currency `quantity` at `price` leaves the holder with.
+pub fn amounts(kind: &Kind, quantity: f64, price: f64) -> Vec<(String, f64)> {
+    match kind {
+        Kind::Pair(base, quote) => vec![(base.clone(), quantity), (quote.clone(), -quantity * price)],
+        Kind::Priced(c) => vec![(c.clone(), quantity * price)],
+    }
+}
+
+/// Caps on the absolute net exposure to one currency, in USD. Per-account caps replace the firm-wide cap for
+/// that currency.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct FxLimits { #[serde(default)] pub currencies: BTreeMap<String, f64>, #[serde(default)] pub accounts: BTreeMap<String, BTreeMap<String, f64>> }
+
+impl FxLimits {
+    /// Currency codes are stored upper-case.
+    pub fn normalize(mut self) -> Result<Self, String> {
+        let upper = |m: BTreeMap<String, f64>| -> Result<BTreeMap<String, f64>, String> {
+            m.into_iter().map(|(c, v)| if v.is_finite() && v > 0.0 { Ok((c.to_ascii_uppercase(), v)) } else { Err(format!("limit for {c} must be positive")) }).collect()
+        };
+        self.currencies = upper(self.currencies)?;
+        self.accounts = self.accounts.into_iter().map(|(a, m)| Ok((a, upper(m)?))).collect::<Result<_, String>>()?;
+        Ok(self)
+    }
+
+    pub fn cap(&self, account: &str, currency: &str) -> Option<f64> {
+        self.accounts.get(account).and_then(|m| m.get(currency)).or_else(|| self.currencies.get(currency)).copied()
+    }
+}
+
+/// Net exposure to one foreign currency. `net_base` is valued in the account's base currency, `net_usd` in USD
+/// for the limit; either is unset when there is no rate to value it at. The component VaR shares sum to `fx_var_*`.
+#[derive(Serialize)]
+pub struct CurrencyExposure {
+    pub currency: String, pub net_local: f64, pub net_base: Option<f64>, pub net_usd: Option<f64>, pub daily_vol: f64, pub component_var_95: f64, pub component_var_99: f64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub limit_usd: Option<f64>, #[serde(skip_serializing_if = "Option::is_none")] pub utilization_pct: Option<f64>,
+}
+
+/// One-day parametric VaR of the account's currency exposures alone, each moving with its rate against the base
+/// currency. Currencies without a rate into the base currency are listed in `unconverted` and left out of the VaR.
+#[derive(Serialize)]
+pub struct FxExposureReport { pub account: String, pub base_currency: String, pub currencies: Vec<CurrencyExposure>, pub fx_var_95: f64, pub fx_var_99: f64, #[serde(skip_serializing_if = "Vec::is_empty")] pub unconverted: Vec<String> }
+
+/// One currency as the report is built from it: its net amount, the rates into base and USD where known, and
+/// its daily vol against the base currency.
+pub struct Line { pub currency: String, pub net_local: f64, pub to_base: Option<f64>, pub to_usd: Option<f64>, pub daily_vol: f64, pub limit_usd: Option<f64> }
+
+pub fn report(account: &str, base: &str, lines: Vec<Line>, corr: impl Fn(&str, &str) -> f64) -> FxExposureReport {
+    let w: Vec<f64> = lines.iter().map(|l| l.to_base.map_or(0.0, |r| l.net_local * r * l.daily_vol)).collect();
+    let cw: Vec<f64> = lines.iter().map(|a| lines.iter().zip(&w).map(|(b, y)| corr(&a.currency, &b.currency) * y).sum()).collect();
+    let sigma = w.iter().zip(&cw).map(|(x, y)| x * y).sum::<f64>().max(0.0).sqrt();
+    let unconverted = lines.iter().filter(|l| l.to_base.is_none()).map(|l| l.currency.clone()).collect();
+    let currencies = lines.into_iter().zip(w.iter().zip(&cw)).map(|(l, (x, y))| {
+        let component = if sigma > 0.0 { x * y / sigma } else { 0.0 };
+        let net_usd = l.to_usd.map(|r| l.net_local * r);
+        CurrencyExposure {
+            net_base: l.to_base.map(|r| l.net_local * r), utilization_pct: net_usd.zip(l.limit_usd).map(|(n, cap)| n.abs() / cap * 100.0),
+            currency: l.currency, net_local: l.net_local, net_usd, daily_vol: l.daily_vol, component_var_95: Z_95 * component, component_var_99: Z_99 * component, limit_usd: l.limit_usd,
+        }
+    }).collect();
+    FxExposureReport { account: account.into(), base_currency: base.into(), currencies, fx_var_95: Z_95 * sigma, fx_var_99: Z_99 * sigma, unconverted }
+}
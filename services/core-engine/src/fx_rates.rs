@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `Feed` rates are read off the last `CCYUSD` or `USDCCY` quote; `Manual` ones were set through the API.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Source { Manual, Feed }
+
+/// `usd` is the USD value of one unit of `currency`.
+#[derive(Deserialize)]
+pub struct RateInput { pub currency: String, pub usd: f64 }
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Rate { pub currency: String, pub usd: f64, pub source: Source, pub at_ms: u64 }
+
+/// Rates set by hand, for currencies the feed does not quote or to stand in while it is down. Whichever of a manual
+/// rate and the feed quote is more recent is the one used.
+#[derive(Default, Clone)]
+pub struct FxRates { manual: BTreeMap<String, Rate> }
+
+impl FxRates {
+    pub fn list(&self) -> Vec<Rate> { self.manual.values().cloned().collect() }
+    pub fn get(&self, currency: &str) -> Option<&Rate> { self.manual.get(currency) }
+
+    /// Sets every rate in `rates` or, if any is invalid, none of them. Returns the rates as stored.
+    pub fn set(&mut self, rates: Vec<RateInput>, now_ms: u64) -> Result<Vec<Rate>, String> {
+        let mut out = Vec::with_capacity(rates.len());
+        for r in rates {
+            let c = r.currency.to_ascii_uppercase();
+            if c.len() != 3 || !c.chars().all(|c| c.is_ascii_alphabetic()) { return Err(format!("{} is not an ISO 4217 code", r.currency)); }
+            if c == "USD" { return Err("USD is the pivot currency and has no rate".into()); }
+            if !(r.usd.is_finite() && r.usd > 0.0) { return Err(format!("rate for {c} must be positive")); }
+            out.push(Rate { currency: c, usd: r.usd, source: Source::Manual, at_ms: now_ms });
+        }
+        for r in &out { self.manual.insert(r.currency.clone(), r.clone()); }
+        Ok(out)
+    }
+
+    pub fn remove(&mut self, currency: &str) -> Option<Rate> { self.manual.remove(&currency.to_ascii_uppercase()) }
+}
+
+/// The fresher of a manual rate and the feed's; the feed wins a tie.
+pub fn latest(manual: Option<&Rate>, feed: Option<Rate>) -> Option<Rate> {
+    match (manual, feed) {
+        (Some(m), Some(f)) if m.at_ms > f.at_ms => Some(m.clone()),
+        (m, None) => m.cloned(),
+        (_, f) => f,
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Caps on same-day FX settlement exposure, in USD. `cutoffs` are each currency's payment cut-off in UTC (HH:MM);
+/// a trade is flagged once one cut-off window holding at least `min_window_usd` carries more than `max_window_share`
+/// of the counterparty's exposure for the day.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Caps {
+    pub default_counterparty_cap: Option<f64>, #[serde(default)] pub counterparty_caps: BTreeMap<String, f64>, pub pair_cap: Option<f64>,
+    pub max_window_share: f64, #[serde(default)] pub min_window_usd: f64, #[serde(default)] pub cutoffs: BTreeMap<String, String>,
+}
+
+/// One side of a booked FX trade settling on `value_date`. `exposure_usd` is what is paid away before the bought
+/// currency can arrive: the full payment when the sold currency's cut-off comes first, otherwise zero.
+#[derive(Serialize, Clone)]
+pub struct Settlement {
+    pub check_id: String, pub account: String, pub counterparty: String, pub pair: String, pub value_date: NaiveDate,
+    pub pay_currency: String, pub pay_amount: f64, pub receive_currency: String, pub receive_amount: f64, pub window: String, pub exposure_usd: f64,
+}
+
+#[derive(Deserialize)]
+pub struct ExposureQuery { pub counterparty: Option<String>, pub value_date: Option<NaiveDate> }
+
+#[derive(Serialize, Default)]
+pub struct CounterpartyExposure { pub total_usd: f64, pub by_pair: BTreeMap<String, f64>, pub by_window: BTreeMap<String, f64> }
+
+/// Key: counterparty, then value date.
+pub type ExposureReport = BTreeMap<String, BTreeMap<NaiveDate, CounterpartyExposure>>;
+
+/// A trade buying (`signed_quantity` > 0) or selling `signed_quantity` of base currency at `rate` quote per base.
+pub struct FxTrade<'a> { pub check_id: &'a str, pub account: &'a str, pub counterparty: &'a str, pub instrument: &'a str, pub signed_quantity: f64, pub rate: f64, pub value_date: NaiveDate }
+
+pub struct FxSettlementBook { pub caps: Caps, settlements: Vec<Settlement> }
+
+const DEFAULT_CUTOFF: &str = "12:00";
+
+/// Base and quote currency of `EURUSD` or `EUR/USD`.
+pub fn split_pair(instrument: &str) -> Option<(String, String)> {
+    let s: String = instrument.chars().filter(|c| c.is_ascii_alphabetic()).collect::<String>().to_ascii_uppercase();
+    (s.len() == 6).then(|| (s[..3].to_string(), s[3..].to_string()))
+}
+
+impl Caps {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.max_window_share > 0.0 && self.max_window_share <= 1.0) { return Err("max_window_share must be in (0, 1]".into()); }
+        if self.min_window_usd.is_nan() || self.min_window_usd < 0.0 { return Err("min_window_usd must be non-negative".into()); }
+        if let Some((c, t)) = self.cutoffs.iter().find(|(_, t)| NaiveTime::parse_from_str(t, "%H:%M").is_err()) { return Err(format!("cut-off for {c} must use HH:MM, got {t}")); }
+        Ok(())
+    }
+
+    pub fn cutoff(&self, currency: &str) -> &str { self.cutoffs.get(currency).map_or(DEFAULT_CUTOFF, |s| s.as_str()) }
+}
+
+impl FxSettlementBook {
+    pub fn new(max_window_share: f64, min_window_usd: f64) -> Self {
+        let cutoffs = [("NZD", "21:00"), ("AUD", "23:00"), ("JPY", "01:00"), ("HKD", "03:00"), ("SGD", "03:00"), ("EUR", "06:00"), ("CHF", "06:00"), ("GBP", "07:00"), ("USD", "16:00"), ("CAD", "16:00")]
+            .into_iter().map(|(c, t)| (c.to_string(), t.to_string())).collect();
+        Self { caps: Caps { default_counterparty_cap: None, counterparty_caps: BTreeMap::new(), pair_cap: None, max_window_share, min_window_usd, cutoffs }, settlements: Vec::new() }
+    }
+
+    /// Settlement of the trade. `usd` converts an amount of a currency into USD.
+    pub fn settlement(&self, t: &FxTrade, usd: impl Fn(&str, f64) -> Option<f64>) -> Result<Settlement, String> {
+        let (base, quote) = split_pair(t.instrument).ok_or_else(|| format!("{} is not a currency pair", t.instrument))?;
+        let pair = format!("{base}/{quote}");
+        let (base_amt, quote_amt) = (t.signed_quantity.abs(), t.signed_quantity.abs() * t.rate);
+        let ((pay, pay_amount), (receive, receive_amount)) = if t.signed_quantity > 0.0 { ((quote, quote_amt), (base, base_amt)) } else { ((base, base_amt), (quote, quote_amt)) };
+        let (pay_cut, recv_cut) = (self.caps.cutoff(&pay).to_string(), self.caps.cutoff(&receive).to_string());
+        let paid_first = NaiveTime::parse_from_str(&pay_cut, "%H:%M").ok() < NaiveTime::parse_from_str(&recv_cut, "%H:%M").ok();
+        let exposure_usd = if paid_first { usd(&pay, pay_amount).ok_or_else(|| format!("no USD rate for {pay}"))? } else { 0.0 };
+        Ok(Settlement {
+            check_id: t.check_id.into(), account: t.account.into(), counterparty: t.counterparty.into(), pair, value_date: t.value_date, window: format!("{pay} {pay_cut} UTC"),
+            pay_currency: pay, pay_amount, receive_currency: receive, receive_amount, exposure_usd,
+        })
+    }
+
+    /// Cap breaches (blocking) and window concentration flags (non-blocking) if `new` were booked.
+    pub fn evaluate(&self, new: &[Settlement]) -> (Vec<String>, Vec<String>) {
+        let (mut reasons, mut flags) = (Vec::new(), Vec::new());
+        let mut seen = std::collections::HashSet::new();
+        for n in new.iter().filter(|n| n.exposure_usd > 0.0) {
+            if !seen.insert((&n.counterparty, n.value_date)) { continue; }
+            let same_day: Vec<&Settlement> = self.settlements.iter().chain(new).filter(|x| x.counterparty == n.counterparty && x.value_date == n.value_date).collect();
+            let total: f64 = same_day.iter().map(|x| x.exposure_usd).sum();
+            if let Some(cap) = self.caps.counterparty_caps.get(&n.counterparty).copied().or(self.caps.default_counterparty_cap).filter(|c| total > *c) {
+                reasons.push(format!("FX settlement max counterparty exposure {cap:.2} exceeded: {total:.2} with {} on {}", n.counterparty, n.value_date));
+            }
+            let mut by_pair: HashMap<&str, f64> = HashMap::new();
+            let mut by_window: HashMap<&str, f64> = HashMap::new();
+            for x in &same_day {
+                *by_pair.entry(&x.pair).or_default() += x.exposure_usd;
+                *by_window.entry(&x.window).or_default() += x.exposure_usd;
+            }
+            if let Some(cap) = self.caps.pair_cap {
+                for (pair, v) in by_pair.iter().filter(|(p, v)| **v > cap && new.iter().any(|x| x.counterparty == n.counterparty && x.pair == **p)) {
+                    reasons.push(format!("FX settlement max pair exposure {cap:.2} exceeded: {v:.2} in {pair} with {}", n.counterparty));
+                }
+            }
+            for (w, v) in by_window.iter().filter(|(w, v)| **v >= self.caps.min_window_usd && **v / total > self.caps.max_window_share && new.iter().any(|x| x.window == **w)) {
+                flags.push(format!("FX settlement concentrated: {:.0}% of exposure to {} on {} pays at {w}", v / total * 100.0, n.counterparty, n.value_date));
+            }
+        }
+        (reasons, flags)
+    }
+
+    pub fn book(&mut self, s: Vec<Settlement>) { self.settlements.extend(s); }
+
+    /// Drops settlements whose value date has passed.
+    pub fn roll(&mut self, today: NaiveDate) { self.settlements.retain(|s| s.value_date >= today); }
+
+    pub fn exposure(&self, q: &ExposureQuery) -> ExposureReport {
+        let mut out = ExposureReport::new();
+        for x in self.settlements.iter().filter(|x| q.counterparty.as_ref().is_none_or(|c| &x.counterparty == c) && q.value_date.is_none_or(|d| x.value_date == d)) {
+            let e = out.entry(x.counterparty.clone()).or_default().entry(x.value_date).or_default();
+            e.total_usd += x.exposure_usd;
+            *e.by_pair.entry(x.pair.clone()).or_default() += x.exposure_usd;
+            *e.by_window.entry(x.window.clone()).or_default() += x.exposure_usd;
+        }
+        out
+    }
+}
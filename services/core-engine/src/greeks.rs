@@ -0,0 +1,113 @@
+use crate::scenarios::{FutureTerms, InstrumentKind, OptionRight, OptionTerms};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Caps on the absolute net Greeks of one underlier or of the whole account, in the units of [`Greeks`].
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct GreekCaps { pub net_delta: Option<f64>, pub net_gamma: Option<f64>, pub net_vega: Option<f64> }
+
+/// `underliers` overrides `default_underlier` for the names it lists.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct GreekLimits { #[serde(default)] pub portfolio: GreekCaps, #[serde(default)] pub default_underlier: GreekCaps, #[serde(default)] pub underliers: BTreeMap<String, GreekCaps> }
+
+/// Cash Greeks: `delta` is the value change per unit move in the underlier price times that price, `gamma` the change
+/// in cash delta for a 1% move and `vega` the value change for one volatility point. Stock carries delta only.
+#[derive(Serialize, Clone, Copy, Default, Debug)]
+pub struct Greeks { pub delta: f64, pub gamma: f64, pub vega: f64 }
+
+/// One holding's Greeks and the underlier they count towards.
+#[derive(Serialize)]
+pub struct PositionGreeks { pub instrument: String, pub kind: InstrumentKind, pub underlying: String, pub quantity: f64, #[serde(flatten)] pub greeks: Greeks }
+
+#[derive(Serialize)]
+pub struct GreekImpact { pub order: Greeks, pub before: Greeks, pub after: Greeks, pub by_underlier: BTreeMap<String, Greeks> }
+
+impl Greeks {
+    pub fn add(&mut self, o: &Greeks) { self.delta += o.delta; self.gamma += o.gamma; self.vega += o.vega; }
+}
+
+impl GreekLimits {
+    pub fn for_underlier(&self, u: &str) -> &GreekCaps { self.underliers.get(u).unwrap_or(&self.default_underlier) }
+}
+
+/// Abramowitz and Stegun 7.1.26; accurate to about 1e-7, which is ample for limit checks.
+fn norm_cdf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs() / std::f64::consts::SQRT_2);
+    let poly = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-(x * x) / 2.0).exp();
+    if x >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+fn norm_pdf(x: f64) -> f64 { (-(x * x) / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt() }
+
+/// Black-Scholes cash Greeks of `quantity` contracts at zero rates. Expired or zero-vol options are treated as
+/// their intrinsic delta.
+pub fn option(terms: &OptionTerms, quantity: f64, spot: f64, vol: f64, today: NaiveDate) -> Result<Greeks, String> {
+    let expiry = terms.expiry.ok_or_else(|| format!("option on {} has no expiry", terms.underlying))?;
+    let units = quantity * terms.multiplier.unwrap_or(1.0);
+    let years = (expiry - today).num_days() as f64 / 365.0;
+    let call = terms.right == OptionRight::Call;
+    if years <= 0.0 || vol <= 0.0 || spot <= 0.0 || terms.strike <= 0.0 {
+        let itm = if call { spot > terms.strike } else { spot < terms.strike };
+        let delta = if !itm { 0.0 } else if call { 1.0 } else { -1.0 };
+        return Ok(Greeks { delta: units * delta * spot, gamma: 0.0, vega: 0.0 });
+    }
+    let sd = vol * years.sqrt();
+    let d1 = ((spot / terms.strike).ln() + sd * sd / 2.0) / sd;
+    let delta = if call { norm_cdf(d1) } else { norm_cdf(d1) - 1.0 };
+    let gamma = norm_pdf(d1) / (spot * sd);
+    let vega = spot * norm_pdf(d1) * years.sqrt();
+    Ok(Greeks { delta: units * delta * spot, gamma: units * gamma * spot * spot / 100.0, vega: units * vega / 100.0 })
+}
+
+/// Cash delta of `quantity` futures contracts at zero rates, so a future moves one for one with its underlier.
+pub fn future(terms: &FutureTerms, quantity: f64, spot: f64) -> Greeks {
+    Greeks { delta: quantity * terms.multiplier.unwrap_or(1.0) * spot, ..Default::default() }
+}
+
+/// Black-Scholes value of one unit at zero rates; intrinsic value once expired or without vol.
+pub fn value(terms: &OptionTerms, spot: f64, vol: f64, today: NaiveDate) -> Option<f64> {
+    let years = (terms.expiry? - today).num_days() as f64 / 365.0;
+    let (k, call) = (terms.strike, terms.right == OptionRight::Call);
+    if years <= 0.0 || vol <= 0.0 || spot <= 0.0 || k <= 0.0 { return Some(if call { (spot - k).max(0.0) } else { (k - spot).max(0.0) }); }
+    let sd = vol * years.sqrt();
+    let d1 = ((spot / k).ln() + sd * sd / 2.0) / sd;
+    let d2 = d1 - sd;
+    Some(if call { spot * norm_cdf(d1) - k * norm_cdf(d2) } else { k * norm_cdf(-d2) - spot * norm_cdf(-d1) })
+}
+
+/// Net Greeks by underlier; `greeks(instrument, quantity)` resolves one holding to its underlier and Greeks.
+pub fn by_underlier(lines: &[(String, f64)], greeks: impl Fn(&str, f64) -> Result<(String, Greeks), String>) -> Result<BTreeMap<String, Greeks>, String> {
+    let mut out: BTreeMap<String, Greeks> = BTreeMap::new();
+    for (instrument, q) in lines.iter().filter(|(_, q)| *q != 0.0) {
+        let (u, g) = greeks(instrument, *q)?;
+        out.entry(u).or_default().add(&g);
+    }
+    Ok(out)
+}
+
+fn total(m: &BTreeMap<String, Greeks>) -> Greeks { m.values().fold(Greeks::default(), |mut t, g| { t.add(g); t }) }
+
+/// Caps broken by the order. A cap only blocks when the order leaves its Greek larger in absolute terms than
+/// before, so risk-reducing trades stay allowed on an account already over a limit.
+pub fn evaluate(limits: &GreekLimits, before: &BTreeMap<String, Greeks>, order: &BTreeMap<String, Greeks>) -> (GreekImpact, Vec<String>) {
+    let mut reasons = Vec::new();
+    // The underlier follows the cap so override keys stay the same for every name.
+    let check = |reasons: &mut Vec<String>, caps: &GreekCaps, b: &Greeks, a: &Greeks, scope: &str, on: &str| {
+        for (name, cap, b, a) in [("delta", caps.net_delta, b.delta, a.delta), ("gamma", caps.net_gamma, b.gamma, a.gamma), ("vega", caps.net_vega, b.vega, a.vega)] {
+            if let Some(cap) = cap.filter(|c| a.abs() > *c && a.abs() > b.abs() + 1e-9) { reasons.push(format!("{scope} max net {name} {cap:.2} exceeded{on}: {a:.2}")); }
+        }
+    };
+    for (u, g) in order {
+        let b = before.get(u).copied().unwrap_or_default();
+        let mut a = b;
+        a.add(g);
+        check(&mut reasons, limits.for_underlier(u), &b, &a, "Underlier", &format!(" on {u}"));
+    }
+    let (b, o) = (total(before), total(order));
+    let mut a = b;
+    a.add(&o);
+    check(&mut reasons, &limits.portfolio, &b, &a, "Portfolio", "");
+    (GreekImpact { order: o, before: b, after: a, by_underlier: order.clone() }, reasons)
+}
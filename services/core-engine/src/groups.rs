@@ -0,0 +1,67 @@
+use crate::circuit_breaker::{self, Level};
+use crate::fat_finger::FatFingerConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Settings shared by a tier of instruments. Members are priced against `collar` and halted on `breaker_levels`
+/// in place of the firm-wide ones; either left unset falls back to them. Limits scoped to the group bind orders in
+/// any member.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct GroupSpec {
+    pub description: Option<String>, #[serde(default)] pub members: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub collar: Option<FatFingerConfig>, #[serde(skip_serializing_if = "Option::is_none")] pub breaker_levels: Option<Vec<Level>>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Group { pub name: String, #[serde(flatten)] pub spec: GroupSpec, pub created_at_ms: u64, pub updated_at_ms: u64 }
+
+/// Groups by name. An instrument belongs to at most one, so what it inherits is never ambiguous.
+#[derive(Default, Clone)]
+pub struct Groups { groups: BTreeMap<String, Group>, by_member: HashMap<String, String> }
+
+impl Groups {
+    pub fn list(&self) -> Vec<Group> { self.groups.values().cloned().collect() }
+    pub fn get(&self, name: &str) -> Option<&Group> { self.groups.get(name) }
+    pub fn group_of(&self, instrument: &str) -> Option<&Group> { self.groups.get(self.by_member.get(instrument)?) }
+
+    /// Instrument to group for those of `instruments` that are in one.
+    pub fn membership<'a>(&self, instruments: impl IntoIterator<Item = &'a str>) -> HashMap<String, String> {
+        instruments.into_iter().filter_map(|i| Some((i.to_string(), self.by_member.get(i)?.clone()))).collect()
+    }
+
+    /// Creates or replaces the group, returning it with the definition it replaced.
+    pub fn set(&mut self, name: &str, spec: GroupSpec, now_ms: u64) -> Result<(Group, Option<Group>), String> {
+        if name.trim().is_empty() { return Err("group name is required".into()); }
+        if spec.members.iter().any(|m| m.trim().is_empty()) { return Err("members must not be empty".into()); }
+        if spec.members.iter().collect::<BTreeSet<_>>().len() != spec.members.len() { return Err("members must be distinct".into()); }
+        if let Some((m, g)) = spec.members.iter().find_map(|m| self.by_member.get(m).filter(|g| *g != name).map(|g| (m, g))) { return Err(format!("{m} is already in group {g}")); }
+        if let Some(c) = &spec.collar { c.validate().map_err(|e| format!("collar: {e}"))?; }
+        if let Some(l) = &spec.breaker_levels { circuit_breaker::validate_levels(l).map_err(|e| format!("breaker_levels: {e}"))?; }
+        let previous = self.remove(name);
+        for m in &spec.members { self.by_member.insert(m.clone(), name.into()); }
+        let g = Group { name: name.into(), spec, created_at_ms: previous.as_ref().map_or(now_ms, |p| p.created_at_ms), updated_at_ms: now_ms };
+        self.groups.insert(name.into(), g.clone());
+        Ok((g, previous))
+    }
+
+    /// Replaces every group with `groups`. A group whose definition is unchanged keeps its timestamps.
+    pub fn replace(&mut self, groups: Vec<(String, GroupSpec)>, now_ms: u64) -> Result<(), String> {
+        let mut next = Groups::default();
+        for (name, spec) in groups {
+            if next.groups.contains_key(&name) { return Err(format!("group {name} appears twice")); }
+            next.set(&name, spec, now_ms).map_err(|e| format!("group {name}: {e}"))?;
+            if let (Some(cur), Some(g)) = (self.groups.get(&name), next.groups.get_mut(&name)) {
+                g.created_at_ms = cur.created_at_ms;
+                if cur.spec == g.spec { g.updated_at_ms = cur.updated_at_ms; }
+            }
+        }
+        *self = next;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Group> {
+        let g = self.groups.remove(name)?;
+        for m in &g.spec.members { self.by_member.remove(m); }
+        Some(g)
+    }
+}
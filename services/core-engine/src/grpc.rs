@@ -0,0 +1,129 @@
+// tonic::Status is large, and every handler in this module returns it.
+#![allow(clippy::result_large_err)]
+
+use crate::{alerts, auth, tags, AppState, Err, MarginRequest, OrderLeg, PositionInput, PreTradeCheckRequest};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use pb::risk_engine_server::{RiskEngine, RiskEngineServer};
+use serde::{de::DeserializeOwned, Serialize};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tonic::{Request, Response, Status};
+
+pub mod pb { tonic::include_proto!("alice.risk.v1"); }
+
+/// The gRPC face of the engine. Each call is admitted like a REST request and then runs the same service functions
+/// as the axum handlers, against the production state only: sandboxes stay REST.
+pub struct Grpc { state: Arc<AppState> }
+
+pub async fn serve(state: Arc<AppState>, addr: String) {
+    let addr = match addr.parse() { Ok(a) => a, Err(e) => return tracing::error!("RISK_GRPC_ADDR {addr}: {e}") };
+    tracing::info!("Risk Engine gRPC on {addr}");
+    if let Err(e) = tonic::transport::Server::builder().add_service(RiskEngineServer::new(Grpc { state })).serve(addr).await { tracing::error!("gRPC server stopped: {e}"); }
+}
+
+fn status((code, Json(e)): (StatusCode, Json<Err>)) -> Status {
+    let message = e.details.map_or_else(|| e.error.clone(), |d| format!("{}: {d}", e.error));
+    match code {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        StatusCode::CONFLICT => Status::failed_precondition(message),
+        StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(message),
+        StatusCode::SERVICE_UNAVAILABLE => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+fn denied(d: auth::Denied) -> Status {
+    match d {
+        auth::Denied::Missing => Status::unauthenticated("API key required: send it in x-api-key or as a bearer token"),
+        auth::Denied::Unknown => Status::unauthenticated("Invalid API key"),
+        auth::Denied::ReadOnly => Status::permission_denied("read-only keys cannot make changes"),
+        auth::Denied::Role(r) => Status::permission_denied(format!("this key cannot act as {r}")),
+        auth::Denied::RateLimited { retry_after_secs } => Status::resource_exhausted(format!("Rate limit exceeded: retry in {retry_after_secs}s")),
+    }
+}
+
+/// The request's metadata as the headers the REST API would have seen, once its key is admitted.
+fn headers<T>(s: &AppState, req: &Request<T>, safe: bool) -> Result<HeaderMap, Status> {
+    let mut h = req.metadata().clone().into_headers();
+    crate::admit(s, &mut h, safe).map_err(denied)?;
+    Ok(h)
+}
+
+/// Enum values travel as their REST spellings.
+fn name(v: impl Serialize) -> String { serde_json::to_value(v).ok().and_then(|v| v.as_str().map(Into::into)).unwrap_or_default() }
+
+fn parse<T: DeserializeOwned>(field: &str, v: &str) -> Result<T, Status> {
+    serde_json::from_value(serde_json::Value::String(v.into())).map_err(|_| Status::invalid_argument(format!("unknown {field} '{v}'")))
+}
+
+fn pretrade_request(r: pb::PreTradeCheckRequest) -> Result<PreTradeCheckRequest, Status> {
+    let value_date = r.value_date.map(|d| chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d").map_err(|e| Status::invalid_argument(format!("value_date: {e}")))).transpose()?;
+    let tags = r.tags.map(|t| tags::Tags { strategy: t.strategy, algo: t.algo, trader: t.trader }).unwrap_or_default();
+    Ok(PreTradeCheckRequest {
+        account: r.account, instrument: r.instrument, side: (!r.side.is_empty()).then(|| parse("side", &r.side)).transpose()?, quantity: r.quantity, price: r.price, asset_class: r.asset_class, venue: r.venue, override_token: r.override_token, reserve: r.reserve,
+        legs: r.legs.into_iter().map(|l| Ok(OrderLeg { instrument: l.instrument, side: parse("side", &l.side)?, quantity: l.quantity, price: l.price })).collect::<Result<_, Status>>()?,
+        algo: None, counterparty: r.counterparty, value_date, tags, latency_budget_ms: r.latency_budget_ms,
+    })
+}
+
+fn alert(a: alerts::Localized) -> pb::Alert {
+    let (locale, text, a) = (name(a.locale), a.text, a.alert);
+    pb::Alert { id: a.id, at_ms: a.at_ms, kind: a.kind, severity: name(a.severity), account: a.account, instrument: a.instrument, message: a.message, text, locale }
+}
+
+type AlertStream = Pin<Box<dyn futures_util::Stream<Item = Result<pb::Alert, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl RiskEngine for Grpc {
+    async fn pre_trade_check(&self, req: Request<pb::PreTradeCheckRequest>) -> Result<Response<pb::PreTradeCheckResponse>, Status> {
+        let h = headers(&self.state, &req, false)?;
+        let r = crate::pretrade(self.state.clone(), h, false, pretrade_request(req.into_inner())?).await.map_err(status)?;
+        Ok(Response::new(pb::PreTradeCheckResponse {
+            check_id: r.check_id, approved: r.approved, reasons: r.reasons, locale: name(r.locale), risk_score: r.risk_score, margin_impact: r.margin_impact,
+            reason_codes: r.reason_codes.into_iter().map(|c| pb::ReasonCode { code: name(c.entry.code), severity: name(c.entry.severity), text: c.entry.text, localization_key: c.entry.localization_key, detail: c.detail }).collect(),
+            position_limit_used_pct: r.position_limit_used_pct, elapsed_us: r.elapsed_us as u64, overridden: r.overridden, override_status: r.override_status,
+            reservation_id: r.reservation.map(|x| x.id), degraded: r.degraded.is_some(), timed_out: r.timed_out,
+        }))
+    }
+
+    async fn margin_calc(&self, req: Request<pb::MarginRequest>) -> Result<Response<pb::MarginResponse>, Status> {
+        headers(&self.state, &req, false)?;
+        let r = req.into_inner();
+        let methodology = r.methodology.as_deref().map(|m| parse("methodology", m)).transpose()?.unwrap_or_default();
+        // Protobuf cannot tell an empty list from none, so an empty one asks for the booked positions.
+        let positions = (!r.positions.is_empty()).then(|| r.positions.into_iter().map(|p| PositionInput { instrument: p.instrument, quantity: p.quantity, price: p.price }).collect());
+        let req = MarginRequest { account: r.account, positions, methodology, returns: Default::default(), var_lookback_days: r.var_lookback_days.map(|d| d as usize) };
+        let m = crate::margin(self.state.clone(), req).await.map_err(status)?;
+        Ok(Response::new(pb::MarginResponse {
+            account: m.account, margin_model: name(m.margin_model), initial_margin: m.initial_margin, maintenance_margin: m.maintenance_margin, available_margin: m.available_margin,
+            margin_utilization_pct: m.margin_utilization_pct, var_95: m.var_95, var_99: m.var_99, var_method: name(m.var_method), funds: m.funds, held_margin: m.held_margin, elapsed_us: m.elapsed_us as u64,
+        }))
+    }
+
+    type StreamAlertsStream = AlertStream;
+
+    /// Alerts dropped because the client fell behind leave a gap in the ids.
+    async fn stream_alerts(&self, req: Request<pb::AlertSubscription>) -> Result<Response<AlertStream>, Status> {
+        let h = headers(&self.state, &req, true)?;
+        let r = req.into_inner();
+        let sub = alerts::Subscription { min_severity: r.min_severity.as_deref().map(|m| parse("min_severity", m)).transpose()?, account: r.account };
+        let locale = crate::account_locale(&self.state, &h, sub.account.as_deref());
+        let rx = self.state.alert_stream.subscribe();
+        let stream = futures_util::stream::unfold((rx, sub), move |(mut rx, sub)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(a) if sub.matches(&a) => return Some((Ok(alert(a.localized(locale))), (rx, sub))),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(missed)) => tracing::warn!(missed, "gRPC alert stream fell behind"),
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// How an order moves the account's risk against what it already holds. `Closing` orders only take existing
+/// positions towards flat; `Hedging` orders offset delta the account holds in the underliers they trade, or its
+/// beta-adjusted exposure, without overshooting it. Everything else is `Increasing`.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction { Closing, Hedging, Increasing }
+
+/// How much more leniently risk-reducing orders are treated: their risk score is multiplied by the factor for their
+/// direction, and with `closing_skips_notional_limits` closing orders are not held to order notional caps.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct HedgePolicy { pub closing_score_factor: f64, pub hedging_score_factor: f64, pub closing_skips_notional_limits: bool }
+
+impl Default for HedgePolicy {
+    fn default() -> Self { Self { closing_score_factor: 0.0, hedging_score_factor: 0.5, closing_skips_notional_limits: true } }
+}
+
+impl HedgePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, f) in [("closing_score_factor", self.closing_score_factor), ("hedging_score_factor", self.hedging_score_factor)] {
+            if !(0.0..=1.0).contains(&f) { return Err(format!("{name} must be within [0, 1]")); }
+        }
+        Ok(())
+    }
+
+    pub fn score_factor(&self, d: Direction) -> f64 {
+        match d { Direction::Closing => self.closing_score_factor, Direction::Hedging => self.hedging_score_factor, Direction::Increasing => 1.0 }
+    }
+
+    pub fn skips_notional_limits(&self, d: Direction) -> bool { d == Direction::Closing && self.closing_skips_notional_limits }
+}
+
+fn reduces(before: f64, after: f64) -> bool { after.abs() < before.abs() - 1e-9 }
+
+/// `deltas` are the account's delta in each underlier the order trades, before and after it; `beta` its
+/// beta-adjusted exposure, asked for only when the deltas do not already show a hedge.
+pub fn classify(closing: bool, deltas: &[(f64, f64)], beta: impl FnOnce() -> Option<(f64, f64)>) -> Direction {
+    if closing { return Direction::Closing; }
+    let offsets = !deltas.is_empty() && deltas.iter().all(|(b, a)| a.abs() <= b.abs() + 1e-9) && deltas.iter().any(|(b, a)| reduces(*b, *a));
+    if offsets || beta().is_some_and(|(b, a)| reduces(b, a)) { Direction::Hedging } else { Direction::Increasing }
+}
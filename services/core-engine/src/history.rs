@@ -0,0 +1,66 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Close { pub instrument: String, pub date: NaiveDate, pub close: f64 }
+
+#[derive(Serialize)]
+pub struct PathPoint { pub date: NaiveDate, pub pnl: f64 }
+
+#[derive(Serialize)]
+pub struct InstrumentPnl { pub instrument: String, pub pnl: f64 }
+
+#[derive(Serialize)]
+pub struct Replay { pub from: NaiveDate, pub to: NaiveDate, pub pnl: f64, pub worst_pnl: f64, pub path: Vec<PathPoint>, pub by_instrument: Vec<InstrumentPnl>, pub instruments_replayed: Vec<String>, pub missing_history: Vec<String> }
+
+/// Daily closing prices per instrument.
+#[derive(Default, Clone)]
+pub struct PriceHistory { closes: HashMap<String, BTreeMap<NaiveDate, f64>>, revision: u64 }
+
+impl PriceHistory {
+    /// Upserts closes; re-loading the same (instrument, date) overwrites, so backfills are idempotent.
+    pub fn load(&mut self, rows: impl IntoIterator<Item = Close>) -> usize {
+        let n = rows.into_iter().map(|r| self.closes.entry(r.instrument).or_default().insert(r.date, r.close)).count();
+        if n > 0 { self.revision += 1; }
+        n
+    }
+
+    /// Changes whenever closes are loaded, so figures derived from the history can tell they are stale.
+    pub fn revision(&self) -> u64 { self.revision }
+
+    pub fn close(&self, instrument: &str, date: NaiveDate) -> Option<f64> { self.closes.get(instrument)?.get(&date).copied() }
+
+    pub fn series(&self, instrument: &str, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<(NaiveDate, f64)> {
+        let Some(m) = self.closes.get(instrument) else { return Vec::new() };
+        m.range(from.unwrap_or(NaiveDate::MIN)..=to.unwrap_or(NaiveDate::MAX)).map(|(d, c)| (*d, *c)).collect()
+    }
+
+    /// Replays each instrument's actual moves over the window against current holdings, marking P&L on every
+    /// date any instrument printed; an instrument without a print that day carries its last close forward.
+    pub fn replay(&self, positions: &[(String, f64, f64)], from: NaiveDate, to: NaiveDate) -> Replay {
+        let (mut legs, mut replayed, mut missing) = (Vec::new(), Vec::new(), Vec::new());
+        for (instrument, qty, price) in positions {
+            let s: BTreeMap<NaiveDate, f64> = self.series(instrument, Some(from), Some(to)).into_iter().collect();
+            if s.len() < 2 { missing.push(instrument.clone()); continue; }
+            replayed.push(instrument.clone());
+            legs.push((instrument, qty * price, s));
+        }
+        let dates: BTreeSet<NaiveDate> = legs.iter().flat_map(|(_, _, s)| s.keys().copied()).collect();
+        let path: Vec<PathPoint> = dates.into_iter().map(|date| {
+            let pnl = legs.iter().map(|(_, notional, s)| {
+                let base = s.values().next().copied().unwrap_or(1.0);
+                let close = s.range(..=date).next_back().map(|(_, c)| *c).unwrap_or(base);
+                notional * (close / base - 1.0)
+            }).sum();
+            PathPoint { date, pnl }
+        }).collect();
+        let by_instrument = legs.iter().map(|(instrument, notional, s)| {
+            let (first, last) = (s.values().next().copied().unwrap_or(1.0), s.values().next_back().copied().unwrap_or(1.0));
+            InstrumentPnl { instrument: (*instrument).clone(), pnl: notional * (last / first - 1.0) }
+        }).collect();
+        let pnl = path.last().map(|p| p.pnl).unwrap_or(0.0);
+        let worst_pnl = path.iter().map(|p| p.pnl).fold(0.0, f64::min);
+        Replay { from, to, pnl, worst_pnl, path, by_instrument, instruments_replayed: replayed, missing_history: missing }
+    }
+}
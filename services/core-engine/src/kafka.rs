@@ -0,0 +1,254 @@
+use crate::events::{BusConfig, BusFuture, EventBus, Message};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+const PRODUCE: i16 = 0;
+const METADATA: i16 = 3;
+/// Responses larger than this are taken for a broken stream rather than read.
+const MAX_RESPONSE: usize = 64 << 20;
+
+#[derive(Default)]
+struct Buf(Vec<u8>);
+
+impl Buf {
+    fn i8(&mut self, v: i8) { self.0.push(v as u8); }
+    fn i16(&mut self, v: i16) { self.0.extend_from_slice(&v.to_be_bytes()); }
+    fn i32(&mut self, v: i32) { self.0.extend_from_slice(&v.to_be_bytes()); }
+    fn i64(&mut self, v: i64) { self.0.extend_from_slice(&v.to_be_bytes()); }
+    fn str(&mut self, s: &str) { self.i16(s.len() as i16); self.0.extend_from_slice(s.as_bytes()); }
+    fn varlong(&mut self, n: i64) {
+        let mut v = ((n << 1) ^ (n >> 63)) as u64;
+        while v >= 0x80 { self.0.push(v as u8 | 0x80); v >>= 7; }
+        self.0.push(v as u8);
+    }
+    /// A varint-length-prefixed byte string; `None` is written as length -1.
+    fn var_bytes(&mut self, b: Option<&[u8]>) {
+        match b { Some(b) => { self.varlong(b.len() as i64); self.0.extend_from_slice(b); } None => self.varlong(-1) }
+    }
+}
+
+struct Rd<'a> { b: &'a [u8], pos: usize }
+
+impl<'a> Rd<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let s = self.b.get(self.pos..self.pos + n).ok_or("truncated response")?;
+        self.pos += n;
+        Ok(s)
+    }
+    fn i8(&mut self) -> Result<i8, String> { Ok(self.take(1)?[0] as i8) }
+    fn i16(&mut self) -> Result<i16, String> { Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap_or_default())) }
+    fn i32(&mut self) -> Result<i32, String> { Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap_or_default())) }
+    fn i64(&mut self) -> Result<i64, String> { Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap_or_default())) }
+    fn nullable_str(&mut self) -> Result<Option<String>, String> {
+        let n = self.i16()?;
+        if n < 0 { return Ok(None); }
+        Ok(Some(String::from_utf8_lossy(self.take(n as usize)?).into_owned()))
+    }
+    fn str(&mut self) -> Result<String, String> { Ok(self.nullable_str()?.unwrap_or_default()) }
+    fn len(&mut self) -> Result<usize, String> { Ok(self.i32()?.max(0) as usize) }
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, b| (0..8).fold(crc ^ *b as u32, |c, _| if c & 1 != 0 { (c >> 1) ^ 0x82f6_3b78 } else { c >> 1 }))
+}
+
+/// Kafka's default partitioner hash, so keyed events land where a Java producer would put them.
+fn murmur2(data: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let mut h = 0x9747_b28c ^ data.len() as u32;
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+    for c in chunks {
+        let mut k = u32::from_le_bytes([c[0], c[1], c[2], c[3]]).wrapping_mul(M);
+        k ^= k >> 24;
+        h = h.wrapping_mul(M) ^ k.wrapping_mul(M);
+    }
+    if !tail.is_empty() {
+        for (i, b) in tail.iter().enumerate() { h ^= (*b as u32) << (8 * i); }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^ (h >> 15)
+}
+
+/// A v2 record batch, uncompressed and without producer ids.
+fn record_batch(msgs: &[&Message]) -> Vec<u8> {
+    let base = msgs.iter().map(|m| m.at_ms).min().unwrap_or_default() as i64;
+    let max = msgs.iter().map(|m| m.at_ms).max().unwrap_or_default() as i64;
+    let mut body = Buf::default();
+    body.i16(0);
+    body.i32(msgs.len() as i32 - 1);
+    body.i64(base);
+    body.i64(max);
+    body.i64(-1);
+    body.i16(-1);
+    body.i32(-1);
+    body.i32(msgs.len() as i32);
+    for (i, m) in msgs.iter().enumerate() {
+        let mut r = Buf::default();
+        r.i8(0);
+        r.varlong(m.at_ms as i64 - base);
+        r.varlong(i as i64);
+        r.var_bytes(m.key.as_deref().map(str::as_bytes));
+        r.var_bytes(Some(&m.value));
+        r.varlong(m.headers.len() as i64);
+        for (k, v) in &m.headers { r.var_bytes(Some(k.as_bytes())); r.var_bytes(Some(v)); }
+        body.varlong(r.0.len() as i64);
+        body.0.extend(r.0);
+    }
+    let mut out = Buf::default();
+    out.i64(0);
+    out.i32(4 + 1 + 4 + body.0.len() as i32);
+    out.i32(-1);
+    out.i8(2);
+    out.0.extend_from_slice(&crc32c(&body.0).to_be_bytes());
+    out.0.extend(body.0);
+    out.0
+}
+
+/// What the producer knows of the cluster: broker addresses by node id, each topic's partition leaders by
+/// partition, and open connections by address.
+#[derive(Default)]
+struct Cluster { brokers: HashMap<i32, String>, leaders: HashMap<String, Vec<i32>>, conns: HashMap<String, TcpStream>, correlation: i32, round_robin: usize }
+
+/// A producer speaking the Kafka protocol directly: Metadata v1 to find partition leaders and Produce v3 to send
+/// to them. Keyed events go to the partition the key hashes to; unkeyed ones round-robin. A batch that fails is
+/// retried once against fresh metadata, so events are delivered at least once.
+pub struct KafkaBus { bootstrap: Vec<String>, client_id: String, acks: i16, timeout: Duration, cluster: Mutex<Cluster> }
+
+impl KafkaBus {
+    pub fn new(c: &BusConfig) -> Self {
+        Self { bootstrap: c.brokers.clone(), client_id: c.client_id.clone(), acks: c.acks, timeout: Duration::from_millis(c.timeout_ms), cluster: Mutex::new(Cluster::default()) }
+    }
+
+    /// Sends one request to `addr` and returns the response body after its correlation id, if one is expected.
+    async fn call(&self, c: &mut Cluster, addr: &str, api_key: i16, version: i16, body: &[u8], expect_response: bool) -> Result<Option<Vec<u8>>, String> {
+        c.correlation = c.correlation.wrapping_add(1);
+        let correlation = c.correlation;
+        let mut req = Buf::default();
+        req.i16(api_key);
+        req.i16(version);
+        req.i32(correlation);
+        req.str(&self.client_id);
+        req.0.extend_from_slice(body);
+        let mut framed = (req.0.len() as i32).to_be_bytes().to_vec();
+        framed.extend(req.0);
+        let conns = &mut c.conns;
+        let result = tokio::time::timeout(self.timeout, async {
+            let conn = match conns.entry(addr.to_string()) {
+                Entry::Occupied(o) => o.into_mut(),
+                Entry::Vacant(v) => v.insert(TcpStream::connect(addr).await.map_err(|e| format!("{addr}: {e}"))?),
+            };
+            conn.write_all(&framed).await.map_err(|e| format!("{addr}: {e}"))?;
+            if !expect_response { return Ok(None); }
+            let mut len = [0u8; 4];
+            conn.read_exact(&mut len).await.map_err(|e| format!("{addr}: {e}"))?;
+            let len = i32::from_be_bytes(len).max(0) as usize;
+            if !(4..=MAX_RESPONSE).contains(&len) { return Err(format!("{addr}: response of {len} bytes")); }
+            let mut buf = vec![0u8; len];
+            conn.read_exact(&mut buf).await.map_err(|e| format!("{addr}: {e}"))?;
+            if buf[..4] != correlation.to_be_bytes() { return Err(format!("{addr}: response out of sequence")); }
+            Ok(Some(buf.split_off(4)))
+        }).await.unwrap_or_else(|_| Err(format!("{addr}: no answer within {}ms", self.timeout.as_millis())));
+        if result.is_err() { c.conns.remove(addr); }
+        result
+    }
+
+    /// Learns the brokers and the partition leaders of `topics` from the first broker that answers.
+    async fn refresh(&self, c: &mut Cluster, topics: &BTreeSet<&str>) -> Result<(), String> {
+        let mut body = Buf::default();
+        body.i32(topics.len() as i32);
+        for t in topics { body.str(t); }
+        let mut candidates: Vec<String> = c.brokers.values().cloned().collect();
+        candidates.extend(self.bootstrap.iter().cloned());
+        let mut last = String::from("no brokers");
+        for addr in candidates {
+            let resp = match self.call(c, &addr, METADATA, 1, &body.0, true).await { Ok(Some(r)) => r, Ok(None) => continue, Err(e) => { last = e; continue } };
+            let mut r = Rd { b: &resp, pos: 0 };
+            for _ in 0..r.len()? {
+                let (id, host, port) = (r.i32()?, r.str()?, r.i32()?);
+                r.nullable_str()?;
+                c.brokers.insert(id, format!("{host}:{port}"));
+            }
+            r.i32()?;
+            for _ in 0..r.len()? {
+                let (error, name) = (r.i16()?, r.str()?);
+                r.i8()?;
+                let mut leaders = Vec::new();
+                for _ in 0..r.len()? {
+                    let (_, index, leader) = (r.i16()?, r.i32()?, r.i32()?);
+                    for _ in 0..2 { let n = r.len()?; r.take(4 * n)?; }
+                    if leaders.len() <= index as usize { leaders.resize(index as usize + 1, -1); }
+                    leaders[index as usize] = leader;
+                }
+                if error != 0 { return Err(format!("metadata for {name}: error code {error}")); }
+                if leaders.is_empty() { return Err(format!("{name} has no partitions")); }
+                c.leaders.insert(name, leaders);
+            }
+            return Ok(());
+        }
+        Err(last)
+    }
+
+    async fn send(&self, c: &mut Cluster, batch: &[Message]) -> Result<(), String> {
+        let missing: BTreeSet<&str> = batch.iter().map(|m| m.topic.as_str()).filter(|t| !c.leaders.contains_key(*t)).collect();
+        if !missing.is_empty() { self.refresh(c, &missing).await?; }
+        let mut by_broker: BTreeMap<String, BTreeMap<(&str, i32), Vec<&Message>>> = BTreeMap::new();
+        for m in batch {
+            let leaders = c.leaders.get(&m.topic).ok_or_else(|| format!("no metadata for {}", m.topic))?;
+            let p = match &m.key { Some(k) => murmur2(k.as_bytes()) as usize & 0x7fff_ffff, None => { c.round_robin = c.round_robin.wrapping_add(1); c.round_robin } } % leaders.len();
+            let addr = c.brokers.get(&leaders[p]).ok_or_else(|| format!("{}[{p}] has no leader", m.topic))?;
+            by_broker.entry(addr.clone()).or_default().entry((m.topic.as_str(), p as i32)).or_default().push(m);
+        }
+        for (addr, parts) in by_broker {
+            let mut body = Buf::default();
+            body.i16(-1);
+            body.i16(self.acks);
+            body.i32(self.timeout.as_millis() as i32);
+            let topics: BTreeMap<&str, Vec<(i32, &Vec<&Message>)>> = parts.iter().fold(BTreeMap::new(), |mut t, ((topic, p), msgs)| { t.entry(*topic).or_insert_with(Vec::new).push((*p, msgs)); t });
+            body.i32(topics.len() as i32);
+            for (topic, partitions) in &topics {
+                body.str(topic);
+                body.i32(partitions.len() as i32);
+                for (p, msgs) in partitions {
+                    let records = record_batch(msgs);
+                    body.i32(*p);
+                    body.i32(records.len() as i32);
+                    body.0.extend(records);
+                }
+            }
+            let Some(resp) = self.call(c, &addr, PRODUCE, 3, &body.0, self.acks != 0).await? else { continue };
+            let mut r = Rd { b: &resp, pos: 0 };
+            for _ in 0..r.len()? {
+                let topic = r.str()?;
+                for _ in 0..r.len()? {
+                    let (p, error) = (r.i32()?, r.i16()?);
+                    r.i64()?;
+                    r.i64()?;
+                    if error != 0 { return Err(format!("{topic}[{p}] on {addr}: error code {error}")); }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EventBus for KafkaBus {
+    fn publish<'a>(&'a self, batch: &'a [Message]) -> BusFuture<'a, ()> {
+        Box::pin(async move {
+            let mut c = self.cluster.lock().await;
+            if let Err(e) = self.send(&mut c, batch).await {
+                tracing::warn!("kafka publish failed, retrying with fresh metadata: {e}");
+                (c.brokers, c.leaders) = (HashMap::new(), HashMap::new());
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                return self.send(&mut c, batch).await;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+const DUPLICATE_WINDOW_MS: u64 = 60_000;
+
+/// Conditions that engage an account's kill switch. `max_daily_loss` is measured against realized P&L posted to the
+/// ledger today; the rejection rate is only judged once `min_orders` checks fall inside the window.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct KillPolicy {
+    pub max_daily_loss: Option<f64>, pub max_rejection_rate_pct: Option<f64>, #[serde(default = "rejection_window")] pub rejection_window_secs: u64,
+    #[serde(default = "min_orders")] pub min_orders: usize, pub max_duplicate_orders: Option<usize>,
+}
+
+fn rejection_window() -> u64 { 300 }
+fn min_orders() -> usize { 10 }
+
+impl KillPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_daily_loss.is_some_and(|l| !(l.is_finite() && l > 0.0)) { return Err("max_daily_loss must be positive".into()); }
+        if self.max_rejection_rate_pct.is_some_and(|r| !(r > 0.0 && r <= 100.0)) { return Err("max_rejection_rate_pct must be within (0, 100]".into()); }
+        if self.rejection_window_secs == 0 || self.min_orders == 0 { return Err("rejection_window_secs and min_orders must be positive".into()); }
+        if self.max_duplicate_orders.is_some_and(|n| n < 2) { return Err("max_duplicate_orders must be at least 2".into()); }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger { Policy, Manual }
+
+/// What a switch halts: every account, the accounts of one desk, or one account.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope { Firm, Desk, Account }
+
+/// An engagement stays until a named person disengages it; policies never lift one on their own. `key` names the
+/// desk or account; firm-wide switches have none.
+#[derive(Serialize, Clone)]
+pub struct Engagement {
+    pub id: String, pub scope: Scope, #[serde(skip_serializing_if = "Option::is_none")] pub key: Option<String>, pub trigger: Trigger, pub reason: String, pub engaged_by: String, pub engaged_at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub disengaged_by: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub disengaged_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Engage { pub reason: String }
+
+#[derive(Deserialize)]
+pub struct Disengage { pub note: String }
+
+/// Engages or disengages a switch at any scope; `reason` is needed to engage and `note` to disengage.
+#[derive(Deserialize)]
+pub struct ScopedRequest { pub scope: Scope, pub key: Option<String>, pub reason: Option<String>, pub note: Option<String> }
+
+#[derive(Deserialize)]
+pub struct HistoryQuery { pub scope: Option<Scope>, pub key: Option<String> }
+
+impl Engagement {
+    fn covers(&self, scope: Scope, key: Option<&str>) -> bool { self.scope == scope && self.key.as_deref() == key && self.disengaged_at_ms.is_none() }
+
+    pub fn label(&self) -> String { match (&self.scope, &self.key) { (Scope::Firm, _) => "firm".into(), (Scope::Desk, k) => format!("desk {}", k.as_deref().unwrap_or_default()), (Scope::Account, k) => format!("account {}", k.as_deref().unwrap_or_default()) } }
+}
+
+pub fn validate(scope: Scope, key: Option<&str>) -> Result<(), String> {
+    match (scope, key) {
+        (Scope::Firm, Some(_)) => Err("firm-wide kill switches take no key".into()),
+        (Scope::Desk | Scope::Account, None | Some("")) => Err("desk and account kill switches need a key".into()),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Default)]
+pub struct KillSwitches {
+    policies: HashMap<String, KillPolicy>, engagements: Vec<Engagement>,
+    outcomes: HashMap<String, VecDeque<(u64, bool)>>, orders: HashMap<String, VecDeque<(u64, String)>>,
+}
+
+impl KillSwitches {
+    pub fn policy(&self, account: &str) -> Option<KillPolicy> { self.policies.get(account).cloned() }
+    pub fn set_policy(&mut self, account: &str, p: KillPolicy) { self.policies.insert(account.into(), p); }
+    pub fn remove_policy(&mut self, account: &str) -> bool { self.policies.remove(account).is_some() }
+
+    pub fn engaged(&self, scope: Scope, key: Option<&str>) -> Option<&Engagement> { self.engagements.iter().rev().find(|e| e.covers(scope, key)) }
+
+    /// Every engaged switch that halts the account: firm-wide, its desk's, then its own.
+    pub fn halting(&self, account: &str, desk: Option<&str>) -> Vec<&Engagement> {
+        let desk = desk.and_then(|d| self.engaged(Scope::Desk, Some(d)));
+        [self.engaged(Scope::Firm, None), desk, self.engaged(Scope::Account, Some(account))].into_iter().flatten().collect()
+    }
+
+    /// Newest first, optionally narrowed by scope and key.
+    pub fn history(&self, q: &HistoryQuery) -> Vec<Engagement> {
+        self.engagements.iter().rev().filter(|e| q.scope.is_none_or(|s| e.scope == s) && q.key.as_ref().is_none_or(|k| e.key.as_ref() == Some(k))).cloned().collect()
+    }
+
+    /// `None` when the switch is already engaged, so one incident raises one engagement.
+    pub fn engage(&mut self, scope: Scope, key: Option<&str>, trigger: Trigger, reason: String, by: &str, now_ms: u64) -> Option<Engagement> {
+        if self.engaged(scope, key).is_some() { return None; }
+        let e = Engagement { id: uuid::Uuid::new_v4().to_string(), scope, key: key.map(Into::into), trigger, reason, engaged_by: by.into(), engaged_at_ms: now_ms, disengaged_by: None, disengaged_at_ms: None, note: None };
+        self.engagements.push(e.clone());
+        Some(e)
+    }
+
+    /// Disengaging an account also clears its windows, so the orders that tripped the switch cannot trip it again
+    /// straight away.
+    pub fn disengage(&mut self, scope: Scope, key: Option<&str>, by: &str, note: String, now_ms: u64) -> Result<Engagement, String> {
+        if note.trim().is_empty() { return Err("disengaging needs a note".into()); }
+        let Some(e) = self.engagements.iter_mut().rev().find(|e| e.covers(scope, key)) else { return Err("kill switch is not engaged".into()) };
+        (e.disengaged_by, e.disengaged_at_ms, e.note) = (Some(by.into()), Some(now_ms), Some(note));
+        if let (Scope::Account, Some(k)) = (scope, key) { self.outcomes.remove(k); self.orders.remove(k); }
+        Ok(e.clone())
+    }
+
+    /// Records an order by its `fingerprint` and reports a breach once identical orders in the last minute reach
+    /// the policy's count.
+    pub fn observe_order(&mut self, account: &str, fingerprint: String, now_ms: u64) -> Option<String> {
+        let max = self.policies.get(account)?.max_duplicate_orders?;
+        let q = self.orders.entry(account.into()).or_default();
+        while q.front().is_some_and(|(t, _)| t + DUPLICATE_WINDOW_MS <= now_ms) { q.pop_front(); }
+        q.push_back((now_ms, fingerprint.clone()));
+        let n = q.iter().filter(|(_, f)| *f == fingerprint).count();
+        (n >= max).then(|| format!("{n} duplicate orders within a minute (max {max}): {fingerprint}"))
+    }
+
+    pub fn observe_outcome(&mut self, account: &str, approved: bool, now_ms: u64) -> Option<String> {
+        let p = self.policies.get(account)?;
+        let (max, window_ms) = (p.max_rejection_rate_pct?, p.rejection_window_secs * 1000);
+        let q = self.outcomes.entry(account.into()).or_default();
+        while q.front().is_some_and(|(t, _)| t + window_ms <= now_ms) { q.pop_front(); }
+        q.push_back((now_ms, approved));
+        let rejected = q.iter().filter(|(_, ok)| !ok).count();
+        let rate = rejected as f64 / q.len() as f64 * 100.0;
+        (q.len() >= p.min_orders && rate > max).then(|| format!("rejection rate {rate:.1}% over {} checks in {}s exceeds {max:.1}%", q.len(), p.rejection_window_secs))
+    }
+
+    /// `loss` is today's realized loss, positive when the account is down.
+    pub fn check_loss(&self, account: &str, loss: f64) -> Option<String> {
+        let max = self.policies.get(account)?.max_daily_loss?;
+        (loss > max).then(|| format!("daily loss {loss:.2} exceeds {max:.2}"))
+    }
+}
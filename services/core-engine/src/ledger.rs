@@ -0,0 +1,177 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind { Deposit, Withdrawal, RealizedPnl, Adjustment, Reversal }
+
+/// Ledger books kept for every trading account, one set per currency the account holds cash in. `cash` is what
+/// the client holds; each movement into or out of it is balanced against the book that explains it.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Book { Cash, Funding, RealizedPnl, Adjustments }
+
+/// Deposits and withdrawals take a positive amount; realized P&L and adjustments are signed, and adjustments must
+/// say why. `currency` defaults to the account's base currency. A `reference` can be posted only once per account,
+/// so replayed postings are refused rather than double-counted.
+#[derive(Deserialize)]
+pub struct Posting { pub kind: EntryKind, pub amount: f64, pub currency: Option<String>, pub reference: Option<String>, pub note: Option<String> }
+
+#[derive(Serialize, Clone)]
+pub struct Line { pub book: Book, pub debit: f64, pub credit: f64 }
+
+/// A balanced journal in one currency: its lines' debits equal its credits, and `cash_balance_after` is the cash
+/// held in that currency once it is posted. Journals are never edited; a mistake is undone by a reversal that
+/// posts the opposite lines and links both ways.
+#[derive(Serialize, Clone)]
+pub struct Journal {
+    pub id: String, pub account: String, pub kind: EntryKind, pub currency: String, pub lines: Vec<Line>, pub cash_balance_after: f64, pub date: NaiveDate, pub at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub reference: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub reverses: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub reversed_by: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct JournalQuery { pub account: Option<String>, pub kind: Option<EntryKind>, pub from: Option<NaiveDate>, pub to: Option<NaiveDate>, pub limit: Option<usize> }
+
+#[derive(Deserialize)]
+pub struct Reverse { pub note: String }
+
+/// Closing balance of every book in one currency on a date the account had postings in it; balances are debit
+/// minus credit.
+#[derive(Serialize)]
+pub struct DailyBalance { pub date: NaiveDate, pub currency: String, pub journals: usize, pub balances: BTreeMap<Book, f64> }
+
+/// Cash held in one currency and, when there is a rate, what it is worth in the base currency.
+#[derive(Serialize)]
+pub struct CurrencyCash { pub currency: String, pub cash_balance: f64, #[serde(skip_serializing_if = "Option::is_none")] pub rate: Option<f64>, #[serde(skip_serializing_if = "Option::is_none")] pub base_value: Option<f64> }
+
+/// `equity` is what margin is measured against: ledger cash plus haircut collateral, in the base currency. Cash in
+/// a currency with no rate into it is listed under `unconverted` and counts for nothing. Deposit and withdrawal totals
+/// leave out journals that were reversed.
+#[derive(Serialize)]
+pub struct Equity {
+    pub account: String, pub base_currency: String, pub cash_balance: f64, pub deposits: f64, pub withdrawals: f64, pub realized_pnl: f64, pub adjustments: f64, pub collateral_value: f64, pub equity: f64,
+    pub currencies: Vec<CurrencyCash>, #[serde(skip_serializing_if = "Vec::is_empty")] pub unconverted: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Ledger { journals: Vec<Journal> }
+
+/// Debits `amount` to cash against `contra`; a negative amount credits cash instead.
+fn lines(contra: Book, amount: f64) -> Vec<Line> {
+    let (d, c) = if amount >= 0.0 { (amount, 0.0) } else { (0.0, -amount) };
+    vec![Line { book: Book::Cash, debit: d, credit: c }, Line { book: contra, debit: c, credit: d }]
+}
+
+impl Journal {
+    pub fn cash_effect(&self) -> f64 { self.lines.iter().filter(|l| l.book == Book::Cash).map(|l| l.debit - l.credit).sum() }
+}
+
+impl Ledger {
+    fn of<'a>(&'a self, account: &'a str) -> impl Iterator<Item = &'a Journal> + 'a { self.journals.iter().filter(move |j| j.account == account) }
+
+    /// `None` when the account has never had a posting in `currency`.
+    pub fn balance(&self, account: &str, currency: &str) -> Option<f64> { self.of(account).filter(|j| j.currency == currency).last().map(|j| j.cash_balance_after) }
+
+    /// Cash by currency; empty for accounts that have never had a posting.
+    pub fn balances(&self, account: &str) -> BTreeMap<String, f64> { self.of(account).map(|j| (j.currency.clone(), j.cash_balance_after)).collect() }
+
+    /// Cash in the base currency, converting each currency at `rate`, with the currencies that have no rate. `None`
+    /// for accounts that have never had a posting.
+    pub fn cash_value(&self, account: &str, rate: impl Fn(&str) -> Option<f64>) -> Option<(f64, Vec<String>)> {
+        let balances = self.balances(account);
+        if balances.is_empty() { return None; }
+        let mut unconverted = Vec::new();
+        let total = balances.iter().map(|(c, b)| rate(c).map(|r| b * r).unwrap_or_else(|| { unconverted.push(c.clone()); 0.0 })).sum();
+        Some((total, unconverted))
+    }
+
+    pub fn get(&self, id: &str) -> Option<Journal> { self.journals.iter().find(|j| j.id == id).cloned() }
+
+    /// Newest first.
+    pub fn journal(&self, q: &JournalQuery) -> Vec<Journal> {
+        self.journals.iter().rev()
+            .filter(|j| q.account.as_ref().is_none_or(|a| &j.account == a) && q.kind.is_none_or(|k| j.kind == k) && q.from.is_none_or(|d| j.date >= d) && q.to.is_none_or(|d| j.date <= d))
+            .take(q.limit.unwrap_or(100)).cloned().collect()
+    }
+
+    fn push(&mut self, account: &str, kind: EntryKind, currency: String, lines: Vec<Line>, meta: (Option<String>, Option<String>, Option<String>), at_ms: u64) -> Journal {
+        let balance = self.balance(account, &currency).unwrap_or(0.0);
+        let date = chrono::DateTime::from_timestamp_millis(at_ms as i64).unwrap_or_default().date_naive();
+        let mut j = Journal { id: uuid::Uuid::new_v4().to_string(), account: account.into(), kind, currency, lines, cash_balance_after: 0.0, date, at_ms, reference: meta.0, note: meta.1, reverses: meta.2, reversed_by: None };
+        j.cash_balance_after = balance + j.cash_effect();
+        self.journals.push(j.clone());
+        j
+    }
+
+    /// Posts in `p.currency`, or else `base_currency`.
+    pub fn post(&mut self, account: &str, base_currency: &str, p: Posting, at_ms: u64) -> Result<Journal, String> {
+        if !p.amount.is_finite() || p.amount == 0.0 { return Err("amount must be non-zero".into()); }
+        let currency = p.currency.as_deref().unwrap_or(base_currency).to_ascii_uppercase();
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) { return Err("currency must be an ISO 4217 code".into()); }
+        let (contra, amount) = match p.kind {
+            EntryKind::Deposit | EntryKind::Withdrawal if p.amount < 0.0 => return Err("deposit and withdrawal amounts must be positive".into()),
+            EntryKind::Adjustment if p.note.as_deref().is_none_or(|n| n.trim().is_empty()) => return Err("adjustments need a note".into()),
+            EntryKind::Reversal => return Err("reversals are made by reversing a journal".into()),
+            EntryKind::Deposit => (Book::Funding, p.amount),
+            EntryKind::Withdrawal => (Book::Funding, -p.amount),
+            EntryKind::RealizedPnl => (Book::RealizedPnl, p.amount),
+            EntryKind::Adjustment => (Book::Adjustments, p.amount),
+        };
+        if let Some(r) = &p.reference { if self.of(account).any(|j| j.reference.as_ref() == Some(r)) { return Err(format!("reference {r} is already posted")); } }
+        let balance = self.balance(account, &currency).unwrap_or(0.0);
+        if p.kind == EntryKind::Withdrawal && p.amount > balance { return Err(format!("withdrawal {:.2} exceeds {currency} cash balance {balance:.2}", p.amount)); }
+        Ok(self.push(account, p.kind, currency, lines(contra, amount), (p.reference, p.note, None), at_ms))
+    }
+
+    /// Posts the opposite of journal `id`. A journal can be reversed once, and reversals cannot be reversed.
+    pub fn reverse(&mut self, id: &str, r: Reverse, at_ms: u64) -> Result<Journal, String> {
+        if r.note.trim().is_empty() { return Err("reversals need a note".into()); }
+        let Some(orig) = self.get(id) else { return Err(format!("journal {id} not found")) };
+        if orig.kind == EntryKind::Reversal { return Err("a reversal cannot itself be reversed".into()); }
+        if let Some(by) = &orig.reversed_by { return Err(format!("journal {id} is already reversed by {by}")); }
+        let lines = orig.lines.iter().map(|l| Line { book: l.book, debit: l.credit, credit: l.debit }).collect();
+        let j = self.push(&orig.account, EntryKind::Reversal, orig.currency.clone(), lines, (None, Some(r.note), Some(orig.id.clone())), at_ms);
+        if let Some(o) = self.journals.iter_mut().find(|x| x.id == orig.id) { o.reversed_by = Some(j.id.clone()); }
+        Ok(j)
+    }
+
+    /// One row per date and currency with postings between `from` and `to`, carrying every book's closing balance
+    /// in that currency.
+    pub fn daily(&self, account: &str, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<DailyBalance> {
+        let (mut balances, mut out): (BTreeMap<&str, BTreeMap<Book, f64>>, Vec<DailyBalance>) = (BTreeMap::new(), Vec::new());
+        for j in self.of(account) {
+            let b = balances.entry(&j.currency).or_default();
+            for l in &j.lines { *b.entry(l.book).or_default() += l.debit - l.credit; }
+            match out.iter_mut().rev().take_while(|d| d.date == j.date).find(|d| d.currency == j.currency) {
+                Some(d) => { d.journals += 1; d.balances = b.clone(); }
+                None => out.push(DailyBalance { date: j.date, currency: j.currency.clone(), journals: 1, balances: b.clone() }),
+            }
+        }
+        out.retain(|d| from.is_none_or(|f| d.date >= f) && to.is_none_or(|t| d.date <= t));
+        out
+    }
+
+    /// Realized P&L booked on `date` in the base currency, reversals included. P&L in a currency with no rate is left out.
+    pub fn realized_on(&self, account: &str, date: NaiveDate, rate: impl Fn(&str) -> Option<f64>) -> f64 {
+        self.of(account).filter(|j| j.date == date).filter_map(|j| Some((rate(&j.currency)?, j)))
+            .flat_map(|(r, j)| j.lines.iter().filter(|l| l.book == Book::RealizedPnl).map(move |l| (l.credit - l.debit) * r)).fold(0.0, |a, b| a + b)
+    }
+
+    /// Equity in `base_currency`, converting each currency's cash at `rate`.
+    pub fn equity(&self, account: &str, base_currency: &str, collateral_value: f64, rate: impl Fn(&str) -> Option<f64>) -> Equity {
+        let live: Vec<&Journal> = self.of(account).filter(|j| j.reversed_by.is_none() && j.reverses.is_none()).collect();
+        let total = |k: EntryKind| live.iter().filter(|j| j.kind == k).filter_map(|j| Some(j.cash_effect() * rate(&j.currency)?)).fold(0.0, |a, b| a + b);
+        let currencies: Vec<CurrencyCash> = self.balances(account).into_iter().map(|(currency, cash_balance)| {
+            let rate = rate(&currency);
+            CurrencyCash { base_value: rate.map(|r| cash_balance * r), currency, cash_balance, rate }
+        }).collect();
+        let cash_balance = currencies.iter().filter_map(|c| c.base_value).sum();
+        Equity {
+            account: account.into(), base_currency: base_currency.into(), cash_balance, deposits: total(EntryKind::Deposit), withdrawals: 0.0 - total(EntryKind::Withdrawal),
+            realized_pnl: total(EntryKind::RealizedPnl), adjustments: total(EntryKind::Adjustment), collateral_value, equity: cash_balance + collateral_value,
+            unconverted: currencies.iter().filter(|c| c.rate.is_none()).map(|c| c.currency.clone()).collect(), currencies,
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::tags::Tags;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope { Firm, Account, Desk, Instrument, Group, Strategy, Algo, Trader, Related }
+
+impl Scope {
+    fn label(self) -> &'static str {
+        match self { Scope::Firm => "Firm", Scope::Account => "Account", Scope::Desk => "Desk", Scope::Instrument => "Instrument", Scope::Group => "Group", Scope::Strategy => "Strategy", Scope::Algo => "Algo", Scope::Trader => "Trader", Scope::Related => "Related" }
+    }
+}
+
+/// Order limits are checked per order, rate limits over a rolling window, and open positions count the instruments
+/// held net of zero across every account in scope once the order fills.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LimitKind { OrderNotional { max: f64 }, OrderQuantity { max: f64 }, OrderRate { max_orders: u32, window_secs: u64 }, OpenPositions { max: usize } }
+
+/// `key` names the account, desk, instrument, instrument group or order tag the limit is held to; firm-wide limits
+/// have none. Tag limits bind every order carrying that tag, whichever account sends it. Related limits are keyed
+/// by an account and bind every account linked to it as one party. `currency` denominates an order notional limit;
+/// without one it is held in the firm's base currency.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct LimitSpec {
+    pub scope: Scope, #[serde(skip_serializing_if = "Option::is_none")] pub key: Option<String>, #[serde(flatten)] pub kind: LimitKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub currency: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Limit {
+    pub id: String, pub scope: Scope, #[serde(skip_serializing_if = "Option::is_none")] pub key: Option<String>, #[serde(flatten)] pub kind: LimitKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub currency: Option<String>, #[serde(default)] pub version: u64, #[serde(default)] pub created_at_ms: u64, #[serde(default)] pub updated_at_ms: u64,
+}
+
+#[derive(Deserialize)]
+pub struct LimitQuery { pub scope: Option<Scope>, pub key: Option<String> }
+
+/// An order as the limits see it: `notional` is the package notional, `legs` are `(instrument, quantity, notional)`
+/// and `groups` maps the leg instruments in a group to it; `related` is the account's related-party group, itself
+/// included. Every limit is multiplied by `scale`, below 1 while the order is in an event window. Notionals are in
+/// the account's base currency. `notional_exempt` orders, those only closing positions, are not held to order
+/// notional limits.
+pub struct Order<'a> { pub account: &'a str, pub desk: Option<&'a str>, pub tags: &'a Tags, pub notional: f64, pub legs: &'a [(String, f64, f64)], pub groups: &'a HashMap<String, String>, pub related: &'a [String], pub scale: f64, pub notional_exempt: bool }
+
+#[derive(Default)]
+pub struct LimitBook { limits: Vec<Limit>, orders: HashMap<String, VecDeque<u64>> }
+
+fn validate(spec: &LimitSpec) -> Result<(), String> {
+    match (spec.scope, spec.key.as_deref()) {
+        (Scope::Firm, Some(_)) => return Err("firm-wide limits take no key".into()),
+        (Scope::Firm, None) => {}
+        (_, None | Some("")) => return Err(format!("{} limits need a key", spec.scope.label().to_lowercase())),
+        _ => {}
+    }
+    if let Some(c) = &spec.currency {
+        if c.len() != 3 || !c.chars().all(|c| c.is_ascii_alphabetic()) { return Err("currency must be an ISO 4217 code".into()); }
+        if !matches!(spec.kind, LimitKind::OrderNotional { .. }) { return Err("only order notional limits take a currency".into()); }
+    }
+    match spec.kind {
+        LimitKind::OrderNotional { max } | LimitKind::OrderQuantity { max } if !(max.is_finite() && max > 0.0) => Err("max must be positive".into()),
+        LimitKind::OrderRate { max_orders, window_secs } if max_orders == 0 || window_secs == 0 => Err("max_orders and window_secs must be positive".into()),
+        LimitKind::OpenPositions { .. } if !matches!(spec.scope, Scope::Firm | Scope::Account | Scope::Desk | Scope::Related) => Err("open position limits apply to firm, account, desk or related accounts".into()),
+        _ => Ok(()),
+    }
+}
+
+impl LimitSpec {
+    pub fn validate(&self) -> Result<(), String> { validate(self) }
+}
+
+impl Limit {
+    fn applies(&self, o: &Order) -> bool {
+        let key = self.key.as_deref();
+        match self.scope {
+            Scope::Firm => true,
+            Scope::Account => key == Some(o.account),
+            Scope::Desk => key.is_some() && key == o.desk,
+            Scope::Instrument => o.legs.iter().any(|l| Some(l.0.as_str()) == key),
+            Scope::Group => o.legs.iter().any(|l| o.groups.get(&l.0).map(String::as_str) == key),
+            Scope::Strategy => key.is_some() && key == o.tags.strategy.as_deref(),
+            Scope::Algo => key.is_some() && key == o.tags.algo.as_deref(),
+            Scope::Trader => key.is_some() && key == o.tags.trader.as_deref(),
+            Scope::Related => o.related.iter().any(|a| Some(a.as_str()) == key),
+        }
+    }
+
+    /// Legs the limit looks at: all of them, or only those in the instrument or group for instrument and group limits.
+    fn legs<'a>(&'a self, o: &'a Order) -> impl Iterator<Item = &'a (String, f64, f64)> + 'a {
+        o.legs.iter().filter(move |l| match self.scope {
+            Scope::Instrument => self.key.as_ref() == Some(&l.0),
+            Scope::Group => self.key.is_some() && self.key.as_ref() == o.groups.get(&l.0),
+            _ => true,
+        })
+    }
+
+    fn amount(&self, x: f64) -> String { self.currency.as_ref().map_or_else(|| format!("{x:.2}"), |c| format!("{x:.2} {c}")) }
+
+    fn breach(&self, what: String, cap: String, value: String) -> String {
+        let on = self.key.as_ref().map(|k| format!(" on {k}")).unwrap_or_default();
+        format!("{} max {what} {cap} exceeded{on}: {value} (limit {})", self.scope.label(), self.id)
+    }
+}
+
+impl LimitBook {
+    pub fn list(&self, q: &LimitQuery) -> Vec<Limit> {
+        self.limits.iter().filter(|l| q.scope.is_none_or(|s| l.scope == s) && q.key.as_ref().is_none_or(|k| l.key.as_ref() == Some(k))).cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Limit> { self.limits.iter().find(|l| l.id == id).cloned() }
+
+    /// The same limits with nothing yet counted against the rate limits.
+    pub fn config_copy(&self) -> Self { Self { limits: self.limits.clone(), orders: HashMap::new() } }
+
+    /// Adds saved limits whose ids are not already configured; returns how many were added.
+    pub fn restore(&mut self, limits: Vec<Limit>) -> usize {
+        let before = self.limits.len();
+        for l in limits { if !self.limits.iter().any(|e| e.id == l.id) { self.limits.push(l); } }
+        self.limits.len() - before
+    }
+
+    /// Replaces every limit with `limits`, keeping their ids. A limit whose scope, key and kind are unchanged keeps
+    /// its version and the orders counted against it; a changed one is versioned as an update.
+    pub fn replace(&mut self, limits: Vec<Limit>, now_ms: u64) -> Result<(), String> {
+        let mut next: Vec<Limit> = Vec::with_capacity(limits.len());
+        for mut l in limits {
+            if l.id.trim().is_empty() { return Err("limits need an id".into()); }
+            if next.iter().any(|n| n.id == l.id) { return Err(format!("limit {} appears twice", l.id)); }
+            l.currency = l.currency.map(|c| c.to_ascii_uppercase());
+            validate(&LimitSpec { scope: l.scope, key: l.key.clone(), kind: l.kind.clone(), currency: l.currency.clone() }).map_err(|e| format!("limit {}: {e}", l.id))?;
+            match self.limits.iter().find(|c| c.id == l.id) {
+                Some(c) if (c.scope, &c.key, &c.kind, &c.currency) == (l.scope, &l.key, &l.kind, &l.currency) => l = c.clone(),
+                Some(c) => (l.version, l.created_at_ms, l.updated_at_ms) = (c.version + 1, c.created_at_ms, now_ms),
+                None => (l.version, l.created_at_ms, l.updated_at_ms) = (1, now_ms, now_ms),
+            }
+            next.push(l);
+        }
+        self.orders.retain(|id, _| next.iter().any(|l| &l.id == id));
+        self.limits = next;
+        Ok(())
+    }
+
+    pub fn create(&mut self, spec: LimitSpec, now_ms: u64) -> Result<Limit, String> {
+        validate(&spec)?;
+        let currency = spec.currency.map(|c| c.to_ascii_uppercase());
+        let l = Limit { id: uuid::Uuid::new_v4().to_string(), scope: spec.scope, key: spec.key, kind: spec.kind, currency, version: 1, created_at_ms: now_ms, updated_at_ms: now_ms };
+        self.limits.push(l.clone());
+        Ok(l)
+    }
+
+    /// `Ok(None)` when there is no such limit. Changing a rate limit keeps the orders already counted.
+    pub fn update(&mut self, id: &str, spec: LimitSpec, now_ms: u64) -> Result<Option<Limit>, String> {
+        validate(&spec)?;
+        let Some(l) = self.limits.iter_mut().find(|l| l.id == id) else { return Ok(None) };
+        (l.scope, l.key, l.kind, l.currency, l.version, l.updated_at_ms) = (spec.scope, spec.key, spec.kind, spec.currency.map(|c| c.to_ascii_uppercase()), l.version + 1, now_ms);
+        Ok(Some(l.clone()))
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.orders.remove(id);
+        let n = self.limits.len();
+        self.limits.retain(|l| l.id != id);
+        n != self.limits.len()
+    }
+
+    pub fn applicable(&self, o: &Order) -> Vec<Limit> { self.limits.iter().filter(|l| l.applies(o)).cloned().collect() }
+
+    /// One reason per breached limit. `open` gives a limit's open position count before and after the order; the
+    /// count only blocks orders that raise it past the cap. `fx` converts the order's notional into the currency a
+    /// notional limit is held in, or says why it cannot.
+    pub fn evaluate(&mut self, o: &Order, open: impl Fn(&Limit) -> (usize, usize), fx: impl Fn(&Limit) -> Result<f64, String>, now_ms: u64) -> Vec<String> {
+        let mut r = Vec::new();
+        for l in self.limits.iter().filter(|l| l.applies(o)) {
+            match l.kind {
+                LimitKind::OrderNotional { .. } if o.notional_exempt => {}
+                LimitKind::OrderNotional { max } => {
+                    let max = max * o.scale;
+                    let n = if matches!(l.scope, Scope::Instrument | Scope::Group) { l.legs(o).map(|x| x.2.abs()).sum() } else { o.notional };
+                    match fx(l).map(|rate| n * rate) {
+                        Ok(n) if n > max => r.push(l.breach("order notional".into(), l.amount(max), l.amount(n))),
+                        Ok(_) => {}
+                        Err(e) => r.push(e),
+                    }
+                }
+                LimitKind::OrderQuantity { max } => {
+                    let max = max * o.scale;
+                    if let Some(q) = l.legs(o).map(|x| x.1.abs()).filter(|q| *q > max).reduce(f64::max) { r.push(l.breach("order quantity".into(), format!("{max}"), format!("{q}"))); }
+                }
+                LimitKind::OrderRate { max_orders, window_secs } => {
+                    let max_orders = (max_orders as f64 * o.scale).floor() as u32;
+                    let q = self.orders.entry(l.id.clone()).or_default();
+                    while q.front().is_some_and(|t| t + window_secs * 1000 <= now_ms) { q.pop_front(); }
+                    if q.len() as u32 >= max_orders { r.push(l.breach("orders".into(), format!("{max_orders} per {window_secs}s"), (q.len() + 1).to_string())); }
+                }
+                LimitKind::OpenPositions { max } => {
+                    let max = (max as f64 * o.scale).floor() as usize;
+                    let (before, after) = open(l);
+                    if after > max && after > before { r.push(l.breach("open positions".into(), max.to_string(), after.to_string())); }
+                }
+            }
+        }
+        r
+    }
+
+    /// Counts an accepted order against every rate limit it falls under.
+    pub fn record(&mut self, o: &Order, now_ms: u64) {
+        for l in self.limits.iter().filter(|l| matches!(l.kind, LimitKind::OrderRate { .. }) && l.applies(o)) { self.orders.entry(l.id.clone()).or_default().push_back(now_ms); }
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HookTarget { Webhook { url: String } }
+
+#[derive(Serialize, Clone)]
+pub struct Hook { pub id: String, #[serde(flatten)] pub target: HookTarget, pub created_at_ms: u64 }
+
+#[derive(Serialize, Clone)]
+pub struct PlanLine { pub instrument: String, pub side: String, pub quantity: f64, pub est_notional: f64, pub margin_release: f64, pub est_cost: f64 }
+
+#[derive(Serialize, Clone)]
+pub struct Delivery { pub hook_id: String, pub at_ms: u64, pub ok: bool, pub detail: String }
+
+#[derive(Serialize, Clone)]
+pub struct Liquidation {
+    pub id: String, pub account: String, pub triggered_at_ms: u64, pub trigger_utilization_pct: f64, pub shortfall: f64, pub plan: Vec<PlanLine>, pub est_liquidation_cost: f64,
+    pub deliveries: Vec<Delivery>, pub latest_utilization_pct: f64, pub risk_reduced: bool, pub resolved_at_ms: Option<u64>,
+}
+
+/// Sells the largest exposures first until the released margin covers the shortfall. `cost(instrument, quantity,
+/// price)` is what trading the line is expected to give up to spread and market impact.
+pub fn plan(positions: &[(String, f64, f64)], shortfall: f64, margin_rate: f64, cost: impl Fn(&str, f64, f64) -> f64) -> Vec<PlanLine> {
+    let mut sorted: Vec<_> = positions.iter().filter(|(_, q, _)| *q != 0.0).collect();
+    sorted.sort_by(|a, b| (b.1 * b.2).abs().total_cmp(&(a.1 * a.2).abs()));
+    let mut released = 0.0;
+    let mut out = Vec::new();
+    for (instrument, qty, price) in sorted {
+        if released >= shortfall { break; }
+        let full = (qty * price).abs() * margin_rate;
+        let frac = ((shortfall - released) / full).min(1.0);
+        let quantity = (qty.abs() * frac).ceil().min(qty.abs());
+        let release = quantity * price.abs() * margin_rate;
+        released += release;
+        out.push(PlanLine { instrument: instrument.clone(), side: if *qty > 0.0 { "sell".into() } else { "buy".into() }, quantity, est_notional: quantity * price.abs(), margin_release: release, est_cost: cost(instrument, quantity, *price) });
+    }
+    out
+}
+
+pub struct Liquidations { pub threshold_pct: f64, hooks: Vec<Hook>, events: Vec<Liquidation> }
+
+impl Liquidations {
+    pub fn new(threshold_pct: f64) -> Self { Self { threshold_pct, hooks: Vec::new(), events: Vec::new() } }
+
+    pub fn hooks(&self) -> Vec<Hook> { self.hooks.clone() }
+    pub fn add_hook(&mut self, target: HookTarget, now_ms: u64) -> Hook {
+        let h = Hook { id: uuid::Uuid::new_v4().to_string(), target, created_at_ms: now_ms };
+        self.hooks.push(h.clone());
+        h
+    }
+    pub fn remove_hook(&mut self, id: &str) -> bool { let n = self.hooks.len(); self.hooks.retain(|h| h.id != id); n != self.hooks.len() }
+
+    pub fn list(&self, account: Option<&str>) -> Vec<Liquidation> { self.events.iter().rev().filter(|e| account.is_none_or(|a| e.account == a)).cloned().collect() }
+
+    /// Opens a liquidation when the account crosses the threshold (at most one open per account) and tracks
+    /// whether utilization subsequently came down. Returns a newly opened liquidation for hook dispatch; `plan` is
+    /// only built when one opens.
+    pub fn observe(&mut self, account: &str, utilization_pct: f64, shortfall: f64, plan: impl FnOnce() -> Vec<PlanLine>, now_ms: u64) -> Option<Liquidation> {
+        if let Some(e) = self.events.iter_mut().find(|e| e.account == account && e.resolved_at_ms.is_none()) {
+            e.latest_utilization_pct = utilization_pct;
+            e.risk_reduced = utilization_pct < e.trigger_utilization_pct;
+            if utilization_pct < self.threshold_pct { e.resolved_at_ms = Some(now_ms); }
+            return None;
+        }
+        if utilization_pct < self.threshold_pct { return None; }
+        let plan = plan();
+        let e = Liquidation {
+            id: uuid::Uuid::new_v4().to_string(), account: account.into(), triggered_at_ms: now_ms, trigger_utilization_pct: utilization_pct, shortfall,
+            est_liquidation_cost: plan.iter().map(|l| l.est_cost).fold(0.0, |a, b| a + b), plan, deliveries: Vec::new(), latest_utilization_pct: utilization_pct, risk_reduced: false, resolved_at_ms: None,
+        };
+        self.events.push(e.clone());
+        Some(e)
+    }
+
+    pub fn record_delivery(&mut self, id: &str, d: Delivery) {
+        if let Some(e) = self.events.iter_mut().find(|e| e.id == id) { e.deliveries.push(d); }
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// `depth` is the quantity typically resting at the touch on either side.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct LiquidityParams { pub spread_bps: f64, pub adv: Option<f64>, pub daily_vol: f64, pub depth: Option<f64> }
+
+impl Default for LiquidityParams {
+    fn default() -> Self { Self { spread_bps: 10.0, adv: None, daily_vol: 0.02, depth: None } }
+}
+
+/// 0 (illiquid) to 100. See [`LiquidityBook::score`] for how the inputs are weighted.
+#[derive(Serialize)]
+pub struct LiquidityScore { pub instrument: String, pub score: f64, pub adv: Option<f64>, pub spread_bps: f64, pub depth: Option<f64> }
+
+#[derive(Deserialize)]
+pub struct HorizonQuery { pub max_days: Option<f64>, pub account: Option<String> }
+
+#[derive(Serialize)]
+pub struct PositionHorizon { pub instrument: String, pub quantity: f64, pub notional: f64, pub score: f64, pub days_to_liquidate: Option<f64> }
+
+/// `slow_notional` is the notional of positions over the horizon or with no ADV to judge them by.
+#[derive(Serialize)]
+pub struct AccountHorizon { pub account: String, pub max_days_to_liquidate: Option<f64>, pub gross_notional: f64, pub slow_notional: f64, pub positions: Vec<PositionHorizon> }
+
+#[derive(Serialize)]
+pub struct HorizonReport { pub max_days: f64, pub accounts_checked: usize, pub accounts: Vec<AccountHorizon> }
+
+#[derive(Serialize)]
+pub struct CloseoutLine { pub instrument: String, pub quantity: f64, pub notional: f64, pub spread_cost: f64, pub impact_cost: f64, pub total_cost: f64, pub days_to_liquidate: Option<f64> }
+
+#[derive(Serialize)]
+pub struct Closeout { pub lines: Vec<CloseoutLine>, pub total_spread_cost: f64, pub total_impact_cost: f64, pub total_cost: f64, pub cost_pct_of_gross: f64, pub max_days_to_liquidate: Option<f64> }
+
+/// Liquidity assumptions per instrument plus the coefficients of the square-root impact model.
+#[derive(Clone)]
+pub struct LiquidityBook { params: HashMap<String, LiquidityParams>, pub impact_coef: f64, pub max_participation: f64 }
+
+impl LiquidityBook {
+    pub fn new(impact_coef: f64, max_participation: f64) -> Self { Self { params: HashMap::new(), impact_coef, max_participation } }
+
+    pub fn get(&self, instrument: &str) -> LiquidityParams { self.params.get(instrument).copied().unwrap_or_default() }
+    pub fn all(&self) -> HashMap<String, LiquidityParams> { self.params.clone() }
+    pub fn set(&mut self, instrument: &str, p: LiquidityParams) { self.params.insert(instrument.into(), p); }
+
+    /// Impact cost of trading `quantity` as a fraction of notional: `k * sigma * sqrt(q / ADV)`. Zero without ADV data.
+    pub fn impact_rate(&self, instrument: &str, quantity: f64) -> f64 {
+        let p = self.get(instrument);
+        p.adv.filter(|a| *a > 0.0).map(|adv| self.impact_coef * p.daily_vol * (quantity.abs() / adv).sqrt()).unwrap_or(0.0)
+    }
+
+    /// Weighted blend of ADV (half; full marks at 10M a day, log scale), spread (30%; zero at 100 bps) and
+    /// touch depth (20%; full marks at 100k, log scale). Without depth the other two carry the weight.
+    pub fn score(&self, instrument: &str) -> LiquidityScore {
+        let p = self.get(instrument);
+        let log_score = |x: f64, full: f64| (x.max(1.0).log10() / full.log10()).clamp(0.0, 1.0);
+        let adv = p.adv.map_or(0.0, |a| log_score(a, 1e7));
+        let spread = (1.0 - p.spread_bps / 100.0).clamp(0.0, 1.0);
+        let score = match p.depth {
+            Some(d) => 0.5 * adv + 0.3 * spread + 0.2 * log_score(d, 1e5),
+            None => (0.5 * adv + 0.3 * spread) / 0.8,
+        };
+        LiquidityScore { instrument: instrument.into(), score: score * 100.0, adv: p.adv, spread_bps: p.spread_bps, depth: p.depth }
+    }
+
+    /// Days to unwind each position at the participation cap, slowest first. `None` when the account holds nothing.
+    pub fn horizons(&self, account: &str, positions: &[(String, f64, f64)], max_days: f64) -> Option<AccountHorizon> {
+        let mut lines: Vec<PositionHorizon> = positions.iter().filter(|(_, q, _)| *q != 0.0).map(|(instrument, q, price)| {
+            let days = self.get(instrument).adv.filter(|a| *a > 0.0).map(|adv| q.abs() / (adv * self.max_participation));
+            PositionHorizon { instrument: instrument.clone(), quantity: *q, notional: (q * price).abs(), score: self.score(instrument).score, days_to_liquidate: days }
+        }).collect();
+        if lines.is_empty() { return None; }
+        lines.sort_by(|a, b| b.days_to_liquidate.unwrap_or(f64::INFINITY).total_cmp(&a.days_to_liquidate.unwrap_or(f64::INFINITY)));
+        let gross_notional = lines.iter().map(|l| l.notional).sum();
+        let slow_notional = lines.iter().filter(|l| l.days_to_liquidate.is_none_or(|d| d > max_days)).map(|l| l.notional).fold(0.0, |a, b| a + b);
+        let max_days_to_liquidate = lines.iter().filter_map(|l| l.days_to_liquidate).reduce(f64::max);
+        Some(AccountHorizon { account: account.into(), max_days_to_liquidate, gross_notional, slow_notional, positions: lines })
+    }
+
+    /// Half the spread plus square-root impact on trading `quantity` at `price`.
+    pub fn cost(&self, instrument: &str, quantity: f64, price: f64) -> f64 {
+        (quantity * price).abs() * (self.get(instrument).spread_bps / 2.0 / 10_000.0 + self.impact_rate(instrument, quantity))
+    }
+
+    /// Cost of flattening every position: pay half the spread plus square-root market impact.
+    pub fn closeout(&self, positions: &[(String, f64, f64)]) -> Closeout {
+        let lines: Vec<CloseoutLine> = positions.iter().filter(|(_, q, _)| *q != 0.0).map(|(instrument, q, price)| {
+            let p = self.get(instrument);
+            let notional = (q * price).abs();
+            let spread_cost = notional * p.spread_bps / 2.0 / 10_000.0;
+            let impact_cost = notional * self.impact_rate(instrument, *q);
+            let days = p.adv.filter(|a| *a > 0.0).map(|adv| q.abs() / (adv * self.max_participation));
+            CloseoutLine { instrument: instrument.clone(), quantity: *q, notional, spread_cost, impact_cost, total_cost: spread_cost + impact_cost, days_to_liquidate: days }
+        }).collect();
+        let gross: f64 = lines.iter().map(|l| l.notional).sum();
+        let (spread, impact) = lines.iter().fold((0.0, 0.0), |(s, i), l| (s + l.spread_cost, i + l.impact_cost));
+        let max_days = lines.iter().filter_map(|l| l.days_to_liquidate).reduce(f64::max);
+        Closeout { total_spread_cost: spread, total_impact_cost: impact, total_cost: spread + impact, cost_pct_of_gross: if gross > 0.0 { (spread + impact) / gross * 100.0 } else { 0.0 }, max_days_to_liquidate: max_days, lines }
+    }
+}
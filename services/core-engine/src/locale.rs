@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Languages reason and alert texts are written in. The discriminant indexes the text tables.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale { #[default] En, Ja, Zh }
+
+impl Locale {
+    /// The locale a language tag such as `ja-JP` or `zh-Hant-TW` selects, by its primary subtag.
+    pub fn parse(tag: &str) -> Option<Self> {
+        match tag.trim().split(['-', '_']).next()?.to_ascii_lowercase().as_str() { "en" => Some(Locale::En), "ja" => Some(Locale::Ja), "zh" => Some(Locale::Zh), _ => None }
+    }
+
+    /// The most preferred supported language in an `Accept-Language` header. Ranges weighted `q=0` are refused
+    /// and `*` names no language in particular, so neither selects one.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut ranges: Vec<(f64, Self)> = header.split(',').filter_map(|r| {
+            let mut parts = r.split(';');
+            let locale = Self::parse(parts.next()?)?;
+            let q = parts.find_map(|p| p.trim().strip_prefix("q=")).map_or(Some(1.0), |q| q.trim().parse::<f64>().ok())?;
+            (q > 0.0).then_some((q, locale))
+        }).collect();
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranges.first().map(|r| r.1)
+    }
+}
+
+/// Locale per tenant (account entity), used when a request's `Accept-Language` names none supported; `default`
+/// covers tenants left out and accounts without an entity.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct LocaleConfig { pub default: Locale, #[serde(default)] pub entities: BTreeMap<String, Locale> }
+
+impl LocaleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.entities.keys().any(|e| e.trim().is_empty()) { return Err("entity names must not be empty".into()); }
+        Ok(())
+    }
+
+    /// `Accept-Language` first, then the tenant's setting, then the default.
+    pub fn select(&self, accept_language: Option<&str>, entity: Option<&str>) -> Locale {
+        accept_language.and_then(Locale::from_accept_language).or_else(|| entity.and_then(|e| self.entities.get(e).copied())).unwrap_or(self.default)
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Borrowable quantity the stock-loan desk has left to hand out for one symbol.
+#[derive(Serialize, Clone)]
+pub struct Inventory { pub symbol: String, pub available: f64, pub granted: f64, pub updated_at_ms: u64 }
+
+#[derive(Deserialize)]
+pub struct InventoryUpdate { pub available: f64 }
+
+#[derive(Deserialize)]
+pub struct Replenish { pub quantity: f64 }
+
+#[derive(Deserialize)]
+pub struct LocateRequest { pub account: String, pub symbol: String, pub quantity: f64, #[serde(default)] pub allow_partial: bool }
+
+/// A grant of borrow against which the account may sell short until `expires_at_ms`; `used` is drawn by approved short sales.
+#[derive(Serialize, Clone)]
+pub struct Locate { pub id: String, pub account: String, pub symbol: String, pub requested: f64, pub quantity: f64, pub used: f64, pub granted_at_ms: u64, pub expires_at_ms: u64 }
+
+#[derive(Deserialize)]
+pub struct LocateQuery { pub account: Option<String>, pub symbol: Option<String>, pub active: Option<bool>, pub limit: Option<usize> }
+
+/// Annualized securities-lending fee for shorting the symbol, as quoted by the lending desk or agent.
+#[derive(Serialize, Clone)]
+pub struct BorrowRate { pub symbol: String, pub fee_bps: f64, pub hard_to_borrow: bool, pub updated_at_ms: u64 }
+
+/// `hard_to_borrow` defaults to whether the fee is at or above the specials threshold.
+#[derive(Deserialize)]
+pub struct BorrowRateInput { pub symbol: String, pub fee_bps: f64, pub hard_to_borrow: Option<bool> }
+
+/// What shorting one order line costs to borrow. `score_uplift` is added to the order's risk score.
+#[derive(Serialize)]
+pub struct BorrowCost { pub symbol: String, pub short_quantity: f64, pub fee_bps: f64, pub hard_to_borrow: bool, pub special: bool, pub daily_cost: f64, pub score_uplift: f64 }
+
+/// Risk-score penalty for shorting a hard-to-borrow name, scaled by the short share of the order.
+const HTB_PENALTY: f64 = 0.1;
+
+/// `enforce` extends the locate requirement from orders marked short to every sell that takes the position below flat.
+pub struct LocateBook { inventory: HashMap<String, Inventory>, locates: Vec<Locate>, rates: HashMap<String, BorrowRate>, pub ttl_ms: u64, pub enforce: bool, pub special_fee_bps: f64 }
+
+impl Locate {
+    fn remaining(&self, now_ms: u64) -> f64 { if now_ms < self.expires_at_ms { (self.quantity - self.used).max(0.0) } else { 0.0 } }
+}
+
+impl LocateBook {
+    pub fn new(ttl_ms: u64, enforce: bool, special_fee_bps: f64) -> Self { Self { inventory: HashMap::new(), locates: Vec::new(), rates: HashMap::new(), ttl_ms, enforce, special_fee_bps } }
+
+    pub fn inventory(&self) -> Vec<Inventory> {
+        let mut v: Vec<Inventory> = self.inventory.values().cloned().collect();
+        v.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        v
+    }
+    pub fn get_inventory(&self, symbol: &str) -> Option<&Inventory> { self.inventory.get(symbol) }
+
+    /// Loads the desk's borrowable quantity for the symbol, replacing whatever was left.
+    pub fn set_inventory(&mut self, symbol: &str, available: f64, now_ms: u64) -> Result<Inventory, String> {
+        if available.is_nan() || available < 0.0 { return Err("available must be non-negative".into()); }
+        let i = self.inventory.entry(symbol.into()).or_insert_with(|| Inventory { symbol: symbol.into(), available: 0.0, granted: 0.0, updated_at_ms: now_ms });
+        i.available = available;
+        i.updated_at_ms = now_ms;
+        Ok(i.clone())
+    }
+
+    pub fn replenish(&mut self, symbol: &str, quantity: f64, now_ms: u64) -> Result<Inventory, String> {
+        if quantity.is_nan() || quantity <= 0.0 { return Err("quantity must be positive".into()); }
+        let available = self.inventory.get(symbol).map_or(0.0, |i| i.available);
+        self.set_inventory(symbol, available + quantity, now_ms)
+    }
+
+    /// Grants a locate out of inventory. Without `allow_partial` the full quantity must be available.
+    pub fn request(&mut self, req: &LocateRequest, now_ms: u64) -> Result<Locate, String> {
+        if req.quantity.is_nan() || req.quantity <= 0.0 { return Err("quantity must be positive".into()); }
+        let inv = self.inventory.get_mut(&req.symbol).filter(|i| i.available > 0.0).ok_or_else(|| format!("no borrow available for {}", req.symbol))?;
+        if inv.available < req.quantity && !req.allow_partial { return Err(format!("only {} of {} available to borrow", inv.available, req.quantity)); }
+        let quantity = req.quantity.min(inv.available);
+        inv.available -= quantity;
+        inv.granted += quantity;
+        inv.updated_at_ms = now_ms;
+        let l = Locate {
+            id: format!("LOC-{}", uuid::Uuid::new_v4().simple()), account: req.account.clone(), symbol: req.symbol.clone(), requested: req.quantity, quantity, used: 0.0,
+            granted_at_ms: now_ms, expires_at_ms: now_ms + self.ttl_ms,
+        };
+        self.locates.push(l.clone());
+        Ok(l)
+    }
+
+    /// Unused, unexpired located quantity the account holds in the symbol.
+    pub fn located(&self, account: &str, symbol: &str, now_ms: u64) -> f64 {
+        self.locates.iter().filter(|l| l.account == account && l.symbol == symbol).map(|l| l.remaining(now_ms)).fold(0.0, |a, b| a + b)
+    }
+
+    /// Draws `quantity` from the account's locates in the symbol, soonest-expiring first.
+    pub fn consume(&mut self, account: &str, symbol: &str, mut quantity: f64, now_ms: u64) {
+        let mut open: Vec<&mut Locate> = self.locates.iter_mut().filter(|l| l.account == account && l.symbol == symbol && l.remaining(now_ms) > 0.0).collect();
+        open.sort_by_key(|l| l.expires_at_ms);
+        for l in open {
+            if quantity <= 0.0 { break; }
+            let take = l.remaining(now_ms).min(quantity);
+            l.used += take;
+            quantity -= take;
+        }
+    }
+
+    /// Replaces the rate for every symbol in the batch; symbols not in it keep their last rate.
+    pub fn load_rates(&mut self, rows: Vec<BorrowRateInput>, now_ms: u64) -> Result<usize, String> {
+        if let Some(r) = rows.iter().find(|r| r.fee_bps.is_nan() || r.fee_bps < 0.0) { return Err(format!("fee_bps for {} must be non-negative", r.symbol)); }
+        let n = rows.len();
+        for r in rows {
+            let hard_to_borrow = r.hard_to_borrow.unwrap_or(r.fee_bps >= self.special_fee_bps);
+            self.rates.insert(r.symbol.clone(), BorrowRate { symbol: r.symbol, fee_bps: r.fee_bps, hard_to_borrow, updated_at_ms: now_ms });
+        }
+        Ok(n)
+    }
+
+    pub fn rates(&self) -> Vec<BorrowRate> {
+        let mut v: Vec<BorrowRate> = self.rates.values().cloned().collect();
+        v.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        v
+    }
+
+    /// Borrow cost of shorting `short_quantity` at `price` within an order of `order_notional`; `None` without a rate on file.
+    pub fn borrow_cost(&self, symbol: &str, short_quantity: f64, price: f64, order_notional: f64) -> Option<BorrowCost> {
+        let r = self.rates.get(symbol)?;
+        let short_notional = short_quantity * price;
+        let share = if order_notional > 0.0 { (short_notional / order_notional).min(1.0) } else { 0.0 };
+        let penalty = r.fee_bps / 10_000.0 + if r.hard_to_borrow { HTB_PENALTY } else { 0.0 };
+        Some(BorrowCost {
+            symbol: symbol.into(), short_quantity, fee_bps: r.fee_bps, hard_to_borrow: r.hard_to_borrow, special: r.fee_bps > self.special_fee_bps,
+            daily_cost: short_notional * r.fee_bps / 10_000.0 / 360.0, score_uplift: share * penalty,
+        })
+    }
+
+    /// Newest first.
+    pub fn list(&self, q: &LocateQuery, now_ms: u64) -> Vec<Locate> {
+        self.locates.iter().rev()
+            .filter(|l| q.account.as_ref().is_none_or(|a| &l.account == a) && q.symbol.as_ref().is_none_or(|s| &l.symbol == s))
+            .filter(|l| q.active.is_none_or(|a| (l.remaining(now_ms) > 0.0) == a))
+            .take(q.limit.unwrap_or(100)).cloned().collect()
+    }
+}
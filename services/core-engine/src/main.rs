@@ -1,58 +1,1252 @@
-use axum::{extract::State, response::Json, routing::{get, post}, Router};
+use axum::{extract::{rejection::JsonRejection, Path, Query, State}, http::{HeaderMap, Method, StatusCode}, response::Json, routing::{delete, get, post, put}, Router};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
-struct AppState { start_time: Instant, stats: Mutex<Stats> }
-struct Stats { total_checks: u64, total_margin_calcs: u64, total_alerts: u64, trades_blocked: u64 }
+mod accounts;
+mod algo;
+mod alerts;
+mod audit;
+mod auth;
+mod backfill;
+mod beta;
+mod breaches;
+mod budget;
+mod calendar;
+mod checks;
+mod circuit_breaker;
+mod collateral;
+mod compression;
+mod concentration;
+mod config_bundle;
+mod corporate_actions;
+mod correlation;
+mod crif;
+mod daily;
+mod deadline;
+mod entitlements;
+mod erroneous;
+mod events;
+mod factor_risk;
+mod feed;
+mod fat_finger;
+mod firm;
+mod fix;
+mod faults;
+mod frtb;
+mod fx_exposure;
+mod fx_rates;
+mod fx_settlement;
+mod greeks;
+mod groups;
+mod hedge;
+mod grpc;
+mod history;
+mod kafka;
+mod kill_switch;
+mod ledger;
+mod limits;
+mod liquidation;
+mod liquidity;
+mod locale;
+mod locates;
+mod margin;
+mod margin_calls;
+mod margin_cycles;
+mod marketdata;
+mod metrics;
+mod optimizer;
+mod otc;
+mod overrides;
+mod policy_packs;
+mod pools;
+mod positions;
+mod reason_codes;
+mod related;
+mod reservations;
+mod reverse;
+mod scenarios;
+mod settlement;
+mod schedule;
+mod simm;
+mod span;
+mod staging;
+mod store;
+mod stress_runs;
+mod suite;
+mod synthetic;
+mod tags;
+mod var;
+mod velocity;
+mod warmup;
+mod wash;
+
+struct AppState {
+    start_time: Instant,
+    stats: Mutex<store::Counters>,
+    checks: Mutex<checks::CheckLog>,
+    tag_stats: Mutex<tags::TagStats>,
+    metrics: Mutex<metrics::Metrics>,
+    auth: Mutex<auth::Auth>,
+    warmup: Mutex<warmup::Warmup>,
+    velocity: Mutex<velocity::VelocityBook>,
+    daily: Mutex<daily::DailyBook>,
+    schedules: Mutex<schedule::Schedules>,
+    calendar: Mutex<calendar::Calendar>,
+    algo_limits: Mutex<algo::AlgoLimits>,
+    scenarios: Mutex<scenarios::ScenarioLibrary>,
+    suite: Mutex<suite::StressSuite>,
+    stress_runs: Mutex<stress_runs::StressRuns>,
+    entitlements: Mutex<entitlements::EntitlementBook>,
+    accounts: Mutex<accounts::AccountBook>,
+    positions: Mutex<positions::PositionBook>,
+    drop_copy: Mutex<fix::DropCopy>,
+    otc: Mutex<otc::OtcBook>,
+    audit: Mutex<audit::AuditLog>,
+    alerts: Mutex<alerts::AlertLog>,
+    breaches: Mutex<breaches::BreachBook>,
+    overrides: Mutex<overrides::Overrides>,
+    reservations: Mutex<reservations::Reservations>,
+    margin_calls: Mutex<margin_calls::MarginCalls>,
+    margin_cycles: Mutex<margin_cycles::MarginCycles>,
+    liquidations: Mutex<liquidation::Liquidations>,
+    liquidity: Mutex<liquidity::LiquidityBook>,
+    locates: Mutex<locates::LocateBook>,
+    correlations: Mutex<correlation::Correlations>,
+    corporate_actions: Mutex<corporate_actions::CorporateActions>,
+    collateral: Mutex<collateral::CollateralBook>,
+    kill_switches: Mutex<kill_switch::KillSwitches>,
+    ledger: Mutex<ledger::Ledger>,
+    limits: Mutex<limits::LimitBook>,
+    marketdata: Mutex<marketdata::MarketData>,
+    margins: Mutex<HashMap<String, margin::MarginSnapshot>>,
+    crif: Mutex<HashMap<String, crif::CrifUpload>>,
+    history: Mutex<history::PriceHistory>,
+    trade_history: Mutex<backfill::TradeHistory>,
+    backfills: Mutex<backfill::Jobs>,
+    settlement: Mutex<settlement::SettlementBook>,
+    fx_settlement: Mutex<fx_settlement::FxSettlementBook>,
+    fx_limits: Mutex<fx_exposure::FxLimits>,
+    fx_rates: Mutex<fx_rates::FxRates>,
+    desk_limits: Mutex<beta::DeskLimits>,
+    factor_model: Mutex<factor_risk::FactorModel>,
+    circuit_breakers: Mutex<circuit_breaker::CircuitBreakers>,
+    erroneous: Mutex<erroneous::ErroneousOrders>,
+    fat_finger: Mutex<fat_finger::FatFingerConfig>,
+    concentration_limits: Mutex<concentration::ConcentrationLimits>,
+    wash_trades: Mutex<wash::WashTrades>,
+    hedging: Mutex<hedge::HedgePolicy>,
+    staging: Mutex<staging::Staging>,
+    classifications: Mutex<BTreeMap<String, concentration::Classification>>,
+    groups: Mutex<groups::Groups>,
+    related: Mutex<related::RelatedAccounts>,
+    pools: Mutex<pools::Pools>,
+    firm_contributions: Mutex<firm::Contributions>,
+    feeds: Mutex<Vec<feed::FeedStatus>>,
+    reason_codes: Mutex<reason_codes::Catalog>,
+    locales: Mutex<locale::LocaleConfig>,
+    reason_counts: Mutex<std::collections::BTreeMap<reason_codes::Code, u64>>,
+    span: Mutex<span::SpanConfig>,
+    latency_budget: Mutex<budget::LatencyBudget>,
+    faults: Mutex<faults::Faults>,
+    default_funds: f64,
+    max_liquidation_days: f64,
+    var_lookback_days: usize,
+    /// What firm-wide figures and limits are held in, and new accounts' base currency by default.
+    firm_currency: String,
+    require_if_match: bool,
+    /// Checks with less than this left before the client's deadline are refused as timed out instead of run.
+    deadline_min_ms: u64,
+    sandboxes: Mutex<HashMap<String, Sandbox>>,
+    alert_stream: tokio::sync::broadcast::Sender<alerts::Alert>,
+    store: Option<Arc<dyn store::Store>>,
+    persist: Option<tokio::sync::mpsc::UnboundedSender<store::Record>>,
+    event_bus: Option<events::BusConfig>,
+    events: Option<tokio::sync::mpsc::UnboundedSender<events::Event>>,
+    event_stats: Mutex<events::BusStats>,
+    http: reqwest::Client,
+}
+
+/// A tenant's isolated engine; background jobs such as margin call escalation run in production only.
+struct Sandbox { state: Arc<AppState>, created_at_ms: u64 }
+
+#[derive(Serialize)]
+struct SandboxInfo { tenant: String, created_at_ms: u64, accounts: usize }
+
+#[derive(Serialize)]
+struct Err { error: String, #[serde(skip_serializing_if = "Option::is_none")] details: Option<String> }
+type ApiResult<T> = Result<Json<T>, (StatusCode, Json<Err>)>;
+
+fn bad_request(e: impl ToString) -> (StatusCode, Json<Err>) { (StatusCode::BAD_REQUEST, Json(Err { error: "Invalid request".into(), details: Some(e.to_string()) })) }
+fn unavailable(e: impl ToString) -> (StatusCode, Json<Err>) { (StatusCode::SERVICE_UNAVAILABLE, Json(Err { error: "Service unavailable".into(), details: Some(e.to_string()) })) }
+/// Order and fill bodies whose fields do not parse, such as an unknown side, are refused as bad requests.
+fn json_body<T>(body: Result<Json<T>, JsonRejection>) -> Result<T, (StatusCode, Json<Err>)> { body.map(|Json(t)| t).map_err(|e| bad_request(e.body_text())) }
+fn not_found(what: &str) -> (StatusCode, Json<Err>) { (StatusCode::NOT_FOUND, Json(Err { error: format!("{what} not found"), details: None })) }
+
+/// The strong entity tag of a configuration resource at `version`.
+fn etag(version: impl std::fmt::Display) -> String { format!("\"{version}\"") }
+
+type Tagged<T> = ([(axum::http::header::HeaderName, String); 1], Json<T>);
+/// Per-limit FX rates and open-position counts, by limit id.
+type LimitInputs = (Vec<(String, Result<f64, String>)>, Vec<(String, (usize, usize))>);
+
+fn tagged<T>(version: impl std::fmt::Display, body: T) -> Tagged<T> { ([(axum::http::header::ETAG, etag(version))], Json(body)) }
+
+/// Refuses a change made against another version than `current`, so two editors cannot silently overwrite each
+/// other. Callers take the resource's lock before reading `current` and keep it through the write. A change without
+/// If-Match goes through unless RISK_REQUIRE_IF_MATCH is set.
+fn check_if_match(s: &AppState, h: &HeaderMap, current: impl std::fmt::Display) -> Result<(), (StatusCode, Json<Err>)> {
+    let tag = etag(current);
+    let Some(m) = h.get(axum::http::header::IF_MATCH).and_then(|v| v.to_str().ok()) else {
+        if !s.require_if_match { return Ok(()); }
+        return Err((StatusCode::PRECONDITION_REQUIRED, Json(Err { error: "If-Match required".into(), details: Some(format!("send the ETag of the version being changed, currently {tag}")) })));
+    };
+    if m.split(',').map(str::trim).any(|t| t == "*" || t.trim_start_matches("W/") == tag) { return Ok(()); }
+    Err((StatusCode::PRECONDITION_FAILED, Json(Err { error: "Version mismatch".into(), details: Some(format!("changed since it was read: current version is {tag}")) })))
+}
+
+fn account_error(e: accounts::AccountError) -> (StatusCode, Json<Err>) {
+    match e {
+        accounts::AccountError::NotFound => not_found("Account"),
+        accounts::AccountError::Exists => (StatusCode::CONFLICT, Json(Err { error: "Account already exists".into(), details: None })),
+        accounts::AccountError::Invalid(m) => bad_request(m),
+    }
+}
+
+fn require_account(s: &AppState, id: &str) -> Result<accounts::Account, (StatusCode, Json<Err>)> {
+    s.accounts.lock().unwrap().get(id).cloned().ok_or_else(|| (StatusCode::NOT_FOUND, Json(Err { error: "Account not found".into(), details: Some(id.into()) })))
+}
+
+fn require_open(s: &AppState, id: &str) -> Result<accounts::Account, (StatusCode, Json<Err>)> {
+    let a = require_account(s, id)?;
+    if a.status == accounts::AccountStatus::Closed { return Err((StatusCode::FORBIDDEN, Json(Err { error: "Account closed".into(), details: Some(id.into()) }))); }
+    Ok(a)
+}
+
+fn raise_alert(s: &AppState, kind: &str, severity: alerts::Severity, account: Option<&str>, instrument: Option<&str>, message: String) {
+    raise_tagged_alert(s, kind, severity, account, instrument, message, &tags::Tags::default());
+}
+
+/// An alert raised by an order, carrying the order's tags.
+fn raise_tagged_alert(s: &AppState, kind: &str, severity: alerts::Severity, account: Option<&str>, instrument: Option<&str>, message: String, tags: &tags::Tags) {
+    let a = { let mut log = s.alerts.lock().unwrap(); let a = log.push(kind, severity, account, instrument, message, now_ms()); a.tags = tags.clone(); a.clone() };
+    tracing::warn!(kind = %a.kind, severity = ?a.severity, "{}", a.message);
+    count(s, |st| st.total_alerts += 1);
+    persist(s, store::Record::Alert(a.clone()));
+    publish(s, events::Kind::Alert, a.account.as_deref().or(a.instrument.as_deref()), &a);
+    // Sending only fails when no dashboard is connected.
+    let _ = s.alert_stream.send(a);
+}
+
+/// Queues `r` for the store; does nothing without `RISK_DATABASE_URL`, and never in a sandbox.
+fn persist(s: &AppState, r: store::Record) {
+    // Sending only fails once the writer has stopped, and then there is nowhere left to write.
+    if let Some(tx) = &s.persist { let _ = tx.send(r); }
+}
+
+/// Queues an event for the event bus; does nothing without `RISK_KAFKA_BROKERS`, and never in a sandbox.
+fn publish(s: &AppState, kind: events::Kind, key: Option<&str>, payload: impl Serialize) {
+    // Sending only fails once the publisher has stopped.
+    if let Some(tx) = &s.events { let _ = tx.send(events::Event { kind, key: key.map(Into::into), at_ms: now_ms(), payload: serde_json::to_value(payload).unwrap_or_default() }); }
+}
+
+fn count(s: &AppState, f: impl FnOnce(&mut store::Counters)) {
+    let c = { let mut st = s.stats.lock().unwrap(); f(&mut st); *st };
+    persist(s, store::Record::Counters(c));
+}
+
+/// Keeps the decision in the recent history and queues it for the store.
+fn record_check(s: &AppState, c: checks::CheckRecord) {
+    s.metrics.lock().unwrap().check(&c.kind, c.approved);
+    { let mut n = s.reason_counts.lock().unwrap(); for r in &c.reasons { *n.entry(reason_codes::classify(r)).or_default() += 1; } }
+    s.checks.lock().unwrap().record(c.clone());
+    publish(s, events::Kind::Decision, Some(&c.account), &c);
+    persist(s, store::Record::Check(c));
+}
+
+fn persist_margin(s: &AppState, account: &str, source: &str, initial_margin: f64, maintenance_margin: f64) {
+    let r = store::MarginRecord { id: uuid::Uuid::new_v4().to_string(), account: account.into(), source: source.into(), initial_margin, maintenance_margin, at_ms: now_ms() };
+    publish(s, events::Kind::Margin, Some(account), &r);
+    persist(s, store::Record::Margin(r));
+}
+
+fn record_breach(s: &AppState, kind: breaches::BreachKind, account: Option<&str>, instrument: Option<&str>, key: &str, message: String) {
+    let (b, new) = s.breaches.lock().unwrap().record(kind, account, instrument, key, message, now_ms());
+    if new { tracing::info!(breach = %b.id, kind = ?b.kind, "breach opened: {}", b.message); }
+    if new && kind == breaches::BreachKind::Limit { raise_alert(s, "limit_breach", alerts::Severity::Warning, account, instrument, format!("limit breach {} opened: {}", b.id, b.message)); }
+}
+
+/// Rejections that come from a breached numeric limit, as opposed to entitlements, account status or a limit that
+/// could not be evaluated, by the reason's code rather than its wording.
+fn is_limit(reason: &str) -> bool { reason_codes::classify(reason).is_limit_breach() }
+
+/// Stable part of a limit rejection, without the figures, so repeats of the same limit land on one breach.
+fn limit_key(reason: &str) -> &str { reason.split(|c: char| c == ':' || c.is_ascii_digit()).next().unwrap_or(reason).trim() }
+
+/// Role forwarded by the gateway from the caller's token.
+fn role(h: &HeaderMap) -> Option<&str> { h.get("x-user-role").and_then(|v| v.to_str().ok()) }
+
+const OVERRIDE_ROLE: &str = "risk_officer";
+const STOCK_LOAN_ROLE: &str = "stock_loan";
+const OPS_ROLE: &str = "ops";
+const ADMIN_ROLE: &str = "admin";
+
+/// Position size that reads as 100% used for accounts without a `max_position_notional`.
+const DEFAULT_POSITION_LIMIT: f64 = 1_000_000.0;
+
+fn require_role(h: &HeaderMap, role_name: &str) -> Result<(), (StatusCode, Json<Err>)> {
+    if role(h) == Some(role_name) { Ok(()) } else { Err((StatusCode::FORBIDDEN, Json(Err { error: "Forbidden".into(), details: Some(format!("requires role {role_name}")) }))) }
+}
+
+/// The language texts go back in for a request about an account of `entity`.
+fn locale(s: &AppState, h: &HeaderMap, entity: Option<&str>) -> locale::Locale {
+    s.locales.lock().unwrap().select(h.get(axum::http::header::ACCEPT_LANGUAGE).and_then(|v| v.to_str().ok()), entity)
+}
+
+fn account_locale(s: &AppState, h: &HeaderMap, account: Option<&str>) -> locale::Locale {
+    let entity = account.and_then(|a| s.accounts.lock().unwrap().get(a).and_then(|a| a.entity.clone()));
+    locale(s, h, entity.as_deref())
+}
+
+fn actor(h: &HeaderMap) -> String { h.get("x-user-id").and_then(|v| v.to_str().ok()).unwrap_or("anonymous").into() }
+
+fn audit(s: &AppState, h: &HeaderMap, action: &str, target: &str, details: serde_json::Value) {
+    s.audit.lock().unwrap().record(&actor(h), action, target, details, now_ms());
+}
 
 #[derive(Serialize)]
 struct Health { status: String, version: String, uptime_secs: u64, total_ops: u64 }
 
+/// A single order uses the top-level instrument fields; a spread or combo sends `legs` instead and is decided as one package.
+#[derive(Deserialize, Serialize)]
+struct PreTradeCheckRequest {
+    account: String, #[serde(default)] instrument: String, side: Option<positions::Side>, #[serde(default)] quantity: f64, #[serde(default)] price: f64,
+    asset_class: Option<String>, venue: Option<String>, override_token: Option<String>, #[serde(default)] reserve: bool, #[serde(default)] legs: Vec<OrderLeg>,
+    algo: Option<algo::AlgoParams>, counterparty: Option<String>, value_date: Option<chrono::NaiveDate>, #[serde(default)] tags: tags::Tags,
+    latency_budget_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct OrderLeg { instrument: String, side: positions::Side, quantity: f64, price: f64 }
+
+/// `net_notional` nets signed leg notionals and is what limit and margin-impact checks see.
+#[derive(Serialize)]
+struct PackageSummary { legs: usize, gross_notional: f64, net_notional: f64 }
+#[derive(Serialize)]
+struct PreTradeCheckResponse { check_id: String, approved: bool, reasons: Vec<String>, reason_codes: Vec<reason_codes::Reason>, locale: locale::Locale, risk_score: f64, margin_impact: f64, position_limit_used_pct: f64, daily_headroom: daily::Headroom, schedule: schedule::ActiveRule, elapsed_us: u128,
+    #[serde(skip_serializing_if = "Option::is_none")] package: Option<PackageSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")] algo: Option<algo::AlgoProfile>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] borrow: Vec<locates::BorrowCost>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] events: Vec<calendar::ActiveEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")] greeks: Option<greeks::GreekImpact>,
+    #[serde(skip_serializing_if = "Option::is_none")] beta: Option<beta::BetaImpact>,
+    #[serde(skip_serializing_if = "Option::is_none")] reservation: Option<reservations::Reservation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] overridden: Vec<String>, #[serde(skip_serializing_if = "Option::is_none")] override_status: Option<String>, risk_direction: hedge::Direction,
+    #[serde(skip_serializing_if = "tags::Tags::is_empty")] tags: tags::Tags, #[serde(skip_serializing_if = "Option::is_none")] degraded: Option<budget::Degraded>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")] timed_out: bool, #[serde(skip_serializing_if = "Option::is_none")] trace: Option<Vec<RuleTrace>> }
+
+/// A program trade decided as one unit: every line gets the single-order checks, and the basket as a whole is held
+/// to the account's basket limits, velocity and daily allowance. Nothing is booked unless every line passes.
+#[derive(Deserialize, Serialize)]
+struct BasketCheckRequest { account: String, #[serde(alias = "orders")] lines: Vec<OrderLeg>, asset_class: Option<String>, venue: Option<String>, #[serde(default)] tags: tags::Tags, latency_budget_ms: Option<u64> }
+
+#[derive(Serialize)]
+struct BasketLine {
+    instrument: String, side: positions::Side, quantity: f64, price: f64, notional: f64, approved: bool, reasons: Vec<String>, #[serde(skip_serializing_if = "Vec::is_empty")] flags: Vec<String>,
+    reason_codes: Vec<reason_codes::Reason>,
+}
+
+/// `before` is the account's current net exposure to the sector, from the position book at last prices.
+#[derive(Serialize)]
+struct SectorExposure { sector: String, before: f64, change: f64, after: f64 }
+
+/// `unclassified` lists instruments without a sector or beta in the factor map; they are left out of those exposures.
+#[derive(Serialize)]
+struct BasketCheckResponse {
+    check_id: String, account: String, approved: bool, reasons: Vec<String>, #[serde(skip_serializing_if = "Vec::is_empty")] flags: Vec<String>, reason_codes: Vec<reason_codes::Reason>, locale: locale::Locale,
+    gross_notional: f64, net_notional: f64, beta_exposure_change: f64, risk_direction: hedge::Direction,
+    sectors: Vec<SectorExposure>, unclassified: Vec<String>, margin_impact: f64, daily_headroom: daily::Headroom, lines: Vec<BasketLine>, elapsed_us: u128,
+    #[serde(skip_serializing_if = "Vec::is_empty")] events: Vec<calendar::ActiveEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")] greeks: Option<greeks::GreekImpact>, #[serde(skip_serializing_if = "tags::Tags::is_empty")] tags: tags::Tags,
+    #[serde(skip_serializing_if = "Option::is_none")] degraded: Option<budget::Degraded>, #[serde(skip_serializing_if = "std::ops::Not::not")] timed_out: bool,
+}
+
 #[derive(Deserialize)]
-struct PreTradeCheckRequest { account: String, instrument: String, side: String, quantity: f64, price: f64 }
+struct ExplainQuery { #[serde(default)] explain: bool }
+
+/// One rule as evaluated during a pre-trade check, in evaluation order.
+#[derive(Serialize)]
+struct RuleTrace { rule: String, inputs: serde_json::Value, threshold: serde_json::Value, passed: bool }
+
+fn trace(t: &mut Option<Vec<RuleTrace>>, rule: impl Into<String>, inputs: serde_json::Value, threshold: serde_json::Value, passed: bool) {
+    if let Some(t) = t { t.push(RuleTrace { rule: rule.into(), inputs, threshold, passed }); }
+}
+
+/// Without `positions` the account's booked positions are margined and the result booked; supplied positions are a
+/// what-if whose figures are returned and nothing else.
+#[derive(Deserialize)]
+struct MarginRequest { account: String, positions: Option<Vec<PositionInput>>, #[serde(default)] methodology: margin::Methodology, #[serde(default)] returns: std::collections::BTreeMap<String, Vec<f64>>, var_lookback_days: Option<usize> }
+/// Without `price` the position is marked at the instrument's last quote.
+#[derive(Deserialize, Serialize)]
+struct PositionInput { instrument: String, quantity: f64, price: Option<f64> }
+#[derive(Serialize)]
+struct MarginResponse {
+    account: String, margin_model: margin::MarginModel, model_version: String, initial_margin: f64, maintenance_margin: f64, available_margin: f64, margin_utilization_pct: f64,
+    var_95: f64, var_99: f64, elapsed_us: u128, funds: f64, used_margin: f64, held_margin: f64, held_by_order: Vec<HeldMargin>,
+    breakdown: margin::Breakdown, #[serde(skip_serializing_if = "Option::is_none")] fx: Option<MarginFx>, var_contribution: correlation::VarContribution,
+    var_method: VarMethod, historical_var: var::HistoricalVar, methodology: margin::Methodology, #[serde(skip_serializing_if = "Option::is_none")] span: Option<span::SpanResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] greek_breaches: Vec<String>, what_if: bool,
+}
+
+/// Headline VaR comes from historical simulation once there are enough scenarios, otherwise from the parametric model.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum VarMethod { Historical, Parametric }
+
+/// Fewer daily scenarios than this leave the 99% quantile resting on one or two days.
+const MIN_VAR_SCENARIOS: usize = 20;
+
+#[derive(Deserialize)]
+struct VarQuery { lookback_days: Option<usize> }
+
+#[derive(Serialize)]
+struct AccountVarResponse { account: String, #[serde(skip_serializing_if = "Vec::is_empty")] unpriced: Vec<String>, #[serde(flatten)] var: var::HistoricalVar }
+
+/// Without `positions` the account's own holdings are used. `paths` and `seed` apply to Monte Carlo runs and
+/// `lookback_days` to historical ones; `correlation` overrides the stored correlations for the pairs it lists.
+#[derive(Deserialize)]
+struct VarRequest {
+    account: String, model: var::Model, positions: Option<Vec<PositionInput>>, lookback_days: Option<usize>, paths: Option<usize>, seed: Option<u64>,
+    correlation: Option<var::CorrelationMatrix>,
+}
+
+#[derive(Serialize)]
+struct VarResponse {
+    account: String, model: var::Model, #[serde(skip_serializing_if = "Vec::is_empty")] unpriced: Vec<String>,
+    #[serde(flatten)] historical: Option<var::HistoricalVar>, #[serde(flatten)] modelled: Option<var::ModelVar>, elapsed_us: u128,
+}
+
+#[derive(Serialize)]
+struct FxConversion { currency: String, rate: f64, notional_local: f64, notional_base: f64 }
+
+/// Positions priced in another currency are converted into the account's base currency before margining;
+/// `effect` is how much that moved initial margin. `unconverted` currencies had no rate and stay unconverted.
+#[derive(Serialize)]
+struct MarginFx { base_currency: String, conversions: Vec<FxConversion>, effect: f64, #[serde(skip_serializing_if = "Vec::is_empty")] unconverted: Vec<String> }
+
+/// Margin held by one open order reservation.
+#[derive(Serialize)]
+struct HeldMargin { reservation_id: String, check_id: String, instrument: String, margin: f64, expires_at_ms: u64 }
+
+#[derive(Deserialize)]
+struct MarginCompareRequest { account: String, positions: Option<Vec<PositionInput>>, models: Option<Vec<margin::MarginModel>> }
+#[derive(Serialize)]
+struct ModelResult { model: margin::MarginModel, model_version: String, initial_margin: f64, maintenance_margin: f64, delta_vs_current: f64, delta_pct_vs_current: Option<f64> }
+#[derive(Serialize)]
+struct MarginCompareResponse { account: String, current_model: margin::MarginModel, gross_notional: f64, results: Vec<ModelResult>, elapsed_us: u128 }
+
+#[derive(Deserialize)]
+struct SimmRequest { account: String, sensitivities: Vec<simm::Sensitivity> }
 #[derive(Serialize)]
-struct PreTradeCheckResponse { check_id: String, approved: bool, reasons: Vec<String>, risk_score: f64, margin_impact: f64, position_limit_used_pct: f64, elapsed_us: u128 }
+struct SimmResponse { account: String, #[serde(flatten)] result: simm::SimmResult, elapsed_us: u128 }
 
 #[derive(Deserialize)]
-struct MarginRequest { account: String, positions: Option<Vec<PositionInput>> }
+struct FrtbRequest { account: String, sensitivities: Vec<frtb::Sensitivity> }
+#[derive(Serialize)]
+struct FrtbResponse { account: String, #[serde(flatten)] result: frtb::FrtbResult, elapsed_us: u128 }
+
+#[derive(Serialize)]
+struct CrifSummary { account: String, uploaded_at_ms: u64, rows: usize, sensitivities: usize, skipped: Vec<crif::RowError> }
+
+/// Without `price_change_pct` the move is measured from the reference price to `price`, or to the last tick.
 #[derive(Deserialize)]
-struct PositionInput { instrument: String, quantity: f64, price: f64 }
+struct CircuitBreakerRequest { instrument: String, price_change_pct: Option<f64>, price: Option<f64> }
+#[derive(Serialize)]
+struct CircuitBreakerResponse {
+    instrument: String, triggered: bool, level: String, halt_duration_secs: u64, price_change_pct: f64, #[serde(skip_serializing_if = "Option::is_none")] reference_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")] halt: Option<circuit_breaker::Halt>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StressTestRequest { scenario: Option<String>, shock_pct: Option<f64>, account: Option<String>, positions: Option<Vec<PositionInput>>, from: Option<chrono::NaiveDate>, to: Option<chrono::NaiveDate>, correlation_shift: Option<correlation::CorrelationShift>, steps: Option<Vec<scenarios::Step>>, compounding: Option<scenarios::Compounding> }
+/// `worst_case_loss` includes `liquidation_cost`: spread and square-root impact of unwinding every position after the shock.
+#[derive(Serialize)]
+struct StressTestResponse { run_id: String, scenario: String, portfolio_impact: f64, worst_case_loss: f64, liquidation_cost: f64, instruments_affected: u32, breaches: Vec<String>, #[serde(skip_serializing_if = "Option::is_none")] historical: Option<history::Replay>, #[serde(skip_serializing_if = "Option::is_none")] correlation: Option<correlation::CorrelationImpact>, #[serde(skip_serializing_if = "Option::is_none")] composed: Option<scenarios::Outcome> }
+
+#[derive(Serialize)]
+struct VelocityResponse { account: String, limits: velocity::VelocityLimits, windows: Vec<velocity::WindowState> }
+
+#[derive(Serialize)]
+struct CloseoutResponse { account: String, #[serde(flatten)] closeout: liquidity::Closeout, liquidity_add_on: f64, elapsed_us: u128 }
+
+#[derive(Serialize)]
+struct TickAck { accepted: usize, rejected: usize, revalued_accounts: Vec<String> }
+
+/// `ticks` is filled only when the spec asked not to ingest them.
 #[derive(Serialize)]
-struct MarginResponse { account: String, initial_margin: f64, maintenance_margin: f64, available_margin: f64, margin_utilization_pct: f64, var_95: f64, var_99: f64, elapsed_us: u128 }
+struct SyntheticResponse { #[serde(flatten)] run: synthetic::Generated, #[serde(skip_serializing_if = "Option::is_none")] ack: Option<TickAck>, #[serde(skip_serializing_if = "Vec::is_empty")] ticks: Vec<marketdata::Tick> }
 
+/// Without `loss_threshold` the target is a margin breach: the loss that takes the account's funds below its last initial margin.
 #[derive(Deserialize)]
-struct CircuitBreakerRequest { instrument: String, price_change_pct: f64 }
+struct ReverseStressRequest { account: Option<String>, positions: Option<Vec<PositionInput>>, loss_threshold: Option<f64> }
+
 #[derive(Serialize)]
-struct CircuitBreakerResponse { instrument: String, triggered: bool, level: String, halt_duration_secs: u64, price_change_pct: f64 }
+struct CorrelationsResponse { default: f64, pairs: Vec<correlation::Pair> }
+
+#[derive(Serialize)]
+struct ReverseStressResponse { account: Option<String>, target: &'static str, #[serde(flatten)] result: reverse::ReverseResult }
 
 #[derive(Deserialize)]
-struct StressTestRequest { scenario: Option<String>, shock_pct: Option<f64> }
+struct RangeQuery { from: Option<chrono::NaiveDate>, to: Option<chrono::NaiveDate> }
+
+/// One configured limit for one account. `used_pct` is `utilization / limit * 100`.
+#[derive(Serialize)]
+struct LimitUtilization { account: String, limit_type: String, limit: f64, utilization: f64, used_pct: f64 }
+
+/// Net Greeks across the account and per underlier; `error` says why they could not be computed.
+#[derive(Serialize, Default)]
+struct GreekSummary { total: greeks::Greeks, by_underlier: std::collections::BTreeMap<String, greeks::Greeks>, #[serde(skip_serializing_if = "Option::is_none")] error: Option<String> }
+
+/// `breaches` are the account's Greek caps its holdings already break; holdings in `unpriced` could not be given
+/// Greeks and are left out of every total.
+#[derive(Serialize)]
+struct GreekReport {
+    account: String, total: greeks::Greeks, by_underlier: std::collections::BTreeMap<String, greeks::Greeks>, positions: Vec<greeks::PositionGreeks>,
+    #[serde(skip_serializing_if = "Option::is_none")] limits: Option<greeks::GreekLimits>, breaches: Vec<String>, #[serde(skip_serializing_if = "Vec::is_empty")] unpriced: Vec<String>, as_of_ms: u64,
+}
+
+/// Something holding the account back from trading freely: its status, a kill switch or an open margin call.
+#[derive(Serialize)]
+struct Restriction { kind: &'static str, detail: String, #[serde(skip_serializing_if = "Option::is_none")] since_ms: Option<u64> }
+
+/// Everything a dashboard shows for one account. `margin` is absent until margin has been calculated;
+/// `open_breaches` includes acknowledged ones.
 #[derive(Serialize)]
-struct StressTestResponse { scenario: String, portfolio_impact: f64, worst_case_loss: f64, instruments_affected: u32, breaches: Vec<String> }
+struct RiskSummary {
+    account: String, status: accounts::AccountStatus, positions: Vec<positions::Position>, #[serde(skip_serializing_if = "Vec::is_empty")] unpriced: Vec<String>,
+    margin: Option<margin::MarginSnapshot>, var: var::HistoricalVar, greeks: GreekSummary, limit_utilization: Vec<LimitUtilization>,
+    open_breaches: Vec<breaches::Breach>, restrictions: Vec<Restriction>, as_of_ms: u64,
+}
+
+/// `sort` is `used_pct` (default) or `utilization`; `order` is `desc` (default) or `asc`.
+#[derive(Deserialize)]
+struct UtilizationQuery { account: Option<String>, limit_type: Option<String>, min_pct: Option<f64>, sort: Option<String>, order: Option<String>, limit: Option<usize> }
+
+#[derive(Deserialize)]
+struct DiffQuery { base: String, compare: String }
+
+#[derive(Deserialize)]
+struct LimitQuery { limit: Option<usize> }
+
+#[derive(Deserialize)]
+struct AccountQuery { account: Option<String> }
 
 #[derive(Serialize)]
-struct StatsResponse { total_checks: u64, total_margin_calcs: u64, total_alerts: u64, trades_blocked: u64, block_rate_pct: f64 }
+struct StatsResponse { total_checks: u64, total_margin_calcs: u64, total_alerts: u64, trades_blocked: u64, block_rate_pct: f64, by_tag: tags::TagStats, by_reason_code: std::collections::BTreeMap<reason_codes::Code, u64>, wash_trades: wash::WashStats }
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt().with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "risk_engine=info".into())).init();
-    let state = Arc::new(AppState { start_time: Instant::now(), stats: Mutex::new(Stats { total_checks: 0, total_margin_calcs: 0, total_alerts: 0, trades_blocked: 0 }) });
-    let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any);
-    let app = Router::new()
+    let mut state = new_state();
+    if let Ok(url) = std::env::var("RISK_DATABASE_URL") { open_store(&mut state, &url).await; }
+    let events = events::BusConfig::from_env().map(|c| open_event_bus(&mut state, c));
+    let feeds = config_feeds();
+    *state.feeds.get_mut().unwrap() = feeds.iter().map(feed::FeedStatus::new).collect();
+    let state = Arc::new(state);
+    if let Some((bus, rx)) = events { tokio::spawn(publish_behind(state.clone(), bus, rx)); }
+    for (i, c) in feeds.iter().enumerate() {
+        match c.kind { feed::FeedKind::Websocket => tokio::spawn(run_feed(state.clone(), i, feed::WebSocketSource::new(c))) };
+    }
+    if let Some(store) = state.store.clone() { tokio::spawn(warm_start(state.clone(), store.clone())); tokio::spawn(refresh_api_keys(state.clone(), store)); }
+    match state.auth.lock().unwrap().key_count() {
+        0 => tracing::warn!("no API keys configured; requests are not authenticated"),
+        n => tracing::info!(keys = n, "API key authentication on"),
+    }
+    let bg = state.clone();
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            tick.tick().await;
+            escalate_margin_calls(&bg);
+            expire_reservations(&bg);
+            let due = bg.suite.lock().unwrap().due(chrono::Utc::now());
+            if due { run_stress_suite(&bg, suite::Trigger::Scheduled); }
+            let due = bg.margin_cycles.lock().unwrap().due(now_ms());
+            if due { run_margin_cycle(&bg, suite::Trigger::Scheduled); }
+            activate_staged(&bg).await;
+            let sandboxes: Vec<Arc<AppState>> = bg.sandboxes.lock().unwrap().values().map(|sb| sb.state.clone()).collect();
+            for sb in &sandboxes { activate_staged(sb).await; }
+        }
+    });
+    if let Ok(addr) = std::env::var("RISK_FIX_DROPCOPY_ADDR") { tokio::spawn(run_drop_copy(state.clone(), addr)); }
+    // An empty RISK_GRPC_ADDR leaves the gRPC API off.
+    let grpc_addr = std::env::var("RISK_GRPC_ADDR").unwrap_or_else(|_| "0.0.0.0:9081".into());
+    if !grpc_addr.is_empty() { tokio::spawn(grpc::serve(state.clone(), grpc_addr)); }
+    let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any).expose_headers([axum::http::header::ETAG]);
+    let app = routes()
+        .route("/api/v1/sandboxes", get(list_sandboxes))
+        .route("/api/v1/sandboxes/:tenant", delete(delete_sandbox))
+        .route("/api/v1/sandboxes/:tenant/reset", post(reset_sandbox))
+        .route_layer(axum::middleware::from_fn_with_state(state.clone(), track))
+        .with_state(state.clone());
+    // Sandbox requests are diverted ahead of routing so the sandbox's own router matches them afresh.
+    let app = Router::new().fallback_service(tower::ServiceBuilder::new().layer(axum::middleware::from_fn_with_state(state.clone(), authenticate)).layer(axum::middleware::from_fn_with_state(state, environment)).service(app)).layer(cors).layer(TraceLayer::new_for_http());
+    let addr = std::env::var("RISK_ADDR").unwrap_or_else(|_| "0.0.0.0:8081".into());
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    tracing::info!("Risk Engine on {addr}");
+    axum::serve(listener, app).await.unwrap();
+}
+
+/// Restores counters and alerts from the store and starts writing to it. The engine refuses to start on a store it
+/// cannot open rather than run without persistence that was asked for.
+async fn open_store(state: &mut AppState, url: &str) {
+    let store: Arc<dyn store::Store> = Arc::from(store::connect(url).await.unwrap_or_else(|e| panic!("RISK_DATABASE_URL: {e}")));
+    store.migrate().await.unwrap_or_else(|e| panic!("store migration failed: {e}"));
+    if let Some(c) = store.counters().await.unwrap_or_else(|e| panic!("loading counters failed: {e}")) { *state.stats.get_mut().unwrap() = c; }
+    let restored = store.alerts().await.unwrap_or_else(|e| panic!("loading alerts failed: {e}"));
+    tracing::info!(alerts = restored.len(), "store opened");
+    *state.alerts.get_mut().unwrap() = alerts::AlertLog::restore(restored);
+    *state.warmup.get_mut().unwrap() = warmup::Warmup::pending(now_ms());
+    let keys = store.api_keys().await.unwrap_or_else(|e| panic!("loading api keys failed: {e}"));
+    state.auth.get_mut().unwrap().set_stored(usable_keys(keys));
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    (state.store, state.persist) = (Some(store.clone()), Some(tx));
+    tokio::spawn(write_behind(store, rx));
+}
+
+/// Drains queued records into the store a batch at a time, off the request path. Only the latest counters in a
+/// batch are written. A batch that fails is logged and dropped so the queue cannot grow without bound.
+async fn write_behind(store: Arc<dyn store::Store>, mut rx: tokio::sync::mpsc::UnboundedReceiver<store::Record>) {
+    while let Some(r) = rx.recv().await {
+        let mut batch = vec![r];
+        while batch.len() < 500 { let Ok(r) = rx.try_recv() else { break }; batch.push(r); }
+        let latest = batch.iter().rposition(|r| matches!(r, store::Record::Counters(_)));
+        let batch: Vec<store::Record> = batch.into_iter().enumerate().filter(|(i, r)| !matches!(r, store::Record::Counters(_)) || Some(*i) == latest).map(|(_, r)| r).collect();
+        if let Err(e) = store.write(&batch).await { tracing::error!(records = batch.len(), "store write failed: {e}"); }
+    }
+}
+
+/// Starts queueing events for Kafka; the publisher is spawned once the state is shared.
+fn open_event_bus(state: &mut AppState, c: events::BusConfig) -> (Arc<dyn events::EventBus>, tokio::sync::mpsc::UnboundedReceiver<events::Event>) {
+    tracing::info!(brokers = %c.brokers.join(","), format = ?c.format, "publishing events to kafka");
+    let bus: Arc<dyn events::EventBus> = Arc::new(kafka::KafkaBus::new(&c));
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    (state.event_bus, state.events) = (Some(c), Some(tx));
+    (bus, rx)
+}
+
+/// Drains queued events onto the bus a batch at a time, off the request path. A batch the bus still refuses after
+/// its retry is logged and dropped, as the store writer does, so the queue cannot grow without bound while brokers
+/// are down.
+async fn publish_behind(s: Arc<AppState>, bus: Arc<dyn events::EventBus>, mut rx: tokio::sync::mpsc::UnboundedReceiver<events::Event>) {
+    let Some(config) = s.event_bus.clone() else { return };
+    while let Some(e) = rx.recv().await {
+        let mut batch = vec![config.message(e)];
+        while batch.len() < 500 { let Ok(e) = rx.try_recv() else { break }; batch.push(config.message(e)); }
+        let result = bus.publish(&batch).await;
+        let mut st = s.event_stats.lock().unwrap();
+        match result {
+            Ok(()) => { st.published += batch.len() as u64; st.last_published_ms = Some(now_ms()); }
+            Err(e) => { tracing::error!(events = batch.len(), "event publish failed: {e}"); st.failed += batch.len() as u64; st.last_error = Some(e); }
+        }
+    }
+}
+
+/// Keys from `RISK_API_KEYS`, a JSON array of [`auth::ApiKey`]. The engine refuses to start on keys it cannot read
+/// rather than run open by mistake.
+fn config_api_keys() -> Vec<auth::ApiKey> {
+    let Ok(v) = std::env::var("RISK_API_KEYS") else { return Vec::new() };
+    let keys: Vec<auth::ApiKey> = serde_json::from_str(&v).unwrap_or_else(|e| panic!("RISK_API_KEYS: {e}"));
+    if let Some(e) = keys.iter().find_map(|k| k.validate().err()) { panic!("RISK_API_KEYS: {e}"); }
+    keys
+}
+
+/// Feeds from `RISK_MARKETDATA_FEEDS`, a JSON array of [`feed::FeedConfig`]; like keys, a bad entry stops startup.
+fn config_feeds() -> Vec<feed::FeedConfig> {
+    let Ok(v) = std::env::var("RISK_MARKETDATA_FEEDS") else { return Vec::new() };
+    let feeds: Vec<feed::FeedConfig> = serde_json::from_str(&v).unwrap_or_else(|e| panic!("RISK_MARKETDATA_FEEDS: {e}"));
+    if let Some(e) = feeds.iter().find_map(|f| f.validate().err()) { panic!("RISK_MARKETDATA_FEEDS: {e}"); }
+    feeds
+}
+
+/// Reads one feed into the engine through the same path as posted ticks, reconnecting after any failure with a
+/// backoff that doubles from one second to thirty.
+async fn run_feed(s: Arc<AppState>, index: usize, mut src: impl feed::MarketDataSource) {
+    let status = |f: &dyn Fn(&mut feed::FeedStatus)| if let Some(x) = s.feeds.lock().unwrap().get_mut(index) { f(x) };
+    let name = s.feeds.lock().unwrap().get(index).map(|f| f.name.clone()).unwrap_or_default();
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let error = match src.connect().await {
+            Err(e) => e,
+            Ok(()) => {
+                status(&|f| { f.connected = true; f.connects += 1; f.last_error = None; });
+                tracing::info!(feed = %name, "market data feed connected");
+                backoff = Duration::from_secs(1);
+                loop {
+                    match src.next().await {
+                        Ok(ticks) if ticks.is_empty() => status(&|f| { f.messages += 1; f.ignored += 1; }),
+                        Ok(ticks) => {
+                            let n = ticks.len() as u64;
+                            apply_ticks(&s, ticks);
+                            let at = now_ms();
+                            status(&|f| { f.messages += 1; f.ticks += n; f.last_tick_at_ms = Some(at); });
+                        }
+                        Err(e) => break e,
+                    }
+                }
+            }
+        };
+        tracing::warn!(feed = %name, retry_in_secs = backoff.as_secs(), "market data feed down: {error}");
+        status(&|f| { f.connected = false; f.last_error = Some(error.clone()); });
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
+
+async fn list_feeds(State(s): State<Arc<AppState>>) -> Json<Vec<feed::FeedStatus>> {
+    Json(s.feeds.lock().unwrap().clone())
+}
+
+/// Database keys that fail validation are skipped, not fatal, so one bad row cannot lock every caller out.
+fn usable_keys(keys: Vec<auth::ApiKey>) -> Vec<auth::ApiKey> {
+    keys.into_iter().filter(|k| k.validate().map_err(|e| tracing::warn!("skipping stored {e}")).is_ok()).collect()
+}
+
+/// Re-reads the database keys every `RISK_API_KEY_REFRESH_SECS` so keys added or revoked there apply without a
+/// restart. A failed read keeps the keys already loaded.
+async fn refresh_api_keys(s: Arc<AppState>, store: Arc<dyn store::Store>) {
+    let mut tick = tokio::time::interval(Duration::from_secs(env_or("RISK_API_KEY_REFRESH_SECS", 60u64).max(1)));
+    tick.tick().await;
+    loop {
+        tick.tick().await;
+        match store.api_keys().await {
+            Ok(keys) => s.auth.lock().unwrap().set_stored(usable_keys(keys)),
+            Err(e) => tracing::error!("api key refresh failed: {e}"),
+        }
+    }
+}
+
+/// Primes memory from the last snapshot a section at a time while `/readyz` reports progress, then saves a fresh
+/// snapshot every `RISK_SNAPSHOT_SECS`. A section that fails to load leaves the engine unready and its snapshot
+/// untouched, so a bad start cannot overwrite a good snapshot with an empty book.
+async fn warm_start(s: Arc<AppState>, store: Arc<dyn store::Store>) {
+    for section in warmup::SECTIONS {
+        s.warmup.lock().unwrap().start(section);
+        let t = Instant::now();
+        let outcome = match store.snapshot(section).await {
+            Ok(Some(n)) => restore_section(&s, section, &n.body).map(|items| (items, Some(n.at_ms))),
+            Ok(None) => Ok((0, None)),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &outcome { tracing::error!(section, "warm start failed: {e}"); }
+        s.warmup.lock().unwrap().finish(section, outcome, t.elapsed().as_millis() as u64, now_ms());
+    }
+    let w = s.warmup.lock().unwrap().clone();
+    if !w.ready { return; }
+    let items = w.stages.iter().map(|st| st.items).sum::<usize>();
+    tracing::info!(items, elapsed_ms = w.finished_at_ms.unwrap_or(w.started_at_ms) - w.started_at_ms, "warm start complete");
+    let mut tick = tokio::time::interval(Duration::from_secs(env_or("RISK_SNAPSHOT_SECS", 30u64).max(1)));
+    loop { tick.tick().await; save_snapshot(&s); }
+}
+
+/// Loads one snapshot section into memory and returns how many items it added. What is already in memory wins, so
+/// accounts, limits and positions booked while warming up are kept and older quotes never replace newer ones.
+fn restore_section(s: &AppState, section: &str, body: &str) -> Result<usize, String> {
+    let parse = |e: serde_json::Error| format!("{section} snapshot does not parse: {e}");
+    Ok(match section {
+        "accounts" => {
+            let saved: Vec<accounts::Account> = serde_json::from_str(body).map_err(parse)?;
+            let mut book = s.accounts.lock().unwrap();
+            let fresh: Vec<accounts::Account> = saved.into_iter().filter(|a| book.get(&a.id).is_none()).collect();
+            let n = fresh.len();
+            for a in fresh { book.insert(a); }
+            n
+        }
+        "limits" => s.limits.lock().unwrap().restore(serde_json::from_str(body).map_err(parse)?),
+        "positions" => {
+            let saved: std::collections::BTreeMap<String, Vec<positions::Position>> = serde_json::from_str(body).map_err(parse)?;
+            let mut book = s.positions.lock().unwrap();
+            let mut n = 0;
+            for (account, p) in saved { if book.list(&account).is_empty() { n += p.len(); book.replace(&account, p); } }
+            n
+        }
+        "reference" => {
+            let saved: warmup::Reference = serde_json::from_str(body).map_err(parse)?;
+            let (mut liq, mut corr) = (s.liquidity.lock().unwrap(), s.correlations.lock().unwrap());
+            let (known, pairs) = (liq.all(), corr.all());
+            let fresh: Vec<_> = saved.liquidity.into_iter().filter(|(i, _)| !known.contains_key(i)).collect();
+            let fresh_pairs: Vec<_> = saved.correlations.into_iter().filter(|p| !pairs.iter().any(|k| (&k.a, &k.b) == (&p.a, &p.b))).collect();
+            let n = fresh.len() + fresh_pairs.len();
+            for (i, p) in fresh { liq.set(&i, p); }
+            for p in fresh_pairs { corr.set(&p.a, &p.b, p.correlation); }
+            n
+        }
+        "prices" => {
+            let saved: HashMap<String, marketdata::Quote> = serde_json::from_str(body).map_err(parse)?;
+            let (mut md, now) = (s.marketdata.lock().unwrap(), now_ms());
+            saved.into_iter().filter(|(i, q)| md.apply(&marketdata::Tick { instrument: i.clone(), price: q.price, bid: q.bid, ask: q.ask, ts_ms: Some(q.at_ms) }, now)).count()
+        }
+        _ => 0,
+    })
+}
+
+/// Queues every snapshot section for the store, all stamped with the same time.
+fn save_snapshot(s: &AppState) {
+    let at_ms = now_ms();
+    let positions: std::collections::BTreeMap<String, Vec<positions::Position>> = { let p = s.positions.lock().unwrap(); p.accounts().into_iter().map(|a| { let l = p.list(&a); (a, l) }).collect() };
+    let reference = warmup::Reference { liquidity: s.liquidity.lock().unwrap().all(), correlations: s.correlations.lock().unwrap().all() };
+    let sections = [
+        ("accounts", serde_json::to_string(&s.accounts.lock().unwrap().list())),
+        ("limits", serde_json::to_string(&s.limits.lock().unwrap().list(&limits::LimitQuery { scope: None, key: None }))),
+        ("positions", serde_json::to_string(&positions)),
+        ("reference", serde_json::to_string(&reference)),
+        ("prices", serde_json::to_string(&s.marketdata.lock().unwrap().all())),
+    ];
+    for (section, body) in sections {
+        match body {
+            Ok(body) => persist(s, store::Record::Snapshot(store::Snapshot { section: section.into(), body, at_ms })),
+            Err(e) => tracing::error!(section, "snapshot failed: {e}"),
+        }
+    }
+}
+
+/// Engine state as configured from the environment. Production runs on one; each tenant sandbox on another.
+fn new_state() -> AppState {
+    AppState {
+        start_time: Instant::now(),
+        stats: Mutex::new(store::Counters::default()),
+        checks: Mutex::new(checks::CheckLog::new(env_or("RISK_CHECK_HISTORY", 100_000))),
+        tag_stats: Mutex::new(tags::TagStats::default()),
+        metrics: Mutex::new(metrics::Metrics::default()),
+        auth: Mutex::new(auth::Auth::new(config_api_keys(), env_or("RISK_API_KEY_RPM", 600))),
+        warmup: Mutex::new(warmup::Warmup::done(now_ms())),
+        velocity: Mutex::new(velocity::VelocityBook::default()),
+        daily: Mutex::new(daily::DailyBook::new(env_or("RISK_SESSION_ROLLOVER_UTC_HOUR", 22))),
+        schedules: Mutex::new(schedule::Schedules::default()),
+        calendar: Mutex::new(calendar::Calendar::default()),
+        algo_limits: Mutex::new(algo::AlgoLimits {
+            max_participation: env_or("RISK_ALGO_MAX_PARTICIPATION", 0.25), max_duration_secs: env_or("RISK_ALGO_MAX_DURATION_SECS", 23_400),
+            max_slice_quantity: std::env::var("RISK_ALGO_MAX_SLICE_QTY").ok().and_then(|v| v.parse().ok()), default_slice_interval_secs: env_or("RISK_ALGO_SLICE_INTERVAL_SECS", 60),
+        }),
+        scenarios: Mutex::new(scenarios::ScenarioLibrary::with_packaged()),
+        suite: Mutex::new(suite::StressSuite::new(env_or("RISK_STRESS_SUITE_MAX_RUNS", 30))),
+        stress_runs: Mutex::new(stress_runs::StressRuns::new(env_or("RISK_STRESS_RUN_HISTORY", 500))),
+        entitlements: Mutex::new(entitlements::EntitlementBook::default()),
+        accounts: Mutex::new(accounts::AccountBook::default()),
+        positions: Mutex::new(positions::PositionBook::default()),
+        drop_copy: Mutex::new(fix::DropCopy::new(&std::env::var("RISK_FIX_COMP_ID").unwrap_or_else(|_| "ALICE-RISK".into()))),
+        otc: Mutex::new(otc::OtcBook::default()),
+        audit: Mutex::new(audit::AuditLog::default()),
+        alerts: Mutex::new(alerts::AlertLog::default()),
+        breaches: Mutex::new(breaches::BreachBook::default()),
+        overrides: Mutex::new(overrides::Overrides::new(env_or("RISK_OVERRIDE_MAX_TTL_SECS", 86_400))),
+        reservations: Mutex::new(reservations::Reservations::new(env_or("RISK_RESERVATION_TTL_SECS", 300) * 1000)),
+        margin_calls: Mutex::new(margin_calls::MarginCalls::new(margin_calls::CallPolicy {
+            thresholds: margin_calls::Thresholds {
+                warning_pct: env_or("RISK_MARGIN_WARNING_UTILIZATION_PCT", 80.0),
+                call_pct: env_or("RISK_MARGIN_CALL_UTILIZATION_PCT", 100.0),
+                liquidation_pct: env_or("RISK_LIQUIDATION_UTILIZATION_PCT", 150.0),
+            },
+            grace_ms: env_or("RISK_MARGIN_CALL_GRACE_SECS", 7200) * 1000,
+            reminder_fraction: 0.5,
+            liquidation_after_ms: env_or("RISK_MARGIN_CALL_LIQUIDATION_AFTER_SECS", 3600) * 1000,
+        })),
+        margin_cycles: Mutex::new(margin_cycles::MarginCycles::new(
+            margin_cycles::CycleConfig { enabled: env_or("RISK_MARGIN_CYCLE_ENABLED", true), interval_secs: env_or("RISK_MARGIN_CYCLE_SECS", 7200).max(60) },
+            env_or("RISK_MARGIN_CYCLE_HISTORY", 100), now_ms(),
+        )),
+        liquidations: Mutex::new(liquidation::Liquidations::new(env_or("RISK_LIQUIDATION_UTILIZATION_PCT", 150.0))),
+        liquidity: Mutex::new(liquidity::LiquidityBook::new(env_or("RISK_IMPACT_COEF", 1.0), env_or("RISK_MAX_PARTICIPATION", 0.2))),
+        locates: Mutex::new(locates::LocateBook::new(env_or("RISK_LOCATE_TTL_SECS", 86_400) * 1000, env_or("RISK_REQUIRE_LOCATES", false), env_or("RISK_BORROW_SPECIAL_BPS", 500.0))),
+        correlations: Mutex::new(correlation::Correlations::new(env_or("RISK_DEFAULT_CORRELATION", 0.3))),
+        corporate_actions: Mutex::new(corporate_actions::CorporateActions::default()),
+        collateral: Mutex::new(collateral::CollateralBook::default()),
+        kill_switches: Mutex::new(kill_switch::KillSwitches::default()),
+        ledger: Mutex::new(ledger::Ledger::default()),
+        limits: Mutex::new(limits::LimitBook::default()),
+        marketdata: Mutex::new(marketdata::MarketData::default()),
+        margins: Mutex::new(HashMap::new()),
+        crif: Mutex::new(HashMap::new()),
+        history: Mutex::new(history::PriceHistory::default()),
+        trade_history: Mutex::new(backfill::TradeHistory::default()),
+        backfills: Mutex::new(backfill::Jobs::default()),
+        settlement: Mutex::new(settlement::SettlementBook::new(env_or("RISK_DEFAULT_SETTLEMENT_DAYS", 2))),
+        fx_settlement: Mutex::new(fx_settlement::FxSettlementBook::new(env_or("RISK_FX_MAX_WINDOW_SHARE", 0.5), env_or("RISK_FX_MIN_WINDOW_USD", 1_000_000.0))),
+        fx_limits: Mutex::new(fx_exposure::FxLimits::default()),
+        fx_rates: Mutex::new(fx_rates::FxRates::default()),
+        desk_limits: Mutex::new(beta::DeskLimits::default()),
+        factor_model: Mutex::new(factor_risk::FactorModel::default()),
+        circuit_breakers: Mutex::new(circuit_breaker::CircuitBreakers::default()),
+        erroneous: Mutex::new(erroneous::ErroneousOrders::default()),
+        fat_finger: Mutex::new(fat_finger::FatFingerConfig::default()),
+        concentration_limits: Mutex::new(concentration::ConcentrationLimits::default()),
+        wash_trades: Mutex::new(wash::WashTrades::default()),
+        hedging: Mutex::new(hedge::HedgePolicy::default()),
+        staging: Mutex::new(staging::Staging::default()),
+        classifications: Mutex::new(BTreeMap::new()),
+        groups: Mutex::new(groups::Groups::default()),
+        related: Mutex::new(related::RelatedAccounts::default()),
+        pools: Mutex::new(pools::Pools::default()),
+        firm_contributions: Mutex::new(firm::Contributions::default()),
+        feeds: Mutex::new(Vec::new()),
+        reason_codes: Mutex::new(reason_codes::Catalog::default()),
+        locales: Mutex::new(locale::LocaleConfig::default()),
+        reason_counts: Mutex::new(std::collections::BTreeMap::new()),
+        span: Mutex::new(span::SpanConfig::default()),
+        latency_budget: Mutex::new(budget::LatencyBudget { budget_ms: std::env::var("RISK_LATENCY_BUDGET_MS").ok().and_then(|v| v.parse().ok()).filter(|b| *b > 0), ..Default::default() }),
+        faults: Mutex::new(faults::Faults::new(env_or("RISK_FAULT_INJECTION", false))),
+        default_funds: env_or("RISK_DEFAULT_ACCOUNT_FUNDS", 1_000_000.0),
+        max_liquidation_days: env_or("RISK_MAX_LIQUIDATION_DAYS", 5.0),
+        var_lookback_days: env_or("RISK_VAR_LOOKBACK_DAYS", 250),
+        firm_currency: env_or("RISK_BASE_CURRENCY", "USD".to_string()).to_ascii_uppercase(),
+        require_if_match: env_or("RISK_REQUIRE_IF_MATCH", false),
+        deadline_min_ms: env_or("RISK_DEADLINE_MIN_MS", 1),
+        sandboxes: Mutex::new(HashMap::new()),
+        alert_stream: tokio::sync::broadcast::channel(env_or("RISK_ALERT_STREAM_BUFFER", 1024usize).max(1)).0,
+        store: None,
+        persist: None,
+        event_bus: None,
+        events: None,
+        event_stats: Mutex::new(events::BusStats::default()),
+        http: reqwest::Client::new(),
+    }
+}
+
+/// Every API route except sandbox management, which only production serves.
+fn routes() -> Router<Arc<AppState>> {
+    Router::new()
         .route("/health", get(health))
+        .route("/metrics", get(prometheus_metrics))
+        .route("/readyz", get(readyz))
         .route("/api/v1/risk/pretrade", post(pretrade_check))
+        .route("/api/v1/risk/pretrade/basket", post(basket_check))
+        .route("/api/v1/risk/pretrade/batch", post(basket_check))
+        .route("/api/v1/risk/summary/firm", get(firm_summary))
+        .route("/api/v1/risk/summary/:account", get(risk_summary))
+        .route("/api/v1/risk/var", post(model_var))
+        .route("/api/v1/risk/algo-limits", get(get_algo_limits).put(set_algo_limits))
+        .route("/api/v1/risk/ex-date-policy", get(get_ex_date_policy).put(set_ex_date_policy))
+        .route("/api/v1/corporate-actions/dividends", get(list_dividends).post(load_dividends))
+        .route("/api/v1/locates", get(list_locates).post(request_locate))
+        .route("/api/v1/locates/inventory", get(list_inventory))
+        .route("/api/v1/locates/borrow-rates", get(list_borrow_rates).post(load_borrow_rates))
+        .route("/api/v1/locates/inventory/:symbol", get(get_inventory).put(set_inventory))
+        .route("/api/v1/locates/inventory/:symbol/replenish", post(replenish_inventory))
         .route("/api/v1/risk/margin", post(margin_calc))
+        .route("/api/v1/risk/margin/compare", post(margin_compare))
+        .route("/api/v1/risk/margin/simm", post(simm_margin))
+        .route("/api/v1/risk/capital/frtb-sa", post(frtb_capital))
         .route("/api/v1/risk/circuit-breaker", post(circuit_breaker))
+        .route("/api/v1/risk/halts", get(list_halts))
+        .route("/api/v1/instrument-groups", get(list_groups))
+        .route("/api/v1/instrument-groups/:name", get(get_group).put(set_group).delete(delete_group))
+        .route("/api/v1/limit-pools", get(list_pools))
+        .route("/api/v1/limit-pools/:name", get(get_pool).put(set_pool).delete(delete_pool))
+        .route("/api/v1/limit-pools/:name/utilization", get(pool_utilization))
+        .route("/api/v1/risk/halts/:instrument", delete(lift_halt))
+        .route("/api/v1/risk/kill-switch", get(list_scoped_kill_switches).post(engage_scoped_kill_switch))
+        .route("/api/v1/risk/kill-switch/disengage", post(disengage_scoped_kill_switch))
+        .route("/api/v1/admin/config/circuit-breaker", get(get_breaker_config).put(set_breaker_config))
+        .route("/api/v1/admin/config/erroneous-orders", get(get_erroneous_config).put(set_erroneous_config))
+        .route("/api/v1/admin/config/fx-exposure-limits", get(get_fx_limits).put(set_fx_limits))
+        .route("/api/v1/admin/config/margin-call-thresholds", get(get_margin_call_thresholds).put(set_margin_call_thresholds))
+        .route("/api/v1/admin/config/event-windows", get(get_event_windows).put(set_event_windows))
+        .route("/api/v1/admin/config/fat-finger", get(get_fat_finger_config).put(set_fat_finger_config))
+        .route("/api/v1/admin/config/concentration-limits", get(get_concentration_limits).put(set_concentration_limits))
+        .route("/api/v1/admin/config/wash-trades", get(get_wash_config).put(set_wash_config))
+        .route("/api/v1/admin/config/hedging", get(get_hedging).put(set_hedging))
+        .route("/api/v1/admin/config/reason-codes", get(get_reason_codes).put(set_reason_codes))
+        .route("/api/v1/admin/config/locales", get(get_locales).put(set_locales))
+        .route("/api/v1/admin/config/latency-budget", get(get_latency_budget).put(set_latency_budget))
+        .route("/api/v1/admin/config/span", get(get_span_config).put(set_span_config))
+        .route("/api/v1/admin/config/export", get(export_config))
+        .route("/api/v1/admin/staged-changes", get(list_staged).post(stage_change))
+        .route("/api/v1/admin/staged-changes/:id", get(get_staged).delete(cancel_staged))
+        .route("/api/v1/admin/config/import", post(import_config))
+        .route("/api/v1/admin/policy-packs", get(list_policy_packs))
+        .route("/api/v1/admin/policy-packs/:id", get(get_policy_pack))
+        .route("/api/v1/admin/policy-packs/:id/apply", post(apply_policy_pack))
+        .route("/api/v1/admin/policy-packs/:id/report", get(policy_pack_report))
+        .route("/api/v1/admin/account-links", get(list_account_links).post(add_account_link))
+        .route("/api/v1/admin/account-links/:id", delete(remove_account_link))
+        .route("/api/v1/admin/event-bus", get(event_bus_status))
+        .route("/api/v1/admin/faults", get(list_faults).post(inject_fault).delete(clear_faults))
+        .route("/api/v1/admin/faults/:id", delete(remove_fault))
         .route("/api/v1/risk/stress-test", post(stress_test))
+        .route("/api/v1/risk/stress-test/reverse", post(reverse_stress))
+        .route("/api/v1/risk/scenarios", get(list_scenarios))
+        .route("/api/v1/risk/scenarios/:name", get(get_scenario).put(set_scenario).delete(delete_scenario))
+        .route("/api/v1/risk/stress-runs", get(list_stress_runs))
+        .route("/api/v1/risk/stress-runs/diff", get(diff_stress_runs))
+        .route("/api/v1/risk/stress-runs/:id", get(get_stress_run))
+        .route("/api/v1/risk/stress-suite", get(get_suite).put(set_suite))
+        .route("/api/v1/risk/stress-suite/run", post(trigger_suite))
+        .route("/api/v1/risk/stress-suite/runs", get(list_suite_runs))
+        .route("/api/v1/risk/stress-suite/runs/:id", get(get_suite_run))
+        .route("/api/v1/risk/factors", get(list_factors))
+        .route("/api/v1/risk/classifications", get(get_classifications).put(set_classifications))
+        .route("/api/v1/risk/concentration/:account", get(account_concentration))
+        .route("/api/v1/risk/greeks/:account", get(account_greeks))
+        .route("/api/v1/risk/factors/:id", get(account_factor_risk).put(set_factors))
+        .route("/api/v1/risk/factor-model", get(get_factor_model).put(set_factor_model))
         .route("/api/v1/risk/stats", get(stats))
-        .layer(cors).layer(TraceLayer::new_for_http()).with_state(state);
-    let addr = std::env::var("RISK_ADDR").unwrap_or_else(|_| "0.0.0.0:8081".into());
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    tracing::info!("Risk Engine on {addr}");
-    axum::serve(listener, app).await.unwrap();
+        .route("/api/v1/risk/checks", get(check_history))
+        .route("/api/v1/risk/checks/:check_id", get(get_check))
+        .route("/api/v1/risk/velocity/:account", get(velocity_state).put(set_velocity_limits))
+        .route("/api/v1/risk/daily-limits", get(daily_limits).put(set_daily_limit))
+        .route("/api/v1/risk/schedules", get(list_schedules))
+        .route("/api/v1/risk/schedules/:group", put(set_schedule).delete(delete_schedule))
+        .route("/api/v1/events", get(list_events).post(create_event))
+        .route("/api/v1/events/:id", get(get_event).put(update_event).delete(delete_event))
+        .route("/api/v1/accounts", get(list_accounts).post(create_account))
+        .route("/api/v1/accounts/:id", get(get_account).patch(update_account))
+        .route("/api/v1/accounts/:id/positions", get(get_positions).put(replace_positions))
+        .route("/api/v1/positions/fill", post(book_fill))
+        .route("/api/v1/positions/drop-copy", get(list_drop_copy_sessions))
+        .route("/api/v1/accounts/:id/collateral", get(get_collateral).put(set_collateral))
+        .route("/api/v1/accounts/:id/ledger", get(get_ledger).post(post_ledger))
+        .route("/api/v1/accounts/:id/ledger/daily", get(ledger_daily))
+        .route("/api/v1/ledger/journal", get(list_journal))
+        .route("/api/v1/ledger/journal/:id", get(get_journal))
+        .route("/api/v1/ledger/journal/:id/reverse", post(reverse_journal))
+        .route("/api/v1/accounts/:id/equity", get(get_equity))
+        .route("/api/v1/accounts/:id/margin-status", get(margin_status))
+        .route("/api/v1/accounts/:id/beta-exposure", get(account_beta_exposure))
+        .route("/api/v1/accounts/:id/var", get(account_var))
+        .route("/api/v1/accounts/:id/fx-exposure", get(account_fx_exposure))
+        .route("/api/v1/accounts/:id/related", get(related_accounts))
+        .route("/api/v1/accounts/:id/cash", put(set_settled_cash))
+        .route("/api/v1/otc/trades", get(list_otc_trades).post(register_otc_trade))
+        .route("/api/v1/otc/trades/:id", get(get_otc_trade))
+        .route("/api/v1/otc/events", post(apply_otc_events))
+        .route("/api/v1/risk/compression", post(compression_analysis))
+        .route("/api/v1/risk/margin-optimization", post(margin_optimization))
+        .route("/api/v1/risk/desks", get(list_desk_limits))
+        .route("/api/v1/risk/desks/:desk", get(desk_beta_exposure).put(set_desk_limit).delete(delete_desk_limit))
+        .route("/api/v1/accounts/:id/cash-projection", get(cash_projection))
+        .route("/api/v1/settlement/conventions", get(get_conventions).put(set_conventions))
+        .route("/api/v1/risk/fx-settlement", get(fx_settlement_exposure))
+        .route("/api/v1/risk/fx-settlement/caps", get(get_fx_caps).put(set_fx_caps))
+        .route("/api/v1/accounts/:id/crif", get(get_crif).put(upload_crif))
+        .route("/api/v1/accounts/:id/crif/simm", get(crif_simm))
+        .route("/api/v1/marketdata", get(list_quotes))
+        .route("/api/v1/fx/rates", get(list_fx_rates).put(set_fx_rates))
+        .route("/api/v1/fx/rates/:currency", delete(delete_fx_rate))
+        .route("/api/v1/marketdata/ticks", post(ingest_ticks))
+        .route("/api/v1/marketdata/feeds", get(list_feeds))
+        .route("/api/v1/marketdata/synthetic", post(generate_ticks))
+        .route("/api/v1/marketdata/history", post(load_history))
+        .route("/api/v1/backfill", get(list_backfills))
+        .route("/api/v1/backfill/prices", post(backfill_prices).layer(axum::extract::DefaultBodyLimit::max(BACKFILL_BODY_LIMIT)))
+        .route("/api/v1/backfill/trades", get(list_historical_trades).post(backfill_trades).layer(axum::extract::DefaultBodyLimit::max(BACKFILL_BODY_LIMIT)))
+        .route("/api/v1/backfill/:job_id", get(get_backfill))
+        .route("/api/v1/marketdata/history/:instrument", get(get_history))
+        .route("/api/v1/marketdata/:instrument", get(get_quote))
+        .route("/api/v1/collateral/haircuts", get(list_haircuts).post(create_haircuts))
+        .route("/api/v1/collateral/haircuts/:id", get(get_haircuts).put(update_haircuts).delete(delete_haircuts))
+        .route("/api/v1/audit", get(audit_log))
+        .route("/api/v1/alerts", get(list_alerts))
+        .route("/ws/alerts", get(alert_stream))
+        .route("/api/v1/breaches", get(list_breaches))
+        .route("/api/v1/overrides", get(list_overrides).post(issue_override))
+        .route("/api/v1/reservations", get(list_reservations))
+        .route("/api/v1/reservations/:id", get(get_reservation))
+        .route("/api/v1/reservations/:id/commit", post(commit_reservation))
+        .route("/api/v1/reservations/:id/release", post(release_reservation))
+        .route("/api/v1/overrides/:token", delete(revoke_override))
+        .route("/api/v1/breaches/aging", get(breach_aging))
+        .route("/api/v1/breaches/:id", get(get_breach).patch(update_breach))
+        .route("/api/v1/margin/calls", get(list_margin_calls))
+        .route("/api/v1/margin/calls/:id", get(get_margin_call))
+        .route("/api/v1/margin/calls/:id/ack", post(ack_margin_call))
+        .route("/api/v1/margin/cycles", get(list_margin_cycles))
+        .route("/api/v1/margin/cycles/config", get(get_cycle_config).put(set_cycle_config))
+        .route("/api/v1/margin/cycles/run", post(trigger_margin_cycle))
+        .route("/api/v1/margin/cycles/:id", get(get_margin_cycle))
+        .route("/api/v1/risk/closeout/simulate", post(closeout_simulate))
+        .route("/api/v1/risk/liquidity", get(list_liquidity))
+        .route("/api/v1/risk/correlations", get(list_correlations).put(set_correlations))
+        .route("/api/v1/risk/liquidity/horizons", get(liquidation_horizons))
+        .route("/api/v1/risk/liquidity/:instrument", get(liquidity_score).put(set_liquidity))
+        .route("/api/v1/liquidations", get(list_liquidations))
+        .route("/api/v1/liquidations/hooks", get(list_hooks).post(add_hook))
+        .route("/api/v1/liquidations/hooks/:id", axum::routing::delete(delete_hook))
+        .route("/api/v1/kill-switch", get(list_kill_switches))
+        .route("/api/v1/kill-switch/:account", get(get_kill_switch))
+        .route("/api/v1/kill-switch/:account/engage", post(engage_kill_switch))
+        .route("/api/v1/kill-switch/:account/disengage", post(disengage_kill_switch))
+        .route("/api/v1/kill-switch/:account/policy", get(get_kill_policy).put(set_kill_policy).delete(delete_kill_policy))
+        .route("/api/v1/limits", get(list_limits).post(create_limit))
+        .route("/api/v1/limits/utilization", get(limit_utilization))
+        .route("/api/v1/limits/:id", get(get_limit).put(update_limit).delete(delete_limit))
+        .route("/api/v1/limits/entitlements/:account", get(get_entitlements).put(set_entitlements).delete(delete_entitlements))
+}
+
+fn env_or<T: std::str::FromStr>(k: &str, d: T) -> T { std::env::var(k).ok().and_then(|v| v.parse().ok()).unwrap_or(d) }
+
+fn now_ms() -> u64 { SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0) }
+
+/// Requests sent with `x-environment: sandbox` run against the sandbox of the tenant named by `x-tenant-id`, which
+/// is created from production on first use. Responses from a sandbox say so in the same header.
+async fn environment(State(s): State<Arc<AppState>>, req: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    use tower::ServiceExt;
+    match req.headers().get("x-environment").map(|v| v.to_str().unwrap_or_default()) {
+        None | Some("production") => return next.run(req).await,
+        Some("sandbox") => {}
+        Some(other) => return bad_request(format!("unknown environment {other:?}; use production or sandbox")).into_response(),
+    }
+    let Some(tenant) = req.headers().get("x-tenant-id").and_then(|v| v.to_str().ok()).filter(|t| !t.is_empty()).map(String::from) else {
+        return bad_request("sandbox requests need an x-tenant-id").into_response();
+    };
+    let sandbox = match sandbox(&s, &tenant) { Ok(sb) => sb, Err(e) => return e.into_response() };
+    let mut res = routes().route_layer(axum::middleware::from_fn_with_state(sandbox.clone(), track)).with_state(sandbox).oneshot(req).await.into_response();
+    res.headers_mut().insert("x-environment", axum::http::HeaderValue::from_static("sandbox"));
+    res
+}
+
+/// With API keys configured every request but probes and scrapes needs one, in `x-api-key` or as a bearer token.
+/// The key decides who the caller is: `x-user-id` becomes the key id, and `x-user-role` survives only on risk-admin
+/// keys, so a role can no longer be claimed by setting a header.
+async fn authenticate(State(s): State<Arc<AppState>>, mut req: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    if matches!(req.uri().path(), "/health" | "/readyz" | "/metrics") { return next.run(req).await; }
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if let Err(d) = admit(&s, req.headers_mut(), safe) { return denied(d); }
+    next.run(req).await
+}
+
+/// Checks the API key in `h` and, once admitted, rewrites the identity headers to the key's. Everything is admitted
+/// while no keys are configured.
+fn admit(s: &AppState, h: &mut HeaderMap, safe: bool) -> Result<(), auth::Denied> {
+    use axum::http::HeaderValue;
+    if !s.auth.lock().unwrap().enabled() { return Ok(()); }
+    let bearer = h.get("authorization").and_then(|v| v.to_str().ok()).and_then(|v| v.strip_prefix("Bearer "));
+    let secret = h.get("x-api-key").and_then(|v| v.to_str().ok()).or(bearer);
+    let caller = s.auth.lock().unwrap().admit(secret, safe, role(h), now_ms())?;
+    if let Ok(id) = HeaderValue::from_str(&caller.key_id) { h.insert("x-user-id", id); }
+    match caller.role.as_deref().map(HeaderValue::from_str) {
+        Some(Ok(r)) => { h.insert("x-user-role", r); }
+        _ => { h.remove("x-user-role"); }
+    }
+    Ok(())
+}
+
+fn denied(d: auth::Denied) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    let (status, error, details) = match d {
+        auth::Denied::Missing => (StatusCode::UNAUTHORIZED, "API key required", Some("send it in x-api-key or as a bearer token".to_string())),
+        auth::Denied::Unknown => (StatusCode::UNAUTHORIZED, "Invalid API key", None),
+        auth::Denied::ReadOnly => (StatusCode::FORBIDDEN, "Forbidden", Some("read-only keys cannot make changes".to_string())),
+        auth::Denied::Role(r) => (StatusCode::FORBIDDEN, "Forbidden", Some(format!("this key cannot act as {r}"))),
+        auth::Denied::RateLimited { retry_after_secs } => {
+            let e = Err { error: "Rate limit exceeded".into(), details: Some(format!("retry in {retry_after_secs}s")) };
+            return (StatusCode::TOO_MANY_REQUESTS, [(axum::http::header::RETRY_AFTER, retry_after_secs.to_string())], Json(e)).into_response();
+        }
+    };
+    (status, Json(Err { error: error.into(), details })).into_response()
+}
+
+/// Counts and times each routed request under its route template, so every account shares one `/accounts/:id` series.
+async fn track(State(s): State<Arc<AppState>>, req: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    let route = req.extensions().get::<axum::extract::MatchedPath>().map(|p| p.as_str().to_string()).unwrap_or_default();
+    let (method, t) = (req.method().to_string(), Instant::now());
+    let res = next.run(req).await;
+    s.metrics.lock().unwrap().request(&method, &route, res.status().as_u16(), t.elapsed());
+    res
+}
+
+/// The tenant's sandbox, created if it has none yet. A tenant is an account entity and needs at least one account.
+fn sandbox(s: &AppState, tenant: &str) -> Result<Arc<AppState>, (StatusCode, Json<Err>)> {
+    if let Some(sb) = s.sandboxes.lock().unwrap().get(tenant) { return Ok(sb.state.clone()); }
+    let state = Arc::new(seed_sandbox(s, tenant)?);
+    Ok(s.sandboxes.lock().unwrap().entry(tenant.into()).or_insert(Sandbox { state, created_at_ms: now_ms() }).state.clone())
+}
+
+/// A fresh engine holding a copy of the tenant's production configuration: its accounts with their entitlements,
+/// velocity, daily and kill switch limits, the firm-wide limits, policies and margin settings, and reference market
+/// data. Positions, cash, collateral holdings, locates, orders counted in rate windows, alerts, breaches, audit and
+/// stats all start empty, and liquidation hooks are not copied so sandbox liquidations notify nobody.
+fn seed_sandbox(p: &AppState, tenant: &str) -> Result<AppState, (StatusCode, Json<Err>)> {
+    let accounts = p.accounts.lock().unwrap().by_entity(tenant);
+    if accounts.is_empty() { return Err(not_found("Tenant")); }
+    let sb = new_state();
+    {
+        let (entitlements, velocity, kill) = (p.entitlements.lock().unwrap(), p.velocity.lock().unwrap(), p.kill_switches.lock().unwrap());
+        let daily = p.daily.lock().unwrap().snapshot(now_ms());
+        let mut d = sb.daily.lock().unwrap();
+        for (i, l) in daily.instrument_limits { d.update(daily::DailyLimitUpdate { account: None, instrument: Some(i), limit: Some(l) }); }
+        for a in accounts {
+            sb.entitlements.lock().unwrap().set(&a.id, entitlements.get(&a.id));
+            sb.velocity.lock().unwrap().set_limits(&a.id, velocity.limits(&a.id));
+            if let Some(k) = kill.policy(&a.id) { sb.kill_switches.lock().unwrap().set_policy(&a.id, k); }
+            if let Some(l) = daily.account_limits.get(&a.id) { d.update(daily::DailyLimitUpdate { account: Some(a.id.clone()), instrument: None, limit: Some(*l) }); }
+            sb.accounts.lock().unwrap().insert(a);
+        }
+    }
+    *sb.algo_limits.lock().unwrap() = *p.algo_limits.lock().unwrap();
+    *sb.schedules.lock().unwrap() = p.schedules.lock().unwrap().clone();
+    *sb.calendar.lock().unwrap() = p.calendar.lock().unwrap().clone();
+    *sb.scenarios.lock().unwrap() = p.scenarios.lock().unwrap().clone();
+    *sb.liquidity.lock().unwrap() = p.liquidity.lock().unwrap().clone();
+    *sb.correlations.lock().unwrap() = p.correlations.lock().unwrap().clone();
+    *sb.corporate_actions.lock().unwrap() = p.corporate_actions.lock().unwrap().clone();
+    *sb.collateral.lock().unwrap() = p.collateral.lock().unwrap().config_copy();
+    *sb.limits.lock().unwrap() = p.limits.lock().unwrap().config_copy();
+    *sb.marketdata.lock().unwrap() = p.marketdata.lock().unwrap().clone();
+    *sb.history.lock().unwrap() = p.history.lock().unwrap().clone();
+    *sb.desk_limits.lock().unwrap() = p.desk_limits.lock().unwrap().clone();
+    *sb.factor_model.lock().unwrap() = p.factor_model.lock().unwrap().clone();
+    *sb.span.lock().unwrap() = p.span.lock().unwrap().clone();
+    sb.settlement.lock().unwrap().conventions = p.settlement.lock().unwrap().conventions.clone();
+    sb.fx_settlement.lock().unwrap().caps = p.fx_settlement.lock().unwrap().caps.clone();
+    *sb.fx_limits.lock().unwrap() = p.fx_limits.lock().unwrap().clone();
+    *sb.fx_rates.lock().unwrap() = p.fx_rates.lock().unwrap().clone();
+    sb.circuit_breakers.lock().unwrap().config = p.circuit_breakers.lock().unwrap().config.clone();
+    sb.erroneous.lock().unwrap().config = p.erroneous.lock().unwrap().config.clone();
+    *sb.fat_finger.lock().unwrap() = p.fat_finger.lock().unwrap().clone();
+    *sb.groups.lock().unwrap() = p.groups.lock().unwrap().clone();
+    *sb.related.lock().unwrap() = p.related.lock().unwrap().clone();
+    *sb.concentration_limits.lock().unwrap() = p.concentration_limits.lock().unwrap().clone();
+    *sb.classifications.lock().unwrap() = p.classifications.lock().unwrap().clone();
+    sb.wash_trades.lock().unwrap().config = p.wash_trades.lock().unwrap().config.clone();
+    *sb.hedging.lock().unwrap() = p.hedging.lock().unwrap().clone();
+    *sb.pools.lock().unwrap() = p.pools.lock().unwrap().config_copy();
+    *sb.reason_codes.lock().unwrap() = p.reason_codes.lock().unwrap().clone();
+    *sb.locales.lock().unwrap() = p.locales.lock().unwrap().clone();
+    *sb.latency_budget.lock().unwrap() = p.latency_budget.lock().unwrap().clone();
+    sb.margin_calls.lock().unwrap().policy.thresholds = p.margin_calls.lock().unwrap().policy.thresholds.clone();
+    sb.liquidations.lock().unwrap().threshold_pct = p.liquidations.lock().unwrap().threshold_pct;
+    let cycles = p.margin_cycles.lock().unwrap().config();
+    sb.margin_cycles.lock().unwrap().set_config(cycles).map_err(bad_request)?;
+    Ok(sb)
+}
+
+fn sandbox_info(tenant: &str, sb: &Sandbox) -> SandboxInfo {
+    SandboxInfo { tenant: tenant.into(), created_at_ms: sb.created_at_ms, accounts: sb.state.accounts.lock().unwrap().list().len() }
+}
+
+async fn list_sandboxes(State(s): State<Arc<AppState>>) -> Json<Vec<SandboxInfo>> {
+    let mut v: Vec<SandboxInfo> = s.sandboxes.lock().unwrap().iter().map(|(t, sb)| sandbox_info(t, sb)).collect();
+    v.sort_by(|a, b| a.tenant.cmp(&b.tenant));
+    Json(v)
+}
+
+/// Discards everything done in the sandbox and copies the tenant's production configuration afresh.
+async fn reset_sandbox(State(s): State<Arc<AppState>>, h: HeaderMap, Path(tenant): Path<String>) -> ApiResult<SandboxInfo> {
+    let sb = Sandbox { state: Arc::new(seed_sandbox(&s, &tenant)?), created_at_ms: now_ms() };
+    let info = sandbox_info(&tenant, &sb);
+    s.sandboxes.lock().unwrap().insert(tenant.clone(), sb);
+    audit(&s, &h, "sandbox.reset", &tenant, serde_json::json!({ "accounts": info.accounts }));
+    Ok(Json(info))
+}
+
+async fn delete_sandbox(State(s): State<Arc<AppState>>, h: HeaderMap, Path(tenant): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    if s.sandboxes.lock().unwrap().remove(&tenant).is_none() { return Err(not_found("Sandbox")); }
+    audit(&s, &h, "sandbox.delete", &tenant, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
 }
 
 async fn health(State(s): State<Arc<AppState>>) -> Json<Health> {
@@ -60,47 +1254,3358 @@ async fn health(State(s): State<Arc<AppState>>) -> Json<Health> {
     Json(Health { status: "ok".into(), version: env!("CARGO_PKG_VERSION").into(), uptime_secs: s.start_time.elapsed().as_secs(), total_ops: st.total_checks + st.total_margin_calcs })
 }
 
-async fn pretrade_check(State(s): State<Arc<AppState>>, Json(req): Json<PreTradeCheckRequest>) -> Json<PreTradeCheckResponse> {
-    let t = Instant::now();
-    let notional = req.quantity * req.price;
-    let risk_score = (notional / 1_000_000.0).min(1.0);
-    let approved = risk_score < 0.8;
-    let mut reasons = Vec::new();
-    if !approved { reasons.push("Position limit exceeded".into()); }
-    if notional > 500_000.0 { reasons.push("Large order flag".into()); }
-    { let mut st = s.stats.lock().unwrap(); st.total_checks += 1; if !approved { st.trades_blocked += 1; st.total_alerts += 1; } }
-    Json(PreTradeCheckResponse { check_id: uuid::Uuid::new_v4().to_string(), approved, reasons, risk_score, margin_impact: notional * 0.1, position_limit_used_pct: risk_score * 100.0, elapsed_us: t.elapsed().as_micros() })
+/// Quantity of the line that needs a locate, if any: its short portion when it is marked short, or always when
+/// locates are enforced.
+fn short_sale(s: &AppState, account: &str, l: &OrderLeg) -> Option<f64> {
+    let enforce = s.locates.lock().unwrap().enforce;
+    if !enforce && l.side != positions::Side::SellShort { return None; }
+    let short = positions::short_quantity(s.positions.lock().unwrap().net(account, &l.instrument), positions::signed_quantity(l.side, l.quantity));
+    (short > 0.0).then_some(short)
 }
 
-async fn margin_calc(State(s): State<Arc<AppState>>, Json(req): Json<MarginRequest>) -> Json<MarginResponse> {
-    let t = Instant::now();
-    let positions = req.positions.unwrap_or_default();
-    let total_notional: f64 = positions.iter().map(|p| p.quantity * p.price).sum();
-    let initial = total_notional * 0.10;
-    let maintenance = total_notional * 0.05;
-    let var95 = total_notional * 0.02;
-    let var99 = total_notional * 0.035;
-    s.stats.lock().unwrap().total_margin_calcs += 1;
-    Json(MarginResponse { account: req.account, initial_margin: initial, maintenance_margin: maintenance, available_margin: 1_000_000.0 - initial, margin_utilization_pct: (initial / 1_000_000.0) * 100.0, var_95: var95, var_99: var99, elapsed_us: t.elapsed().as_micros() })
+/// Draws the short portion of each approved line from the account's locates.
+fn consume_locates(s: &AppState, account: &str, lines: &[OrderLeg]) {
+    let draws: Vec<(&str, f64)> = lines.iter().filter_map(|l| short_sale(s, account, l).map(|q| (l.instrument.as_str(), q))).collect();
+    let mut lb = s.locates.lock().unwrap();
+    for (symbol, q) in draws { lb.consume(account, symbol, q, now_ms()); }
 }
 
-async fn circuit_breaker(State(s): State<Arc<AppState>>, Json(req): Json<CircuitBreakerRequest>) -> Json<CircuitBreakerResponse> {
-    let abs_change = req.price_change_pct.abs();
-    let (triggered, level, halt) = if abs_change >= 20.0 { (true, "L3", 3600) } else if abs_change >= 13.0 { (true, "L2", 900) } else if abs_change >= 7.0 { (true, "L1", 300) } else { (false, "none", 0) };
-    if triggered { s.stats.lock().unwrap().total_alerts += 1; }
-    Json(CircuitBreakerResponse { instrument: req.instrument, triggered, level: level.into(), halt_duration_secs: halt, price_change_pct: req.price_change_pct })
+/// The USD rate of `currency`: the last `CCYUSD` or `USDCCY` quote, or the rate set by hand if that is newer.
+fn usd_rate(s: &AppState, currency: &str) -> Option<fx_rates::Rate> {
+    let feed = {
+        let md = s.marketdata.lock().unwrap();
+        md.quote(&format!("{currency}USD")).map(|q| (q.price, q.at_ms)).or_else(|| md.quote(&format!("USD{currency}")).filter(|q| q.price > 0.0).map(|q| (1.0 / q.price, q.at_ms)))
+    };
+    let feed = feed.map(|(usd, at_ms)| fx_rates::Rate { currency: currency.into(), usd, source: fx_rates::Source::Feed, at_ms });
+    fx_rates::latest(s.fx_rates.lock().unwrap().get(currency), feed)
 }
 
-async fn stress_test(State(_s): State<Arc<AppState>>, Json(req): Json<StressTestRequest>) -> Json<StressTestResponse> {
-    let scenario = req.scenario.unwrap_or_else(|| "market-crash".into());
-    let shock = req.shock_pct.unwrap_or(-20.0);
-    let impact = shock * 10000.0;
-    let breaches = if shock.abs() > 15.0 { vec!["VaR limit breach".into(), "Margin call triggered".into()] } else { vec![] };
-    Json(StressTestResponse { scenario, portfolio_impact: impact, worst_case_loss: impact * 1.5, instruments_affected: 25, breaches })
+fn usd_value(s: &AppState, currency: &str, amount: f64) -> Option<f64> {
+    if currency == "USD" { return Some(amount); }
+    usd_rate(s, currency).map(|r| amount * r.usd)
 }
 
-async fn stats(State(s): State<Arc<AppState>>) -> Json<StatsResponse> {
-    let st = s.stats.lock().unwrap();
-    let block_rate = if st.total_checks > 0 { st.trades_blocked as f64 / st.total_checks as f64 * 100.0 } else { 0.0 };
-    Json(StatsResponse { total_checks: st.total_checks, total_margin_calcs: st.total_margin_calcs, total_alerts: st.total_alerts, trades_blocked: st.trades_blocked, block_rate_pct: block_rate })
+/// Rates into `base` for the foreign currencies `instruments` are priced in, by instrument, with a reason for each
+/// currency that has none. Instruments missing from the map are valued as they are.
+fn price_rates<'a>(s: &AppState, base: &str, instruments: impl Iterator<Item = &'a str>) -> (HashMap<String, f64>, Vec<String>) {
+    let ccys: Vec<(&str, String)> = { let sc = s.scenarios.lock().unwrap(); instruments.filter_map(|i| Some((i, sc.factor(i)?.currency.as_ref()?.to_ascii_uppercase()))).filter(|(_, c)| c != base).collect() };
+    let (mut rates, mut missing) = (HashMap::new(), Vec::new());
+    for (i, c) in ccys {
+        match fx_rate(s, &c, base) {
+            Some(r) => { rates.insert(i.to_string(), r); }
+            None => { let r = format!("No FX rate from {c} into {base} for {i}"); if !missing.contains(&r) { missing.push(r); } }
+        }
+    }
+    (rates, missing)
+}
+
+/// Daily vols estimated from the feed for the instruments in `legs` that have one; the rest fall back to their
+/// liquidity parameters.
+fn live_vols(s: &AppState, legs: &[(String, f64, f64)]) -> HashMap<String, f64> {
+    let md = s.marketdata.lock().unwrap();
+    legs.iter().filter_map(|(i, _, _)| Some((i.clone(), md.daily_vol(i)?))).collect()
+}
+
+/// Spot and vol an option is priced from: its underlier's last price, and its implied vol or else the underlier's
+/// daily vol, live from its ticks or from its liquidity parameters, scaled to a year.
+fn underlier(s: &AppState, o: &scenarios::OptionTerms) -> Option<scenarios::Underlier> {
+    let (spot, live) = { let md = s.marketdata.lock().unwrap(); (md.price(&o.underlying)?, md.daily_vol(&o.underlying)) };
+    let vol = o.implied_vol.unwrap_or_else(|| live.unwrap_or_else(|| s.liquidity.lock().unwrap().get(&o.underlying).daily_vol) * 252f64.sqrt());
+    Some(scenarios::Underlier { spot, vol, today: chrono::Utc::now().date_naive() })
+}
+
+/// Greeks of `q` units of one instrument and the underlier they count towards: options from their terms, futures
+/// as delta in their underlier, anything else as cash delta at its quoted price or, failing that, the price of the
+/// matching order leg.
+fn instrument_greeks(s: &AppState, sc: &scenarios::ScenarioLibrary, instrument: &str, q: f64, legs: &[OrderLeg]) -> Result<(String, scenarios::InstrumentKind, greeks::Greeks), String> {
+    let price = |i: &str| s.marketdata.lock().unwrap().price(i).or_else(|| legs.iter().find(|l| l.instrument == i).map(|l| l.price)).ok_or_else(|| format!("no price for {i}"));
+    let f = sc.factor(instrument);
+    match (f.and_then(|f| f.option.as_ref()), f.and_then(|f| f.future.as_ref())) {
+        (Some(o), _) => {
+            let u = underlier(s, o).ok_or_else(|| format!("no price for {}", o.underlying))?;
+            Ok((o.underlying.clone(), scenarios::InstrumentKind::Option, greeks::option(o, q, u.spot, u.vol, u.today)?))
+        }
+        // Priced off the future itself when the underlier has no quote of its own.
+        (None, Some(fu)) => Ok((fu.underlying.clone(), scenarios::InstrumentKind::Future, greeks::future(fu, q, price(&fu.underlying).or_else(|_| price(instrument))?))),
+        (None, None) => Ok((instrument.to_string(), scenarios::InstrumentKind::Cash, greeks::Greeks { delta: q * price(instrument)?, ..Default::default() })),
+    }
+}
+
+/// Greeks of `lines` by underlier.
+fn greeks_by_underlier(s: &AppState, lines: &[(String, f64)], legs: &[OrderLeg]) -> Result<std::collections::BTreeMap<String, greeks::Greeks>, String> {
+    let sc = s.scenarios.lock().unwrap();
+    greeks::by_underlier(lines, |instrument: &str, q: f64| instrument_greeks(s, &sc, instrument, q, legs).map(|(u, _, g)| (u, g)))
+}
+
+/// Whether the order closes, hedges or adds to what the account holds. Only holdings in the underliers the order
+/// trades are given Greeks, so a large book stays cheap to classify; an order that cannot be priced is judged on
+/// its beta alone.
+fn risk_direction(s: &AppState, account: &str, legs: &[OrderLeg]) -> hedge::Direction {
+    let mut net = std::collections::BTreeMap::<&str, f64>::new();
+    for l in legs { *net.entry(l.instrument.as_str()).or_default() += positions::signed_quantity(l.side, l.quantity); }
+    let (closing, held) = {
+        let book = s.positions.lock().unwrap();
+        (net.iter().all(|(i, q)| book.is_reducing(account, i, *q)), book.list(account))
+    };
+    let order: Vec<(String, f64)> = net.iter().map(|(i, q)| (i.to_string(), *q)).collect();
+    let deltas: Vec<(f64, f64)> = {
+        let sc = s.scenarios.lock().unwrap();
+        let greeks_of = |i: &str, q: f64| instrument_greeks(s, &sc, i, q, legs).map(|(u, _, g)| (u, g));
+        let underlying = |i: &str| sc.factor(i).and_then(|f| f.option.as_ref().map(|o| o.underlying.clone()).or_else(|| f.future.as_ref().map(|f| f.underlying.clone()))).unwrap_or_else(|| i.to_string());
+        greeks::by_underlier(&order, greeks_of).ok().and_then(|o| {
+            let mine: Vec<(String, f64)> = held.iter().filter(|p| o.contains_key(&underlying(&p.instrument))).map(|p| (p.instrument.clone(), p.quantity)).collect();
+            let before = greeks::by_underlier(&mine, greeks_of).ok()?;
+            Some(o.iter().map(|(u, g)| { let b = before.get(u).map_or(0.0, |b| b.delta); (b, b + g.delta) }).collect())
+        }).unwrap_or_default()
+    };
+    hedge::classify(closing, &deltas, || {
+        let lines: Vec<(String, f64, f64)> = legs.iter().map(|l| (l.instrument.clone(), positions::signed_quantity(l.side, l.quantity), l.price)).collect();
+        let order = { let sc = s.scenarios.lock().unwrap(); beta::exposure(&lines, |i| sc.factor(i)).beta_exposure };
+        let before = beta_exposure(s, account, &[]).beta_exposure;
+        Some((before, before + order))
+    })
+}
+
+/// Greek impact of the order against the account's Greek limits, for accounts that set them.
+fn greek_check(s: &AppState, account: &accounts::Account, legs: &[OrderLeg]) -> Option<(Option<greeks::GreekImpact>, Vec<String>)> {
+    let limits = account.default_limits.greeks.as_ref()?;
+    let held: Vec<(String, f64)> = s.positions.lock().unwrap().list(&account.id).into_iter().map(|p| (p.instrument, p.quantity)).collect();
+    let order: Vec<(String, f64)> = legs.iter().map(|l| (l.instrument.clone(), positions::signed_quantity(l.side, l.quantity))).collect();
+    match (greeks_by_underlier(s, &held, legs), greeks_by_underlier(s, &order, legs)) {
+        (Ok(before), Ok(order)) => { let (impact, reasons) = greeks::evaluate(limits, &before, &order); Some((Some(impact), reasons)) }
+        (Err(e), _) | (_, Err(e)) => Some((None, vec![format!("Greek limits not evaluated: {e}")])),
+    }
+}
+
+fn beta_exposure(s: &AppState, account: &str, extra: &[(String, f64, f64)]) -> beta::BetaExposure {
+    let mut lines = portfolio(s, Some(account), None);
+    lines.extend_from_slice(extra);
+    let sc = s.scenarios.lock().unwrap();
+    beta::exposure(&lines, |i| sc.factor(i))
+}
+
+/// Beta-adjusted exposure the order adds against the account's and its desk's caps. `None` when neither has one.
+fn beta_check(s: &AppState, account: &accounts::Account, legs: &[OrderLeg]) -> Option<(beta::BetaImpact, Vec<String>)> {
+    let desk = account.desk.as_ref().and_then(|d| s.desk_limits.lock().unwrap().get(d).map(|l| (d.clone(), l)));
+    let account_cap = account.default_limits.max_beta_exposure;
+    if account_cap.is_none() && desk.is_none() { return None; }
+    let order_lines: Vec<(String, f64, f64)> = legs.iter().map(|l| (l.instrument.clone(), positions::signed_quantity(l.side, l.quantity), l.price)).collect();
+    let order = { let sc = s.scenarios.lock().unwrap(); beta::exposure(&order_lines, |i| sc.factor(i)).beta_exposure };
+    let before = beta_exposure(s, &account.id, &[]).beta_exposure;
+    let mut reasons: Vec<String> = account_cap.and_then(|c| beta::breach("Account", "", c, before, before + order)).into_iter().collect();
+    let desk_before = desk.as_ref().map(|(d, l)| {
+        let members = s.accounts.lock().unwrap().by_desk(d);
+        let b: f64 = members.iter().map(|a| beta_exposure(s, &a.id, &[]).beta_exposure).fold(0.0, |x, y| x + y);
+        reasons.extend(beta::breach("Desk", &format!(" on {d}"), l.max_beta_exposure, b, b + order));
+        b
+    });
+    Some((beta::BetaImpact { order, account_before: before, account_after: before + order, desk: desk.map(|(d, _)| d), desk_before, desk_after: desk_before.map(|b| b + order) }, reasons))
+}
+
+/// Herstatt exposure of FX legs traded with a named counterparty: the settlements they would book, cap breaches
+/// and concentration flags. Legs that are not FX, or orders without a counterparty, are not assessed.
+fn fx_settlement_check(s: &AppState, req: &PreTradeCheckRequest, legs: &[OrderLeg], check_id: &str) -> (Vec<fx_settlement::Settlement>, Vec<String>, Vec<String>) {
+    let Some(cp) = req.counterparty.as_deref() else { return Default::default() };
+    let today = chrono::Utc::now().date_naive();
+    let value_date = req.value_date.unwrap_or_else(|| settlement::add_business_days(today, 2));
+    let is_fx = |i: &str| req.asset_class.as_deref().is_some_and(|a| a.eq_ignore_ascii_case("fx"))
+        || s.scenarios.lock().unwrap().factor(i).is_some_and(|f| f.asset_class == scenarios::AssetClass::Fx);
+    let (mut settlements, mut flags) = (Vec::new(), Vec::new());
+    for l in legs.iter().filter(|l| is_fx(&l.instrument)) {
+        let t = fx_settlement::FxTrade { check_id, account: &req.account, counterparty: cp, instrument: &l.instrument, signed_quantity: positions::signed_quantity(l.side, l.quantity), rate: l.price, value_date };
+        let r = s.fx_settlement.lock().unwrap().settlement(&t, |c, a| usd_value(s, c, a));
+        match r { Ok(x) => settlements.push(x), Err(e) => flags.push(format!("FX settlement not assessed: {e}")) }
+    }
+    let mut fx = s.fx_settlement.lock().unwrap();
+    fx.roll(today);
+    let (reasons, more) = fx.evaluate(&settlements);
+    flags.extend(more);
+    (settlements, reasons, flags)
+}
+
+/// What an instrument does to the currency book: an FX pair when the order or its factor mapping says FX, otherwise
+/// whatever currency its factor mapping prices it in.
+fn fx_kind(s: &AppState, instrument: &str, asset_class: Option<&str>) -> Option<fx_exposure::Kind> {
+    let sc = s.scenarios.lock().unwrap();
+    let f = sc.factor(instrument);
+    let fx = asset_class.is_some_and(|a| a.eq_ignore_ascii_case("fx")) || f.is_some_and(|f| f.asset_class == scenarios::AssetClass::Fx);
+    if let Some((b, q)) = fx_settlement::split_pair(instrument).filter(|_| fx) { return Some(fx_exposure::Kind::Pair(b, q)); }
+    f.and_then(|f| f.currency.as_deref()).map(|c| fx_exposure::Kind::Priced(c.to_ascii_uppercase()))
+}
+
+/// Net amount of each currency other than `base` that `(instrument, signed quantity, price)` holdings leave.
+fn currency_book(s: &AppState, base: &str, legs: &[(String, f64, f64)], asset_class: Option<&str>) -> BTreeMap<String, f64> {
+    let mut book = BTreeMap::new();
+    for (i, q, p) in legs {
+        let Some(k) = fx_kind(s, i, asset_class) else { continue };
+        for (c, a) in fx_exposure::amounts(&k, *q, *p) { if c != base { *book.entry(c).or_default() += a; } }
+    }
+    book.retain(|_, a| *a != 0.0);
+    book
+}
+
+/// Daily vol of `currency` against `base`: live from either quoting of the pair, else its liquidity parameters.
+fn fx_vol(s: &AppState, currency: &str, base: &str) -> f64 {
+    let pair = format!("{currency}{base}");
+    let live = { let md = s.marketdata.lock().unwrap(); md.daily_vol(&pair).or_else(|| md.daily_vol(&format!("{base}{currency}"))) };
+    live.unwrap_or_else(|| s.liquidity.lock().unwrap().get(&pair).daily_vol)
+}
+
+/// Per-currency caps on orders that add FX exposure. A currency over its cap once the order fills refuses the
+/// order unless the order brings that exposure down.
+fn fx_exposure_check(s: &AppState, account: &accounts::Account, legs: &[OrderLeg], asset_class: Option<&str>) -> (Vec<String>, Vec<String>) {
+    let order: Vec<(String, f64, f64)> = legs.iter().map(|l| (l.instrument.clone(), positions::signed_quantity(l.side, l.quantity), l.price)).collect();
+    let delta = currency_book(s, &account.base_currency, &order, asset_class);
+    let limits = s.fx_limits.lock().unwrap().clone();
+    if delta.keys().all(|c| limits.cap(&account.id, c).is_none()) { return Default::default(); }
+    let held = currency_book(s, &account.base_currency, &portfolio(s, Some(&account.id), None), None);
+    let (mut reasons, mut flags) = (Vec::new(), Vec::new());
+    for (c, d) in &delta {
+        let Some(cap) = limits.cap(&account.id, c) else { continue };
+        let Some(rate) = usd_value(s, c, 1.0) else { flags.push(format!("FX exposure not assessed: no USD rate for {c}")); continue };
+        let before = held.get(c).copied().unwrap_or(0.0) * rate;
+        let after = before + d * rate;
+        if after.abs() > cap && after.abs() > before.abs() { reasons.push(format!("FX exposure max {c} {cap:.2} USD exceeded: net {after:.2} USD after the order, {before:.2} before")); }
+    }
+    (reasons, flags)
+}
+
+/// Issuer and sector of each of `instruments`, the sector falling back to the one in the factor mapping.
+fn classify(s: &AppState, instruments: impl Iterator<Item = String>) -> BTreeMap<String, concentration::Classification> {
+    let (cl, sc) = (s.classifications.lock().unwrap(), s.scenarios.lock().unwrap());
+    instruments.map(|i| {
+        let mut c = cl.get(&i).cloned().unwrap_or_default();
+        if c.sector.is_none() { c.sector = sc.factor(&i).and_then(|f| f.sector.clone()); }
+        (i, c)
+    }).collect()
+}
+
+fn bucket_of(classes: &BTreeMap<String, concentration::Classification>) -> impl Fn(&str, concentration::Dimension) -> Option<String> + '_ {
+    move |i, d| match d {
+        concentration::Dimension::Instrument => Some(i.into()),
+        concentration::Dimension::Issuer => classes.get(i)?.issuer.clone(),
+        concentration::Dimension::Sector => classes.get(i)?.sector.clone(),
+    }
+}
+
+/// Value of each priced holding in the account's base currency.
+fn holding_values(s: &AppState, account: &accounts::Account) -> BTreeMap<String, f64> {
+    let (legs, _) = to_base(s, &account.base_currency, &portfolio(s, Some(&account.id), None));
+    let mut out = BTreeMap::new();
+    for (i, q, p) in legs { *out.entry(i).or_default() += q * p; }
+    out
+}
+
+/// Concentration breaches and warnings if the order filled, its legs valued in base currency at `fx`.
+fn concentration_check(s: &AppState, account: &accounts::Account, legs: &[OrderLeg], fx: &HashMap<String, f64>) -> (Vec<String>, Vec<String>) {
+    let limits = s.concentration_limits.lock().unwrap().clone();
+    if !limits.any(&account.id) { return Default::default(); }
+    let before = holding_values(s, account);
+    let mut after = before.clone();
+    for l in legs { *after.entry(l.instrument.clone()).or_default() += positions::signed_quantity(l.side, l.quantity) * l.price * fx.get(&l.instrument).copied().unwrap_or(1.0); }
+    let classes = classify(s, after.keys().cloned());
+    let touched: Vec<String> = legs.iter().map(|l| l.instrument.clone()).collect();
+    concentration::check(&limits, &account.id, &before, &after, &touched, bucket_of(&classes))
+}
+
+/// Cash flows the lines would settle and, for accounts with a cash ledger, the first value date on which the
+/// projected balance would go negative because of them.
+fn settlement_check(s: &AppState, account: &str, venue: Option<&str>, lines: &[OrderLeg], check_id: &str) -> (Vec<settlement::Flow>, Option<String>) {
+    let sb = s.settlement.lock().unwrap();
+    if !sb.tracked(account) { return (Vec::new(), None); }
+    let today = chrono::Utc::now().date_naive();
+    let value_date = sb.value_date(venue, today);
+    let flows: Vec<settlement::Flow> = lines.iter().map(|l| settlement::Flow {
+        value_date, amount: -positions::signed_quantity(l.side, l.quantity) * l.price, instrument: l.instrument.clone(), check_id: check_id.into(),
+    }).collect();
+    if flows.iter().map(|f| f.amount).sum::<f64>() >= 0.0 { return (flows, None); }
+    let p = sb.project(account, today, &flows);
+    let short = p.and_then(|p| {
+        if value_date <= today && p.settled < 0.0 { return Some((today, p.settled)); }
+        p.by_value_date.iter().find(|d| d.value_date >= value_date && d.projected_balance < 0.0).map(|d| (d.value_date, d.projected_balance))
+    });
+    (flows, short.map(|(d, b)| format!("Unfunded settlement obligation: projected cash {b:.2} on {d}")))
+}
+
+fn account_status_reasons(a: &accounts::Account) -> Vec<String> {
+    match a.status {
+        accounts::AccountStatus::Suspended => vec!["Account suspended: all trading blocked".into()],
+        accounts::AccountStatus::Closed => vec!["Account closed: trading not permitted".into()],
+        _ => Vec::new(),
+    }
+}
+
+fn kill_switch_reasons(s: &AppState, account: &accounts::Account) -> Vec<String> {
+    s.kill_switches.lock().unwrap().halting(&account.id, account.desk.as_deref()).into_iter().map(|e| format!("kill switch active ({}): {}", e.label(), e.reason)).collect()
+}
+
+/// Engages the account's kill switch on a policy breach and notifies; a switch already engaged is left as it is.
+fn trip_kill_switch(s: &AppState, account: &str, reason: String) {
+    let Some(e) = s.kill_switches.lock().unwrap().engage(kill_switch::Scope::Account, Some(account), kill_switch::Trigger::Policy, reason, "system", now_ms()) else { return };
+    s.audit.lock().unwrap().record("system", "kill_switch.engage", account, serde_json::to_value(&e).unwrap_or_default(), now_ms());
+    raise_alert(s, "kill_switch", alerts::Severity::Critical, Some(account), None, format!("kill switch engaged: {}; a risk officer must disengage it", e.reason));
+}
+
+fn engage_switch(s: &AppState, h: &HeaderMap, scope: kill_switch::Scope, key: Option<&str>, reason: String) -> Result<(StatusCode, Json<kill_switch::Engagement>), (StatusCode, Json<Err>)> {
+    kill_switch::validate(scope, key).map_err(bad_request)?;
+    if scope == kill_switch::Scope::Account { require_account(s, key.unwrap_or_default())?; }
+    if reason.trim().is_empty() { return Err(bad_request("engaging needs a reason")); }
+    let e = s.kill_switches.lock().unwrap().engage(scope, key, kill_switch::Trigger::Manual, reason, &actor(h), now_ms()).ok_or_else(|| bad_request("kill switch is already engaged"))?;
+    audit(s, h, "kill_switch.engage", key.unwrap_or("firm"), serde_json::to_value(&e).unwrap_or_default());
+    let account = key.filter(|_| scope == kill_switch::Scope::Account);
+    raise_alert(s, "kill_switch", alerts::Severity::Critical, account, None, format!("{} kill switch engaged by {}: {}", e.label(), e.engaged_by, e.reason));
+    Ok((StatusCode::CREATED, Json(e)))
+}
+
+/// Only a named risk officer can lift a kill switch, whatever engaged it.
+fn disengage_switch(s: &AppState, h: &HeaderMap, scope: kill_switch::Scope, key: Option<&str>, note: String) -> ApiResult<kill_switch::Engagement> {
+    require_role(h, OVERRIDE_ROLE)?;
+    if h.get("x-user-id").is_none() { return Err(bad_request("disengaging needs an x-user-id")); }
+    kill_switch::validate(scope, key).map_err(bad_request)?;
+    let e = s.kill_switches.lock().unwrap().disengage(scope, key, &actor(h), note, now_ms()).map_err(bad_request)?;
+    audit(s, h, "kill_switch.disengage", key.unwrap_or("firm"), serde_json::to_value(&e).unwrap_or_default());
+    let account = key.filter(|_| scope == kill_switch::Scope::Account);
+    raise_alert(s, "kill_switch", alerts::Severity::Info, account, None, format!("{} kill switch disengaged by {}: {}", e.label(), actor(h), e.note.clone().unwrap_or_default()));
+    Ok(Json(e))
+}
+
+async fn list_kill_switches(State(s): State<Arc<AppState>>, Query(q): Query<AccountQuery>) -> Json<Vec<kill_switch::Engagement>> {
+    let q = kill_switch::HistoryQuery { scope: Some(kill_switch::Scope::Account), key: q.account };
+    Json(s.kill_switches.lock().unwrap().history(&q))
+}
+
+async fn get_kill_switch(State(s): State<Arc<AppState>>, Path(account): Path<String>) -> ApiResult<kill_switch::Engagement> {
+    require_account(&s, &account)?;
+    s.kill_switches.lock().unwrap().engaged(kill_switch::Scope::Account, Some(&account)).cloned().map(Json).ok_or_else(|| not_found("Engaged kill switch"))
+}
+
+async fn engage_kill_switch(State(s): State<Arc<AppState>>, h: HeaderMap, Path(account): Path<String>, Json(req): Json<kill_switch::Engage>) -> Result<(StatusCode, Json<kill_switch::Engagement>), (StatusCode, Json<Err>)> {
+    require_role(&h, OVERRIDE_ROLE)?;
+    engage_switch(&s, &h, kill_switch::Scope::Account, Some(&account), req.reason)
+}
+
+async fn disengage_kill_switch(State(s): State<Arc<AppState>>, h: HeaderMap, Path(account): Path<String>, Json(req): Json<kill_switch::Disengage>) -> ApiResult<kill_switch::Engagement> {
+    disengage_switch(&s, &h, kill_switch::Scope::Account, Some(&account), req.note)
+}
+
+/// Every engagement at every scope, newest first.
+async fn list_scoped_kill_switches(State(s): State<Arc<AppState>>, Query(q): Query<kill_switch::HistoryQuery>) -> Json<Vec<kill_switch::Engagement>> {
+    Json(s.kill_switches.lock().unwrap().history(&q))
+}
+
+/// Risk officers' emergency stop for an account, a desk or the whole firm.
+async fn engage_scoped_kill_switch(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<kill_switch::ScopedRequest>) -> Result<(StatusCode, Json<kill_switch::Engagement>), (StatusCode, Json<Err>)> {
+    require_role(&h, OVERRIDE_ROLE)?;
+    engage_switch(&s, &h, req.scope, req.key.as_deref(), req.reason.unwrap_or_default())
+}
+
+async fn disengage_scoped_kill_switch(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<kill_switch::ScopedRequest>) -> ApiResult<kill_switch::Engagement> {
+    disengage_switch(&s, &h, req.scope, req.key.as_deref(), req.note.unwrap_or_default())
+}
+
+async fn get_kill_policy(State(s): State<Arc<AppState>>, Path(account): Path<String>) -> ApiResult<kill_switch::KillPolicy> {
+    s.kill_switches.lock().unwrap().policy(&account).map(Json).ok_or_else(|| not_found("Kill switch policy"))
+}
+
+async fn set_kill_policy(State(s): State<Arc<AppState>>, h: HeaderMap, Path(account): Path<String>, Json(req): Json<kill_switch::KillPolicy>) -> ApiResult<kill_switch::KillPolicy> {
+    require_role(&h, OVERRIDE_ROLE)?;
+    require_account(&s, &account)?;
+    req.validate().map_err(bad_request)?;
+    s.kill_switches.lock().unwrap().set_policy(&account, req.clone());
+    audit(&s, &h, "kill_switch.policy", &account, serde_json::to_value(&req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn delete_kill_policy(State(s): State<Arc<AppState>>, h: HeaderMap, Path(account): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, OVERRIDE_ROLE)?;
+    if !s.kill_switches.lock().unwrap().remove_policy(&account) { return Err(not_found("Kill switch policy")); }
+    audit(&s, &h, "kill_switch.policy_delete", &account, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Ex-dividend findings for one order line under the configured policy: a short stock position carried over the
+/// ex-date, or a short in-the-money call whose time value is below the dividend.
+fn ex_date_findings(s: &AppState, account: &str, l: &OrderLeg) -> Vec<(corporate_actions::Action, String)> {
+    use corporate_actions::{Action, REASON};
+    let signed = positions::signed_quantity(l.side, l.quantity);
+    let pos = s.positions.lock().unwrap().net(account, &l.instrument);
+    if positions::short_quantity(pos, signed) <= 0.0 { return Vec::new(); }
+    let terms = s.scenarios.lock().unwrap().factor(&l.instrument).and_then(|f| f.option.clone());
+    let today = chrono::Utc::now().date_naive();
+    let mut out = Vec::new();
+    match terms {
+        Some(o) if o.right == scenarios::OptionRight::Call => {
+            let spot = s.marketdata.lock().unwrap().price(&o.underlying);
+            let ca = s.corporate_actions.lock().unwrap();
+            if let (Some(d), Some(spot), true) = (ca.upcoming(&o.underlying, today), spot, ca.policy.short_calls != Action::Off) {
+                let time_value = (l.price - (spot - o.strike).max(0.0)).max(0.0);
+                if spot > o.strike && d.amount > time_value {
+                    out.push((ca.policy.short_calls, format!("{REASON}: short call on {} may be exercised early before ex-date {}: dividend {:.4} exceeds time value {time_value:.4}", o.underlying, d.ex_date, d.amount)));
+                }
+            }
+        }
+        Some(_) => {}
+        None => {
+            let ca = s.corporate_actions.lock().unwrap();
+            let short = -(pos + signed);
+            if let (Some(d), true) = (ca.upcoming(&l.instrument, today), ca.policy.short_stock != Action::Off && short * l.price >= ca.policy.short_stock_min_notional) {
+                out.push((ca.policy.short_stock, format!("{REASON}: short {short} {} over ex-date {} owes dividend of {:.2}", l.instrument, d.ex_date, short * d.amount)));
+            }
+        }
+    }
+    out
+}
+
+/// Event windows open at `now` for any of `instruments`, each listed once.
+fn active_events<'a>(s: &AppState, instruments: impl IntoIterator<Item = &'a str>, now: u64) -> Vec<calendar::ActiveEvent> {
+    let cal = s.calendar.lock().unwrap();
+    let mut events: Vec<calendar::ActiveEvent> = instruments.into_iter().flat_map(|i| cal.active(i, now)).collect();
+    events.sort_by(|a, b| a.id.cmp(&b.id));
+    events.dedup_by(|a, b| a.id == b.id);
+    events
+}
+
+/// Checks that judge one order line on its own: entitlements, reduce-only, per-order quantity, the scheduled window
+/// and ex-dividend policy. Returns blocking reasons and non-blocking flags.
+fn leg_checks(s: &AppState, a: &accounts::Account, l: &OrderLeg, sched: &schedule::ActiveRule, asset_class: Option<&str>, venue: Option<&str>, tr: &mut Option<Vec<RuleTrace>>) -> (Vec<String>, Vec<String>) {
+    use serde_json::json;
+    let scope = entitlements::OrderScope { symbol: &l.instrument, asset_class, venue };
+    let mut reasons = s.entitlements.lock().unwrap().evaluate(&a.id, &scope);
+    trace(tr, "entitlements", json!({ "symbol": l.instrument, "asset_class": asset_class, "venue": venue }), serde_json::Value::Null, reasons.is_empty());
+    let now = now_ms();
+    let halt = s.circuit_breakers.lock().unwrap().active(&l.instrument, now).cloned();
+    trace(tr, "trading_halt", json!({ "instrument": l.instrument }), json!(halt.as_ref().map(|h| h.expires_at_ms)), halt.is_none());
+    if let Some(h) = halt { reasons.push(format!("Trading halted in {}: {} circuit breaker, {}s remaining", l.instrument, h.level, (h.expires_at_ms - now).div_ceil(1000))); }
+    let blocking: Vec<calendar::ActiveEvent> = s.calendar.lock().unwrap().active(&l.instrument, now).into_iter().filter(|e| e.block).collect();
+    trace(tr, "event_block", json!({ "instrument": l.instrument }), json!(blocking.iter().map(|e| &e.id).collect::<Vec<_>>()), blocking.is_empty());
+    reasons.extend(blocking.iter().map(|e| format!("Event window blocks {}: {} until {}", l.instrument, e.name, e.window_end_ms)));
+    if a.status == accounts::AccountStatus::ReduceOnly {
+        let reducing = s.positions.lock().unwrap().is_reducing(&a.id, &l.instrument, positions::signed_quantity(l.side, l.quantity));
+        trace(tr, "reduce_only", json!({ "instrument": l.instrument, "side": l.side, "quantity": l.quantity }), json!("reducing"), reducing);
+        if !reducing { reasons.push("Account is reduce-only: order would increase exposure".into()); }
+    }
+    if let Some(short) = short_sale(s, &a.id, l) {
+        let located = s.locates.lock().unwrap().located(&a.id, &l.instrument, now_ms());
+        trace(tr, "locate", json!({ "instrument": l.instrument, "short_quantity": short }), json!({ "located": located }), located >= short);
+        if located < short { reasons.push(format!("Short sale not located: {located} of {short} located")); }
+    }
+    // Membership rules require clearly erroneous prices to be refused under their own reason, whatever else fails.
+    if let Some(f) = s.erroneous.lock().unwrap().assess(&l.instrument, l.price).filter(|_| l.price > 0.0) {
+        trace(tr, "clearly_erroneous", json!({ "instrument": l.instrument, "price": l.price, "reference_price": f.reference_price, "prints": f.prints, "deviation_pct": f.deviation_pct }), json!(f.band_pct), !f.erroneous());
+        if f.erroneous() { reasons.push(format!("Clearly erroneous price: {:.4} is {:+.2}% from {:.4}, the median of the last {} prints (band {:.2}%)", l.price, f.deviation_pct, f.reference_price, f.prints, f.band_pct)); }
+    }
+    let mut flags = Vec::new();
+    let quote = s.marketdata.lock().unwrap().quote(&l.instrument);
+    let collar = s.groups.lock().unwrap().group_of(&l.instrument).and_then(|g| g.spec.collar.clone()).unwrap_or_else(|| s.fat_finger.lock().unwrap().clone());
+    if let Some(f) = collar.assess(quote, l.price, now_ms()).filter(|_| l.price > 0.0) {
+        trace(tr, "fat_finger", json!({ "instrument": l.instrument, "price": l.price, "reference": f.reference, "reference_price": f.reference_price, "deviation_pct": f.deviation_pct }), json!(f.action), f.action != corporate_actions::Action::Block);
+        let detail = format!("{:.4} is {:+.2}% from {:.4}", l.price, f.deviation_pct, f.reference_price);
+        match f.action {
+            corporate_actions::Action::Block => reasons.push(format!("Fat-finger price: {detail} (reference {})", f.reference.name())),
+            corporate_actions::Action::Flag => flags.push(format!("Fat-finger warning: {detail} (reference {})", f.reference.name())),
+            corporate_actions::Action::Off => {}
+        }
+    }
+    let notional = l.quantity * l.price;
+    if let Some(q) = a.default_limits.max_order_quantity {
+        trace(tr, "account_max_order_quantity", json!({ "instrument": l.instrument, "quantity": l.quantity }), json!(q), l.quantity <= q);
+        if l.quantity > q { reasons.push(format!("Account max order quantity {q} exceeded")); }
+    }
+    if let Some(q) = sched.rule.max_quantity {
+        trace(tr, "schedule_max_quantity", json!({ "instrument": l.instrument, "quantity": l.quantity, "windows": sched.windows }), json!(q), l.quantity <= q);
+        if l.quantity > q { reasons.push(format!("Scheduled max quantity {q} exceeded ({})", sched.windows.join(", "))); }
+    }
+    if let Some(n) = sched.rule.max_notional {
+        trace(tr, "schedule_max_notional", json!({ "instrument": l.instrument, "notional": notional, "windows": sched.windows }), json!(n), notional <= n);
+        if notional > n { reasons.push(format!("Scheduled max notional {n:.2} exceeded ({})", sched.windows.join(", "))); }
+    }
+    let (blocks, ex_flags): (Vec<_>, Vec<_>) = ex_date_findings(s, &a.id, l).into_iter().partition(|(act, _)| *act == corporate_actions::Action::Block);
+    trace(tr, "ex_dividend", json!({ "instrument": l.instrument, "findings": ex_flags.iter().chain(&blocks).map(|f| &f.1).collect::<Vec<_>>() }), serde_json::Value::Null, blocks.is_empty());
+    reasons.extend(blocks.into_iter().map(|f| f.1));
+    flags.extend(ex_flags.into_iter().map(|f| f.1));
+    (reasons, flags)
+}
+
+async fn pretrade_check(State(s): State<Arc<AppState>>, h: HeaderMap, Query(x): Query<ExplainQuery>, body: Result<Json<PreTradeCheckRequest>, JsonRejection>) -> ApiResult<PreTradeCheckResponse> {
+    let req = json_body(body)?;
+    pretrade(s, h, x.explain, req).await.map(Json)
+}
+
+/// The pre-trade check behind both the REST and gRPC APIs. `h` carries who is asking and in which language.
+async fn pretrade(s: Arc<AppState>, h: HeaderMap, explain: bool, req: PreTradeCheckRequest) -> Result<PreTradeCheckResponse, (StatusCode, Json<Err>)> {
+    use serde_json::json;
+    let t = Instant::now();
+    let account = require_account(&s, &req.account)?;
+    req.tags.validate().map_err(bad_request)?;
+    if req.latency_budget_ms == Some(0) { return Err(bad_request("latency_budget_ms must be positive")); }
+    let inputs = serde_json::to_value(&req).unwrap_or_default();
+    let deadline = match client_deadline(&s, &h)? {
+        Ok(d) => d,
+        Err(reason) => {
+            let (check_id, reasons) = timed_out(&s, "pretrade", &account, &req.tags, inputs, reason, t);
+            let locale = locale(&s, &h, account.entity.as_deref());
+            let reason_codes = s.reason_codes.lock().unwrap().reasons(&reasons, locale);
+            let daily_headroom = s.daily.lock().unwrap().headroom(&req.account, &req.instrument, now_ms());
+            return Ok(PreTradeCheckResponse {
+                check_id, approved: false, reasons, reason_codes, locale, risk_score: 0.0, margin_impact: 0.0, position_limit_used_pct: 0.0, daily_headroom, schedule: Default::default(),
+                elapsed_us: t.elapsed().as_micros(), package: None, algo: None, borrow: Vec::new(), events: Vec::new(), greeks: None, beta: None, reservation: None, overridden: Vec::new(),
+                override_status: None, risk_direction: hedge::Direction::Increasing, tags: req.tags, degraded: None, timed_out: true, trace: None,
+            });
+        }
+    };
+    let legs = match (req.legs.is_empty(), req.side) {
+        (true, Some(side)) => vec![OrderLeg { instrument: req.instrument.clone(), side, quantity: req.quantity, price: req.price }],
+        (true, None) => return Err(bad_request("an order needs a side")),
+        (false, _) => req.legs.clone(),
+    };
+    if legs.iter().any(|l| l.instrument.is_empty()) { return Err(bad_request("every order leg needs an instrument")); }
+    if !legs.iter().all(|l| l.quantity.is_finite() && l.quantity > 0.0 && l.price.is_finite() && l.price > 0.0) { return Err(bad_request("quantity and price must be positive")); }
+    let is_package = legs.len() > 1;
+    if is_package && req.algo.is_some() { return Err(bad_request("algo parameters apply to single orders, not packages")); }
+    if let Some(a) = &req.algo { algo::validate(a).map_err(bad_request)?; }
+    let primary = legs[0].instrument.clone();
+    // Notionals are in the account's base currency from here on; prices stay as quoted.
+    let (fx, unconverted) = price_rates(&s, &account.base_currency, legs.iter().map(|l| l.instrument.as_str()));
+    let rate = |i: &str| fx.get(i).copied().unwrap_or(1.0);
+    let leg_notional: Vec<f64> = legs.iter().map(|l| l.quantity * l.price * rate(&l.instrument)).collect();
+    let gross_notional: f64 = leg_notional.iter().map(|n| n.abs()).sum();
+    let notional = if is_package { legs.iter().map(|l| positions::signed_quantity(l.side, l.quantity) * l.price * rate(&l.instrument)).sum::<f64>().abs() } else { leg_notional[0] };
+    // Leg-specific reasons carry the leg as a suffix so the limit text itself stays intact.
+    let tag = |i: usize| if is_package { format!(" [leg {} {}]", i + 1, legs[i].instrument) } else { String::new() };
+    let now = now_ms();
+    let check_id = uuid::Uuid::new_v4().to_string();
+    let mut tr = explain.then(Vec::new);
+    let borrow: Vec<locates::BorrowCost> = legs.iter().filter_map(|l| {
+        let short = short_sale(&s, &req.account, l)?;
+        s.locates.lock().unwrap().borrow_cost(&l.instrument, short, l.price, gross_notional)
+    }).collect();
+    let risk_direction = risk_direction(&s, &req.account, &legs);
+    let hedging = s.hedging.lock().unwrap().clone();
+    let notional_exempt = hedging.skips_notional_limits(risk_direction);
+    // Shorting hard-to-borrow or expensive names carries recall and squeeze risk on top of plain size.
+    let risk_score = (notional / 1_000_000.0 + borrow.iter().map(|b| b.score_uplift).sum::<f64>()).min(1.0) * hedging.score_factor(risk_direction);
+    let schedules: Vec<schedule::ActiveRule> = { let sc = s.schedules.lock().unwrap(); legs.iter().map(|l| sc.active(&l.instrument, now)).collect() };
+    let events = active_events(&s, legs.iter().map(|l| l.instrument.as_str()), now);
+    let scale = calendar::multiplier(&events);
+    let threshold = schedules.iter().map(|r| r.rule.risk_threshold.unwrap_or(0.8)).fold(f64::INFINITY, f64::min) * scale;
+    let mut reasons = account_status_reasons(&account);
+    trace(&mut tr, "account_status", json!({ "status": account.status }), json!("active"), reasons.is_empty());
+    if !fx.is_empty() || !unconverted.is_empty() { trace(&mut tr, "fx_conversion", json!({ "base_currency": account.base_currency, "rates": fx }), serde_json::Value::Null, unconverted.is_empty()); }
+    reasons.extend(unconverted);
+    let fingerprint = legs.iter().map(|l| format!("{} {} {}@{}", l.side.label(), l.quantity, l.instrument, l.price)).collect::<Vec<_>>().join(" / ");
+    let tripped = s.kill_switches.lock().unwrap().observe_order(&req.account, fingerprint, now);
+    let loss = -s.ledger.lock().unwrap().realized_on(&req.account, chrono::Utc::now().date_naive(), |c| fx_rate(&s, c, &account.base_currency));
+    let tripped = tripped.or_else(|| s.kill_switches.lock().unwrap().check_loss(&req.account, loss));
+    if let Some(r) = tripped { trip_kill_switch(&s, &req.account, r); }
+    let killed = kill_switch_reasons(&s, &account);
+    trace(&mut tr, "kill_switch", json!({ "engaged": !killed.is_empty() }), serde_json::Value::Null, killed.is_empty());
+    reasons.extend(killed);
+    use faults::Target;
+    let instruments: Vec<&str> = legs.iter().map(|l| l.instrument.as_str()).collect();
+    let deps = injected_faults(&s, &[Target::Pretrade, Target::Accounts, Target::Positions, Target::Reservations, Target::MarketData], &instruments);
+    let (injected, degraded) = match within_budget(&s, req.latency_budget_ms, deadline, t, deps).await { Ok(f) => (f, None), Err(d) => (Vec::new(), Some(d)) };
+    if !injected.is_empty() { trace(&mut tr, "dependencies", json!({ "failures": injected }), serde_json::Value::Null, false); }
+    reasons.extend(injected);
+    if let Some(d) = &degraded {
+        let refused = d.reason(notional);
+        trace(&mut tr, "latency_budget", json!({ "stage": d.stage }), json!({ "budget_ms": d.budget_ms, "fallback": d.fallback }), refused.is_none());
+        tracing::warn!(check = %check_id, budget_ms = d.budget_ms, "pre-trade check degraded to the fast path");
+        reasons.extend(refused);
+    }
+    let mut flags = Vec::new();
+    for (i, (l, sched)) in legs.iter().zip(&schedules).enumerate() {
+        let (r, f) = leg_checks(&s, &account, l, sched, req.asset_class.as_deref(), req.venue.as_deref(), &mut tr);
+        reasons.extend(r.into_iter().map(|r| format!("{r}{}", tag(i))));
+        flags.extend(f.into_iter().map(|r| format!("{r}{}", tag(i))));
+    }
+    let algo = req.algo.as_ref().map(|a| {
+        let adv = s.liquidity.lock().unwrap().get(&primary).adv;
+        let limits = *s.algo_limits.lock().unwrap();
+        let p = limits.evaluate(a, legs[0].quantity, adv);
+        trace(&mut tr, "algo_participation", json!({ "requested": a.participation_rate, "projected": p.projected_participation, "adv": adv }), json!(limits.max_participation), p.reasons.iter().all(|r| !r.contains("participation")));
+        trace(&mut tr, "algo_duration", json!({ "duration_secs": a.duration_secs }), json!(limits.max_duration_secs), a.duration_secs <= limits.max_duration_secs);
+        if let Some(q) = limits.max_slice_quantity { trace(&mut tr, "algo_slice_quantity", json!({ "max_slice_quantity": p.max_slice_quantity, "slices": p.slices }), json!(q), p.max_slice_quantity <= q); }
+        p
+    });
+    reasons.extend(algo.iter().flat_map(|p| p.reasons.clone()));
+    if risk_score >= threshold { reasons.push("Position limit exceeded".into()); }
+    trace(&mut tr, "risk_direction", json!({ "direction": risk_direction }), json!(hedging), true);
+    trace(&mut tr, "position_limit", json!({ "risk_score": risk_score, "notional": notional }), json!(threshold), risk_score < threshold);
+    let exposure = position_exposure(&s, &req.account, &legs, &fx);
+    let cap = account.default_limits.max_position_notional.map(|c| c * scale);
+    let position_limit_used_pct = exposure.iter().map(|(_, _, after)| after.abs() / cap.unwrap_or(DEFAULT_POSITION_LIMIT) * 100.0).fold(0.0, f64::max);
+    if let Some(c) = cap {
+        let over: Vec<String> = exposure.iter().filter(|(_, before, after)| after.abs() > c && after.abs() > before.abs() + 1e-9).map(|(i, _, after)| format!("Account max position notional {c:.2} exceeded on {i}: {:.2}", after.abs())).collect();
+        trace(&mut tr, "account_max_position_notional", json!(exposure.iter().map(|(i, b, a)| json!({ "instrument": i, "before": b, "after": a })).collect::<Vec<_>>()), json!(c), over.is_empty());
+        reasons.extend(over);
+    }
+    let max_order_notional = account.default_limits.max_order_notional.map(|n| n * scale).filter(|_| !notional_exempt);
+    if let Some(n) = max_order_notional.filter(|n| notional > *n) { reasons.push(format!("Account max order notional {n:.2} exceeded")); }
+    if !events.is_empty() { trace(&mut tr, "event_windows", json!(events.iter().map(|e| &e.id).collect::<Vec<_>>()), json!({ "limit_multiplier": scale }), true); }
+    if let Some(n) = max_order_notional { trace(&mut tr, "account_max_order_notional", json!({ "notional": notional }), json!(n), notional <= n); }
+    let (fx_settlements, fx_reasons, fx_flags) = fx_settlement_check(&s, &req, &legs, &check_id);
+    if !fx_settlements.is_empty() { trace(&mut tr, "fx_settlement", json!({ "exposure_usd": fx_settlements.iter().map(|x| x.exposure_usd).sum::<f64>(), "counterparty": req.counterparty }), serde_json::Value::Null, fx_reasons.is_empty()); }
+    reasons.extend(fx_reasons);
+    flags.extend(fx_flags);
+    let (fx_limit_reasons, fx_limit_flags) = fx_exposure_check(&s, &account, &legs, req.asset_class.as_deref());
+    trace(&mut tr, "fx_exposure", json!({ "legs": legs.len() }), json!(s.fx_limits.lock().unwrap().currencies), fx_limit_reasons.is_empty());
+    reasons.extend(fx_limit_reasons);
+    flags.extend(fx_limit_flags);
+    let (conc_reasons, conc_flags) = concentration_check(&s, &account, &legs, &fx);
+    if !(conc_reasons.is_empty() && conc_flags.is_empty()) { trace(&mut tr, "concentration", json!({ "breaches": conc_reasons.len(), "warnings": conc_flags.len() }), json!(s.concentration_limits.lock().unwrap().caps), conc_reasons.is_empty()); }
+    reasons.extend(conc_reasons);
+    flags.extend(conc_flags);
+    let (flows, unfunded) = settlement_check(&s, &req.account, req.venue.as_deref(), &legs, &check_id);
+    trace(&mut tr, "settlement_cash", json!({ "flows": flows.iter().map(|f| f.amount).sum::<f64>(), "value_date": flows.first().map(|f| f.value_date) }), json!(0.0), unfunded.is_none());
+    reasons.extend(unfunded);
+    let (greeks, greek_reasons) = greek_check(&s, &account, &legs).unwrap_or_default();
+    if let Some(g) = &greeks { trace(&mut tr, "greek_limits", json!({ "order": g.order, "after": g.after }), json!(account.default_limits.greeks), greek_reasons.is_empty()); }
+    reasons.extend(greek_reasons);
+    let (beta, beta_reasons) = beta_check(&s, &account, &legs).map_or((None, Vec::new()), |(b, r)| (Some(b), r));
+    if let Some(b) = &beta { trace(&mut tr, "beta_exposure", json!(b), json!({ "account": account.default_limits.max_beta_exposure }), beta_reasons.is_empty()); }
+    reasons.extend(beta_reasons);
+    let daily_legs: Vec<(&str, f64)> = legs.iter().zip(&leg_notional).map(|(l, n)| (l.instrument.as_str(), n.abs())).collect();
+    let limit_legs: Vec<(String, f64, f64)> = legs.iter().zip(&leg_notional).map(|(l, n)| (l.instrument.clone(), l.quantity, *n)).collect();
+    let leg_groups = s.groups.lock().unwrap().membership(limit_legs.iter().map(|l| l.0.as_str()));
+    let related = s.related.lock().unwrap().group(&req.account);
+    let wash_group = (related.len() > 1).then(|| related[0].as_str());
+    let limit_order = limits::Order { account: &req.account, desk: account.desk.as_deref(), tags: &req.tags, notional, legs: &limit_legs, groups: &leg_groups, related: &related, scale, notional_exempt };
+    let scoped = s.limits.lock().unwrap().applicable(&limit_order);
+    let limits_evaluated: Vec<String> = scoped.iter().map(|l| l.id.clone()).collect();
+    let (limit_fx, open_counts) = limit_inputs(&s, &account, &scoped, &legs);
+    let wash_legs: Vec<wash::Leg> = legs.iter().map(|l| (l.instrument.as_str(), positions::signed_quantity(l.side, l.quantity) > 0.0, l.price)).collect();
+    let ovr = req.override_token.as_ref().map(|t| s.overrides.lock().unwrap().check(t, &req.account, &primary, now));
+    let mut override_status = ovr.as_ref().map(|o| match o { Ok(o) => format!("accepted {}", o.token), Err(e) => format!("rejected: {e}") });
+    let mut overridden = Vec::new();
+    let margin_impact = notional * 0.1;
+    // Reserving checks also hold margin, so concurrent in-flight orders cannot spend the same funds twice.
+    let free_margin = req.reserve.then(|| account_funds(&s, &req.account) - s.margins.lock().unwrap().get(&req.account).map_or(0.0, |m| m.initial_margin));
+    let (approved, daily_headroom, reservation) = {
+        let mut v = s.velocity.lock().unwrap();
+        let mut d = s.daily.lock().unwrap();
+        if tr.is_some() {
+            for w in v.windows(&req.account, now) {
+                trace(&mut tr, format!("velocity_{}_orders", w.window), json!({ "orders": w.orders + 1 }), json!(w.max_orders), w.orders < w.max_orders);
+                trace(&mut tr, format!("velocity_{}_notional", w.window), json!({ "notional": w.notional + notional }), json!(w.max_notional), w.notional + notional <= w.max_notional);
+            }
+            if let Some(r) = d.headroom(&req.account, &primary, now).account_remaining { trace(&mut tr, "daily_account_notional", json!({ "notional": notional }), json!({ "remaining": r }), notional <= r); }
+            for (instrument, n) in &daily_legs {
+                if let Some(r) = d.headroom(&req.account, instrument, now).instrument_remaining { trace(&mut tr, "daily_instrument_notional", json!({ "instrument": instrument, "notional": n }), json!({ "remaining": r }), *n <= r); }
+            }
+        }
+        reasons.extend(v.evaluate(&req.account, notional, now));
+        reasons.extend(d.evaluate(&req.account, notional, &daily_legs, now));
+        // Members of a pool are checked and booked against it under one lock, however many check at once.
+        let session = d.session_of(now);
+        let mut pl = s.pools.lock().unwrap();
+        if let (true, Some((pool, r))) = (tr.is_some(), pl.remaining(&req.account, session)) { trace(&mut tr, "limit_pool_notional", json!({ "pool": pool, "notional": notional }), json!({ "remaining": r }), notional <= r); }
+        reasons.extend(pl.evaluate(&req.account, notional, session));
+        let mut lb = s.limits.lock().unwrap();
+        let fx_of = |l: &limits::Limit| limit_fx.iter().find(|(id, _)| *id == l.id).map_or(Ok(1.0), |(_, r)| r.clone());
+        let breached = lb.evaluate(&limit_order, |l| open_counts.iter().find(|(id, _)| *id == l.id).map_or((0, 0), |(_, c)| *c), fx_of, now);
+        if tr.is_some() { trace(&mut tr, "limits", json!({ "applicable": lb.applicable(&limit_order).iter().map(|l| &l.id).collect::<Vec<_>>() }), serde_json::Value::Null, breached.is_empty()); }
+        reasons.extend(breached);
+        // Checked and booked under one lock, so two halves of a self-match checked at once cannot both pass.
+        let mut w = s.wash_trades.lock().unwrap();
+        let (wash, wash_flags) = w.evaluate(&req.account, req.tags.trader.as_deref(), wash_group, &wash_legs, now);
+        if w.config.enabled { trace(&mut tr, "wash_trade", json!({ "legs": wash_legs.len(), "scope": w.config.scope }), json!({ "window_secs": w.config.window_secs, "mode": w.config.mode }), wash.is_empty() && wash_flags.is_empty()); }
+        reasons.extend(wash);
+        flags.extend(wash_flags);
+        let mut rs = s.reservations.lock().unwrap();
+        if let Some(free) = free_margin {
+            let available = free - rs.held_margin(&req.account);
+            trace(&mut tr, "margin_headroom", json!({ "margin_impact": margin_impact }), json!({ "available": available }), margin_impact <= available);
+            if margin_impact > available { reasons.push(format!("Insufficient margin headroom: {:.2} available", available.max(0.0))); }
+        }
+        if let Some(Ok(o)) = &ovr { (overridden, reasons) = reasons.into_iter().partition(|r| is_limit(r) && o.covers(limit_key(r))); }
+        let ok = reasons.is_empty();
+        let mut reservation = None;
+        // The whole package is booked or none of it is.
+        if ok {
+            v.record(&req.account, notional, now);
+            lb.record(&limit_order, now);
+            w.record(&req.account, req.tags.trader.as_deref(), wash_group, &wash_legs, now);
+            if req.reserve {
+                d.reserve(&req.account, notional, &daily_legs);
+                pl.reserve(&req.account, notional, session);
+                reservation = Some(rs.reserve(&check_id, &req.account, notional, margin_impact, &daily_legs, now));
+            } else {
+                d.record(&req.account, notional, &daily_legs, now);
+                pl.record(&req.account, notional, session);
+            }
+        }
+        (ok, d.headroom(&req.account, &primary, now), reservation)
+    };
+    if approved {
+        consume_locates(&s, &req.account, &legs);
+        s.settlement.lock().unwrap().book(&req.account, flows);
+        s.fx_settlement.lock().unwrap().book(fx_settlements);
+    }
+    if let (Some(Ok(o)), true) = (&ovr, approved && !overridden.is_empty()) {
+        s.overrides.lock().unwrap().consume(&o.token);
+        override_status = Some(format!("applied {}", o.token));
+        audit(&s, &h, "override.use", &o.token, serde_json::json!({ "account": req.account, "legs": legs, "waived": overridden, "reason_code": o.reason_code, "issued_by": o.issued_by, "tags": req.tags }));
+        raise_tagged_alert(&s, "override_used", alerts::Severity::Warning, Some(&req.account), Some(&primary), format!("override {} issued by {} ({:?}) waived: {}", o.token, o.issued_by, o.reason_code, overridden.join("; ")), &req.tags);
+    }
+    if !approved {
+        for r in reasons.iter().filter(|r| is_limit(r)) { record_breach(&s, breaches::BreachKind::Limit, Some(&req.account), Some(&primary), limit_key(r), r.clone()); }
+    }
+    reasons.append(&mut flags);
+    if notional > 500_000.0 { reasons.push("Large order flag".into()); }
+    for b in &borrow {
+        if b.hard_to_borrow { reasons.push(format!("Hard to borrow: {}", b.symbol)); }
+        if b.special { reasons.push(format!("Borrow special: {} fee {:.0} bps above {:.0} bps", b.symbol, b.fee_bps, s.locates.lock().unwrap().special_fee_bps)); }
+    }
+    if algo.as_ref().is_some_and(|p| p.projected_participation.is_none()) { reasons.push("Algo participation not projected: no ADV for instrument".into()); }
+    trace(&mut tr, "large_order_flag", json!({ "notional": notional }), json!(500_000.0), true);
+    count(&s, |st| { st.total_checks += 1; if !approved { st.trades_blocked += 1; } });
+    s.tag_stats.lock().unwrap().record(&req.tags, approved);
+    let elapsed_us = t.elapsed().as_micros();
+    record_check(&s, checks::CheckRecord { check_id: check_id.clone(), kind: "pretrade".into(), account: req.account.clone(), approved, reasons: reasons.clone(), tags: req.tags.clone(), inputs, limits_evaluated, elapsed_us: elapsed_us as u64, at_ms: now });
+    if !approved { raise_tagged_alert(&s, "trade_blocked", alerts::Severity::Warning, Some(&req.account), Some(&primary), reasons.join("; "), &req.tags); }
+    let tripped = s.kill_switches.lock().unwrap().observe_outcome(&req.account, approved, now);
+    if let Some(r) = tripped { trip_kill_switch(&s, &req.account, r); }
+    let package = is_package.then_some(PackageSummary { legs: legs.len(), gross_notional, net_notional: notional });
+    let schedule = schedules.into_iter().next().unwrap_or_default();
+    let locale = locale(&s, &h, account.entity.as_deref());
+    let reason_codes = s.reason_codes.lock().unwrap().reasons(&reasons, locale);
+    Ok(PreTradeCheckResponse { check_id, approved, reasons, reason_codes, locale, risk_score, margin_impact, position_limit_used_pct, daily_headroom, schedule, elapsed_us, package, algo, borrow, events, greeks, beta, reservation, overridden, override_status, risk_direction, tags: req.tags, timed_out: degraded.as_ref().is_some_and(budget::Degraded::timed_out), degraded, trace: tr })
+}
+
+/// What the limit book needs to evaluate an order but cannot look up under its own lock: the rate from the
+/// account's base currency into each notional limit's, and each open-position limit's counts. Limits without a
+/// currency of their own are held in the firm's.
+fn limit_inputs(s: &AppState, account: &accounts::Account, scoped: &[limits::Limit], legs: &[OrderLeg]) -> LimitInputs {
+    let limit_fx = scoped.iter().filter(|l| matches!(l.kind, limits::LimitKind::OrderNotional { .. })).map(|l| {
+        let to = l.currency.as_deref().unwrap_or(&s.firm_currency);
+        (l.id.clone(), fx_rate(s, &account.base_currency, to).ok_or_else(|| format!("No FX rate from {} into {to} for limit {}", account.base_currency, l.id)))
+    }).collect();
+    let open_counts = scoped.iter().filter(|l| matches!(l.kind, limits::LimitKind::OpenPositions { .. })).map(|l| (l.id.clone(), open_positions(s, l, &account.id, legs))).collect();
+    (limit_fx, open_counts)
+}
+
+async fn basket_check(State(s): State<Arc<AppState>>, h: HeaderMap, body: Result<Json<BasketCheckRequest>, JsonRejection>) -> ApiResult<BasketCheckResponse> {
+    let req = json_body(body)?;
+    let t = Instant::now();
+    let account = require_account(&s, &req.account)?;
+    if req.lines.is_empty() { return Err(bad_request("a basket needs at least one line")); }
+    req.tags.validate().map_err(bad_request)?;
+    if req.latency_budget_ms == Some(0) { return Err(bad_request("latency_budget_ms must be positive")); }
+    let inputs = serde_json::to_value(&req).unwrap_or_default();
+    if req.lines.iter().any(|l| l.instrument.is_empty()) { return Err(bad_request("every basket line needs an instrument")); }
+    if !req.lines.iter().all(|l| l.quantity.is_finite() && l.quantity > 0.0 && l.price.is_finite() && l.price > 0.0) { return Err(bad_request("quantity and price must be positive")); }
+    let deadline = match client_deadline(&s, &h)? {
+        Ok(d) => d,
+        Err(reason) => {
+            let (check_id, reasons) = timed_out(&s, "basket", &account, &req.tags, inputs, reason, t);
+            let locale = locale(&s, &h, account.entity.as_deref());
+            let reason_codes = s.reason_codes.lock().unwrap().reasons(&reasons, locale);
+            let daily_headroom = s.daily.lock().unwrap().headroom(&req.account, &req.lines[0].instrument, now_ms());
+            return Ok(Json(BasketCheckResponse {
+                check_id, account: req.account, approved: false, reasons, flags: Vec::new(), reason_codes, locale, gross_notional: 0.0, net_notional: 0.0, beta_exposure_change: 0.0, risk_direction: hedge::Direction::Increasing,
+                sectors: Vec::new(), unclassified: Vec::new(), margin_impact: 0.0, daily_headroom, lines: Vec::new(), elapsed_us: t.elapsed().as_micros(), events: Vec::new(), greeks: None,
+                tags: req.tags, degraded: None, timed_out: true,
+            }));
+        }
+    };
+    use faults::Target;
+    let instruments: Vec<&str> = req.lines.iter().map(|l| l.instrument.as_str()).collect();
+    let deps = injected_faults(&s, &[Target::Pretrade, Target::Accounts, Target::Positions, Target::Reservations, Target::MarketData], &instruments);
+    let (injected, degraded) = match within_budget(&s, req.latency_budget_ms, deadline, t, deps).await { Ok(f) => (f, None), Err(d) => (Vec::new(), Some(d)) };
+    let now = now_ms();
+    let schedules: Vec<schedule::ActiveRule> = { let sc = s.schedules.lock().unwrap(); req.lines.iter().map(|l| sc.active(&l.instrument, now)).collect() };
+    let locale = locale(&s, &h, account.entity.as_deref());
+    let (fx, unconverted) = price_rates(&s, &account.base_currency, instruments.iter().copied());
+    let rate = |i: &str| fx.get(i).copied().unwrap_or(1.0);
+    let lines: Vec<BasketLine> = req.lines.iter().zip(&schedules).map(|(l, sched)| {
+        let (mut reasons, flags) = leg_checks(&s, &account, l, sched, req.asset_class.as_deref(), req.venue.as_deref(), &mut None);
+        reasons.extend(unconverted.iter().filter(|r| r.ends_with(&format!(" for {}", l.instrument))).cloned());
+        let reason_codes = s.reason_codes.lock().unwrap().reasons(&[reasons.as_slice(), flags.as_slice()].concat(), locale);
+        BasketLine { instrument: l.instrument.clone(), side: l.side, quantity: l.quantity, price: l.price, notional: l.quantity * l.price * rate(&l.instrument), approved: reasons.is_empty(), reasons, flags, reason_codes }
+    }).collect();
+    let signed = |l: &OrderLeg| positions::signed_quantity(l.side, l.quantity) * l.price * rate(&l.instrument);
+    let gross_notional: f64 = lines.iter().map(|l| l.notional.abs()).sum();
+    let net_notional: f64 = req.lines.iter().map(signed).sum();
+    let factors = s.scenarios.lock().unwrap().factors();
+    let held = portfolio(&s, Some(&req.account), None);
+    let (mut by_sector, mut unclassified) = (std::collections::BTreeMap::<String, (f64, f64)>::new(), Vec::new());
+    let mut beta_exposure_change = 0.0;
+    for l in &req.lines {
+        let f = factors.get(&l.instrument);
+        match f.and_then(|f| f.sector.clone()) { Some(sec) => by_sector.entry(sec).or_default().1 += signed(l), None => unclassified.push(l.instrument.clone()) }
+        match f.and_then(|f| f.beta) { Some(b) => beta_exposure_change += b * signed(l), None => unclassified.push(l.instrument.clone()) }
+    }
+    for (i, q, p) in &held {
+        if let Some(e) = factors.get(i).and_then(|f| f.sector.as_ref()).and_then(|sec| by_sector.get_mut(sec)) { e.0 += q * p; }
+    }
+    unclassified.sort();
+    unclassified.dedup();
+    let sectors: Vec<SectorExposure> = by_sector.into_iter().map(|(sector, (before, change))| SectorExposure { sector, before, change, after: before + change }).collect();
+
+    let mut reasons = account_status_reasons(&account);
+    reasons.extend(kill_switch_reasons(&s, &account));
+    reasons.extend(injected);
+    if let Some(d) = &degraded {
+        tracing::warn!(account = %req.account, budget_ms = d.budget_ms, "basket check degraded to the fast path");
+        reasons.extend(d.reason(gross_notional));
+    }
+    let rejected: Vec<&str> = lines.iter().filter(|l| !l.approved).map(|l| l.instrument.as_str()).collect();
+    if !rejected.is_empty() { reasons.push(format!("Basket lines rejected: {}", rejected.join(", "))); }
+    let events = active_events(&s, req.lines.iter().map(|l| l.instrument.as_str()), now);
+    let scale = calendar::multiplier(&events);
+    let threshold = schedules.iter().map(|r| r.rule.risk_threshold.unwrap_or(0.8)).fold(f64::INFINITY, f64::min) * scale;
+    let risk_direction = risk_direction(&s, &req.account, &req.lines);
+    let hedging = s.hedging.lock().unwrap().clone();
+    let notional_exempt = hedging.skips_notional_limits(risk_direction);
+    if (gross_notional / 1_000_000.0).min(1.0) * hedging.score_factor(risk_direction) >= threshold { reasons.push("Position limit exceeded".into()); }
+    let limits = &account.default_limits;
+    if let Some(n) = limits.max_basket_notional.map(|n| n * scale).filter(|n| gross_notional > *n) { reasons.push(format!("Account max basket notional {n:.2} exceeded")); }
+    if let Some(n) = limits.max_basket_sector_change {
+        for e in sectors.iter().filter(|e| e.change.abs() > n) { reasons.push(format!("Account max basket sector change {n:.2} exceeded ({})", e.sector)); }
+    }
+    if let Some(n) = limits.max_basket_beta_change.filter(|n| beta_exposure_change.abs() > *n) { reasons.push(format!("Account max basket beta change {n:.2} exceeded")); }
+    let check_id = uuid::Uuid::new_v4().to_string();
+    let (flows, unfunded) = settlement_check(&s, &req.account, req.venue.as_deref(), &req.lines, &check_id);
+    reasons.extend(unfunded);
+    let (greeks, greek_reasons) = greek_check(&s, &account, &req.lines).unwrap_or_default();
+    reasons.extend(greek_reasons);
+    reasons.extend(beta_check(&s, &account, &req.lines).into_iter().flat_map(|(_, r)| r));
+    let (conc_reasons, mut flags) = concentration_check(&s, &account, &req.lines, &fx);
+    reasons.extend(conc_reasons);
+    let daily_legs: Vec<(&str, f64)> = lines.iter().map(|l| (l.instrument.as_str(), l.notional.abs())).collect();
+    // The configured limits see the basket as one order for its gross notional, so a cap no single line reaches
+    // still holds the basket back.
+    let limit_legs: Vec<(String, f64, f64)> = req.lines.iter().map(|l| (l.instrument.clone(), l.quantity, signed(l))).collect();
+    let leg_groups = s.groups.lock().unwrap().membership(limit_legs.iter().map(|l| l.0.as_str()));
+    let related = s.related.lock().unwrap().group(&req.account);
+    let wash_group = (related.len() > 1).then(|| related[0].as_str());
+    let limit_order = limits::Order { account: &req.account, desk: account.desk.as_deref(), tags: &req.tags, notional: gross_notional, legs: &limit_legs, groups: &leg_groups, related: &related, scale, notional_exempt };
+    let scoped = s.limits.lock().unwrap().applicable(&limit_order);
+    let limits_evaluated: Vec<String> = scoped.iter().map(|l| l.id.clone()).collect();
+    let (limit_fx, open_counts) = limit_inputs(&s, &account, &scoped, &req.lines);
+    let wash_legs: Vec<wash::Leg> = req.lines.iter().map(|l| (l.instrument.as_str(), positions::signed_quantity(l.side, l.quantity) > 0.0, l.price)).collect();
+    let (approved, daily_headroom) = {
+        let mut v = s.velocity.lock().unwrap();
+        let mut d = s.daily.lock().unwrap();
+        reasons.extend(v.evaluate(&req.account, gross_notional, now));
+        reasons.extend(d.evaluate(&req.account, gross_notional, &daily_legs, now));
+        let session = d.session_of(now);
+        let mut pl = s.pools.lock().unwrap();
+        reasons.extend(pl.evaluate(&req.account, gross_notional, session));
+        let mut lb = s.limits.lock().unwrap();
+        let fx_of = |l: &limits::Limit| limit_fx.iter().find(|(id, _)| *id == l.id).map_or(Ok(1.0), |(_, r)| r.clone());
+        reasons.extend(lb.evaluate(&limit_order, |l| open_counts.iter().find(|(id, _)| *id == l.id).map_or((0, 0), |(_, c)| *c), fx_of, now));
+        let mut w = s.wash_trades.lock().unwrap();
+        let (wash, wash_flags) = w.evaluate(&req.account, req.tags.trader.as_deref(), wash_group, &wash_legs, now);
+        reasons.extend(wash);
+        flags.extend(wash_flags);
+        let ok = reasons.is_empty();
+        if ok {
+            v.record(&req.account, gross_notional, now);
+            lb.record(&limit_order, now);
+            w.record(&req.account, req.tags.trader.as_deref(), wash_group, &wash_legs, now);
+            d.record(&req.account, gross_notional, &daily_legs, now);
+            pl.record(&req.account, gross_notional, session);
+        }
+        (ok, d.headroom(&req.account, &lines[0].instrument, now))
+    };
+    if approved {
+        consume_locates(&s, &req.account, &req.lines);
+        s.settlement.lock().unwrap().book(&req.account, flows);
+    }
+    if !approved {
+        for l in &lines {
+            for r in l.reasons.iter().filter(|r| is_limit(r)) { record_breach(&s, breaches::BreachKind::Limit, Some(&req.account), Some(&l.instrument), limit_key(r), r.clone()); }
+        }
+        for r in reasons.iter().filter(|r| is_limit(r)) { record_breach(&s, breaches::BreachKind::Limit, Some(&req.account), None, limit_key(r), r.clone()); }
+        raise_tagged_alert(&s, "trade_blocked", alerts::Severity::Warning, Some(&req.account), None, format!("basket of {} lines: {}", lines.len(), reasons.join("; ")), &req.tags);
+    }
+    count(&s, |st| { st.total_checks += 1; if !approved { st.trades_blocked += 1; } });
+    s.tag_stats.lock().unwrap().record(&req.tags, approved);
+    let elapsed_us = t.elapsed().as_micros();
+    record_check(&s, checks::CheckRecord { check_id: check_id.clone(), kind: "basket".into(), account: req.account.clone(), approved, reasons: reasons.clone(), tags: req.tags.clone(), inputs, limits_evaluated, elapsed_us: elapsed_us as u64, at_ms: now_ms() });
+    let reason_codes = s.reason_codes.lock().unwrap().reasons(&[reasons.as_slice(), flags.as_slice()].concat(), locale);
+    Ok(Json(BasketCheckResponse {
+        check_id, account: req.account, approved, reasons, flags, reason_codes, locale, gross_notional, net_notional, beta_exposure_change, risk_direction,
+        sectors, unclassified, margin_impact: gross_notional * 0.1, daily_headroom, lines, elapsed_us, events, greeks, tags: req.tags,
+        timed_out: degraded.as_ref().is_some_and(budget::Degraded::timed_out), degraded,
+    }))
+}
+
+async fn get_algo_limits(State(s): State<Arc<AppState>>) -> Json<algo::AlgoLimits> {
+    Json(*s.algo_limits.lock().unwrap())
+}
+
+async fn set_algo_limits(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<algo::AlgoLimits>) -> ApiResult<algo::AlgoLimits> {
+    if !(req.max_participation > 0.0 && req.max_participation <= 1.0) { return Err(bad_request("max_participation must be in (0, 1]")); }
+    if req.max_duration_secs == 0 || req.default_slice_interval_secs == 0 { return Err(bad_request("durations must be positive")); }
+    *s.algo_limits.lock().unwrap() = req;
+    audit(&s, &h, "algo_limits.set", "global", serde_json::to_value(req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn get_ex_date_policy(State(s): State<Arc<AppState>>) -> Json<corporate_actions::ExDatePolicy> {
+    Json(s.corporate_actions.lock().unwrap().policy)
+}
+
+async fn set_ex_date_policy(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<corporate_actions::ExDatePolicy>) -> ApiResult<corporate_actions::ExDatePolicy> {
+    if req.short_stock_min_notional.is_nan() || req.short_stock_min_notional < 0.0 { return Err(bad_request("short_stock_min_notional must be non-negative")); }
+    s.corporate_actions.lock().unwrap().policy = req;
+    audit(&s, &h, "ex_date_policy.set", "global", serde_json::to_value(req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn list_dividends(State(s): State<Arc<AppState>>, Query(q): Query<corporate_actions::DividendQuery>) -> Json<Vec<corporate_actions::Dividend>> {
+    Json(s.corporate_actions.lock().unwrap().list(&q))
+}
+
+async fn load_dividends(State(s): State<Arc<AppState>>, Json(rows): Json<Vec<corporate_actions::Dividend>>) -> ApiResult<serde_json::Value> {
+    if let Some(d) = rows.iter().find(|d| !(d.amount.is_finite() && d.amount >= 0.0)) { return Err(bad_request(format!("invalid amount for {} on {}", d.symbol, d.ex_date))); }
+    let loaded = s.corporate_actions.lock().unwrap().load(rows);
+    Ok(Json(serde_json::json!({ "loaded": loaded })))
+}
+
+async fn fx_settlement_exposure(State(s): State<Arc<AppState>>, Query(q): Query<fx_settlement::ExposureQuery>) -> Json<fx_settlement::ExposureReport> {
+    let mut fx = s.fx_settlement.lock().unwrap();
+    fx.roll(chrono::Utc::now().date_naive());
+    Json(fx.exposure(&q))
+}
+
+async fn account_beta_exposure(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<beta::BetaExposure> {
+    require_account(&s, &id)?;
+    Ok(Json(beta_exposure(&s, &id, &[])))
+}
+
+async fn list_desk_limits(State(s): State<Arc<AppState>>) -> Json<std::collections::BTreeMap<String, beta::DeskLimit>> {
+    Json(s.desk_limits.lock().unwrap().list())
+}
+
+async fn desk_beta_exposure(State(s): State<Arc<AppState>>, Path(desk): Path<String>) -> ApiResult<beta::DeskExposure> {
+    let members = s.accounts.lock().unwrap().by_desk(&desk);
+    let max_beta_exposure = s.desk_limits.lock().unwrap().get(&desk).map(|l| l.max_beta_exposure);
+    if members.is_empty() && max_beta_exposure.is_none() { return Err(not_found("Desk")); }
+    let accounts: Vec<beta::AccountExposure> = members.into_iter().map(|a| beta::AccountExposure { exposure: beta_exposure(&s, &a.id, &[]), account: a.id }).collect();
+    let (net_notional, beta_exposure) = accounts.iter().fold((0.0, 0.0), |(n, b), a| (n + a.exposure.net_notional, b + a.exposure.beta_exposure));
+    Ok(Json(beta::DeskExposure { desk, max_beta_exposure, net_notional, beta_exposure, accounts }))
+}
+
+/// Instruments held net of zero across the limit's accounts, before and after `legs` fill for `account`.
+fn open_positions(s: &AppState, l: &limits::Limit, account: &str, legs: &[OrderLeg]) -> (usize, usize) {
+    let related = match (l.scope, l.key.as_deref()) { (limits::Scope::Related, Some(k)) => s.related.lock().unwrap().group(k), _ => Vec::new() };
+    let ids: Vec<String> = {
+        let ab = s.accounts.lock().unwrap();
+        match (l.scope, l.key.as_deref()) {
+            (limits::Scope::Related, Some(_)) => related,
+            (limits::Scope::Desk, Some(d)) => ab.by_desk(d).into_iter().map(|a| a.id).collect(),
+            (limits::Scope::Firm, _) => ab.list().into_iter().map(|a| a.id).collect(),
+            _ => vec![account.to_string()],
+        }
+    };
+    let book = s.positions.lock().unwrap();
+    let before: usize = ids.iter().map(|a| book.list(a).len()).sum();
+    let mut mine: HashMap<String, f64> = book.list(account).into_iter().map(|p| (p.instrument, p.quantity)).collect();
+    for leg in legs { *mine.entry(leg.instrument.clone()).or_default() += positions::signed_quantity(leg.side, leg.quantity); }
+    let held = if ids.iter().any(|a| a == account) { book.list(account).len() } else { 0 };
+    (before, before - held + mine.values().filter(|q| q.abs() >= 1e-9).count())
+}
+
+async fn list_limits(State(s): State<Arc<AppState>>, Query(q): Query<limits::LimitQuery>) -> Json<Vec<limits::Limit>> {
+    Json(s.limits.lock().unwrap().list(&q))
+}
+
+async fn get_limit(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Tagged<limits::Limit>, (StatusCode, Json<Err>)> {
+    s.limits.lock().unwrap().get(&id).map(|l| tagged(l.version, l)).ok_or_else(|| not_found("Limit"))
+}
+
+async fn create_limit(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<limits::LimitSpec>) -> Result<(StatusCode, Tagged<limits::Limit>), (StatusCode, Json<Err>)> {
+    let l = s.limits.lock().unwrap().create(req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "limit.create", &l.id, serde_json::to_value(&l).unwrap_or_default());
+    Ok((StatusCode::CREATED, tagged(l.version, l)))
+}
+
+async fn update_limit(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<limits::LimitSpec>) -> Result<Tagged<limits::Limit>, (StatusCode, Json<Err>)> {
+    let l = {
+        let mut lb = s.limits.lock().unwrap();
+        check_if_match(&s, &h, lb.get(&id).ok_or_else(|| not_found("Limit"))?.version)?;
+        lb.update(&id, req, now_ms()).map_err(bad_request)?.ok_or_else(|| not_found("Limit"))?
+    };
+    audit(&s, &h, "limit.update", &id, serde_json::to_value(&l).unwrap_or_default());
+    Ok(tagged(l.version, l))
+}
+
+async fn delete_limit(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    {
+        let mut lb = s.limits.lock().unwrap();
+        check_if_match(&s, &h, lb.get(&id).ok_or_else(|| not_found("Limit"))?.version)?;
+        lb.remove(&id);
+    }
+    audit(&s, &h, "limit.delete", &id, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_desk_limit(State(s): State<Arc<AppState>>, h: HeaderMap, Path(desk): Path<String>, Json(req): Json<beta::DeskLimit>) -> ApiResult<beta::DeskLimit> {
+    if req.max_beta_exposure.is_nan() || req.max_beta_exposure < 0.0 { return Err(bad_request("max_beta_exposure must be non-negative")); }
+    s.desk_limits.lock().unwrap().set(&desk, req);
+    audit(&s, &h, "desk_limit.set", &desk, serde_json::to_value(req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn delete_desk_limit(State(s): State<Arc<AppState>>, h: HeaderMap, Path(desk): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    if !s.desk_limits.lock().unwrap().remove(&desk) { return Err(not_found("Desk limit")); }
+    audit(&s, &h, "desk_limit.delete", &desk, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The factor mapping is keyed by instrument on PUT; on GET the id is an account whose risk is decomposed.
+async fn account_factor_risk(State(s): State<Arc<AppState>>, Path(id): Path<String>, Query(q): Query<factor_risk::FactorQuery>) -> ApiResult<factor_risk::FactorReport> {
+    require_account(&s, &id)?;
+    let held = portfolio(&s, Some(&id), None);
+    let report = match q.method {
+        factor_risk::Method::Loadings => { let liq = s.liquidity.lock().unwrap(); s.factor_model.lock().unwrap().decompose(&id, &held, |i| liq.get(i).daily_vol) }
+        factor_risk::Method::Pca => { let h = s.history.lock().unwrap(); factor_risk::pca(&id, &held, |i| h.series(i, q.from, q.to), q.components.unwrap_or(3)).map_err(bad_request)? }
+    };
+    Ok(Json(report))
+}
+
+async fn get_factor_model(State(s): State<Arc<AppState>>) -> Json<factor_risk::FactorModel> {
+    Json(s.factor_model.lock().unwrap().clone())
+}
+
+async fn set_factor_model(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<factor_risk::FactorModel>) -> ApiResult<factor_risk::FactorModel> {
+    req.validate().map_err(bad_request)?;
+    *s.factor_model.lock().unwrap() = req.clone();
+    audit(&s, &h, "factor_model.set", "global", serde_json::json!({ "factors": req.factors, "instruments": req.loadings.len() }));
+    Ok(Json(req))
+}
+
+async fn account_fx_exposure(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<fx_exposure::FxExposureReport> {
+    let account = require_account(&s, &id)?;
+    let base = account.base_currency.as_str();
+    let book = currency_book(&s, base, &portfolio(&s, Some(&id), None), None);
+    let limits = s.fx_limits.lock().unwrap().clone();
+    let lines = book.into_iter().map(|(c, net_local)| fx_exposure::Line {
+        to_base: fx_rate(&s, &c, base), to_usd: usd_value(&s, &c, 1.0), daily_vol: fx_vol(&s, &c, base), limit_usd: limits.cap(&id, &c), currency: c, net_local,
+    }).collect();
+    let corr = s.correlations.lock().unwrap();
+    Ok(Json(fx_exposure::report(&id, base, lines, |a, b| corr.get(&format!("{a}{base}"), &format!("{b}{base}")))))
+}
+
+async fn related_accounts(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<related::RelatedReport> {
+    require_account(&s, &id)?;
+    let (accounts, links) = { let r = s.related.lock().unwrap(); let g = r.group(&id); let l = r.within(&g); (g, l) };
+    let currency = s.firm_currency.clone();
+    let mut unconverted = std::collections::BTreeSet::new();
+    let books: Vec<Vec<(String, f64)>> = accounts.iter().filter_map(|a| {
+        let a = s.accounts.lock().unwrap().get(a).cloned()?;
+        let Some(rate) = fx_rate(&s, &a.base_currency, &currency) else { unconverted.insert(a.base_currency.clone()); return None };
+        let (legs, fx) = to_base(&s, &a.base_currency, &portfolio(&s, Some(&a.id), None));
+        unconverted.extend(fx.into_iter().flat_map(|f| f.unconverted));
+        Some(legs.into_iter().map(|(i, q, p)| (i, q * p * rate)).collect())
+    }).collect();
+    let (gross_notional, net_notional, exposure) = related::aggregate(&books);
+    Ok(Json(related::RelatedReport { account: id, accounts, links, currency, gross_notional, net_notional, exposure, unconverted: unconverted.into_iter().collect(), as_of_ms: now_ms() }))
+}
+
+async fn get_fx_limits(State(s): State<Arc<AppState>>) -> Json<fx_exposure::FxLimits> {
+    Json(s.fx_limits.lock().unwrap().clone())
+}
+
+async fn set_fx_limits(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<fx_exposure::FxLimits>) -> ApiResult<fx_exposure::FxLimits> {
+    require_role(&h, ADMIN_ROLE)?;
+    let req = req.normalize().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut *s.fx_limits.lock().unwrap(), req.clone());
+    if previous != req { audit(&s, &h, "fx_exposure_limits.config", "fx_exposure_limits", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_fx_caps(State(s): State<Arc<AppState>>) -> Json<fx_settlement::Caps> {
+    Json(s.fx_settlement.lock().unwrap().caps.clone())
+}
+
+/// Omitting `cutoffs` keeps the current cut-off table.
+async fn set_fx_caps(State(s): State<Arc<AppState>>, h: HeaderMap, Json(mut req): Json<fx_settlement::Caps>) -> ApiResult<fx_settlement::Caps> {
+    req.validate().map_err(bad_request)?;
+    { let mut fx = s.fx_settlement.lock().unwrap(); if req.cutoffs.is_empty() { req.cutoffs = fx.caps.cutoffs.clone(); } fx.caps = req.clone(); }
+    audit(&s, &h, "fx_settlement_caps.set", "global", serde_json::to_value(&req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn list_otc_trades(State(s): State<Arc<AppState>>, Query(q): Query<otc::TradeQuery>) -> Json<Vec<otc::OtcTrade>> {
+    Json(s.otc.lock().unwrap().list(&q))
+}
+
+async fn get_otc_trade(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<otc::OtcTrade> {
+    s.otc.lock().unwrap().get(&id).cloned().map(Json).ok_or_else(|| not_found("Trade"))
+}
+
+/// Books the trade and opens its position.
+async fn register_otc_trade(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<otc::OtcTrade>) -> Result<(StatusCode, Json<otc::OtcTrade>), (StatusCode, Json<Err>)> {
+    require_open(&s, &req.account)?;
+    let t = s.otc.lock().unwrap().register(req).map_err(bad_request)?;
+    s.positions.lock().unwrap().adjust(&t.account, &t.instrument, t.notional);
+    audit(&s, &h, "otc.register", &t.trade_id, serde_json::json!({ "account": t.account, "instrument": t.instrument, "notional": t.notional, "counterparty": t.counterparty }));
+    Ok((StatusCode::CREATED, Json(t)))
+}
+
+/// Applies lifecycle events in order. Each one moves positions and books any cash it settles into the account's
+/// cash ledger; a failed event is reported and does not stop the rest of the batch.
+async fn apply_otc_events(State(s): State<Arc<AppState>>, h: HeaderMap, Json(events): Json<Vec<otc::LifecycleEvent>>) -> Json<Vec<otc::EventResult>> {
+    let mut out = Vec::new();
+    for e in events {
+        let result = |outcome, error| otc::EventResult { event_id: e.event_id.clone(), trade_id: e.trade_id.clone(), outcome, error };
+        if s.otc.lock().unwrap().seen(&e.event_id) { out.push(result(otc::Outcome::Duplicate, None)); continue; }
+        if let otc::EventKind::Novation { to_account: Some(to), .. } = &e.kind {
+            if require_open(&s, to).is_err() { out.push(result(otc::Outcome::Rejected, Some(format!("unknown or closed account {to}")))); continue; }
+        }
+        let applied = s.otc.lock().unwrap().apply(&e);
+        match applied {
+            Ok((t, fx)) => {
+                { let mut p = s.positions.lock().unwrap(); for (a, i, d) in &fx.positions { p.adjust(a, i, *d); } }
+                if let Some((a, value_date, amount)) = fx.cash {
+                    s.settlement.lock().unwrap().book(&a, vec![settlement::Flow { value_date, amount, instrument: t.instrument.clone(), check_id: e.event_id.clone() }]);
+                }
+                audit(&s, &h, "otc.event", &t.trade_id, serde_json::to_value(&e).unwrap_or_default());
+                out.push(result(otc::Outcome::Applied, None));
+            }
+            Err(err) => out.push(result(otc::Outcome::Rejected, Some(err))),
+        }
+    }
+    Json(out)
+}
+
+/// Accounts an entity-level analysis runs over: every account of `entity`, or the named `accounts`.
+fn scope_accounts(s: &AppState, entity: Option<&str>, ids: Option<&[String]>) -> Result<Vec<accounts::Account>, (StatusCode, Json<Err>)> {
+    let v = match (entity, ids) {
+        (Some(e), None) => s.accounts.lock().unwrap().by_entity(e),
+        (None, Some(ids)) => ids.iter().map(|id| require_account(s, id)).collect::<Result<_, _>>()?,
+        (Some(_), Some(_)) => return Err(bad_request("give entity or accounts, not both")),
+        (None, None) => return Err(bad_request("entity or accounts is required")),
+    };
+    if v.is_empty() { return Err(bad_request("no accounts in scope")); }
+    Ok(v)
+}
+
+/// Positions valued at the last price, or at 1.0 (notional terms) for instruments without one, which are reported back.
+fn priced(s: &AppState, positions: &[(String, f64)], unpriced: &mut std::collections::BTreeSet<String>) -> Vec<(String, f64, f64)> {
+    let md = s.marketdata.lock().unwrap();
+    positions.iter().map(|(i, q)| {
+        let p = md.price(i).unwrap_or_else(|| { unpriced.insert(i.clone()); 1.0 });
+        (i.clone(), *q, p)
+    }).collect()
+}
+
+async fn compression_analysis(State(s): State<Arc<AppState>>, Json(req): Json<compression::CompressionRequest>) -> ApiResult<compression::CompressionReport> {
+    let accounts = if req.counterparty.is_some() && req.entity.is_none() && req.accounts.is_none() { s.accounts.lock().unwrap().list() } else { scope_accounts(&s, req.entity.as_deref(), req.accounts.as_deref())? };
+    let books: Vec<(String, Vec<(String, f64)>)> = { let p = s.positions.lock().unwrap(); accounts.iter().map(|a| (a.id.clone(), p.list(&a.id).into_iter().map(|x| (x.instrument, x.quantity)).collect())).collect() };
+    let (proposals, line_items_before) = match &req.counterparty {
+        Some(cp) => {
+            let trades: Vec<otc::OtcTrade> = s.otc.lock().unwrap().list(&otc::TradeQuery { account: None, status: Some(otc::TradeStatus::Live) })
+                .into_iter().filter(|t| &t.counterparty == cp && accounts.iter().any(|a| a.id == t.account)).collect();
+            (compression::tear_ups(&trades), trades.len())
+        }
+        None => (compression::offsets(&books), books.iter().map(|b| b.1.len()).sum()),
+    };
+    let mut unpriced = std::collections::BTreeSet::new();
+    let by_account: Vec<compression::AccountMargin> = accounts.iter().zip(&books).map(|(a, (_, before))| {
+        let mut after = before.clone();
+        for t in proposals.iter().flat_map(|p| p.trades.iter().map(move |t| (p, t))).filter(|(_, t)| t.account == a.id) {
+            let d = positions::signed_quantity(t.1.side, t.1.quantity);
+            match after.iter_mut().find(|x| x.0 == t.0.instrument) { Some(x) => x.1 += d, None => after.push((t.0.instrument.clone(), d)) }
+        }
+        let (b, af) = (priced(&s, before, &mut unpriced), priced(&s, &after, &mut unpriced));
+        let liq = s.liquidity.lock().unwrap();
+        let m = |legs: &[(String, f64, f64)]| margin::compute(a.margin_model, legs, |i| liq.get(i).daily_vol).initial_margin;
+        compression::AccountMargin { account: a.id.clone(), before: m(&b), after: m(&af) }
+    }).collect();
+    let (margin_before, margin_after) = (by_account.iter().map(|m| m.before).sum::<f64>(), by_account.iter().map(|m| m.after).sum::<f64>());
+    let removed: usize = proposals.iter().map(|p| p.lines_removed).sum();
+    Ok(Json(compression::CompressionReport {
+        accounts: accounts.into_iter().map(|a| a.id).collect(), counterparty: req.counterparty, line_items_before, line_items_after: line_items_before.saturating_sub(removed),
+        margin_before, margin_after, margin_reduction: margin_before - margin_after, by_account, unpriced: unpriced.into_iter().collect(), proposals,
+    }))
+}
+
+/// Only active accounts are offered as transfer targets; each book is margined under its own account's model.
+async fn margin_optimization(State(s): State<Arc<AppState>>, Json(req): Json<optimizer::OptimizeRequest>) -> ApiResult<optimizer::OptimizeReport> {
+    let accounts: Vec<accounts::Account> = scope_accounts(&s, req.entity.as_deref(), req.accounts.as_deref())?.into_iter().filter(|a| a.status != accounts::AccountStatus::Closed).collect();
+    if accounts.len() < 2 { return Err(bad_request("margin optimization needs at least two open accounts")); }
+    let mut unpriced = std::collections::BTreeSet::new();
+    let books: Vec<optimizer::Book> = accounts.iter().map(|a| {
+        let held: Vec<(String, f64)> = s.positions.lock().unwrap().list(&a.id).into_iter().map(|p| (p.instrument, p.quantity)).collect();
+        optimizer::Book { account: a.id.clone(), positions: priced(&s, &held, &mut unpriced), accepts: a.status == accounts::AccountStatus::Active }
+    }).collect();
+    let (before, after, suggestions) = {
+        let liq = s.liquidity.lock().unwrap();
+        optimizer::optimize(books, |i, legs| margin::compute(accounts[i].margin_model, legs, |x| liq.get(x).daily_vol).initial_margin, req.max_suggestions.unwrap_or(10).min(100))
+    };
+    Ok(Json(optimizer::OptimizeReport { accounts: accounts.into_iter().map(|a| a.id).collect(), margin_before: before, margin_after: after, total_savings: before - after, suggestions }))
+}
+
+async fn get_conventions(State(s): State<Arc<AppState>>) -> Json<settlement::Conventions> {
+    Json(s.settlement.lock().unwrap().conventions.clone())
+}
+
+async fn set_conventions(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<settlement::Conventions>) -> ApiResult<settlement::Conventions> {
+    if req.markets.values().chain([&req.default_days]).any(|d| *d > 10) { return Err(bad_request("settlement cycles above T+10 are not supported")); }
+    s.settlement.lock().unwrap().conventions = req.clone();
+    audit(&s, &h, "settlement_conventions.set", "global", serde_json::to_value(&req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn set_settled_cash(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<settlement::CashUpdate>) -> ApiResult<settlement::Projection> {
+    require_open(&s, &id)?;
+    if !req.settled.is_finite() { return Err(bad_request("settled must be a finite amount")); }
+    let today = chrono::Utc::now().date_naive();
+    let p = { let mut sb = s.settlement.lock().unwrap(); sb.set_settled(&id, req.settled, today); sb.project(&id, today, &[]) };
+    audit(&s, &h, "cash.set", &id, serde_json::json!({ "settled": req.settled }));
+    p.map(Json).ok_or_else(|| not_found("Cash ledger"))
+}
+
+async fn cash_projection(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<settlement::Projection> {
+    require_account(&s, &id)?;
+    s.settlement.lock().unwrap().project(&id, chrono::Utc::now().date_naive(), &[]).map(Json).ok_or_else(|| not_found("Cash ledger"))
+}
+
+async fn list_inventory(State(s): State<Arc<AppState>>) -> Json<Vec<locates::Inventory>> {
+    Json(s.locates.lock().unwrap().inventory())
+}
+
+async fn get_inventory(State(s): State<Arc<AppState>>, Path(symbol): Path<String>) -> ApiResult<locates::Inventory> {
+    s.locates.lock().unwrap().get_inventory(&symbol).cloned().map(Json).ok_or_else(|| not_found("Inventory"))
+}
+
+async fn set_inventory(State(s): State<Arc<AppState>>, h: HeaderMap, Path(symbol): Path<String>, Json(req): Json<locates::InventoryUpdate>) -> ApiResult<locates::Inventory> {
+    require_role(&h, STOCK_LOAN_ROLE)?;
+    let i = s.locates.lock().unwrap().set_inventory(&symbol, req.available, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "locate_inventory.set", &symbol, serde_json::json!({ "available": i.available }));
+    Ok(Json(i))
+}
+
+async fn replenish_inventory(State(s): State<Arc<AppState>>, h: HeaderMap, Path(symbol): Path<String>, Json(req): Json<locates::Replenish>) -> ApiResult<locates::Inventory> {
+    require_role(&h, STOCK_LOAN_ROLE)?;
+    let i = s.locates.lock().unwrap().replenish(&symbol, req.quantity, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "locate_inventory.replenish", &symbol, serde_json::json!({ "quantity": req.quantity, "available": i.available }));
+    Ok(Json(i))
+}
+
+async fn request_locate(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<locates::LocateRequest>) -> Result<(StatusCode, Json<locates::Locate>), (StatusCode, Json<Err>)> {
+    require_open(&s, &req.account)?;
+    let l = s.locates.lock().unwrap().request(&req, now_ms())
+        .map_err(|e| (StatusCode::CONFLICT, Json(Err { error: "Locate not granted".into(), details: Some(e) })))?;
+    audit(&s, &h, "locate.grant", &l.id, serde_json::json!({ "account": l.account, "symbol": l.symbol, "requested": l.requested, "quantity": l.quantity }));
+    Ok((StatusCode::CREATED, Json(l)))
+}
+
+async fn list_borrow_rates(State(s): State<Arc<AppState>>) -> Json<Vec<locates::BorrowRate>> {
+    Json(s.locates.lock().unwrap().rates())
+}
+
+async fn load_borrow_rates(State(s): State<Arc<AppState>>, Json(rows): Json<Vec<locates::BorrowRateInput>>) -> ApiResult<serde_json::Value> {
+    let loaded = s.locates.lock().unwrap().load_rates(rows, now_ms()).map_err(bad_request)?;
+    Ok(Json(serde_json::json!({ "loaded": loaded })))
+}
+
+async fn list_locates(State(s): State<Arc<AppState>>, Query(q): Query<locates::LocateQuery>) -> Json<Vec<locates::Locate>> {
+    Json(s.locates.lock().unwrap().list(&q, now_ms()))
+}
+
+/// Rate converting one unit of `from` into `to`, crossed through USD.
+fn fx_rate(s: &AppState, from: &str, to: &str) -> Option<f64> {
+    if from == to { return Some(1.0); }
+    Some(usd_value(s, from, 1.0)? / usd_value(s, to, 1.0).filter(|v| *v > 0.0)?)
+}
+
+/// Reprices positions whose factor mapping names a currency other than `base`. `None` when nothing is foreign.
+fn to_base(s: &AppState, base: &str, legs: &[(String, f64, f64)]) -> (Vec<(String, f64, f64)>, Option<MarginFx>) {
+    let ccys: Vec<Option<String>> = { let sc = s.scenarios.lock().unwrap(); legs.iter().map(|(i, _, _)| sc.factor(i).and_then(|f| f.currency.clone()).map(|c| c.to_ascii_uppercase()).filter(|c| c != base)).collect() };
+    if ccys.iter().all(Option::is_none) { return (legs.to_vec(), None); }
+    let mut fx = MarginFx { base_currency: base.into(), conversions: Vec::new(), effect: 0.0, unconverted: Vec::new() };
+    let out = legs.iter().zip(&ccys).map(|((i, q, p), ccy)| {
+        let Some(c) = ccy else { return (i.clone(), *q, *p) };
+        let Some(rate) = fx_rate(s, c, base) else { if !fx.unconverted.contains(c) { fx.unconverted.push(c.clone()); } return (i.clone(), *q, *p) };
+        match fx.conversions.iter_mut().find(|x| &x.currency == c) {
+            Some(x) => { x.notional_local += q * p; x.notional_base += q * p * rate; }
+            None => fx.conversions.push(FxConversion { currency: c.clone(), rate, notional_local: q * p, notional_base: q * p * rate }),
+        }
+        (i.clone(), *q, p * rate)
+    }).collect();
+    (out, Some(fx))
+}
+
+/// The snapshot a requirement would give the account against its funds and the reservations holding margin,
+/// without booking it.
+fn margin_headroom(s: &AppState, account: &str, initial: f64, maintenance: f64) -> (margin::MarginSnapshot, Vec<reservations::Reservation>) {
+    let open = s.reservations.lock().unwrap().open(account);
+    let held: f64 = open.iter().map(|r| r.margin).sum();
+    (margin::MarginSnapshot::new(initial, maintenance, held, account_funds(s, account), now_ms()), open)
+}
+
+/// Stores the account's margin snapshot and drives its margin call and liquidation from it. Returns the
+/// snapshot, the reservations holding margin and the call if its status changed.
+fn book_margin(s: &Arc<AppState>, account: &str, legs: &[(String, f64, f64)], initial: f64, maintenance: f64) -> (margin::MarginSnapshot, Vec<reservations::Reservation>, Option<margin_calls::MarginCall>) {
+    let (snap, open) = margin_headroom(s, account, initial, maintenance);
+    // Calls and liquidations look at filled positions only; held margin just narrows what is available.
+    let (utilization, shortfall) = (snap.margin_utilization_pct, (initial - snap.funds).max(0.0));
+    s.margins.lock().unwrap().insert(account.into(), snap.clone());
+    let call = s.margin_calls.lock().unwrap().observe(account, utilization, shortfall, now_ms());
+    if let Some(c) = &call { margin_call_alert(s, c); }
+    let gross: f64 = legs.iter().map(|(_, q, p)| (q * p).abs()).sum();
+    let margin_rate = if gross > 0.0 && initial > 0.0 { initial / gross } else { 0.10 };
+    let plan = || { let lb = s.liquidity.lock().unwrap(); liquidation::plan(legs, shortfall, margin_rate, |i, q, p| lb.cost(i, q, p)) };
+    let liq = s.liquidations.lock().unwrap().observe(account, utilization, shortfall, plan, now_ms());
+    if let Some(e) = liq { dispatch_liquidation(s.clone(), e); }
+    (snap, open, call)
+}
+
+async fn account_var(State(s): State<Arc<AppState>>, Path(id): Path<String>, Query(q): Query<VarQuery>) -> ApiResult<AccountVarResponse> {
+    let account = require_account(&s, &id)?;
+    let (unpriced, var) = historical_var(&s, &account, q.lookback_days.unwrap_or(s.var_lookback_days));
+    Ok(Json(AccountVarResponse { account: id, unpriced, var }))
+}
+
+/// Paths used when a Monte Carlo request does not say.
+const DEFAULT_VAR_PATHS: usize = 10_000;
+
+async fn model_var(State(s): State<Arc<AppState>>, Json(req): Json<VarRequest>) -> ApiResult<VarResponse> {
+    let t = Instant::now();
+    let account = require_account(&s, &req.account)?;
+    if req.lookback_days == Some(0) { return Err(bad_request("lookback_days must be positive")); }
+    let paths = req.paths.unwrap_or(DEFAULT_VAR_PATHS);
+    if !(1..=var::MAX_PATHS).contains(&paths) { return Err(bad_request(format!("paths must be between 1 and {}", var::MAX_PATHS))); }
+    if let Some(c) = &req.correlation { c.validate().map_err(bad_request)?; }
+    let (local, unpriced) = match req.positions {
+        Some(p) => (marked(&s, p).map_err(bad_request)?, Vec::new()),
+        None => {
+            let unpriced = { let md = s.marketdata.lock().unwrap(); s.positions.lock().unwrap().list(&account.id).into_iter().filter(|p| md.price(&p.instrument).is_none()).map(|p| p.instrument).collect() };
+            (portfolio(&s, Some(&account.id), None), unpriced)
+        }
+    };
+    let (legs, _) = to_base(&s, &account.base_currency, &local);
+    if req.model == var::Model::Historical {
+        let lookback = req.lookback_days.unwrap_or(s.var_lookback_days);
+        let v = { let h = s.history.lock().unwrap(); var::historical(&legs, &Default::default(), |i| h.series(i, None, None), lookback) };
+        return Ok(Json(VarResponse { account: req.account, model: req.model, unpriced, historical: Some(v), modelled: None, elapsed_us: t.elapsed().as_micros() }));
+    }
+    let mut net: std::collections::BTreeMap<String, f64> = std::collections::BTreeMap::new();
+    for (i, q, p) in &legs { *net.entry(i.clone()).or_default() += q * p; }
+    let exposures: Vec<(String, f64)> = net.into_iter().filter(|(_, n)| *n != 0.0).collect();
+    let live = live_vols(&s, &legs);
+    let vols: Vec<f64> = { let liq = s.liquidity.lock().unwrap(); exposures.iter().map(|(i, _)| live.get(i).copied().unwrap_or_else(|| liq.get(i).daily_vol)).collect() };
+    let instruments: Vec<String> = exposures.iter().map(|(i, _)| i.clone()).collect();
+    let corr = { let c = s.correlations.lock().unwrap(); var::CorrelationMatrix::resolve(req.correlation.as_ref(), &instruments, |a, b| c.get(a, b)) };
+    let modelled = match req.model {
+        var::Model::MonteCarlo => {
+            let seed = req.seed.unwrap_or_else(now_ms);
+            tokio::task::spawn_blocking(move || var::monte_carlo(&exposures, &vols, &corr, paths, seed)).await.map_err(unavailable)?.map_err(bad_request)?
+        }
+        _ => var::parametric(&exposures, &vols, &corr),
+    };
+    Ok(Json(VarResponse { account: req.account, model: req.model, unpriced, historical: None, modelled: Some(modelled), elapsed_us: t.elapsed().as_micros() }))
+}
+
+/// Historical VaR of the account's holdings in its base currency, and the holdings left out for want of a price.
+fn historical_var(s: &AppState, account: &accounts::Account, lookback_days: usize) -> (Vec<String>, var::HistoricalVar) {
+    let mut unpriced = std::collections::BTreeSet::new();
+    let held: Vec<(String, f64)> = s.positions.lock().unwrap().list(&account.id).into_iter().map(|p| (p.instrument, p.quantity)).collect();
+    { let md = s.marketdata.lock().unwrap(); unpriced.extend(held.iter().filter(|(i, _)| md.price(i).is_none()).map(|(i, _)| i.clone())); }
+    let (legs, _) = to_base(s, &account.base_currency, &portfolio(s, Some(&account.id), None));
+    let var = { let h = s.history.lock().unwrap(); var::historical(&legs, &Default::default(), |i| h.series(i, None, None), lookback_days) };
+    (unpriced.into_iter().collect(), var)
+}
+
+async fn margin_calc(State(s): State<Arc<AppState>>, Json(req): Json<MarginRequest>) -> ApiResult<MarginResponse> {
+    margin(s, req).await.map(Json)
+}
+
+/// The margin calculation behind both the REST and gRPC APIs.
+async fn margin(s: Arc<AppState>, req: MarginRequest) -> Result<MarginResponse, (StatusCode, Json<Err>)> {
+    let t = Instant::now();
+    let account = require_open(&s, &req.account)?;
+    if req.var_lookback_days == Some(0) { return Err(bad_request("var_lookback_days must be positive")); }
+    if let Some(i) = req.returns.iter().find(|(_, r)| r.iter().any(|x| !x.is_finite())).map(|(i, _)| i) { return Err(bad_request(format!("returns for {i} must be finite"))); }
+    let what_if = req.positions.is_some();
+    let positions = req.positions.unwrap_or_else(|| s.positions.lock().unwrap().list(&req.account).into_iter().map(|p| PositionInput { instrument: p.instrument, quantity: p.quantity, price: None }).collect());
+    // A margin figure computed from partial data would understate requirements, so refuse rather than guess.
+    let instruments: Vec<&str> = positions.iter().map(|p| p.instrument.as_str()).collect();
+    let injected = injected_faults(&s, &[faults::Target::Margin, faults::Target::Accounts, faults::Target::Positions, faults::Target::Reservations, faults::Target::MarketData], &instruments).await;
+    if !injected.is_empty() { return Err(unavailable(injected.join("; "))); }
+    let local = marked(&s, positions).map_err(bad_request)?;
+    // The margined book is held to the account's Greek caps as if it were bought from flat.
+    let greek_breaches = account.default_limits.greeks.as_ref().map(|l| {
+        let priced: Vec<OrderLeg> = local.iter().map(|(i, q, p)| OrderLeg { instrument: i.clone(), side: positions::Side::Buy, quantity: *q, price: *p }).collect();
+        let lines: Vec<(String, f64)> = local.iter().map(|(i, q, _)| (i.clone(), *q)).collect();
+        greeks_by_underlier(&s, &lines, &priced).map_or_else(|e| vec![format!("Greek limits not evaluated: {e}")], |g| greeks::evaluate(l, &Default::default(), &g).1)
+    }).unwrap_or_default();
+    let (legs, fx) = to_base(&s, &account.base_currency, &local);
+    let model = account.margin_model;
+    let span = match req.methodology {
+        margin::Methodology::Flat => None,
+        margin::Methodology::Span => Some((span_margin(&s, &legs).map_err(bad_request)?, fx.as_ref().and_then(|_| span_margin(&s, &local).ok()).map(|r| r.initial_margin))),
+    };
+    let live = live_vols(&s, &legs);
+    let (m, breakdown, unconverted, var_contribution) = {
+        let liq = s.liquidity.lock().unwrap();
+        let vol = |i: &str| live.get(i).copied().unwrap_or_else(|| liq.get(i).daily_vol);
+        let contribution = s.correlations.lock().unwrap().contributions(&legs, vol);
+        match &span {
+            None => (margin::compute(model, &legs, vol), margin::breakdown(model, &legs, vol), fx.as_ref().map(|_| margin::compute(model, &local, vol).initial_margin), contribution),
+            Some((r, unconverted)) => (margin::MarginResult { initial_margin: r.initial_margin, maintenance_margin: r.maintenance_margin }, span::breakdown(r, &legs), *unconverted, contribution),
+        }
+    };
+    let fx = fx.map(|mut f| { f.effect = m.initial_margin - unconverted.unwrap_or(m.initial_margin); f });
+    let (initial, maintenance) = (m.initial_margin, m.maintenance_margin);
+    let lookback = req.var_lookback_days.unwrap_or(s.var_lookback_days);
+    let historical_var = { let h = s.history.lock().unwrap(); var::historical(&legs, &req.returns, |i| h.series(i, None, None), lookback) };
+    let (var_method, var95, var99) = if historical_var.scenarios >= MIN_VAR_SCENARIOS { (VarMethod::Historical, historical_var.var_95, historical_var.var_99) } else { (VarMethod::Parametric, var_contribution.var_95, var_contribution.var_99) };
+    count(&s, |st| st.total_margin_calcs += 1);
+    s.metrics.lock().unwrap().margin_calc("margin", t.elapsed());
+    // Only the booked book's requirement is the account's; a what-if must not move its history, calls or headroom.
+    let (snap, open) = if what_if { margin_headroom(&s, &req.account, initial, maintenance) } else {
+        persist_margin(&s, &req.account, "margin", initial, maintenance);
+        let (snap, open, _) = book_margin(&s, &req.account, &legs, initial, maintenance);
+        (snap, open)
+    };
+    let utilization = snap.margin_utilization_pct;
+    Ok(MarginResponse { account: req.account, margin_model: model, model_version: model.version().into(), initial_margin: initial, maintenance_margin: maintenance, available_margin: snap.available_margin, margin_utilization_pct: utilization, var_95: var95, var_99: var99, elapsed_us: t.elapsed().as_micros(),
+        funds: snap.funds, used_margin: initial, held_margin: snap.held_margin, breakdown, fx, var_contribution, var_method, historical_var, methodology: req.methodology, span: span.map(|s| s.0), greek_breaches, what_if,
+        held_by_order: open.into_iter().map(|r| HeldMargin { reservation_id: r.id, check_id: r.check_id, instrument: r.instrument, margin: r.margin, expires_at_ms: r.expires_at_ms }).collect(),
+    })
+}
+
+/// SPAN requirement for `legs`; options are repriced off their underlier as for the Greek limits.
+fn span_margin(s: &AppState, legs: &[(String, f64, f64)]) -> Result<span::SpanResult, String> {
+    let config = s.span.lock().unwrap().clone();
+    let sc = s.scenarios.lock().unwrap();
+    span::compute(&config, legs, |i| sc.factor(i).and_then(|f| f.option.clone()), |o| underlier(s, o), |i| s.liquidity.lock().unwrap().get(i).daily_vol)
+}
+
+async fn margin_compare(State(s): State<Arc<AppState>>, Json(req): Json<MarginCompareRequest>) -> ApiResult<MarginCompareResponse> {
+    let t = Instant::now();
+    let account = require_account(&s, &req.account)?;
+    let legs = marked(&s, req.positions.unwrap_or_default()).map_err(bad_request)?;
+    let models = req.models.filter(|m| !m.is_empty()).unwrap_or_else(|| margin::MarginModel::ALL.to_vec());
+    let liq = s.liquidity.lock().unwrap();
+    let current = margin::compute(account.margin_model, &legs, |i| liq.get(i).daily_vol).initial_margin;
+    let results = models.into_iter().map(|model| {
+        let m = margin::compute(model, &legs, |i| liq.get(i).daily_vol);
+        let delta = m.initial_margin - current;
+        ModelResult { model, model_version: model.version().into(), initial_margin: m.initial_margin, maintenance_margin: m.maintenance_margin, delta_vs_current: delta, delta_pct_vs_current: (current != 0.0).then(|| delta / current.abs() * 100.0) }
+    }).collect();
+    Ok(Json(MarginCompareResponse { account: req.account, current_model: account.margin_model, gross_notional: legs.iter().map(|(_, q, p)| (q * p).abs()).sum(), results, elapsed_us: t.elapsed().as_micros() }))
+}
+
+async fn simm_margin(State(s): State<Arc<AppState>>, Json(req): Json<SimmRequest>) -> ApiResult<SimmResponse> {
+    let t = Instant::now();
+    require_open(&s, &req.account)?;
+    if req.sensitivities.iter().any(|x| !x.amount.is_finite()) { return Err(bad_request("sensitivity amounts must be finite")); }
+    let result = simm::compute(&req.sensitivities);
+    count(&s, |st| st.total_margin_calcs += 1);
+    Ok(Json(SimmResponse { account: req.account, result, elapsed_us: t.elapsed().as_micros() }))
+}
+
+async fn frtb_capital(State(s): State<Arc<AppState>>, Json(req): Json<FrtbRequest>) -> ApiResult<FrtbResponse> {
+    let t = Instant::now();
+    require_account(&s, &req.account)?;
+    if req.sensitivities.iter().any(|x| !x.amount.is_finite()) { return Err(bad_request("sensitivity amounts must be finite")); }
+    Ok(Json(FrtbResponse { account: req.account, result: frtb::compute(&req.sensitivities), elapsed_us: t.elapsed().as_micros() }))
+}
+
+fn crif_summary(account: &str, u: &crif::CrifUpload) -> CrifSummary {
+    CrifSummary { account: account.into(), uploaded_at_ms: u.uploaded_at_ms, rows: u.rows, sensitivities: u.sensitivities.len(), skipped: u.skipped.clone() }
+}
+
+async fn upload_crif(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, body: String) -> ApiResult<CrifSummary> {
+    require_open(&s, &id)?;
+    let upload = crif::parse(&body, now_ms()).map_err(bad_request)?;
+    let summary = crif_summary(&id, &upload);
+    audit(&s, &h, "crif.upload", &id, serde_json::json!({ "rows": summary.rows, "skipped": summary.skipped.len() }));
+    s.crif.lock().unwrap().insert(id, upload);
+    Ok(Json(summary))
+}
+
+async fn get_crif(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<CrifSummary> {
+    require_account(&s, &id)?;
+    s.crif.lock().unwrap().get(&id).map(|u| Json(crif_summary(&id, u))).ok_or_else(|| not_found("CRIF upload"))
+}
+
+async fn crif_simm(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<SimmResponse> {
+    let t = Instant::now();
+    require_open(&s, &id)?;
+    let result = s.crif.lock().unwrap().get(&id).map(|u| simm::compute(&u.sensitivities)).ok_or_else(|| not_found("CRIF upload"))?;
+    count(&s, |st| st.total_margin_calcs += 1);
+    Ok(Json(SimmResponse { account: id, result, elapsed_us: t.elapsed().as_micros() }))
+}
+
+/// A trip halts the instrument: pre-trade checks refuse orders in it until the halt expires or is lifted.
+async fn circuit_breaker(State(s): State<Arc<AppState>>, Json(req): Json<CircuitBreakerRequest>) -> ApiResult<CircuitBreakerResponse> {
+    let group_levels = s.groups.lock().unwrap().group_of(&req.instrument).and_then(|g| g.spec.breaker_levels.clone());
+    let mut cb = s.circuit_breakers.lock().unwrap();
+    let (change, reference_price) = match req.price_change_pct {
+        Some(c) => (c, None),
+        None => {
+            let price = req.price.or_else(|| s.marketdata.lock().unwrap().price(&req.instrument)).ok_or_else(|| bad_request("price_change_pct or a price is required"))?;
+            let r = cb.reference(&req.instrument, now_ms()).filter(|r| *r > 0.0).ok_or_else(|| bad_request(format!("no reference price for {} in the last {}s", req.instrument, cb.config.reference_window_secs)))?;
+            ((price / r - 1.0) * 100.0, Some(r))
+        }
+    };
+    let tripped = circuit_breaker::trip(group_levels.as_deref().unwrap_or(&cb.config.levels), change).cloned();
+    let halt = tripped.as_ref().map(|l| cb.halt(&req.instrument, l, change, now_ms()));
+    drop(cb);
+    let (level, halt_secs) = tripped.map_or(("none".to_string(), 0), |l| (l.name, l.halt_secs));
+    let triggered = halt_secs > 0;
+    if triggered {
+        s.metrics.lock().unwrap().breaker_trip(&level);
+        publish(&s, events::Kind::CircuitBreaker, Some(&req.instrument), serde_json::json!({ "action": "halt", "halt": halt }));
+        raise_alert(&s, "circuit_breaker", alerts::Severity::Critical, None, Some(&req.instrument), format!("{level} halt for {halt_secs}s after {change:.2}% move"));
+    }
+    Ok(Json(CircuitBreakerResponse { instrument: req.instrument, triggered, level, halt_duration_secs: halt_secs, price_change_pct: change, reference_price, halt }))
+}
+
+async fn list_groups(State(s): State<Arc<AppState>>) -> Json<Vec<groups::Group>> {
+    Json(s.groups.lock().unwrap().list())
+}
+
+async fn get_group(State(s): State<Arc<AppState>>, Path(name): Path<String>) -> ApiResult<groups::Group> {
+    s.groups.lock().unwrap().get(&name).cloned().map(Json).ok_or_else(|| not_found("Instrument group"))
+}
+
+async fn set_group(State(s): State<Arc<AppState>>, h: HeaderMap, Path(name): Path<String>, Json(req): Json<groups::GroupSpec>) -> ApiResult<groups::Group> {
+    require_role(&h, ADMIN_ROLE)?;
+    let (g, previous) = s.groups.lock().unwrap().set(&name, req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "instrument_group.set", &name, serde_json::json!({ "previous": previous.map(|p| p.spec), "new": g.spec }));
+    Ok(Json(g))
+}
+
+async fn delete_group(State(s): State<Arc<AppState>>, h: HeaderMap, Path(name): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    let g = s.groups.lock().unwrap().remove(&name).ok_or_else(|| not_found("Instrument group"))?;
+    audit(&s, &h, "instrument_group.delete", &name, serde_json::to_value(&g.spec).unwrap_or_default());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_account_links(State(s): State<Arc<AppState>>, Query(q): Query<related::LinkQuery>) -> Json<Vec<related::Link>> {
+    Json(s.related.lock().unwrap().list(q.account.as_deref()))
+}
+
+/// Links two accounts as one party: limits scoped to either, and related-scope wash trade surveillance, then cover both.
+async fn add_account_link(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<related::LinkSpec>) -> Result<(StatusCode, Json<related::Link>), (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    require_account(&s, req.a.trim())?;
+    require_account(&s, req.b.trim())?;
+    let link = s.related.lock().unwrap().add(req, &actor(&h), now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "account_links.add", &link.id, serde_json::to_value(&link).unwrap_or_default());
+    Ok((StatusCode::CREATED, Json(link)))
+}
+
+async fn remove_account_link(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    let link = s.related.lock().unwrap().remove(&id).ok_or_else(|| not_found("Account link"))?;
+    audit(&s, &h, "account_links.remove", &id, serde_json::to_value(&link).unwrap_or_default());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_pools(State(s): State<Arc<AppState>>) -> Json<Vec<pools::Pool>> {
+    Json(s.pools.lock().unwrap().list())
+}
+
+async fn get_pool(State(s): State<Arc<AppState>>, Path(name): Path<String>) -> ApiResult<pools::Pool> {
+    s.pools.lock().unwrap().get(&name).cloned().map(Json).ok_or_else(|| not_found("Limit pool"))
+}
+
+async fn set_pool(State(s): State<Arc<AppState>>, h: HeaderMap, Path(name): Path<String>, Json(req): Json<pools::PoolSpec>) -> ApiResult<pools::Pool> {
+    require_role(&h, ADMIN_ROLE)?;
+    for m in &req.members { require_account(&s, m)?; }
+    let (p, previous) = s.pools.lock().unwrap().set(&name, req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "limit_pool.set", &name, serde_json::json!({ "previous": previous.map(|p| p.spec), "new": p.spec }));
+    Ok(Json(p))
+}
+
+/// Reservations still held against the pool are dropped with it; their orders stay bound by the accounts' own limits.
+async fn delete_pool(State(s): State<Arc<AppState>>, h: HeaderMap, Path(name): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    let p = s.pools.lock().unwrap().remove(&name).ok_or_else(|| not_found("Limit pool"))?;
+    audit(&s, &h, "limit_pool.delete", &name, serde_json::to_value(&p.spec).unwrap_or_default());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// This session's draw on the pool, in total and by member.
+async fn pool_utilization(State(s): State<Arc<AppState>>, Path(name): Path<String>) -> ApiResult<pools::PoolUtilization> {
+    let session = s.daily.lock().unwrap().session_of(now_ms());
+    s.pools.lock().unwrap().utilization(&name, session).map(Json).ok_or_else(|| not_found("Limit pool"))
+}
+
+async fn list_halts(State(s): State<Arc<AppState>>) -> Json<Vec<circuit_breaker::Halt>> {
+    Json(s.circuit_breakers.lock().unwrap().halts(now_ms()))
+}
+
+/// Risk officers can reopen an instrument before its halt runs out.
+async fn lift_halt(State(s): State<Arc<AppState>>, h: HeaderMap, Path(instrument): Path<String>) -> ApiResult<circuit_breaker::Halt> {
+    require_role(&h, OVERRIDE_ROLE)?;
+    let halt = s.circuit_breakers.lock().unwrap().lift(&instrument, now_ms()).ok_or_else(|| not_found("Halt"))?;
+    audit(&s, &h, "circuit_breaker.lift", &instrument, serde_json::to_value(&halt).unwrap_or_default());
+    publish(&s, events::Kind::CircuitBreaker, Some(&instrument), serde_json::json!({ "action": "lift", "halt": halt, "lifted_by": actor(&h) }));
+    raise_alert(&s, "circuit_breaker", alerts::Severity::Info, None, Some(&instrument), format!("{} halt lifted by {}", halt.level, actor(&h)));
+    Ok(Json(halt))
+}
+
+/// Waits on a check's dependencies for what is left of its latency budget, the request's own or the configured
+/// one, or of the client's deadline if that comes sooner. `Err` marks the check for the fast path when the budget
+/// runs out first, and as timed out when the deadline does.
+async fn within_budget<T>(s: &AppState, requested_ms: Option<u64>, deadline_ms: Option<u64>, started: Instant, deps: impl std::future::Future<Output = T>) -> Result<T, budget::Degraded> {
+    let config = s.latency_budget.lock().unwrap().clone();
+    let budget = requested_ms.or(config.budget_ms).map(|b| (b, Duration::from_millis(b).saturating_sub(started.elapsed())));
+    let to_deadline = deadline_ms.map(|d| Duration::from_millis(d.saturating_sub(now_ms())));
+    let (budget_ms, left, stage) = match (budget, to_deadline) {
+        (None, None) => return Ok(deps.await),
+        (Some((b, left)), None) => (b, left, "dependencies"),
+        (Some((b, left)), Some(d)) if left <= d => (b, left, "dependencies"),
+        (_, Some(d)) => (d.as_millis() as u64, d, budget::DEADLINE),
+    };
+    tokio::time::timeout(left, deps).await.map_err(|_| budget::Degraded { budget_ms, stage, fallback: config.fallback })
+}
+
+/// The client's deadline for a check, or the reason it is refused unrun because too little of it is left.
+fn client_deadline(s: &AppState, h: &HeaderMap) -> Result<Result<Option<u64>, String>, (StatusCode, Json<Err>)> {
+    let now = now_ms();
+    match deadline::parse(h, now).map_err(bad_request)? {
+        Some(d) if d < now.saturating_add(s.deadline_min_ms) => Ok(Err(deadline::reason(d, now))),
+        d => Ok(Ok(d)),
+    }
+}
+
+/// Records a check refused unrun for its deadline and returns its id and reasons. Alerts and the kill switch ignore
+/// it, since nothing about the order was judged.
+fn timed_out(s: &AppState, kind: &str, account: &accounts::Account, tags: &tags::Tags, inputs: serde_json::Value, reason: String, started: Instant) -> (String, Vec<String>) {
+    let (check_id, reasons) = (uuid::Uuid::new_v4().to_string(), vec![reason]);
+    tracing::info!(check = %check_id, account = %account.id, "check refused unrun: client deadline");
+    count(s, |st| { st.total_checks += 1; st.trades_blocked += 1; });
+    s.tag_stats.lock().unwrap().record(tags, false);
+    record_check(s, checks::CheckRecord { check_id: check_id.clone(), kind: kind.into(), account: account.id.clone(), approved: false, reasons: reasons.clone(), tags: tags.clone(), inputs, limits_evaluated: Vec::new(), elapsed_us: started.elapsed().as_micros() as u64, at_ms: now_ms() });
+    (check_id, reasons)
+}
+
+/// Sleeps off injected latency and returns injected failures for a request on `targets` pricing `instruments`.
+async fn injected_faults(s: &AppState, targets: &[faults::Target], instruments: &[&str]) -> Vec<String> {
+    let p = s.faults.lock().unwrap().probe(targets, instruments, now_ms());
+    if p.delay_ms > 0 { tokio::time::sleep(std::time::Duration::from_millis(p.delay_ms)).await; }
+    p.failures
+}
+
+fn require_faults(s: &AppState, h: &HeaderMap) -> Result<(), (StatusCode, Json<Err>)> {
+    require_role(h, ADMIN_ROLE)?;
+    if s.faults.lock().unwrap().enabled { Ok(()) } else { Err(not_found("Fault injection")) }
+}
+
+async fn list_faults(State(s): State<Arc<AppState>>, h: HeaderMap) -> ApiResult<Vec<faults::Fault>> {
+    require_faults(&s, &h)?;
+    Ok(Json(s.faults.lock().unwrap().list(now_ms())))
+}
+
+async fn inject_fault(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<faults::FaultSpec>) -> Result<(StatusCode, Json<faults::Fault>), (StatusCode, Json<Err>)> {
+    require_faults(&s, &h)?;
+    let f = s.faults.lock().unwrap().inject(req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "fault.inject", &f.id, serde_json::to_value(&f).unwrap_or_default());
+    Ok((StatusCode::CREATED, Json(f)))
+}
+
+async fn remove_fault(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_faults(&s, &h)?;
+    if !s.faults.lock().unwrap().remove(&id) { return Err(not_found("Fault")); }
+    audit(&s, &h, "fault.remove", &id, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn clear_faults(State(s): State<Arc<AppState>>, h: HeaderMap) -> ApiResult<serde_json::Value> {
+    require_faults(&s, &h)?;
+    let cleared = s.faults.lock().unwrap().clear();
+    audit(&s, &h, "fault.clear", "faults", serde_json::json!({ "cleared": cleared }));
+    Ok(Json(serde_json::json!({ "cleared": cleared })))
+}
+
+async fn get_breaker_config(State(s): State<Arc<AppState>>) -> Json<circuit_breaker::BreakerConfig> {
+    Json(s.circuit_breakers.lock().unwrap().config.clone())
+}
+
+/// Takes effect for the next check; the audit entry keeps the previous parameters next to the new ones.
+async fn set_breaker_config(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<circuit_breaker::BreakerConfig>) -> ApiResult<circuit_breaker::BreakerConfig> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut s.circuit_breakers.lock().unwrap().config, req.clone());
+    if previous != req { audit(&s, &h, "circuit_breaker.config", "circuit_breaker", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_fat_finger_config(State(s): State<Arc<AppState>>) -> Json<fat_finger::FatFingerConfig> {
+    Json(s.fat_finger.lock().unwrap().clone())
+}
+
+async fn set_fat_finger_config(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<fat_finger::FatFingerConfig>) -> ApiResult<fat_finger::FatFingerConfig> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut *s.fat_finger.lock().unwrap(), req.clone());
+    if previous != req { audit(&s, &h, "fat_finger.config", "fat_finger", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_concentration_limits(State(s): State<Arc<AppState>>) -> Json<concentration::ConcentrationLimits> {
+    Json(s.concentration_limits.lock().unwrap().clone())
+}
+
+async fn set_concentration_limits(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<concentration::ConcentrationLimits>) -> ApiResult<concentration::ConcentrationLimits> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut *s.concentration_limits.lock().unwrap(), req.clone());
+    if previous != req { audit(&s, &h, "concentration_limits.config", "concentration_limits", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_reason_codes(State(s): State<Arc<AppState>>, h: HeaderMap) -> Json<Vec<reason_codes::Entry>> {
+    let locale = locale(&s, &h, None);
+    Json(s.reason_codes.lock().unwrap().entries(locale))
+}
+
+/// Replaces the admin-set entries; the body lists only the codes that differ from their defaults.
+async fn set_reason_codes(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<Vec<reason_codes::Entry>>) -> ApiResult<Vec<reason_codes::Entry>> {
+    require_role(&h, ADMIN_ROLE)?;
+    let mut c = s.reason_codes.lock().unwrap();
+    let previous = c.set(req).map_err(bad_request)?;
+    let new = c.overrides();
+    if previous != new { audit(&s, &h, "reason_codes.config", "reason_codes", serde_json::json!({ "previous": previous, "new": new })); }
+    Ok(Json(c.entries(locale::Locale::En)))
+}
+
+async fn get_locales(State(s): State<Arc<AppState>>) -> Json<locale::LocaleConfig> {
+    Json(s.locales.lock().unwrap().clone())
+}
+
+async fn set_locales(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<locale::LocaleConfig>) -> ApiResult<locale::LocaleConfig> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut *s.locales.lock().unwrap(), req.clone());
+    if previous != req { audit(&s, &h, "locales.config", "locales", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_event_windows(State(s): State<Arc<AppState>>) -> Json<calendar::CalendarConfig> {
+    Json(s.calendar.lock().unwrap().config.clone())
+}
+
+async fn set_event_windows(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<calendar::CalendarConfig>) -> ApiResult<calendar::CalendarConfig> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut s.calendar.lock().unwrap().config, req.clone());
+    if previous != req { audit(&s, &h, "event_windows.config", "event_windows", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_erroneous_config(State(s): State<Arc<AppState>>) -> Json<erroneous::ErroneousConfig> {
+    Json(s.erroneous.lock().unwrap().config.clone())
+}
+
+async fn set_erroneous_config(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<erroneous::ErroneousConfig>) -> ApiResult<erroneous::ErroneousConfig> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut s.erroneous.lock().unwrap().config, req.clone());
+    if previous != req { audit(&s, &h, "erroneous_orders.config", "erroneous_orders", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_latency_budget(State(s): State<Arc<AppState>>) -> Json<budget::LatencyBudget> {
+    Json(s.latency_budget.lock().unwrap().clone())
+}
+
+async fn set_latency_budget(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<budget::LatencyBudget>) -> ApiResult<budget::LatencyBudget> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut *s.latency_budget.lock().unwrap(), req.clone());
+    if previous != req { audit(&s, &h, "latency_budget.config", "latency_budget", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn event_bus_status(State(s): State<Arc<AppState>>) -> Json<events::BusStatus> {
+    let config = s.event_bus.clone();
+    let avro_schema = config.as_ref().filter(|c| c.format == events::Format::Avro).map(|_| events::AVRO_SCHEMA);
+    Json(events::BusStatus { enabled: config.is_some(), config, stats: s.event_stats.lock().unwrap().clone(), avro_schema })
+}
+
+async fn get_wash_config(State(s): State<Arc<AppState>>) -> Json<wash::WashConfig> {
+    Json(s.wash_trades.lock().unwrap().config.clone())
+}
+
+async fn set_wash_config(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<wash::WashConfig>) -> ApiResult<wash::WashConfig> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut s.wash_trades.lock().unwrap().config, req.clone());
+    if previous != req { audit(&s, &h, "wash_trades.config", "wash_trades", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_hedging(State(s): State<Arc<AppState>>) -> Json<hedge::HedgePolicy> {
+    Json(s.hedging.lock().unwrap().clone())
+}
+
+async fn set_hedging(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<hedge::HedgePolicy>) -> ApiResult<hedge::HedgePolicy> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut *s.hedging.lock().unwrap(), req.clone());
+    if previous != req { audit(&s, &h, "hedging.config", "hedging", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn get_span_config(State(s): State<Arc<AppState>>) -> Json<span::SpanConfig> {
+    Json(s.span.lock().unwrap().clone())
+}
+
+async fn set_span_config(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<span::SpanConfig>) -> ApiResult<span::SpanConfig> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut *s.span.lock().unwrap(), req.clone());
+    if previous != req { audit(&s, &h, "span.config", "span", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+fn settings(s: &AppState) -> config_bundle::Settings {
+    config_bundle::Settings {
+        fat_finger: s.fat_finger.lock().unwrap().clone(), circuit_breaker: s.circuit_breakers.lock().unwrap().config.clone(), erroneous_orders: s.erroneous.lock().unwrap().config.clone(),
+        event_windows: s.calendar.lock().unwrap().config.clone(), margin_call_thresholds: s.margin_calls.lock().unwrap().policy.thresholds.clone(),
+        concentration_limits: s.concentration_limits.lock().unwrap().clone(), wash_trades: s.wash_trades.lock().unwrap().config.clone(), hedging: s.hedging.lock().unwrap().clone(),
+    }
+}
+
+async fn export_config(State(s): State<Arc<AppState>>, h: HeaderMap) -> ApiResult<config_bundle::ConfigBundle> {
+    require_role(&h, ADMIN_ROLE)?;
+    let groups = s.groups.lock().unwrap().list().into_iter().map(|g| config_bundle::NamedGroup { name: g.name, spec: g.spec }).collect();
+    Ok(Json(config_bundle::ConfigBundle {
+        format_version: config_bundle::FORMAT_VERSION, exported_at_ms: now_ms(), source: std::env::var("RISK_ENVIRONMENT_NAME").ok(),
+        limits: Some(s.limits.lock().unwrap().list(&limits::LimitQuery { scope: None, key: None })), schedules: Some(s.schedules.lock().unwrap().list()),
+        scenarios: Some(s.scenarios.lock().unwrap().list()), instrument_groups: Some(groups), settings: Some(settings(&s)),
+    }))
+}
+
+/// Validates the whole document against copies of the current configuration before anything changes, so an import
+/// applies every section or none. `dry_run` reports the diff without applying it.
+async fn import_config(State(s): State<Arc<AppState>>, h: HeaderMap, Query(q): Query<config_bundle::ImportQuery>, Json(b): Json<config_bundle::ConfigBundle>) -> ApiResult<config_bundle::ImportReport> {
+    require_role(&h, ADMIN_ROLE)?;
+    if b.format_version != config_bundle::FORMAT_VERSION { return Err(bad_request(format!("format_version {} is not supported; this engine reads {}", b.format_version, config_bundle::FORMAT_VERSION))); }
+    let now = now_ms();
+    let mut sections = std::collections::BTreeMap::new();
+    let limits = b.limits.map(|incoming| {
+        let current = s.limits.lock().unwrap().list(&limits::LimitQuery { scope: None, key: None });
+        sections.insert("limits", config_bundle::diff(&current, &incoming, |l| l.id.clone()));
+        s.limits.lock().unwrap().config_copy().replace(incoming.clone(), now).map(|_| incoming)
+    }).transpose().map_err(bad_request)?;
+    let schedules = b.schedules.map(|incoming| {
+        let mut next = s.schedules.lock().unwrap().clone();
+        sections.insert("schedules", config_bundle::diff(&next.list(), &incoming, |g| g.group.clone()));
+        next.replace(incoming).map(|_| next)
+    }).transpose().map_err(bad_request)?;
+    let scenarios = b.scenarios.map(|incoming| {
+        let mut next = s.scenarios.lock().unwrap().clone();
+        sections.insert("scenarios", config_bundle::diff(&next.list(), &incoming, |scn| scn.name.clone()));
+        next.replace(incoming).map(|_| next)
+    }).transpose().map_err(bad_request)?;
+    let groups = b.instrument_groups.map(|incoming| {
+        let mut next = s.groups.lock().unwrap().clone();
+        let current: Vec<_> = next.list().into_iter().map(|g| config_bundle::NamedGroup { name: g.name, spec: g.spec }).collect();
+        sections.insert("instrument_groups", config_bundle::diff(&current, &incoming, |g| g.name.clone()));
+        next.replace(incoming.into_iter().map(|g| (g.name, g.spec)).collect(), now).map(|_| next)
+    }).transpose().map_err(bad_request)?;
+    if let Some(new) = &b.settings {
+        new.validate().map_err(bad_request)?;
+        sections.insert("settings", config_bundle::diff(&settings(&s).entries(), &new.entries(), |e| e.0.clone()));
+    }
+    let changed = sections.values().any(|d| !(d.added.is_empty() && d.changed.is_empty() && d.removed.is_empty()));
+    let report = config_bundle::ImportReport { dry_run: q.dry_run, applied: !q.dry_run && changed, source: b.source, sections };
+    if !report.applied { return Ok(Json(report)); }
+    let previous = export_config(State(s.clone()), h.clone()).await?.0;
+    // Replacing the live book keeps the orders counted against limits that stay.
+    if let Some(l) = limits { s.limits.lock().unwrap().replace(l, now).map_err(bad_request)?; }
+    if let Some(x) = schedules { *s.schedules.lock().unwrap() = x; }
+    if let Some(x) = scenarios { *s.scenarios.lock().unwrap() = x; }
+    if let Some(g) = groups { *s.groups.lock().unwrap() = g; }
+    if let Some(new) = b.settings {
+        *s.fat_finger.lock().unwrap() = new.fat_finger;
+        s.circuit_breakers.lock().unwrap().config = new.circuit_breaker;
+        s.erroneous.lock().unwrap().config = new.erroneous_orders;
+        s.calendar.lock().unwrap().config = new.event_windows;
+        s.liquidations.lock().unwrap().threshold_pct = new.margin_call_thresholds.liquidation_pct;
+        s.margin_calls.lock().unwrap().policy.thresholds = new.margin_call_thresholds;
+        *s.concentration_limits.lock().unwrap() = new.concentration_limits;
+        s.wash_trades.lock().unwrap().config = new.wash_trades;
+        *s.hedging.lock().unwrap() = new.hedging;
+    }
+    audit(&s, &h, "config.import", "config", serde_json::json!({ "source": report.source, "sections": report.sections, "previous": previous }));
+    Ok(Json(report))
+}
+
+async fn list_policy_packs() -> Json<Vec<policy_packs::Pack>> { Json(policy_packs::all()) }
+
+async fn get_policy_pack(Path(id): Path<String>) -> ApiResult<policy_packs::Pack> {
+    policy_packs::get(&id).map(Json).ok_or_else(|| not_found("Policy pack"))
+}
+
+/// Lays a pack's controls over the current configuration and imports the result, so a pack applies, dry-runs and
+/// is audited exactly as an imported document would be.
+async fn apply_policy_pack(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Query(q): Query<config_bundle::ImportQuery>) -> ApiResult<config_bundle::ImportReport> {
+    require_role(&h, ADMIN_ROLE)?;
+    let pack = policy_packs::get(&id).ok_or_else(|| not_found("Policy pack"))?;
+    let current = s.limits.lock().unwrap().list(&limits::LimitQuery { scope: None, key: None });
+    let (settings, limits) = pack.overlay(settings(&s), current);
+    let bundle = config_bundle::ConfigBundle {
+        format_version: config_bundle::FORMAT_VERSION, exported_at_ms: now_ms(), source: Some(format!("policy pack {}", pack.id)),
+        limits: Some(limits), schedules: None, scenarios: None, instrument_groups: None, settings: Some(settings),
+    };
+    import_config(State(s), h, Query(q), Json(bundle)).await
+}
+
+async fn policy_pack_report(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<policy_packs::Report> {
+    let pack = policy_packs::get(&id).ok_or_else(|| not_found("Policy pack"))?;
+    let limits = s.limits.lock().unwrap().list(&limits::LimitQuery { scope: None, key: None });
+    Ok(Json(pack.report(&settings(&s), &limits)))
+}
+
+async fn list_staged(State(s): State<Arc<AppState>>, Query(q): Query<staging::StagedQuery>) -> Json<Vec<staging::Staged>> {
+    Json(s.staging.lock().unwrap().list(q.status.as_deref()))
+}
+
+async fn get_staged(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<staging::Staged> {
+    s.staging.lock().unwrap().get(&id).cloned().map(Json).ok_or_else(|| not_found("Staged change"))
+}
+
+/// Holds a change until its activation time instead of applying it mid-session. The body is checked now as its
+/// endpoint would check it; a bundle is dry-run against the current configuration.
+async fn stage_change(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<staging::StageRequest>) -> Result<(StatusCode, Json<staging::Staged>), (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    let now = now_ms();
+    let at = match (req.activate_at_ms, req.at_next_session) {
+        (Some(_), true) => return Err(bad_request("set activate_at_ms or at_next_session, not both")),
+        (Some(at), false) if at > now => at,
+        (Some(_), false) => return Err(bad_request("activate_at_ms must be in the future")),
+        (None, true) => s.daily.lock().unwrap().next_session_start(now),
+        (None, false) => return Err(bad_request("activate_at_ms or at_next_session is required")),
+    };
+    req.change.validate().map_err(bad_request)?;
+    if let staging::Change::Bundle(b) = &req.change { import_config(State(s.clone()), h.clone(), Query(config_bundle::ImportQuery { dry_run: true }), Json((**b).clone())).await.map(drop)?; }
+    let e = s.staging.lock().unwrap().stage(req.change, at, actor(&h), req.reason, now);
+    audit(&s, &h, "config.stage", &e.id, serde_json::json!({ "change": e.change, "activate_at_ms": e.activate_at_ms, "reason": e.reason }));
+    Ok((StatusCode::CREATED, Json(e)))
+}
+
+async fn cancel_staged(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> ApiResult<staging::Staged> {
+    require_role(&h, ADMIN_ROLE)?;
+    let e = s.staging.lock().unwrap().cancel(&id, actor(&h), now_ms()).map_err(|finished| match finished {
+        true => (StatusCode::CONFLICT, Json(Err { error: "Staged change is no longer pending".into(), details: None })),
+        false => not_found("Staged change"),
+    })?;
+    audit(&s, &h, "config.stage_cancel", &id, serde_json::json!({ "activate_at_ms": e.activate_at_ms }));
+    Ok(Json(e))
+}
+
+/// Applies a staged change through the endpoint that would have made it, acting as whoever staged it.
+async fn apply_change(s: &Arc<AppState>, e: &staging::Staged) -> Result<(), (StatusCode, Json<Err>)> {
+    use staging::Change;
+    let mut h = HeaderMap::new();
+    if let Ok(v) = e.staged_by.parse() { h.insert("x-user-id", v); }
+    h.insert("x-user-role", axum::http::HeaderValue::from_static(ADMIN_ROLE));
+    let expected = match &e.change { Change::Limit { expected_version, .. } | Change::LimitDelete { expected_version, .. } => *expected_version, _ => None };
+    if let Some(v) = expected.and_then(|v| etag(v).parse().ok()) { h.insert(axum::http::header::IF_MATCH, v); }
+    let st = State(s.clone());
+    match e.change.clone() {
+        Change::Limit { id: None, spec, .. } => create_limit(st, h, Json(spec)).await.map(drop),
+        Change::Limit { id: Some(id), spec, .. } => update_limit(st, h, Path(id), Json(spec)).await.map(drop),
+        Change::LimitDelete { id, .. } => delete_limit(st, h, Path(id)).await.map(drop),
+        Change::CircuitBreaker(c) => set_breaker_config(st, h, Json(c)).await.map(drop),
+        Change::FatFinger(c) => set_fat_finger_config(st, h, Json(c)).await.map(drop),
+        Change::ErroneousOrders(c) => set_erroneous_config(st, h, Json(c)).await.map(drop),
+        Change::EventWindows(c) => set_event_windows(st, h, Json(c)).await.map(drop),
+        Change::MarginCallThresholds(c) => set_margin_call_thresholds(st, h, Json(c)).await.map(drop),
+        Change::ConcentrationLimits(c) => set_concentration_limits(st, h, Json(c)).await.map(drop),
+        Change::WashTrades(c) => set_wash_config(st, h, Json(c)).await.map(drop),
+        Change::Hedging(c) => set_hedging(st, h, Json(c)).await.map(drop),
+        Change::Span(c) => set_span_config(st, h, Json(c)).await.map(drop),
+        Change::LatencyBudget(c) => set_latency_budget(st, h, Json(c)).await.map(drop),
+        Change::Bundle(b) => import_config(st, h, Query(config_bundle::ImportQuery { dry_run: false }), Json(*b)).await.map(drop),
+    }
+}
+
+/// Applies the staged changes that have come due, oldest activation first. One that no longer applies, such as
+/// an update to a limit deleted since, is marked failed and alerted on.
+async fn activate_staged(s: &Arc<AppState>) {
+    let due = s.staging.lock().unwrap().due(now_ms());
+    for e in due {
+        let target = serde_json::to_value(&e.change).ok().and_then(|v| v["target"].as_str().map(String::from)).unwrap_or_default();
+        match apply_change(s, &e).await {
+            Ok(()) => s.audit.lock().unwrap().record(&e.staged_by, "config.activate", &e.id, serde_json::json!({ "target": target, "activate_at_ms": e.activate_at_ms }), now_ms()),
+            Err((_, Json(err))) => {
+                let error = err.details.map_or_else(|| err.error.clone(), |d| format!("{}: {d}", err.error));
+                raise_alert(s, "staged_change_failed", alerts::Severity::Warning, None, None, format!("staged {target} change {} could not be applied: {error}", e.id));
+                s.staging.lock().unwrap().failed(&e.id, error);
+            }
+        }
+    }
+}
+
+async fn stress_test(State(s): State<Arc<AppState>>, Json(mut req): Json<StressTestRequest>) -> ApiResult<StressTestResponse> {
+    if let Some(shift) = &req.correlation_shift {
+        if !shift.all.is_none_or(correlation::valid) || !shift.groups.iter().all(|g| correlation::valid(g.correlation)) { return Err(bad_request("correlations must be within [-1, 1]")); }
+    }
+    let window = match (req.from, req.to) {
+        (Some(from), Some(to)) if from < to => Some((from, to)),
+        (None, None) => None,
+        _ => return Err(bad_request("historical stress needs both from and to, with from before to")),
+    };
+    let inputs = serde_json::to_value(&req).unwrap_or_default();
+    // Inline steps win; otherwise a scenario name that matches the library runs the stored definition.
+    let composed = match req.steps.take() {
+        Some(steps) => { scenarios::validate(&steps).map_err(bad_request)?; Some((steps, req.compounding.unwrap_or_default(), None)) }
+        None => req.scenario.as_ref().and_then(|n| s.scenarios.lock().unwrap().get(n).map(|c| (c.steps.clone(), req.compounding.unwrap_or(c.compounding), Some(c.version)))),
+    };
+    if composed.is_some() && window.is_some() { return Err(bad_request("composed scenarios cannot be combined with a historical window")); }
+    let legs = portfolio(&s, req.account.as_deref(), req.positions.take());
+    let instruments: Vec<&str> = legs.iter().map(|l| l.0.as_str()).collect();
+    let injected = injected_faults(&s, &[faults::Target::Stress, faults::Target::Positions, faults::Target::MarketData], &instruments).await;
+    if !injected.is_empty() { return Err(unavailable(injected.join("; "))); }
+    let contribution = |instrument: &str, pnl: f64| legs.iter().find(|(i, _, _)| i == instrument).map(|(i, q, p)| stress_runs::Contribution { instrument: i.clone(), quantity: *q, price: *p, pnl });
+    let (mut resp, mode, version, contributions) = match (window, composed) {
+        (Some((from, to)), _) => {
+            let r = historical_stress(&s, &req, &legs, from, to);
+            let c = r.historical.as_ref().map(|h| h.by_instrument.iter().filter_map(|x| contribution(&x.instrument, x.pnl)).collect()).unwrap_or_default();
+            (r, stress_runs::Mode::Historical, None, c)
+        }
+        (None, composed) => {
+            // Without a scenario to run, a bare shock moves every price the same way.
+            let (mode, (steps, compounding, version)) = match composed {
+                Some(c) => (stress_runs::Mode::Composed, c),
+                None => (stress_runs::Mode::Flat, (vec![scenarios::Step::Market { shock_pct: req.shock_pct.unwrap_or(-20.0) }], scenarios::Compounding::Multiplicative, None)),
+            };
+            scenarios::validate(&steps).map_err(bad_request)?;
+            let out = s.scenarios.lock().unwrap().run(&steps, compounding, &legs, |o| underlier(&s, o));
+            let c = out.instruments.iter().filter_map(|x| contribution(&x.instrument, x.pnl)).collect();
+            let default_name = if mode == stress_runs::Mode::Flat { "market-crash" } else { "custom" };
+            (StressTestResponse {
+                run_id: String::new(), scenario: req.scenario.clone().unwrap_or_else(|| default_name.into()), portfolio_impact: out.pnl, worst_case_loss: out.pnl.min(0.0), liquidation_cost: 0.0,
+                instruments_affected: out.instruments.iter().filter(|i| i.pnl != 0.0).count() as u32, breaches: loss_breaches(&s, req.account.as_deref(), &legs, out.pnl),
+                historical: None, correlation: None, composed: Some(out),
+            }, mode, version, c)
+        }
+    };
+    // Unwinding after a shock trades at the shocked prices, so the impact bill scales with them.
+    let shocked: Vec<(String, f64, f64)> = legs.iter().map(|(i, q, p)| {
+        let ret = resp.composed.as_ref().and_then(|o| o.instruments.iter().find(|x| &x.instrument == i)).map_or(0.0, |x| x.return_pct / 100.0);
+        (i.clone(), *q, (p * (1.0 + ret)).max(0.0))
+    }).collect();
+    resp.liquidation_cost = { let liq = s.liquidity.lock().unwrap(); shocked.iter().map(|(i, q, p)| liq.cost(i, *q, *p)).fold(0.0, |a, b| a + b) };
+    resp.worst_case_loss -= resp.liquidation_cost;
+    if let Some(shift) = &req.correlation_shift {
+        let c = { let liq = s.liquidity.lock().unwrap(); s.correlations.lock().unwrap().impact(&legs, |i| liq.get(i).daily_vol, shift) };
+        let gross: f64 = legs.iter().map(|(_, q, p)| (q * p).abs()).sum();
+        if gross > 0.0 && c.stressed_var_99 / gross > 0.15 && !resp.breaches.iter().any(|b| b == "VaR limit breach") { resp.breaches.push("VaR limit breach".into()); }
+        resp.correlation = Some(c);
+    }
+    resp.run_id = uuid::Uuid::new_v4().to_string();
+    for b in &resp.breaches { record_breach(&s, stress_breach_kind(b), req.account.as_deref(), None, &format!("{}: {b}", resp.scenario), format!("{b} in stress run {} ({})", resp.run_id, resp.scenario)); }
+    s.stress_runs.lock().unwrap().record(stress_runs::StressRun {
+        id: resp.run_id.clone(), at_ms: now_ms(), mode, scenario: resp.scenario.clone(), scenario_version: version, suite_run_id: None, inputs,
+        results: vec![stress_runs::AccountStress { account: req.account.clone(), pnl: resp.portfolio_impact, breaches: resp.breaches.clone(), contributions }],
+    });
+    Ok(Json(resp))
+}
+
+fn historical_stress(s: &AppState, req: &StressTestRequest, legs: &[(String, f64, f64)], from: chrono::NaiveDate, to: chrono::NaiveDate) -> StressTestResponse {
+    let replay = s.history.lock().unwrap().replay(legs, from, to);
+    let breaches = loss_breaches(s, req.account.as_deref(), legs, replay.worst_pnl);
+    StressTestResponse {
+        run_id: String::new(), scenario: req.scenario.clone().unwrap_or_else(|| format!("historical {from}..{to}")), portfolio_impact: replay.pnl, worst_case_loss: replay.worst_pnl, liquidation_cost: 0.0,
+        instruments_affected: replay.instruments_replayed.len() as u32, breaches, historical: Some(replay), correlation: None, composed: None,
+    }
+}
+
+fn stress_breach_kind(breach: &str) -> breaches::BreachKind { if breach.starts_with("VaR") { breaches::BreachKind::Var } else { breaches::BreachKind::Stress } }
+
+/// Breaches implied by a scenario P&L: a loss beyond 15% of gross, or one that takes funds below the last initial margin.
+fn loss_breaches(s: &AppState, account: Option<&str>, legs: &[(String, f64, f64)], pnl: f64) -> Vec<String> {
+    let gross: f64 = legs.iter().map(|(_, q, p)| (q * p).abs()).sum();
+    let mut breaches = Vec::new();
+    if gross > 0.0 && -pnl / gross > 0.15 { breaches.push("VaR limit breach".into()); }
+    if let Some(a) = account {
+        let initial = s.margins.lock().unwrap().get(a).map(|m| m.initial_margin).unwrap_or(0.0);
+        if account_funds(s, a) + pnl < initial { breaches.push("Margin call triggered".into()); }
+    }
+    breaches
+}
+
+async fn reverse_stress(State(s): State<Arc<AppState>>, Json(req): Json<ReverseStressRequest>) -> ApiResult<ReverseStressResponse> {
+    if let Some(a) = &req.account { require_account(&s, a)?; }
+    let (target, threshold) = match (req.loss_threshold, &req.account) {
+        (Some(t), _) if t.is_finite() && t > 0.0 => ("loss", t),
+        (Some(_), _) => return Err(bad_request("loss_threshold must be positive")),
+        (None, Some(a)) => {
+            let initial = s.margins.lock().unwrap().get(a).map(|m| m.initial_margin).unwrap_or(0.0);
+            ("margin", account_funds(&s, a) - initial)
+        }
+        (None, None) => return Err(bad_request("provide loss_threshold or an account to target a margin breach")),
+    };
+    let legs = portfolio(&s, req.account.as_deref(), req.positions);
+    let result = { let liq = s.liquidity.lock().unwrap(); reverse::search(&legs, |i| liq.get(i).daily_vol, threshold) };
+    Ok(Json(ReverseStressResponse { account: req.account, target, result }))
+}
+
+/// Positions supplied on the request, otherwise the account's booked positions marked at the last cached price.
+/// Positions with no price of either kind are left out.
+fn portfolio(s: &AppState, account: Option<&str>, positions: Option<Vec<PositionInput>>) -> Vec<(String, f64, f64)> {
+    let md = s.marketdata.lock().unwrap();
+    if let Some(p) = positions { return p.into_iter().filter_map(|p| Some((p.price.or_else(|| md.price(&p.instrument))?, p)).map(|(px, p)| (p.instrument, p.quantity, px))).collect(); }
+    let Some(account) = account else { return Vec::new() };
+    s.positions.lock().unwrap().list(account).into_iter().filter_map(|p| md.price(&p.instrument).map(|px| (p.instrument, p.quantity, px))).collect()
+}
+
+/// Supplied positions at their own price or else the last quote. Margin must not be figured on part of a book, so
+/// a position with neither is refused.
+fn marked(s: &AppState, positions: Vec<PositionInput>) -> Result<Vec<(String, f64, f64)>, String> {
+    let md = s.marketdata.lock().unwrap();
+    positions.into_iter().map(|p| match p.price.or_else(|| md.price(&p.instrument)) {
+        Some(px) => Ok((p.instrument, p.quantity, px)),
+        None => Err(format!("no price for {}: give one or feed a quote", p.instrument)),
+    }).collect()
+}
+
+async fn velocity_state(State(s): State<Arc<AppState>>, Path(account): Path<String>) -> ApiResult<VelocityResponse> {
+    require_account(&s, &account)?;
+    let mut v = s.velocity.lock().unwrap();
+    Ok(Json(VelocityResponse { limits: v.limits(&account), windows: v.windows(&account, now_ms()), account }))
+}
+
+async fn set_velocity_limits(State(s): State<Arc<AppState>>, h: HeaderMap, Path(account): Path<String>, Json(limits): Json<velocity::VelocityLimits>) -> ApiResult<VelocityResponse> {
+    require_role(&h, ADMIN_ROLE)?;
+    require_account(&s, &account)?;
+    limits.validate().map_err(bad_request)?;
+    let mut v = s.velocity.lock().unwrap();
+    let previous = v.limits(&account);
+    v.set_limits(&account, limits);
+    if previous != limits { audit(&s, &h, "velocity_limits.config", &account, serde_json::json!({ "previous": previous, "new": limits })); }
+    Ok(Json(VelocityResponse { limits, windows: v.windows(&account, now_ms()), account }))
+}
+
+async fn daily_limits(State(s): State<Arc<AppState>>) -> Json<daily::DailySnapshot> {
+    Json(s.daily.lock().unwrap().snapshot(now_ms()))
+}
+
+async fn set_daily_limit(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<daily::DailyLimitUpdate>) -> ApiResult<daily::DailySnapshot> {
+    require_role(&h, ADMIN_ROLE)?;
+    if let Some(a) = &req.account { require_account(&s, a)?; }
+    req.validate().map_err(bad_request)?;
+    audit(&s, &h, "daily_limit.set", "daily_limits", serde_json::to_value(&req).unwrap_or_default());
+    let mut d = s.daily.lock().unwrap();
+    d.update(req);
+    Ok(Json(d.snapshot(now_ms())))
+}
+
+async fn list_events(State(s): State<Arc<AppState>>, Query(q): Query<calendar::EventQuery>) -> Json<Vec<calendar::Event>> {
+    Json(s.calendar.lock().unwrap().list(&q))
+}
+
+async fn get_event(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<calendar::Event> {
+    s.calendar.lock().unwrap().get(&id).cloned().map(Json).ok_or_else(|| not_found("Event"))
+}
+
+async fn create_event(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<calendar::EventSpec>) -> Result<(StatusCode, Json<calendar::Event>), (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    let e = s.calendar.lock().unwrap().create(req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "event.create", &e.id, serde_json::to_value(&e).unwrap_or_default());
+    Ok((StatusCode::CREATED, Json(e)))
+}
+
+async fn update_event(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<calendar::EventSpec>) -> ApiResult<calendar::Event> {
+    require_role(&h, ADMIN_ROLE)?;
+    let e = s.calendar.lock().unwrap().update(&id, req, now_ms()).map_err(bad_request)?.ok_or_else(|| not_found("Event"))?;
+    audit(&s, &h, "event.update", &e.id, serde_json::to_value(&e).unwrap_or_default());
+    Ok(Json(e))
+}
+
+async fn delete_event(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    if !s.calendar.lock().unwrap().remove(&id) { return Err(not_found("Event")); }
+    audit(&s, &h, "event.delete", &id, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_schedules(State(s): State<Arc<AppState>>) -> Json<Vec<schedule::GroupSchedule>> {
+    Json(s.schedules.lock().unwrap().list())
+}
+
+async fn set_schedule(State(s): State<Arc<AppState>>, h: HeaderMap, Path(group): Path<String>, Json(req): Json<schedule::GroupSchedule>) -> ApiResult<schedule::GroupSchedule> {
+    require_role(&h, ADMIN_ROLE)?;
+    let g = s.schedules.lock().unwrap().set(&group, req).map_err(bad_request)?;
+    audit(&s, &h, "schedule.set", &group, serde_json::to_value(&g).unwrap_or_default());
+    Ok(Json(g))
+}
+
+async fn delete_schedule(State(s): State<Arc<AppState>>, h: HeaderMap, Path(group): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    if !s.schedules.lock().unwrap().remove(&group) { return Err(not_found("Schedule")); }
+    audit(&s, &h, "schedule.delete", &group, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_entitlements(State(s): State<Arc<AppState>>, Path(account): Path<String>) -> ApiResult<entitlements::Entitlements> {
+    require_account(&s, &account)?;
+    Ok(Json(s.entitlements.lock().unwrap().get(&account)))
+}
+
+async fn set_entitlements(State(s): State<Arc<AppState>>, h: HeaderMap, Path(account): Path<String>, Json(req): Json<entitlements::Entitlements>) -> ApiResult<entitlements::Entitlements> {
+    require_role(&h, ADMIN_ROLE)?;
+    require_account(&s, &account)?;
+    s.entitlements.lock().unwrap().set(&account, req.clone());
+    audit(&s, &h, "entitlements.set", &account, serde_json::to_value(&req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn delete_entitlements(State(s): State<Arc<AppState>>, h: HeaderMap, Path(account): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    require_account(&s, &account)?;
+    if !s.entitlements.lock().unwrap().remove(&account) { return Err(not_found("Entitlements")); }
+    audit(&s, &h, "entitlements.delete", &account, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Utilization of every daily, velocity and margin limit set for `accounts`; unset limits are left out.
+fn utilization(s: &AppState, accounts: &[String], now: u64) -> Vec<LimitUtilization> {
+    let mut rows = Vec::new();
+    let mut push = |account: &str, limit_type: String, limit: f64, utilization: f64| {
+        if limit > 0.0 { rows.push(LimitUtilization { account: account.into(), limit_type, limit, utilization, used_pct: utilization / limit * 100.0 }); }
+    };
+    let daily = s.daily.lock().unwrap().snapshot(now);
+    for a in accounts {
+        if let Some(l) = daily.account_limits.get(a) { push(a, "daily_notional".into(), *l, daily.account_used.get(a).copied().unwrap_or(0.0)); }
+        for w in s.velocity.lock().unwrap().windows(a, now) {
+            push(a, format!("velocity_{}_orders", w.window), w.max_orders as f64, w.orders as f64);
+            push(a, format!("velocity_{}_notional", w.window), w.max_notional, w.notional);
+        }
+        if let Some(m) = s.margins.lock().unwrap().get(a) { push(a, "margin".into(), m.funds, m.initial_margin); }
+    }
+    rows
+}
+
+async fn limit_utilization(State(s): State<Arc<AppState>>, Query(q): Query<UtilizationQuery>) -> ApiResult<Vec<LimitUtilization>> {
+    let by_utilization = match q.sort.as_deref() { None | Some("used_pct") => false, Some("utilization") => true, Some(o) => return Err(bad_request(format!("unknown sort '{o}'"))) };
+    let ascending = match q.order.as_deref() { None | Some("desc") => false, Some("asc") => true, Some(o) => return Err(bad_request(format!("unknown order '{o}'"))) };
+    let ids: Vec<String> = s.accounts.lock().unwrap().list().into_iter().map(|a| a.id).filter(|a| q.account.as_ref().is_none_or(|x| x == a)).collect();
+    let mut rows = utilization(&s, &ids, now_ms());
+    rows.retain(|r| q.limit_type.as_ref().is_none_or(|t| &r.limit_type == t) && q.min_pct.is_none_or(|p| r.used_pct >= p));
+    rows.sort_by(|x, y| {
+        let (a, b) = if by_utilization { (x.utilization, y.utilization) } else { (x.used_pct, y.used_pct) };
+        if ascending { a.total_cmp(&b) } else { b.total_cmp(&a) }
+    });
+    rows.truncate(q.limit.unwrap_or(100));
+    Ok(Json(rows))
+}
+
+fn apply_default_limits(s: &AppState, a: &accounts::Account) {
+    if let Some(v) = a.default_limits.velocity { s.velocity.lock().unwrap().set_limits(&a.id, v); }
+    if let Some(l) = a.default_limits.daily_notional { s.daily.lock().unwrap().update(daily::DailyLimitUpdate { account: Some(a.id.clone()), instrument: None, limit: Some(l) }); }
+}
+
+async fn list_accounts(State(s): State<Arc<AppState>>) -> Json<Vec<accounts::Account>> {
+    Json(s.accounts.lock().unwrap().list())
+}
+
+async fn get_account(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Tagged<accounts::Account>, (StatusCode, Json<Err>)> {
+    require_account(&s, &id).map(|a| tagged(a.version, a))
+}
+
+async fn create_account(State(s): State<Arc<AppState>>, h: HeaderMap, Json(mut req): Json<accounts::CreateAccount>) -> Result<(StatusCode, Tagged<accounts::Account>), (StatusCode, Json<Err>)> {
+    req.base_currency.get_or_insert_with(|| s.firm_currency.clone());
+    let a = s.accounts.lock().unwrap().create(req, now_ms()).map_err(account_error)?;
+    apply_default_limits(&s, &a);
+    audit(&s, &h, "account.create", &a.id, serde_json::json!({ "status": a.status, "base_currency": a.base_currency, "margin_model": a.margin_model }));
+    Ok((StatusCode::CREATED, tagged(a.version, a)))
+}
+
+async fn update_account(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<accounts::UpdateAccount>) -> Result<Tagged<accounts::Account>, (StatusCode, Json<Err>)> {
+    let limits_changed = req.default_limits.is_some();
+    let reason = req.reason.clone();
+    let (before, a) = {
+        let mut book = s.accounts.lock().unwrap();
+        let before = book.get(&id).cloned().ok_or_else(|| account_error(accounts::AccountError::NotFound))?;
+        check_if_match(&s, &h, before.version)?;
+        (before, book.update(&id, req, now_ms()).map_err(account_error)?)
+    };
+    if limits_changed { apply_default_limits(&s, &a); }
+    if before.status != a.status { audit(&s, &h, "account.status", &id, serde_json::json!({ "from": before.status, "to": a.status, "reason": reason })); }
+    audit(&s, &h, "account.update", &id, serde_json::json!({ "base_currency": a.base_currency, "margin_model": a.margin_model, "limits_changed": limits_changed, "reason": reason, "version": a.version }));
+    Ok(tagged(a.version, a))
+}
+
+async fn get_positions(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Vec<positions::Position>> {
+    let account = require_account(&s, &id)?;
+    let mut held = s.positions.lock().unwrap().list(&id);
+    let sc = s.scenarios.lock().unwrap();
+    for p in &mut held { p.currency = Some(sc.factor(&p.instrument).and_then(|f| f.currency.as_deref()).map_or_else(|| account.base_currency.clone(), str::to_ascii_uppercase)); }
+    Ok(Json(held))
+}
+
+async fn account_greeks(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<GreekReport> {
+    let account = require_account(&s, &id)?;
+    let held = s.positions.lock().unwrap().list(&id);
+    let (mut positions, mut unpriced) = (Vec::new(), Vec::new());
+    {
+        let sc = s.scenarios.lock().unwrap();
+        for p in held.into_iter().filter(|p| p.quantity != 0.0) {
+            match instrument_greeks(&s, &sc, &p.instrument, p.quantity, &[]) {
+                Ok((underlying, kind, greeks)) => positions.push(greeks::PositionGreeks { instrument: p.instrument, kind, underlying, quantity: p.quantity, greeks }),
+                Err(e) => unpriced.push(format!("{}: {e}", p.instrument)),
+            }
+        }
+    }
+    let mut by_underlier = std::collections::BTreeMap::<String, greeks::Greeks>::new();
+    for p in &positions { by_underlier.entry(p.underlying.clone()).or_default().add(&p.greeks); }
+    let total = by_underlier.values().fold(greeks::Greeks::default(), |mut t, g| { t.add(g); t });
+    // Checked as one order from a flat book, so every cap the holdings are over reads as an order check would.
+    let breaches = account.default_limits.greeks.as_ref().map(|l| greeks::evaluate(l, &Default::default(), &by_underlier).1).unwrap_or_default();
+    Ok(Json(GreekReport { account: id, total, by_underlier, positions, limits: account.default_limits.greeks, breaches, unpriced, as_of_ms: now_ms() }))
+}
+
+async fn risk_summary(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<RiskSummary> {
+    let account = require_account(&s, &id)?;
+    let now = now_ms();
+    let positions = s.positions.lock().unwrap().list(&id);
+    let (unpriced, var) = historical_var(&s, &account, s.var_lookback_days);
+    let held: Vec<(String, f64)> = positions.iter().map(|p| (p.instrument.clone(), p.quantity)).collect();
+    let greeks = match greeks_by_underlier(&s, &held, &[]) {
+        Ok(by_underlier) => GreekSummary { total: by_underlier.values().fold(greeks::Greeks::default(), |mut t, g| { t.add(g); t }), by_underlier, error: None },
+        Err(e) => GreekSummary { error: Some(e), ..Default::default() },
+    };
+    let q = breaches::BreachQuery { status: None, kind: None, account: Some(id.clone()), owner: None, limit: Some(usize::MAX) };
+    let open_breaches = s.breaches.lock().unwrap().query(&q).into_iter().filter(|b| b.status != breaches::BreachStatus::Resolved).collect();
+    let mut restrictions: Vec<Restriction> = match account.status {
+        accounts::AccountStatus::Active => Vec::new(),
+        accounts::AccountStatus::ReduceOnly => vec![Restriction { kind: "account_status", detail: "Account reduce-only: orders may only reduce positions".into(), since_ms: None }],
+        _ => account_status_reasons(&account).into_iter().map(|detail| Restriction { kind: "account_status", detail, since_ms: None }).collect(),
+    };
+    restrictions.extend(s.kill_switches.lock().unwrap().halting(&id, account.desk.as_deref()).into_iter().map(|e| Restriction { kind: "kill_switch", detail: format!("kill switch active ({}): {}", e.label(), e.reason), since_ms: Some(e.engaged_at_ms) }));
+    restrictions.extend(s.margin_calls.lock().unwrap().list(&margin_calls::CallQuery { account: Some(id.clone()), open: Some(true) }).into_iter().filter_map(|c| Some(Restriction { kind: "margin_call", detail: format!("margin call {} for {:.2}, due {}", c.id, c.shortfall, c.deadline_ms?), since_ms: Some(c.issued_at_ms) })));
+    Ok(Json(RiskSummary {
+        margin: margin_snapshot(&s, &id), limit_utilization: utilization(&s, std::slice::from_ref(&id), now), status: account.status,
+        account: id, positions, unpriced, var, greeks, open_breaches, restrictions, as_of_ms: now,
+    }))
+}
+
+/// Aggregates every account, or one tenant's, into the reporting currency. Each account's exposures and VaR P&L are
+/// cached against a fingerprint of its priced holdings and the price history, so only accounts that changed since the
+/// last summary are revalued.
+async fn firm_summary(State(s): State<Arc<AppState>>, Query(q): Query<firm::FirmQuery>) -> ApiResult<firm::FirmSummary> {
+    use std::hash::{Hash, Hasher};
+    let currency = q.currency.as_deref().unwrap_or(&s.firm_currency).to_ascii_uppercase();
+    let top = q.top.unwrap_or(10).clamp(1, 100);
+    let accounts = match &q.entity { Some(e) => s.accounts.lock().unwrap().by_entity(e), None => s.accounts.lock().unwrap().list() };
+    if q.entity.is_some() && accounts.is_empty() { return Err(not_found("Entity")); }
+    let revision = s.history.lock().unwrap().revision();
+    let lookback = s.var_lookback_days;
+    let mut recomputed_accounts = 0;
+    for a in &accounts {
+        let (legs, _) = to_base(&s, &a.base_currency, &portfolio(&s, Some(&a.id), None));
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (revision, lookback, &a.base_currency).hash(&mut hasher);
+        for (i, qty, px) in &legs { (i, qty.to_bits(), px.to_bits()).hash(&mut hasher); }
+        let fingerprint = hasher.finish();
+        if s.firm_contributions.lock().unwrap().is_current(&a.id, fingerprint) { continue; }
+        let (pnl, missing_history) = { let h = s.history.lock().unwrap(); var::dated_pnl(&legs, |i| h.series(i, None, None), lookback) };
+        let exposures = legs.iter().map(|(i, qty, px)| (i.clone(), qty * px)).collect();
+        s.firm_contributions.lock().unwrap().insert(&a.id, firm::Contribution { fingerprint, exposures, pnl, missing_history });
+        recomputed_accounts += 1;
+    }
+    let mut unconverted = std::collections::BTreeSet::new();
+    let rates: std::collections::HashMap<&str, f64> = accounts.iter().filter_map(|a| {
+        let r = fx_rate(&s, &a.base_currency, &currency);
+        if r.is_none() { unconverted.insert(a.base_currency.clone()); }
+        Some((a.id.as_str(), r?))
+    }).collect();
+    let mut margin = firm::MarginTotals::default();
+    for a in &accounts {
+        if let (Some(r), Some(m)) = (rates.get(a.id.as_str()), margin_snapshot(&s, &a.id)) { firm::add_margin(&mut margin, &m, *r); }
+    }
+    let mut worst_stress: Vec<firm::StressResult> = Vec::new();
+    for run in s.stress_runs.lock().unwrap().list(&stress_runs::RunQuery { scenario: None, account: None, limit: Some(usize::MAX) }) {
+        if worst_stress.iter().any(|w| w.scenario == run.scenario) { continue; }
+        let mine: Vec<f64> = run.results.iter().filter_map(|r| Some(r.pnl * rates.get(r.account.as_deref()?)?)).collect();
+        if mine.is_empty() { continue; }
+        worst_stress.push(firm::StressResult { scenario: run.scenario, run_id: run.id, at_ms: run.at_ms, pnl: mine.iter().sum(), accounts: mine.len() });
+    }
+    worst_stress.sort_by(|a, b| a.pnl.total_cmp(&b.pnl));
+    worst_stress.truncate(top);
+    let cache = s.firm_contributions.lock().unwrap();
+    let parts: Vec<(&firm::Contribution, f64)> = accounts.iter().filter_map(|a| Some((cache.get(&a.id)?, *rates.get(a.id.as_str())?))).collect();
+    let (exposure, top_concentrations, var) = firm::aggregate(&parts, top, lookback);
+    let groups = s.related.lock().unwrap().groups().into_iter().filter_map(|g| {
+        let members: Vec<(String, (&firm::Contribution, f64))> = g.into_iter().filter_map(|a| { let part = (cache.get(&a)?, *rates.get(a.as_str())?); Some((a, part)) }).collect();
+        (members.len() > 1).then(|| members.into_iter().unzip())
+    }).collect();
+    let related_groups = firm::related_groups(groups, exposure.gross, top);
+    Ok(Json(firm::FirmSummary {
+        entity: q.entity, currency, accounts: accounts.len(), exposure, margin, var, top_concentrations, related_groups, worst_stress,
+        unconverted: unconverted.into_iter().collect(), recomputed_accounts, as_of_ms: now_ms(),
+    }))
+}
+
+/// `(instrument, notional held before, notional held after)` for each leg, at the leg's price, netting legs that
+/// trade the same instrument.
+fn position_exposure(s: &AppState, account: &str, legs: &[OrderLeg], fx: &HashMap<String, f64>) -> Vec<(String, f64, f64)> {
+    let book = s.positions.lock().unwrap();
+    legs.iter().map(|l| {
+        let held = book.net(account, &l.instrument);
+        let order: f64 = legs.iter().filter(|o| o.instrument == l.instrument).map(|o| positions::signed_quantity(o.side, o.quantity)).sum();
+        let price = l.price * fx.get(&l.instrument).copied().unwrap_or(1.0);
+        (l.instrument.clone(), held * price, (held + order) * price)
+    }).collect()
+}
+
+async fn book_fill(State(s): State<Arc<AppState>>, h: HeaderMap, body: Result<Json<positions::Fill>, JsonRejection>) -> ApiResult<positions::FillAck> {
+    let req = json_body(body)?;
+    let ack = apply_fill(&s, &req)?;
+    if !ack.duplicate { audit(&s, &h, "positions.fill", &req.account, serde_json::json!({ "fill_id": ack.fill_id, "instrument": req.instrument, "side": req.side, "quantity": req.quantity, "price": req.price, "position": ack.position })); }
+    Ok(Json(ack))
+}
+
+/// Books a fill into the position keeper once per fill id, for the fill endpoint and the FIX drop copy alike.
+fn apply_fill(s: &AppState, f: &positions::Fill) -> Result<positions::FillAck, (StatusCode, Json<Err>)> {
+    require_account(s, &f.account)?;
+    if f.instrument.is_empty() { return Err(bad_request("fill needs an instrument")); }
+    if !(f.quantity.is_finite() && f.quantity > 0.0 && f.price.is_finite() && f.price > 0.0) { return Err(bad_request("quantity and price must be positive")); }
+    Ok(s.positions.lock().unwrap().fill(f))
+}
+
+/// Accepts FIX 4.4 drop-copy sessions from the OMS, one task per connection.
+async fn run_drop_copy(s: Arc<AppState>, addr: String) {
+    let listener = match tokio::net::TcpListener::bind(&addr).await { Ok(l) => l, Err(e) => return tracing::error!("RISK_FIX_DROPCOPY_ADDR {addr}: {e}") };
+    tracing::info!("FIX drop copy on {addr}");
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => { tokio::spawn(drop_copy_session(s.clone(), stream, peer)); }
+            Err(e) => tracing::warn!("drop copy accept failed: {e}"),
+        }
+    }
+}
+
+/// Runs one session until either side logs out or the connection fails, heartbeating at the interval the
+/// counterparty asked for at logon.
+async fn drop_copy_session(s: Arc<AppState>, mut stream: tokio::net::TcpStream, peer: std::net::SocketAddr) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let comp_id = s.drop_copy.lock().unwrap().comp_id.clone();
+    let (mut session, mut decoder, mut buf) = (fix::Session::new(&comp_id), fix::Decoder::default(), vec![0u8; 8192]);
+    let mut heartbeat = tokio::time::interval(Duration::from_secs(session.heartbeat_secs));
+    let status = |session: &fix::Session, f: &dyn Fn(&mut fix::SessionStatus)| if let Some(c) = &session.counterparty { f(s.drop_copy.lock().unwrap().status(c)) };
+    let error = loop {
+        let (mut out, mut end) = (Vec::new(), None);
+        tokio::select! {
+            n = stream.read(&mut buf) => match n {
+                Ok(0) => end = Some("connection closed".to_string()),
+                Err(e) => end = Some(e.to_string()),
+                Ok(n) => {
+                    decoder.push(&buf[..n]);
+                    while let Some(m) = decoder.next() {
+                        let m = match m { Ok(m) => m, Err(e) => { status(&session, &|st| { st.rejected += 1; st.last_error = Some(e.clone()); }); continue } };
+                        let was_on = session.logged_on();
+                        let step = session.on_message(&m);
+                        if !was_on && session.logged_on() {
+                            status(&session, &|st| { st.connected = true; st.connects += 1; st.last_error = None; });
+                            let period = Duration::from_secs(session.heartbeat_secs);
+                            heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                            tracing::info!(counterparty = ?session.counterparty, %peer, "drop copy logged on");
+                        }
+                        let (gaps, in_seq) = (session.gaps, session.in_seq());
+                        status(&session, &|st| { st.messages += 1; st.gaps = gaps; st.in_seq = in_seq; });
+                        if step.application { if let Some(c) = session.counterparty.clone() { drop_copy_report(&s, &c, &m); } }
+                        out.extend(step.replies);
+                        if step.close { end = Some(step.error.unwrap_or_else(|| "logged out".into())); break; }
+                    }
+                }
+            },
+            _ = heartbeat.tick() => if session.logged_on() { out.push(session.heartbeat()) },
+        }
+        for m in &out { if let Err(e) = stream.write_all(m).await { end.get_or_insert(e.to_string()); break; } }
+        if let Some(e) = end { break e; }
+    };
+    tracing::info!(counterparty = ?session.counterparty, %peer, "drop copy disconnected: {error}");
+    status(&session, &|st| { st.connected = false; st.last_error = Some(error.clone()); });
+}
+
+/// Books what one application message carries and remargins the account when positions moved. Reports the
+/// session cannot book are counted as rejected with the reason kept as the session's last error.
+fn drop_copy_report(s: &Arc<AppState>, counterparty: &str, m: &fix::Message) {
+    let outcome = if m.msg_type() == "8" { fix::execution(m).and_then(|e| drop_copy_execution(s, counterparty, e)) } else { Ok(None) };
+    let mut dc = s.drop_copy.lock().unwrap();
+    let st = dc.status(counterparty);
+    match outcome {
+        Ok(None) => st.ignored += 1,
+        Ok(Some((kind, duplicate))) => {
+            match (kind, duplicate) { (_, true) => st.duplicates += 1, ("cancel", _) => st.cancels += 1, ("correction", _) => st.corrections += 1, _ => st.fills += 1 }
+            st.last_fill_at_ms = Some(now_ms());
+        }
+        Err(e) => { tracing::warn!(counterparty, "drop copy report rejected: {e}"); st.rejected += 1; st.last_error = Some(e); }
+    }
+}
+
+/// The fills an execution books: a trade itself, the reversal of a cancelled trade, or both for a correction.
+/// Returns what kind of report it was and whether it had been booked already.
+fn drop_copy_execution(s: &Arc<AppState>, counterparty: &str, e: fix::Execution) -> Result<Option<(&'static str, bool)>, String> {
+    let reverse = |exec_id: &str, id: String| s.drop_copy.lock().unwrap().reversal(exec_id, id).ok_or_else(|| format!("ExecRefID {exec_id} names no booked fill"));
+    let (kind, fills) = match e {
+        fix::Execution::Ignored => return Ok(None),
+        fix::Execution::Fill(f) => ("fill", vec![f]),
+        fix::Execution::Cancel { exec_id, cancels } => ("cancel", vec![reverse(&cancels, exec_id)?]),
+        fix::Execution::Correct { fill, corrects } => { let id = format!("{}:reversal", fill.fill_id.as_deref().unwrap_or_default()); ("correction", vec![reverse(&corrects, id)?, fill]) }
+    };
+    let mut duplicate = false;
+    for f in &fills {
+        let ack = apply_fill(s, f).map_err(|(_, Json(e))| e.details.map_or_else(|| e.error.clone(), |d| format!("{}: {d}", e.error)))?;
+        duplicate |= ack.duplicate;
+        if ack.duplicate { continue; }
+        s.drop_copy.lock().unwrap().booked(f);
+        s.audit.lock().unwrap().record(&format!("fix:{counterparty}"), "positions.fill", &f.account, serde_json::json!({ "fill_id": ack.fill_id, "instrument": f.instrument, "side": f.side, "quantity": f.quantity, "price": f.price, "position": ack.position }), now_ms());
+    }
+    let account = s.accounts.lock().unwrap().get(&fills[0].account).cloned();
+    if let (Some(a), false) = (account, duplicate) { remargin(s, &a, "drop_copy"); }
+    Ok(Some((kind, duplicate)))
+}
+
+async fn list_drop_copy_sessions(State(s): State<Arc<AppState>>) -> Json<Vec<fix::SessionStatus>> {
+    Json(s.drop_copy.lock().unwrap().sessions())
+}
+
+async fn replace_positions(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<Vec<positions::Position>>) -> ApiResult<Vec<positions::Position>> {
+    require_open(&s, &id)?;
+    audit(&s, &h, "positions.replace", &id, serde_json::json!({ "count": req.len() }));
+    let mut p = s.positions.lock().unwrap();
+    p.replace(&id, req);
+    Ok(Json(p.list(&id)))
+}
+
+fn margin_call_alert(s: &AppState, c: &margin_calls::MarginCall) {
+    use margin_calls::CallStatus::*;
+    let severity = match c.status {
+        Issued | Reminded => alerts::Severity::Warning, Escalated | Expired | LiquidationRecommended => alerts::Severity::Critical, Acknowledged | Met => alerts::Severity::Info,
+    };
+    let note = c.transitions.last().map(|t| t.note.clone()).unwrap_or_default();
+    let due = c.deadline_ms.map(|d| format!(", due {d}")).unwrap_or_default();
+    raise_alert(s, "margin_call", severity, Some(&c.account), None, format!("margin call {} {:?} {:?}{due}: {note}", c.id, c.level, c.status));
+}
+
+async fn closeout_simulate(State(s): State<Arc<AppState>>, Json(req): Json<MarginRequest>) -> ApiResult<CloseoutResponse> {
+    let t = Instant::now();
+    require_open(&s, &req.account)?;
+    let legs = marked(&s, req.positions.unwrap_or_default()).map_err(bad_request)?;
+    let closeout = s.liquidity.lock().unwrap().closeout(&legs);
+    Ok(Json(CloseoutResponse { account: req.account, liquidity_add_on: closeout.total_cost, closeout, elapsed_us: t.elapsed().as_micros() }))
+}
+
+async fn list_scenarios(State(s): State<Arc<AppState>>) -> Json<Vec<scenarios::Scenario>> {
+    Json(s.scenarios.lock().unwrap().list())
+}
+
+async fn get_scenario(State(s): State<Arc<AppState>>, Path(name): Path<String>) -> Result<Tagged<scenarios::Scenario>, (StatusCode, Json<Err>)> {
+    s.scenarios.lock().unwrap().get(&name).cloned().map(|scn| tagged(scn.version, scn)).ok_or_else(|| not_found("Scenario"))
+}
+
+/// A scenario that does not exist yet stands at version 0, so `If-Match: "0"` creates it only if nobody else has.
+async fn set_scenario(State(s): State<Arc<AppState>>, h: HeaderMap, Path(name): Path<String>, Json(req): Json<scenarios::Scenario>) -> Result<Tagged<scenarios::Scenario>, (StatusCode, Json<Err>)> {
+    let scn = {
+        let mut sc = s.scenarios.lock().unwrap();
+        check_if_match(&s, &h, sc.get(&name).map_or(0, |x| x.version))?;
+        sc.set(&name, req).map_err(bad_request)?
+    };
+    audit(&s, &h, "scenario.set", &name, serde_json::to_value(&scn).unwrap_or_default());
+    Ok(tagged(scn.version, scn))
+}
+
+async fn delete_scenario(State(s): State<Arc<AppState>>, h: HeaderMap, Path(name): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    {
+        let mut sc = s.scenarios.lock().unwrap();
+        check_if_match(&s, &h, sc.get(&name).ok_or_else(|| not_found("Scenario"))?.version)?;
+        sc.remove(&name);
+    }
+    audit(&s, &h, "scenario.delete", &name, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_stress_runs(State(s): State<Arc<AppState>>, Query(q): Query<stress_runs::RunQuery>) -> Json<Vec<stress_runs::StressRun>> {
+    Json(s.stress_runs.lock().unwrap().list(&q))
+}
+
+async fn get_stress_run(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<stress_runs::StressRun> {
+    s.stress_runs.lock().unwrap().get(&id).cloned().map(Json).ok_or_else(|| not_found("Stress run"))
+}
+
+async fn diff_stress_runs(State(s): State<Arc<AppState>>, Query(q): Query<DiffQuery>) -> ApiResult<stress_runs::RunDiff> {
+    s.stress_runs.lock().unwrap().diff(&q.base, &q.compare).map(Json).ok_or_else(|| not_found("Stress run"))
+}
+
+async fn get_suite(State(s): State<Arc<AppState>>) -> Json<suite::SuiteConfig> {
+    Json(s.suite.lock().unwrap().config())
+}
+
+async fn set_suite(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<suite::SuiteConfig>) -> ApiResult<suite::SuiteConfig> {
+    let cfg = s.suite.lock().unwrap().set_config(req).map_err(bad_request)?;
+    audit(&s, &h, "stress_suite.set", "stress-suite", serde_json::to_value(&cfg).unwrap_or_default());
+    Ok(Json(cfg))
+}
+
+async fn trigger_suite(State(s): State<Arc<AppState>>, h: HeaderMap) -> Json<suite::SuiteRun> {
+    let run = run_stress_suite(&s, suite::Trigger::Manual);
+    audit(&s, &h, "stress_suite.run", &run.id, serde_json::json!({ "results": run.results.len(), "new_breaches": run.new_breaches.len() }));
+    Json(run)
+}
+
+async fn list_suite_runs(State(s): State<Arc<AppState>>, Query(q): Query<LimitQuery>) -> Json<Vec<suite::SuiteRun>> {
+    Json(s.suite.lock().unwrap().runs(q.limit.unwrap_or(10)))
+}
+
+async fn get_suite_run(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<suite::SuiteRun> {
+    s.suite.lock().unwrap().get(&id).map(Json).ok_or_else(|| not_found("Stress suite run"))
+}
+
+/// Runs every configured scenario against every account that is not closed, marking positions at the last cached
+/// price, and alerts on breaches that the previous run did not report.
+fn run_stress_suite(s: &AppState, trigger: suite::Trigger) -> suite::SuiteRun {
+    let started = now_ms();
+    let id = uuid::Uuid::new_v4().to_string();
+    let names = s.suite.lock().unwrap().config().scenarios;
+    let (mut defs, mut missing) = (Vec::new(), Vec::new());
+    { let lib = s.scenarios.lock().unwrap(); for n in names { match lib.get(&n) { Some(d) => defs.push(d.clone()), None => missing.push(n) } } }
+    let accounts: Vec<String> = s.accounts.lock().unwrap().list().into_iter().filter(|a| a.status != accounts::AccountStatus::Closed).map(|a| a.id).collect();
+    let books: Vec<_> = accounts.iter().map(|a| (a.clone(), portfolio(s, Some(a), None))).collect();
+    let (mut results, mut stress_run_ids) = (Vec::new(), Vec::new());
+    for scn in &defs {
+        let mut per_account = Vec::new();
+        for (a, legs) in &books {
+            let out = s.scenarios.lock().unwrap().run(&scn.steps, scn.compounding, legs, |o| underlier(s, o));
+            let breaches = loss_breaches(s, Some(a), legs, out.pnl);
+            for b in &breaches { record_breach(s, stress_breach_kind(b), Some(a), None, &format!("{}: {b}", scn.name), format!("{b} under scenario '{}' in stress suite run {id}", scn.name)); }
+            let contributions = legs.iter().zip(&out.instruments).map(|((i, q, p), r)| stress_runs::Contribution { instrument: i.clone(), quantity: *q, price: *p, pnl: r.pnl }).collect();
+            results.push(suite::AccountResult { account: a.clone(), scenario: scn.name.clone(), pnl: out.pnl, breaches: breaches.clone() });
+            per_account.push(stress_runs::AccountStress { account: Some(a.clone()), pnl: out.pnl, breaches, contributions });
+        }
+        let run_id = uuid::Uuid::new_v4().to_string();
+        s.stress_runs.lock().unwrap().record(stress_runs::StressRun {
+            id: run_id.clone(), at_ms: now_ms(), mode: stress_runs::Mode::Composed, scenario: scn.name.clone(), scenario_version: Some(scn.version),
+            suite_run_id: Some(id.clone()), inputs: serde_json::to_value(scn).unwrap_or_default(), results: per_account,
+        });
+        stress_run_ids.push(run_id);
+    }
+    let run = suite::SuiteRun {
+        id, run_date: chrono::Utc::now().date_naive(), started_at_ms: started, finished_at_ms: now_ms(), trigger,
+        results, stress_run_ids, missing_scenarios: missing, new_breaches: Vec::new(),
+    };
+    let run = s.suite.lock().unwrap().record(run);
+    for b in &run.new_breaches { raise_alert(s, "stress_breach", alerts::Severity::Warning, Some(&b.account), None, format!("{} under scenario '{}' in stress suite run {}", b.breach, b.scenario, run.id)); }
+    tracing::info!(run = %run.id, accounts = accounts.len(), new_breaches = run.new_breaches.len(), "stress suite run complete");
+    run
+}
+
+async fn list_factors(State(s): State<Arc<AppState>>) -> Json<HashMap<String, scenarios::InstrumentFactors>> {
+    Json(s.scenarios.lock().unwrap().factors())
+}
+
+async fn set_factors(State(s): State<Arc<AppState>>, Path(instrument): Path<String>, Json(req): Json<scenarios::InstrumentFactors>) -> ApiResult<scenarios::InstrumentFactors> {
+    req.validate().map_err(bad_request)?;
+    s.scenarios.lock().unwrap().set_factors(&instrument, req.clone());
+    Ok(Json(req))
+}
+
+async fn get_classifications(State(s): State<Arc<AppState>>) -> Json<BTreeMap<String, concentration::Classification>> {
+    Json(s.classifications.lock().unwrap().clone())
+}
+
+/// Replaces the whole mapping; instruments left out fall back to their factor mapping's sector and have no issuer.
+async fn set_classifications(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<BTreeMap<String, concentration::Classification>>) -> ApiResult<BTreeMap<String, concentration::Classification>> {
+    let blank = |v: &Option<String>| v.as_ref().is_some_and(|v| v.trim().is_empty());
+    if let Some(i) = req.iter().find(|(i, c)| i.trim().is_empty() || blank(&c.issuer) || blank(&c.sector)).map(|(i, _)| i) { return Err(bad_request(format!("classification of '{i}' has an empty name"))); }
+    let previous = std::mem::replace(&mut *s.classifications.lock().unwrap(), req.clone());
+    audit(&s, &h, "classifications.upload", "classifications", serde_json::json!({ "instruments": req.len(), "previous_instruments": previous.len() }));
+    Ok(Json(req))
+}
+
+async fn account_concentration(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<concentration::Report> {
+    let account = require_account(&s, &id)?;
+    let holdings = holding_values(&s, &account);
+    let unpriced = { let md = s.marketdata.lock().unwrap(); s.positions.lock().unwrap().list(&id).into_iter().filter(|p| md.price(&p.instrument).is_none()).map(|p| p.instrument).collect() };
+    let classes = classify(&s, holdings.keys().cloned());
+    let unclassified = classes.iter().filter(|(_, c)| c.issuer.is_none() || c.sector.is_none()).map(|(i, _)| i.clone()).collect();
+    let limits = s.concentration_limits.lock().unwrap().clone();
+    let (buckets, gross_value, exempt) = concentration::report(&limits, &id, &holdings, bucket_of(&classes));
+    Ok(Json(concentration::Report { account: id, base_currency: account.base_currency, gross_value, exempt, buckets, unclassified, unpriced }))
+}
+
+async fn list_correlations(State(s): State<Arc<AppState>>) -> Json<CorrelationsResponse> {
+    let c = s.correlations.lock().unwrap();
+    Json(CorrelationsResponse { default: c.default, pairs: c.all() })
+}
+
+async fn set_correlations(State(s): State<Arc<AppState>>, h: HeaderMap, Json(pairs): Json<Vec<correlation::Pair>>) -> ApiResult<CorrelationsResponse> {
+    if let Some(p) = pairs.iter().find(|p| !correlation::valid(p.correlation)) { return Err(bad_request(format!("correlation for {}/{} must be within [-1, 1]", p.a, p.b))); }
+    let mut c = s.correlations.lock().unwrap();
+    for p in &pairs { c.set(&p.a, &p.b, p.correlation); }
+    let resp = CorrelationsResponse { default: c.default, pairs: c.all() };
+    drop(c);
+    audit(&s, &h, "correlations.set", "correlations", serde_json::to_value(&pairs).unwrap_or_default());
+    Ok(Json(resp))
+}
+
+async fn list_liquidity(State(s): State<Arc<AppState>>) -> Json<HashMap<String, liquidity::LiquidityParams>> {
+    Json(s.liquidity.lock().unwrap().all())
+}
+
+async fn set_liquidity(State(s): State<Arc<AppState>>, h: HeaderMap, Path(instrument): Path<String>, Json(req): Json<liquidity::LiquidityParams>) -> ApiResult<liquidity::LiquidityParams> {
+    if req.spread_bps < 0.0 || req.daily_vol < 0.0 || req.adv.is_some_and(|a| a <= 0.0) || req.depth.is_some_and(|d| d < 0.0) { return Err(bad_request("spread_bps, daily_vol and depth must be non-negative, adv positive")); }
+    s.liquidity.lock().unwrap().set(&instrument, req);
+    audit(&s, &h, "liquidity.set", &instrument, serde_json::to_value(req).unwrap_or_default());
+    Ok(Json(req))
+}
+
+async fn liquidity_score(State(s): State<Arc<AppState>>, Path(instrument): Path<String>) -> Json<liquidity::LiquidityScore> {
+    Json(s.liquidity.lock().unwrap().score(&instrument))
+}
+
+/// Accounts holding anything that would take longer than `max_days` to unwind, or that has no ADV to tell.
+async fn liquidation_horizons(State(s): State<Arc<AppState>>, Query(q): Query<liquidity::HorizonQuery>) -> ApiResult<liquidity::HorizonReport> {
+    let max_days = q.max_days.unwrap_or(s.max_liquidation_days);
+    if max_days.is_nan() || max_days <= 0.0 { return Err(bad_request("max_days must be positive")); }
+    let ids: Vec<String> = match &q.account { Some(a) => vec![require_account(&s, a)?.id], None => s.accounts.lock().unwrap().list().into_iter().map(|a| a.id).collect() };
+    let mut accounts: Vec<liquidity::AccountHorizon> = ids.iter().filter_map(|id| {
+        let mut unpriced = std::collections::BTreeSet::new();
+        let held: Vec<(String, f64)> = s.positions.lock().unwrap().list(id).into_iter().map(|p| (p.instrument, p.quantity)).collect();
+        let held = priced(&s, &held, &mut unpriced);
+        s.liquidity.lock().unwrap().horizons(id, &held, max_days)
+    }).filter(|h| h.positions.iter().any(|p| p.days_to_liquidate.is_none_or(|d| d > max_days))).collect();
+    accounts.sort_by(|a, b| b.max_days_to_liquidate.unwrap_or(f64::INFINITY).total_cmp(&a.max_days_to_liquidate.unwrap_or(f64::INFINITY)));
+    Ok(Json(liquidity::HorizonReport { max_days, accounts_checked: ids.len(), accounts }))
+}
+
+fn dispatch_liquidation(s: Arc<AppState>, e: liquidation::Liquidation) {
+    raise_alert(&s, "liquidation", alerts::Severity::Critical, Some(&e.account), None, format!("liquidation {} triggered at {:.2}% utilization, {} plan lines", e.id, e.trigger_utilization_pct, e.plan.len()));
+    for h in s.liquidations.lock().unwrap().hooks() {
+        let (s, e) = (s.clone(), e.clone());
+        tokio::spawn(async move {
+            let (ok, detail) = match &h.target {
+                liquidation::HookTarget::Webhook { url } => match s.http.post(url).json(&e).timeout(Duration::from_secs(5)).send().await {
+                    Ok(r) => (r.status().is_success(), format!("HTTP {}", r.status().as_u16())),
+                    Err(err) => (false, err.to_string()),
+                },
+            };
+            if !ok { tracing::warn!(hook = %h.id, liquidation = %e.id, "liquidation hook failed: {detail}"); }
+            s.liquidations.lock().unwrap().record_delivery(&e.id, liquidation::Delivery { hook_id: h.id.clone(), at_ms: now_ms(), ok, detail });
+        });
+    }
+}
+
+async fn list_liquidations(State(s): State<Arc<AppState>>, Query(q): Query<AccountQuery>) -> Json<Vec<liquidation::Liquidation>> {
+    Json(s.liquidations.lock().unwrap().list(q.account.as_deref()))
+}
+
+async fn list_hooks(State(s): State<Arc<AppState>>) -> Json<Vec<liquidation::Hook>> {
+    Json(s.liquidations.lock().unwrap().hooks())
+}
+
+async fn add_hook(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<liquidation::HookTarget>) -> Result<(StatusCode, Json<liquidation::Hook>), (StatusCode, Json<Err>)> {
+    let liquidation::HookTarget::Webhook { url } = &req;
+    if !(url.starts_with("http://") || url.starts_with("https://")) { return Err(bad_request("webhook url must be http(s)")); }
+    let hook = s.liquidations.lock().unwrap().add_hook(req, now_ms());
+    audit(&s, &h, "liquidation_hook.create", &hook.id, serde_json::to_value(&hook).unwrap_or_default());
+    Ok((StatusCode::CREATED, Json(hook)))
+}
+
+async fn delete_hook(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    if !s.liquidations.lock().unwrap().remove_hook(&id) { return Err(not_found("Hook")); }
+    audit(&s, &h, "liquidation_hook.delete", &id, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn escalate_margin_calls(s: &AppState) {
+    let changed = s.margin_calls.lock().unwrap().escalate(now_ms());
+    for c in changed {
+        if c.status == margin_calls::CallStatus::Expired {
+            let active = s.accounts.lock().unwrap().get(&c.account).is_some_and(|a| a.status == accounts::AccountStatus::Active);
+            if active {
+                let upd = accounts::UpdateAccount { entity: None, desk: None, base_currency: None, margin_model: None, default_limits: None, status: Some(accounts::AccountStatus::ReduceOnly), reason: Some(format!("margin call {} expired", c.id)) };
+                if s.accounts.lock().unwrap().update(&c.account, upd, now_ms()).is_ok() {
+                    s.audit.lock().unwrap().record("system", "account.status", &c.account, serde_json::json!({ "from": accounts::AccountStatus::Active, "to": accounts::AccountStatus::ReduceOnly, "reason": format!("margin call {} expired", c.id) }), now_ms());
+                }
+            }
+        }
+        margin_call_alert(s, &c);
+    }
+}
+
+async fn list_margin_calls(State(s): State<Arc<AppState>>, Query(q): Query<margin_calls::CallQuery>) -> Json<Vec<margin_calls::MarginCall>> {
+    Json(s.margin_calls.lock().unwrap().list(&q))
+}
+
+async fn get_margin_call(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<margin_calls::MarginCall> {
+    s.margin_calls.lock().unwrap().get(&id).cloned().map(Json).ok_or_else(|| not_found("Margin call"))
+}
+
+async fn ack_margin_call(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, body: Option<Json<margin_calls::AckRequest>>) -> ApiResult<margin_calls::MarginCall> {
+    require_role(&h, OPS_ROLE)?;
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let c = s.margin_calls.lock().unwrap().acknowledge(&id, &actor(&h), req.note, now_ms()).map_err(|e| match e {
+        margin_calls::AckError::NotFound => not_found("Margin call"),
+        margin_calls::AckError::Conflict(d) => (StatusCode::CONFLICT, Json(Err { error: "Margin call cannot be acknowledged".into(), details: Some(d) })),
+    })?;
+    audit(&s, &h, "margin_call.ack", &c.id, serde_json::json!({ "account": c.account, "level": c.level }));
+    margin_call_alert(&s, &c);
+    Ok(Json(c))
+}
+
+async fn get_margin_call_thresholds(State(s): State<Arc<AppState>>) -> Json<margin_calls::Thresholds> {
+    Json(s.margin_calls.lock().unwrap().policy.thresholds.clone())
+}
+
+/// The liquidation threshold is the one liquidations trigger at, so both move together.
+async fn set_margin_call_thresholds(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<margin_calls::Thresholds>) -> ApiResult<margin_calls::Thresholds> {
+    require_role(&h, ADMIN_ROLE)?;
+    req.validate().map_err(bad_request)?;
+    let previous = std::mem::replace(&mut s.margin_calls.lock().unwrap().policy.thresholds, req.clone());
+    s.liquidations.lock().unwrap().threshold_pct = req.liquidation_pct;
+    if previous != req { audit(&s, &h, "margin_call_thresholds.config", "margin_call_thresholds", serde_json::json!({ "previous": previous, "new": req })); }
+    Ok(Json(req))
+}
+
+async fn list_margin_cycles(State(s): State<Arc<AppState>>, Query(q): Query<LimitQuery>) -> Json<Vec<margin_cycles::Cycle>> {
+    Json(s.margin_cycles.lock().unwrap().list(q.limit.unwrap_or(10)))
+}
+
+async fn get_margin_cycle(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<margin_cycles::Cycle> {
+    s.margin_cycles.lock().unwrap().get(&id).map(Json).ok_or_else(|| not_found("Margin cycle"))
+}
+
+async fn get_cycle_config(State(s): State<Arc<AppState>>) -> Json<margin_cycles::CycleConfig> {
+    Json(s.margin_cycles.lock().unwrap().config())
+}
+
+async fn set_cycle_config(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<margin_cycles::CycleConfig>) -> ApiResult<margin_cycles::CycleConfig> {
+    let cfg = s.margin_cycles.lock().unwrap().set_config(req).map_err(bad_request)?;
+    audit(&s, &h, "margin_cycle.set", "margin-cycles", serde_json::to_value(&cfg).unwrap_or_default());
+    Ok(Json(cfg))
+}
+
+async fn trigger_margin_cycle(State(s): State<Arc<AppState>>, h: HeaderMap) -> Json<margin_cycles::Cycle> {
+    let c = run_margin_cycle(&s, suite::Trigger::Manual);
+    audit(&s, &h, "margin_cycle.run", &c.id, serde_json::json!({ "accounts_checked": c.accounts_checked, "calls_issued": c.calls_issued }));
+    Json(c)
+}
+
+/// Margins the account's booked positions as they stand and drives its margin call from the result.
+fn remargin(s: &Arc<AppState>, a: &accounts::Account, source: &str) -> (margin::MarginSnapshot, Option<margin_calls::MarginCall>) {
+    let t = Instant::now();
+    let (legs, _) = to_base(s, &a.base_currency, &portfolio(s, Some(&a.id), None));
+    let m = { let liq = s.liquidity.lock().unwrap(); margin::compute(a.margin_model, &legs, |i| liq.get(i).daily_vol) };
+    s.metrics.lock().unwrap().margin_calc(source, t.elapsed());
+    persist_margin(s, &a.id, source, m.initial_margin, m.maintenance_margin);
+    let (snap, _, call) = book_margin(s, &a.id, &legs, m.initial_margin, m.maintenance_margin);
+    (snap, call)
+}
+
+/// Re-margins every account that is not closed from its booked positions at the last cached prices, the same way
+/// a margin request would, and publishes the cycle summary as an alert.
+fn run_margin_cycle(s: &Arc<AppState>, trigger: suite::Trigger) -> margin_cycles::Cycle {
+    let started = now_ms();
+    let accounts: Vec<accounts::Account> = s.accounts.lock().unwrap().list().into_iter().filter(|a| a.status != accounts::AccountStatus::Closed).collect();
+    let mut results = Vec::new();
+    for a in &accounts {
+        let mut unpriced = std::collections::BTreeSet::new();
+        let held: Vec<(String, f64)> = s.positions.lock().unwrap().list(&a.id).into_iter().map(|p| (p.instrument, p.quantity)).collect();
+        { let md = s.marketdata.lock().unwrap(); unpriced.extend(held.iter().filter(|(i, _)| md.price(i).is_none()).map(|(i, _)| i.clone())); }
+        let (snap, call) = remargin(s, a, "margin_cycle");
+        results.push(margin_cycles::CycleAccount {
+            account: a.id.clone(), initial_margin: snap.initial_margin, funds: snap.funds, margin_utilization_pct: snap.margin_utilization_pct,
+            shortfall: (snap.initial_margin - snap.funds).max(0.0), call_id: call.as_ref().map(|c| c.id.clone()), call_status: call.map(|c| c.status), unpriced: unpriced.into_iter().collect(),
+        });
+    }
+    count(s, |st| st.total_margin_calcs += results.len() as u64);
+    let count = |st: margin_calls::CallStatus| results.iter().filter(|r| r.call_status == Some(st)).count();
+    let cycle = margin_cycles::Cycle {
+        id: uuid::Uuid::new_v4().to_string(), trigger, started_at_ms: started, finished_at_ms: now_ms(), accounts_checked: results.len(),
+        accounts_in_shortfall: results.iter().filter(|r| r.shortfall > 0.0).count(), calls_issued: count(margin_calls::CallStatus::Issued), calls_met: count(margin_calls::CallStatus::Met),
+        total_shortfall: results.iter().map(|r| r.shortfall).fold(0.0, |a, b| a + b), accounts: results,
+    };
+    s.margin_cycles.lock().unwrap().record(cycle.clone());
+    let severity = if cycle.calls_issued > 0 { alerts::Severity::Warning } else { alerts::Severity::Info };
+    raise_alert(s, "margin_cycle", severity, None, None, format!("margin cycle {}: {} accounts, {} in shortfall ({:.2}), {} calls issued, {} met", cycle.id, cycle.accounts_checked, cycle.accounts_in_shortfall, cycle.total_shortfall, cycle.calls_issued, cycle.calls_met));
+    cycle
+}
+
+async fn list_reservations(State(s): State<Arc<AppState>>, Query(q): Query<reservations::ReservationQuery>) -> Json<Vec<reservations::Reservation>> {
+    Json(s.reservations.lock().unwrap().list(&q))
+}
+
+async fn get_reservation(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<reservations::Reservation> {
+    s.reservations.lock().unwrap().get(&id).cloned().map(Json).ok_or_else(|| not_found("Reservation"))
+}
+
+/// Fill callback: books the filled notional against the daily limit and hands back the rest of the reservation.
+async fn commit_reservation(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<reservations::CommitRequest>) -> ApiResult<reservations::Reservation> {
+    let r = close_reservation(&s, &id, reservations::ReservationStatus::Committed, req.filled_notional)?;
+    let filled = r.filled_notional.unwrap_or(r.notional);
+    let ratio = if r.notional > 0.0 { filled / r.notional } else { 1.0 };
+    let session = { let mut d = s.daily.lock().unwrap(); d.record(&r.account, filled, &r.leg_notionals(ratio), now_ms()); d.session_of(now_ms()) };
+    s.pools.lock().unwrap().record(&r.account, filled, session);
+    audit(&s, &h, "reservation.commit", &id, serde_json::json!({ "account": r.account, "reserved": r.notional, "filled": filled }));
+    Ok(Json(r))
+}
+
+/// Cancel/reject callback.
+async fn release_reservation(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> ApiResult<reservations::Reservation> {
+    let r = close_reservation(&s, &id, reservations::ReservationStatus::Released, None)?;
+    audit(&s, &h, "reservation.release", &id, serde_json::json!({ "account": r.account, "reserved": r.notional }));
+    Ok(Json(r))
+}
+
+fn close_reservation(s: &AppState, id: &str, status: reservations::ReservationStatus, filled: Option<f64>) -> Result<reservations::Reservation, (StatusCode, Json<Err>)> {
+    let r = {
+        let mut rs = s.reservations.lock().unwrap();
+        if rs.get(id).is_none() { return Err(not_found("Reservation")); }
+        rs.close(id, status, filled, now_ms()).map_err(bad_request)?
+    };
+    release_held(s, &r);
+    Ok(r)
+}
+
+/// Hands a closed reservation's notional back to the daily book and the account's pool.
+fn release_held(s: &AppState, r: &reservations::Reservation) {
+    let session = { let mut d = s.daily.lock().unwrap(); d.release(&r.account, r.notional, &r.leg_notionals(1.0)); d.session_of(now_ms()) };
+    s.pools.lock().unwrap().release(&r.account, r.notional, session);
+}
+
+fn expire_reservations(s: &AppState) {
+    let expired = s.reservations.lock().unwrap().expire(now_ms());
+    for r in expired {
+        release_held(s, &r);
+        s.audit.lock().unwrap().record("system", "reservation.expire", &r.id, serde_json::json!({ "account": r.account, "reserved": r.notional }), now_ms());
+    }
+}
+
+async fn issue_override(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<overrides::IssueOverride>) -> Result<(StatusCode, Json<overrides::Override>), (StatusCode, Json<Err>)> {
+    require_role(&h, OVERRIDE_ROLE)?;
+    require_open(&s, &req.account)?;
+    let o = s.overrides.lock().unwrap().issue(req, &actor(&h), now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "override.issue", &o.token, serde_json::to_value(&o).unwrap_or_default());
+    raise_alert(&s, "override_issued", alerts::Severity::Warning, Some(&o.account), o.instrument.as_deref(), format!("override {} issued by {} ({:?}) for {}", o.token, o.issued_by, o.reason_code, o.limit.as_deref().unwrap_or("all limits")));
+    Ok((StatusCode::CREATED, Json(o)))
+}
+
+async fn list_overrides(State(s): State<Arc<AppState>>, Query(q): Query<overrides::OverrideQuery>) -> Json<Vec<overrides::Override>> {
+    Json(s.overrides.lock().unwrap().list(&q, now_ms()))
+}
+
+async fn revoke_override(State(s): State<Arc<AppState>>, h: HeaderMap, Path(token): Path<String>) -> ApiResult<overrides::Override> {
+    require_role(&h, OVERRIDE_ROLE)?;
+    let o = s.overrides.lock().unwrap().revoke(&token).ok_or_else(|| not_found("Override"))?;
+    audit(&s, &h, "override.revoke", &token, serde_json::json!({ "account": o.account }));
+    Ok(Json(o))
+}
+
+async fn list_breaches(State(s): State<Arc<AppState>>, Query(q): Query<breaches::BreachQuery>) -> Json<Vec<breaches::Breach>> {
+    Json(s.breaches.lock().unwrap().query(&q))
+}
+
+async fn get_breach(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<breaches::Breach> {
+    s.breaches.lock().unwrap().get(&id).cloned().map(Json).ok_or_else(|| not_found("Breach"))
+}
+
+async fn update_breach(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<breaches::UpdateBreach>) -> ApiResult<breaches::Breach> {
+    let details = serde_json::json!({ "owner": req.owner, "status": req.status, "note": req.note });
+    let b = {
+        let mut book = s.breaches.lock().unwrap();
+        if book.get(&id).is_none() { return Err(not_found("Breach")); }
+        book.update(&id, req, &actor(&h), now_ms()).map_err(bad_request)?
+    };
+    audit(&s, &h, "breach.update", &id, details);
+    Ok(Json(b))
+}
+
+async fn breach_aging(State(s): State<Arc<AppState>>) -> Json<breaches::AgingReport> {
+    Json(s.breaches.lock().unwrap().aging(now_ms()))
+}
+
+async fn list_alerts(State(s): State<Arc<AppState>>, h: HeaderMap, Query(q): Query<alerts::AlertQuery>) -> Json<Vec<alerts::Localized>> {
+    let locale = account_locale(&s, &h, q.account.as_deref());
+    Json(s.alerts.lock().unwrap().query(&q).into_iter().map(|a| a.localized(locale)).collect())
+}
+
+/// Streams alerts as they are raised. The query sets the initial filter; a text message holding a subscription
+/// replaces it. Alerts raised before the connection opened are not replayed.
+/// Alert texts are in the language chosen when the connection opens.
+async fn alert_stream(State(s): State<Arc<AppState>>, h: HeaderMap, Query(sub): Query<alerts::Subscription>, ws: axum::extract::ws::WebSocketUpgrade) -> axum::response::Response {
+    let rx = s.alert_stream.subscribe();
+    let locale = account_locale(&s, &h, sub.account.as_deref());
+    ws.on_upgrade(move |socket| stream_alerts(socket, rx, sub, locale))
+}
+
+async fn stream_alerts(mut socket: axum::extract::ws::WebSocket, mut rx: tokio::sync::broadcast::Receiver<alerts::Alert>, mut sub: alerts::Subscription, locale: locale::Locale) {
+    use axum::extract::ws::Message;
+    use tokio::sync::broadcast::error::RecvError;
+    let mut event = Some(alerts::StreamEvent::Subscribed(sub.clone()));
+    loop {
+        if let Some(e) = event.take() {
+            let text = serde_json::to_string(&e).unwrap_or_default();
+            if socket.send(Message::Text(text)).await.is_err() { return; }
+        }
+        tokio::select! {
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(t))) => event = Some(match serde_json::from_str::<alerts::Subscription>(&t) {
+                    Ok(next) => { sub = next; alerts::StreamEvent::Subscribed(sub.clone()) }
+                    Err(e) => alerts::StreamEvent::Error { message: format!("invalid subscription: {e}") },
+                }),
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+            a = rx.recv() => match a {
+                Ok(a) => event = sub.matches(&a).then(|| alerts::StreamEvent::Alert(a.localized(locale))),
+                Err(RecvError::Lagged(missed)) => event = Some(alerts::StreamEvent::Lagged { missed }),
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+async fn get_collateral(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<collateral::Valuation> {
+    require_account(&s, &id)?;
+    Ok(Json(collateral_valuation(&s, &id)))
+}
+
+async fn set_collateral(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(mut req): Json<Vec<collateral::CollateralItem>>) -> ApiResult<collateral::Valuation> {
+    require_open(&s, &id)?;
+    if req.iter().any(|c| c.quantity < 0.0 || c.price < 0.0) { return Err(bad_request("collateral quantity and price must be non-negative")); }
+    { let md = s.marketdata.lock().unwrap(); for c in req.iter_mut() { if let Some(p) = md.price(&c.asset) { c.price = p; } } }
+    audit(&s, &h, "collateral.replace", &id, serde_json::json!({ "count": req.len() }));
+    s.collateral.lock().unwrap().set_holdings(&id, req);
+    revalue_collateral(&s, &id);
+    Ok(Json(collateral_valuation(&s, &id)))
+}
+
+fn base_currency(s: &AppState, account: &str) -> String {
+    s.accounts.lock().unwrap().get(account).map_or_else(|| "USD".into(), |a| a.base_currency.clone())
+}
+
+fn collateral_valuation(s: &AppState, account: &str) -> collateral::Valuation {
+    let base = base_currency(s, account);
+    s.collateral.lock().unwrap().value(account, &base, now_ms(), |c| fx_rate(s, c, &base))
+}
+
+/// Ledger cash in every currency plus haircut collateral, converted into the account's base currency. Only accounts
+/// with neither fall back to the configured default funds.
+fn account_funds(s: &AppState, account: &str) -> f64 {
+    let base = base_currency(s, account);
+    let cash = s.ledger.lock().unwrap().cash_value(account, |c| fx_rate(s, c, &base)).map(|c| c.0);
+    let held = s.collateral.lock().unwrap().has_holdings(account);
+    let collateral = held.then(|| collateral_valuation(s, account).collateral_value);
+    if cash.is_none() && collateral.is_none() { s.default_funds } else { cash.unwrap_or(0.0) + collateral.unwrap_or(0.0) }
+}
+
+fn collateral_value(s: &AppState, account: &str) -> f64 {
+    if s.collateral.lock().unwrap().has_holdings(account) { collateral_valuation(s, account).collateral_value } else { 0.0 }
+}
+
+async fn get_equity(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<ledger::Equity> {
+    let a = require_account(&s, &id)?;
+    let collateral = collateral_value(&s, &id);
+    Ok(Json(s.ledger.lock().unwrap().equity(&id, &a.base_currency, collateral, |c| fx_rate(&s, c, &a.base_currency))))
+}
+
+async fn get_ledger(State(s): State<Arc<AppState>>, Path(id): Path<String>, Query(mut q): Query<ledger::JournalQuery>) -> ApiResult<Vec<ledger::Journal>> {
+    require_account(&s, &id)?;
+    q.account = Some(id);
+    Ok(Json(s.ledger.lock().unwrap().journal(&q)))
+}
+
+async fn ledger_daily(State(s): State<Arc<AppState>>, Path(id): Path<String>, Query(q): Query<RangeQuery>) -> ApiResult<Vec<ledger::DailyBalance>> {
+    require_account(&s, &id)?;
+    Ok(Json(s.ledger.lock().unwrap().daily(&id, q.from, q.to)))
+}
+
+async fn list_journal(State(s): State<Arc<AppState>>, Query(q): Query<ledger::JournalQuery>) -> Json<Vec<ledger::Journal>> {
+    Json(s.ledger.lock().unwrap().journal(&q))
+}
+
+async fn get_journal(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<ledger::Journal> {
+    s.ledger.lock().unwrap().get(&id).map(Json).ok_or_else(|| not_found("Journal"))
+}
+
+/// Cash may not leave an account while it backs margin in use: outflows are capped at the available margin of the
+/// last snapshot, net of held margin.
+fn check_cash_out(s: &AppState, account: &str, amount: f64) -> Result<(), (StatusCode, Json<Err>)> {
+    let held = s.reservations.lock().unwrap().held_margin(account);
+    let used = s.margins.lock().unwrap().get(account).map_or(0.0, |m| m.initial_margin);
+    let available = account_funds(s, account) - used - held;
+    if amount > available { return Err(bad_request(format!("cash out {amount:.2} exceeds available margin {available:.2}"))); }
+    Ok(())
+}
+
+async fn post_ledger(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<ledger::Posting>) -> Result<(StatusCode, Json<ledger::Journal>), (StatusCode, Json<Err>)> {
+    let a = require_account(&s, &id)?;
+    let rate = |c: &str| fx_rate(&s, c, &a.base_currency);
+    // Cash in a currency with no rate counts for nothing towards funds, so taking it out frees nothing either.
+    if req.kind == ledger::EntryKind::Withdrawal {
+        let ccy = req.currency.as_deref().unwrap_or(&a.base_currency).to_ascii_uppercase();
+        check_cash_out(&s, &id, req.amount * rate(&ccy).unwrap_or(0.0))?;
+    }
+    let (j, loss) = {
+        let mut l = s.ledger.lock().unwrap();
+        let j = l.post(&id, &a.base_currency, req, now_ms()).map_err(bad_request)?;
+        let loss = -l.realized_on(&id, j.date, rate);
+        (j, loss)
+    };
+    audit(&s, &h, "ledger.post", &id, serde_json::to_value(&j).unwrap_or_default());
+    revalue_collateral(&s, &id);
+    let tripped = s.kill_switches.lock().unwrap().check_loss(&id, loss);
+    if let Some(r) = tripped { trip_kill_switch(&s, &id, r); }
+    Ok((StatusCode::CREATED, Json(j)))
+}
+
+async fn reverse_journal(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<ledger::Reverse>) -> Result<(StatusCode, Json<ledger::Journal>), (StatusCode, Json<Err>)> {
+    let orig = s.ledger.lock().unwrap().get(&id).ok_or_else(|| not_found("Journal"))?;
+    let reversible = orig.reversed_by.is_none() && orig.kind != ledger::EntryKind::Reversal;
+    if reversible && orig.cash_effect() > 0.0 {
+        let base = base_currency(&s, &orig.account);
+        check_cash_out(&s, &orig.account, orig.cash_effect() * fx_rate(&s, &orig.currency, &base).unwrap_or(0.0))?;
+    }
+    let j = s.ledger.lock().unwrap().reverse(&id, req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "ledger.reverse", &orig.account, serde_json::json!({ "journal": id, "reversal": j.id, "note": j.note }));
+    revalue_collateral(&s, &orig.account);
+    Ok((StatusCode::CREATED, Json(j)))
+}
+
+/// Re-checks the last margin requirement against current funds and alerts on a fresh breach.
+fn revalue_collateral(s: &AppState, account: &str) {
+    let funds = account_funds(s, account);
+    let breach = s.margins.lock().unwrap().get_mut(account).and_then(|m| m.refund(funds, now_ms()).then(|| m.clone()));
+    if let Some(m) = breach {
+        let msg = format!("collateral value {:.2} fell below initial margin {:.2}", m.funds, m.initial_margin);
+        record_breach(s, breaches::BreachKind::Margin, Some(account), None, "collateral below initial margin", msg.clone());
+        raise_alert(s, "collateral_breach", alerts::Severity::Critical, Some(account), None, msg);
+    }
+}
+
+async fn margin_status(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<margin::MarginSnapshot> {
+    require_account(&s, &id)?;
+    margin_snapshot(&s, &id).map(Json).ok_or_else(|| not_found("Margin snapshot"))
+}
+
+/// The account's last margin snapshot net of margin held for open reservations.
+fn margin_snapshot(s: &AppState, id: &str) -> Option<margin::MarginSnapshot> {
+    let held = s.reservations.lock().unwrap().held_margin(id);
+    let mut m = s.margins.lock().unwrap().get(id).cloned()?;
+    m.hold(held);
+    Some(m)
+}
+
+async fn list_quotes(State(s): State<Arc<AppState>>) -> Json<HashMap<String, marketdata::Quote>> {
+    Json(s.marketdata.lock().unwrap().all())
+}
+
+/// Every currency with a USD rate, from the feed's `CCYUSD` and `USDCCY` quotes or set by hand, at the rate in use.
+async fn list_fx_rates(State(s): State<Arc<AppState>>) -> Json<Vec<fx_rates::Rate>> {
+    let mut ccys: std::collections::BTreeSet<String> = s.marketdata.lock().unwrap().all().into_keys().filter(|k| k.len() == 6 && k.chars().all(|c| c.is_ascii_uppercase()))
+        .filter_map(|k| k.strip_prefix("USD").or_else(|| k.strip_suffix("USD")).map(Into::into)).filter(|c| c != "USD").collect();
+    ccys.extend(s.fx_rates.lock().unwrap().list().into_iter().map(|r| r.currency));
+    Json(ccys.iter().filter_map(|c| usd_rate(&s, c)).collect())
+}
+
+/// Rates that should override the feed until it next quotes the currency, or stand in for one it does not quote.
+async fn set_fx_rates(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<Vec<fx_rates::RateInput>>) -> ApiResult<Vec<fx_rates::Rate>> {
+    require_role(&h, ADMIN_ROLE)?;
+    let set = s.fx_rates.lock().unwrap().set(req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "fx_rates.set", "fx_rates", serde_json::json!({ "rates": set }));
+    Ok(Json(set))
+}
+
+async fn delete_fx_rate(State(s): State<Arc<AppState>>, h: HeaderMap, Path(currency): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    require_role(&h, ADMIN_ROLE)?;
+    let r = s.fx_rates.lock().unwrap().remove(&currency).ok_or_else(|| not_found("FX rate"))?;
+    audit(&s, &h, "fx_rates.delete", &r.currency, serde_json::json!({ "previous": r }));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn load_history(State(s): State<Arc<AppState>>, Json(rows): Json<Vec<history::Close>>) -> ApiResult<serde_json::Value> {
+    if let Some(r) = rows.iter().find(|r| !(r.close.is_finite() && r.close > 0.0)) { return Err(bad_request(format!("invalid close for {} on {}", r.instrument, r.date))); }
+    let loaded = s.history.lock().unwrap().load(rows);
+    Ok(Json(serde_json::json!({ "loaded": loaded })))
+}
+
+/// Request bodies of up to this many bytes are read for backfill batches; other endpoints keep axum's 2 MiB.
+const BACKFILL_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// The job a batch belongs to, once the batch is well formed and of the job's kind.
+fn backfill_job<T>(s: &AppState, req: &backfill::Batch<T>, kind: backfill::Kind) -> Result<String, (StatusCode, Json<Err>)> {
+    if req.rows.is_empty() { return Err(bad_request("rows must not be empty")); }
+    if req.rows.len() > backfill::MAX_BATCH_ROWS { return Err(bad_request(format!("at most {} rows per batch", backfill::MAX_BATCH_ROWS))); }
+    if let Some(n) = req.batches { if req.batch >= n { return Err(bad_request(format!("batch {} is outside 0..{n}", req.batch))); } }
+    let job_id = req.job_id.clone().unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    if job_id.trim().is_empty() { return Err(bad_request("job_id must not be blank")); }
+    s.backfills.lock().unwrap().check_kind(&job_id, kind).map_err(bad_request)?;
+    Ok(job_id)
+}
+
+/// Loads daily closes into the price history historical VaR and replays read. A close already held for the same
+/// instrument and date is overwritten, so re-running a job is safe; rows that fail validation are skipped and listed.
+async fn backfill_prices(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<backfill::Batch<history::Close>>) -> ApiResult<backfill::Job> {
+    let job_id = backfill_job(&s, &req, backfill::Kind::Prices)?;
+    let today = chrono::Utc::now().date_naive();
+    let mut o = backfill::Outcome::new(req.rows.len());
+    {
+        let mut hist = s.history.lock().unwrap();
+        for (i, r) in req.rows.into_iter().enumerate() {
+            if r.instrument.trim().is_empty() { o.reject(i, "instrument is required".into()); continue; }
+            if !(r.close.is_finite() && r.close > 0.0) { o.reject(i, format!("invalid close for {} on {}", r.instrument, r.date)); continue; }
+            if r.date > today { o.reject(i, format!("{} is in the future", r.date)); continue; }
+            let changed = hist.close(&r.instrument, r.date) != Some(r.close);
+            o.accept(r.date, changed);
+            if changed { hist.load([r]); }
+        }
+    }
+    let job = s.backfills.lock().unwrap().record(&job_id, backfill::Kind::Prices, req.batch, req.batches, o, now_ms());
+    audit(&s, &h, "backfill.prices", &job_id, serde_json::json!({ "batch": req.batch, "status": job.status, "rows": job.counts.rows, "loaded": job.counts.loaded, "rejected": job.counts.rejected }));
+    Ok(Json(job))
+}
+
+/// Loads executions from before the engine ran, keyed by `trade_id` so a trade sent twice is kept once. They are
+/// history for backtests and leave current positions alone; each must belong to a known account.
+async fn backfill_trades(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<backfill::Batch<backfill::HistoricalTrade>>) -> ApiResult<backfill::Job> {
+    let job_id = backfill_job(&s, &req, backfill::Kind::Trades)?;
+    let now = chrono::Utc::now();
+    let mut o = backfill::Outcome::new(req.rows.len());
+    {
+        let (accounts, mut trades) = (s.accounts.lock().unwrap(), s.trade_history.lock().unwrap());
+        for (i, t) in req.rows.into_iter().enumerate() {
+            if let Err(e) = backfill::validate_trade(&t, now) { o.reject(i, format!("{}: {e}", t.trade_id)); continue; }
+            if accounts.get(&t.account).is_none() { o.reject(i, format!("{}: unknown account {}", t.trade_id, t.account)); continue; }
+            let day = t.executed_at.date_naive();
+            o.accept(day, trades.upsert(t));
+        }
+    }
+    let job = s.backfills.lock().unwrap().record(&job_id, backfill::Kind::Trades, req.batch, req.batches, o, now_ms());
+    audit(&s, &h, "backfill.trades", &job_id, serde_json::json!({ "batch": req.batch, "status": job.status, "rows": job.counts.rows, "loaded": job.counts.loaded, "rejected": job.counts.rejected }));
+    Ok(Json(job))
+}
+
+async fn list_backfills(State(s): State<Arc<AppState>>, Query(q): Query<backfill::JobQuery>) -> Json<Vec<backfill::Job>> {
+    Json(s.backfills.lock().unwrap().list(&q))
+}
+
+async fn get_backfill(State(s): State<Arc<AppState>>, Path(job_id): Path<String>) -> ApiResult<backfill::Job> {
+    s.backfills.lock().unwrap().get(&job_id).map(Json).ok_or_else(|| not_found("Backfill"))
+}
+
+async fn list_historical_trades(State(s): State<Arc<AppState>>, Query(q): Query<backfill::TradeQuery>) -> Json<Vec<backfill::HistoricalTrade>> {
+    Json(s.trade_history.lock().unwrap().query(&q))
+}
+
+async fn get_history(State(s): State<Arc<AppState>>, Path(instrument): Path<String>, Query(q): Query<RangeQuery>) -> Json<Vec<history::Close>> {
+    Json(s.history.lock().unwrap().series(&instrument, q.from, q.to).into_iter().map(|(date, close)| history::Close { instrument: instrument.clone(), date, close }).collect())
+}
+
+async fn get_quote(State(s): State<Arc<AppState>>, Path(instrument): Path<String>) -> ApiResult<marketdata::Quote> {
+    s.marketdata.lock().unwrap().quote(&instrument).map(Json).ok_or_else(|| not_found("Quote"))
+}
+
+async fn ingest_ticks(State(s): State<Arc<AppState>>, Json(ticks): Json<Vec<marketdata::Tick>>) -> Json<TickAck> {
+    Json(apply_ticks(&s, ticks))
+}
+
+/// Feeds ticks to the quote cache, circuit breakers, erroneous-trade bands and collateral in order, then revalues
+/// the accounts whose collateral moved.
+fn apply_ticks(s: &AppState, ticks: Vec<marketdata::Tick>) -> TickAck {
+    let now = now_ms();
+    let (mut accepted, mut rejected, mut moved) = (0, 0, std::collections::BTreeSet::new());
+    for t in ticks {
+        let valid = t.price.is_finite() && t.price > 0.0;
+        if !valid || !s.marketdata.lock().unwrap().apply(&t, now) { rejected += 1; continue; }
+        accepted += 1;
+        s.circuit_breakers.lock().unwrap().observe(&t.instrument, t.price, t.ts_ms.unwrap_or(now));
+        s.erroneous.lock().unwrap().observe(&t.instrument, t.price);
+        moved.extend(s.collateral.lock().unwrap().reprice(&t.instrument, t.price));
+    }
+    for a in &moved { revalue_collateral(s, a); }
+    TickAck { accepted, rejected, revalued_accounts: moved.into_iter().collect() }
+}
+
+/// Generates a synthetic price path ending now. Ingested paths move circuit breakers and collateral exactly as a
+/// live feed would; in a sandbox they touch only the sandbox.
+async fn generate_ticks(State(s): State<Arc<AppState>>, h: HeaderMap, Json(spec): Json<synthetic::GeneratorSpec>) -> ApiResult<SyntheticResponse> {
+    let now = now_ms();
+    let mut run = {
+        let md = s.marketdata.lock().unwrap();
+        synthetic::generate(&spec, spec.seed.unwrap_or(now), now, |i| md.price(i)).map_err(bad_request)?
+    };
+    let ticks = std::mem::take(&mut run.ticks);
+    if !spec.ingest { return Ok(Json(SyntheticResponse { run, ack: None, ticks })); }
+    let ack = apply_ticks(&s, ticks);
+    audit(&s, &h, "marketdata.synthetic", &run.seed.to_string(), serde_json::json!({ "steps": run.steps, "step_ms": run.step_ms, "instruments": run.paths.iter().map(|p| &p.instrument).collect::<Vec<_>>(), "accepted": ack.accepted }));
+    Ok(Json(SyntheticResponse { run, ack: Some(ack), ticks: Vec::new() }))
+}
+
+async fn list_haircuts(State(s): State<Arc<AppState>>) -> Json<Vec<collateral::HaircutSchedule>> {
+    Json(s.collateral.lock().unwrap().schedules())
+}
+
+async fn get_haircuts(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<collateral::HaircutSchedule> {
+    s.collateral.lock().unwrap().schedule(&id).cloned().map(Json).ok_or_else(|| not_found("Haircut schedule"))
+}
+
+async fn create_haircuts(State(s): State<Arc<AppState>>, h: HeaderMap, Json(req): Json<collateral::ScheduleInput>) -> Result<(StatusCode, Json<collateral::HaircutSchedule>), (StatusCode, Json<Err>)> {
+    let sched = s.collateral.lock().unwrap().create_schedule(req, now_ms()).map_err(bad_request)?;
+    audit(&s, &h, "haircut_schedule.create", &sched.id, serde_json::to_value(&sched).unwrap_or_default());
+    Ok((StatusCode::CREATED, Json(sched)))
+}
+
+async fn update_haircuts(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>, Json(req): Json<collateral::ScheduleInput>) -> ApiResult<collateral::HaircutSchedule> {
+    let sched = s.collateral.lock().unwrap().update_schedule(&id, req, now_ms()).map_err(bad_request)?.ok_or_else(|| not_found("Haircut schedule"))?;
+    audit(&s, &h, "haircut_schedule.update", &id, serde_json::to_value(&sched).unwrap_or_default());
+    Ok(Json(sched))
+}
+
+async fn delete_haircuts(State(s): State<Arc<AppState>>, h: HeaderMap, Path(id): Path<String>) -> Result<StatusCode, (StatusCode, Json<Err>)> {
+    if !s.collateral.lock().unwrap().delete_schedule(&id) { return Err(not_found("Haircut schedule")); }
+    audit(&s, &h, "haircut_schedule.delete", &id, serde_json::Value::Null);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn audit_log(State(s): State<Arc<AppState>>, Query(q): Query<audit::AuditQuery>) -> Json<Vec<audit::AuditEntry>> {
+    Json(s.audit.lock().unwrap().query(&q))
+}
+
+/// Pre-trade and basket decisions, newest first: the full history from the store when one is configured, otherwise
+/// the last `RISK_CHECK_HISTORY` held in memory.
+async fn check_history(State(s): State<Arc<AppState>>, Query(q): Query<checks::CheckQuery>) -> ApiResult<checks::CheckPage> {
+    if q.from.zip(q.to).is_some_and(|(f, t)| f > t) { return Err(bad_request("from must not be after to")); }
+    match s.store.clone() {
+        Some(store) => store.checks(&q).await.map(Json).map_err(unavailable),
+        None => Ok(Json(s.checks.lock().unwrap().query(&q))),
+    }
+}
+
+async fn get_check(State(s): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<checks::CheckRecord> {
+    let recent = s.checks.lock().unwrap().get(&id);
+    let found = match (recent, s.store.clone()) {
+        (Some(c), _) => Some(c),
+        (None, Some(store)) => store.check(&id).await.map_err(unavailable)?,
+        (None, None) => None,
+    };
+    found.map(Json).ok_or_else(|| not_found("Check"))
+}
+
+async fn stats(State(s): State<Arc<AppState>>) -> Json<StatsResponse> {
+    let st = s.stats.lock().unwrap();
+    Json(StatsResponse { total_checks: st.total_checks, total_margin_calcs: st.total_margin_calcs, total_alerts: st.total_alerts, trades_blocked: st.trades_blocked, block_rate_pct: st.block_rate_pct(), by_tag: s.tag_stats.lock().unwrap().clone(), by_reason_code: s.reason_counts.lock().unwrap().clone(), wash_trades: s.wash_trades.lock().unwrap().stats })
+}
+
+/// 503 until the warm start has loaded every snapshot section, so traffic is held off a cold engine.
+async fn readyz(State(s): State<Arc<AppState>>) -> (StatusCode, Json<warmup::Warmup>) {
+    let w = s.warmup.lock().unwrap().clone();
+    (if w.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE }, Json(w))
+}
+
+async fn prometheus_metrics(State(s): State<Arc<AppState>>) -> ([(axum::http::header::HeaderName, &'static str); 1], String) {
+    let st = *s.stats.lock().unwrap();
+    let gauges = metrics::Gauges { block_rate_pct: st.block_rate_pct(), alerts_total: st.total_alerts, uptime_secs: s.start_time.elapsed().as_secs() };
+    ([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], s.metrics.lock().unwrap().render(&gauges))
 }
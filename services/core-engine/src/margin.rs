@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+/// Last computed requirement per account, kept so collateral moves can be checked between margin cycles.
+/// `initial_margin` is used by filled positions; `held_margin` is held for open orders and only reduces availability,
+/// while utilization and breaches stay on positions alone.
+#[derive(Serialize, Clone)]
+pub struct MarginSnapshot { pub initial_margin: f64, pub maintenance_margin: f64, pub held_margin: f64, pub funds: f64, pub available_margin: f64, pub margin_utilization_pct: f64, pub at_ms: u64, pub collateral_breach: bool }
+
+impl MarginSnapshot {
+    pub fn new(initial_margin: f64, maintenance_margin: f64, held_margin: f64, funds: f64, at_ms: u64) -> Self {
+        let mut s = Self { initial_margin, maintenance_margin, held_margin, funds, available_margin: 0.0, margin_utilization_pct: 0.0, at_ms, collateral_breach: false };
+        s.refund(funds, at_ms);
+        s
+    }
+
+    pub fn hold(&mut self, held_margin: f64) { self.held_margin = held_margin; self.available_margin = self.funds - self.initial_margin - held_margin; }
+
+    /// Recomputes availability against new funds; returns true when this newly creates a breach.
+    pub fn refund(&mut self, funds: f64, at_ms: u64) -> bool {
+        self.funds = funds;
+        self.available_margin = funds - self.initial_margin - self.held_margin;
+        self.margin_utilization_pct = if funds > 0.0 { self.initial_margin / funds * 100.0 } else if self.initial_margin > 0.0 { f64::MAX } else { 0.0 };
+        self.at_ms = at_ms;
+        let was = self.collateral_breach;
+        self.collateral_breach = self.initial_margin > 0.0 && funds < self.initial_margin;
+        self.collateral_breach && !was
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginModel { #[default] Simple, SpanStyle, VarBased, Portfolio }
+
+impl MarginModel {
+    pub const ALL: [MarginModel; 4] = [MarginModel::Simple, MarginModel::SpanStyle, MarginModel::VarBased, MarginModel::Portfolio];
+
+    pub fn version(self) -> &'static str {
+        match self { MarginModel::Simple => "1.0", MarginModel::SpanStyle => "1.0", MarginModel::VarBased => "1.0", MarginModel::Portfolio => "1.0" }
+    }
+}
+
+/// How an on-demand margin request is computed: `flat` runs the account's margin model, `span` the SPAN
+/// methodology configured per product group.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Methodology { #[default] Flat, Span }
+
+pub struct MarginResult { pub initial_margin: f64, pub maintenance_margin: f64 }
+
+const Z_99: f64 = 2.326;
+const MAINTENANCE_RATIO: f64 = 0.5;
+
+/// Runs `positions` (instrument, signed quantity, price) through `model`; `vol` gives each instrument's daily volatility.
+pub fn compute(model: MarginModel, positions: &[(String, f64, f64)], vol: impl Fn(&str) -> f64) -> MarginResult {
+    let initial = match model {
+        // Flat 10% of summed notional, as the engine has always charged.
+        MarginModel::Simple => positions.iter().map(|(_, q, p)| q * p).sum::<f64>() * 0.10,
+        // Each position charged a three-sigma scanning move; cross-product credits are left to the full SPAN methodology.
+        MarginModel::SpanStyle => positions.iter().map(|(i, q, p)| (q * p).abs() * 3.0 * vol(i)).sum(),
+        // Two-day 99% parametric VaR, positions treated as independent.
+        MarginModel::VarBased => Z_99 * 2f64.sqrt() * positions.iter().map(|(i, q, p)| (q * p * vol(i)).powi(2)).sum::<f64>().sqrt(),
+        // Worst loss over uniform ±15% moves, floored at 0.5% of gross so hedged books still post something.
+        MarginModel::Portfolio => {
+            let net: f64 = positions.iter().map(|(_, q, p)| q * p).sum();
+            let gross: f64 = positions.iter().map(|(_, q, p)| (q * p).abs()).sum();
+            [-0.15, -0.10, -0.05, 0.05, 0.10, 0.15].iter().map(|s| -net * s).fold(0.0, f64::max).max(gross * 0.005)
+        }
+    };
+    let maintenance = if model == MarginModel::Simple { positions.iter().map(|(_, q, p)| q * p).sum::<f64>() * 0.05 } else { initial * MAINTENANCE_RATIO };
+    MarginResult { initial_margin: initial, maintenance_margin: maintenance }
+}
+
+#[derive(Serialize)]
+pub struct PositionMargin { pub instrument: String, pub quantity: f64, pub price: f64, pub notional: f64, pub standalone: f64, pub contribution: f64 }
+
+/// Negative amounts are credits, positive ones add-ons.
+#[derive(Serialize)]
+pub struct Adjustment { pub kind: &'static str, pub amount: f64 }
+
+/// Itemized initial margin: `standalone_total` plus every offset and add-on equals `initial_margin`, and so do the
+/// per-position contributions.
+#[derive(Serialize)]
+pub struct Breakdown { pub positions: Vec<PositionMargin>, pub standalone_total: f64, pub offsets: Vec<Adjustment>, pub add_ons: Vec<Adjustment>, pub initial_margin: f64 }
+
+/// Same requirement as [`compute`], attributed to positions. Standalone is what a position would post alone;
+/// contributions split the actual total (VaR by Euler allocation, the portfolio model by its worst shock).
+pub fn breakdown(model: MarginModel, positions: &[(String, f64, f64)], vol: impl Fn(&str) -> f64) -> Breakdown {
+    let total = compute(model, positions, &vol).initial_margin;
+    let notional: Vec<f64> = positions.iter().map(|(_, q, p)| q * p).collect();
+    let net: f64 = notional.iter().sum();
+    let gross: f64 = notional.iter().map(|n| n.abs()).sum();
+    let k = Z_99 * 2f64.sqrt();
+    let var_sum: f64 = positions.iter().zip(&notional).map(|((i, _, _), n)| (n * vol(i)).powi(2)).sum();
+    let scenario_loss = 0.15 * net.abs();
+    let floored = scenario_loss < gross * 0.005;
+    let (standalone, contribution): (Vec<f64>, Vec<f64>) = positions.iter().zip(&notional).map(|((i, _, _), n)| match model {
+        MarginModel::Simple => (n.abs() * 0.10, n * 0.10),
+        MarginModel::SpanStyle => { let m = n.abs() * 3.0 * vol(i); (m, m) }
+        MarginModel::VarBased => (k * (n * vol(i)).abs(), if var_sum > 0.0 { k * (n * vol(i)).powi(2) / var_sum.sqrt() } else { 0.0 }),
+        MarginModel::Portfolio => (n.abs() * 0.15, if floored { n.abs() * 0.005 } else { n * 0.15 * net.signum() }),
+    }).unzip();
+    let standalone_total: f64 = standalone.iter().sum();
+    let (mut offsets, mut add_ons) = (Vec::new(), Vec::new());
+    match model {
+        MarginModel::Simple => offsets.push(Adjustment { kind: "long_short_netting", amount: total - standalone_total }),
+        MarginModel::SpanStyle => {}
+        MarginModel::VarBased => offsets.push(Adjustment { kind: "diversification", amount: total - standalone_total }),
+        MarginModel::Portfolio => {
+            offsets.push(Adjustment { kind: "hedge_netting", amount: scenario_loss - standalone_total });
+            if floored { add_ons.push(Adjustment { kind: "gross_floor", amount: total - scenario_loss }); }
+        }
+    }
+    offsets.retain(|o| o.amount.abs() > 1e-9);
+    let positions = positions.iter().zip(notional).zip(standalone.into_iter().zip(contribution))
+        .map(|(((instrument, quantity, price), notional), (standalone, contribution))| PositionMargin { instrument: instrument.clone(), quantity: *quantity, price: *price, notional, standalone, contribution })
+        .collect();
+    Breakdown { positions, standalone_total, offsets, add_ons, initial_margin: total }
+}
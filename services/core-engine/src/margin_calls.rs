@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CallStatus { Issued, Acknowledged, Reminded, Escalated, Expired, LiquidationRecommended, Met }
+
+impl CallStatus {
+    pub fn is_open(self) -> bool { self != CallStatus::Met }
+}
+
+/// How far utilization has gone. A `Warning` only gives notice and carries no deadline; a `Call` must be met by
+/// its deadline; `Liquidation` is where the account's positions start being closed out.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Level { Warning, Call, Liquidation }
+
+/// Margin utilization percentages at which each level starts.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Thresholds { pub warning_pct: f64, pub call_pct: f64, pub liquidation_pct: f64 }
+
+impl Thresholds {
+    pub fn validate(&self) -> Result<(), String> {
+        if ![self.warning_pct, self.call_pct, self.liquidation_pct].iter().all(|p| p.is_finite() && *p > 0.0) { return Err("thresholds must be positive".into()); }
+        if !(self.warning_pct < self.call_pct && self.call_pct <= self.liquidation_pct) { return Err("thresholds must rise from warning_pct to call_pct to liquidation_pct".into()); }
+        Ok(())
+    }
+
+    pub fn level(&self, utilization_pct: f64) -> Option<Level> {
+        if utilization_pct >= self.liquidation_pct { Some(Level::Liquidation) } else if utilization_pct >= self.call_pct { Some(Level::Call) } else if utilization_pct >= self.warning_pct { Some(Level::Warning) } else { None }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Transition { pub status: CallStatus, pub at_ms: u64, pub note: String }
+
+#[derive(Serialize, Clone)]
+pub struct MarginCall {
+    pub id: String, pub account: String, pub level: Level, pub shortfall: f64, pub utilization_pct: f64, pub status: CallStatus, pub issued_at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub deadline_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub acknowledged_by: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub acknowledged_at_ms: Option<u64>,
+    pub transitions: Vec<Transition>,
+}
+
+#[derive(Deserialize)]
+pub struct CallQuery { pub account: Option<String>, pub open: Option<bool> }
+
+#[derive(Deserialize, Default)]
+pub struct AckRequest { pub note: Option<String> }
+
+pub struct CallPolicy { pub thresholds: Thresholds, pub grace_ms: u64, pub reminder_fraction: f64, pub liquidation_after_ms: u64 }
+
+pub enum AckError { NotFound, Conflict(String) }
+
+pub struct MarginCalls { pub policy: CallPolicy, calls: Vec<MarginCall> }
+
+impl MarginCall {
+    fn transition(&mut self, status: CallStatus, now_ms: u64, note: String) {
+        self.status = status;
+        self.transitions.push(Transition { status, at_ms: now_ms, note });
+    }
+}
+
+impl MarginCalls {
+    pub fn new(policy: CallPolicy) -> Self { Self { policy, calls: Vec::new() } }
+
+    pub fn get(&self, id: &str) -> Option<&MarginCall> { self.calls.iter().find(|c| c.id == id) }
+
+    pub fn list(&self, q: &CallQuery) -> Vec<MarginCall> {
+        self.calls.iter().rev().filter(|c| q.account.as_ref().is_none_or(|a| &c.account == a) && q.open.is_none_or(|o| c.status.is_open() == o)).cloned().collect()
+    }
+
+    /// Issues a call when utilization crosses the warning threshold, escalates the open call as it crosses the
+    /// higher ones and marks it met once utilization drops back below the warning threshold. A warning escalated
+    /// to a call gets its deadline then. Returns the call only when its status changed.
+    pub fn observe(&mut self, account: &str, utilization_pct: f64, shortfall: f64, now_ms: u64) -> Option<MarginCall> {
+        let level = self.policy.thresholds.level(utilization_pct);
+        let grace_ms = self.policy.grace_ms;
+        let open = self.calls.iter_mut().find(|c| c.account == account && c.status.is_open());
+        match (open, level) {
+            (Some(c), None) => { c.transition(CallStatus::Met, now_ms, format!("utilization back to {utilization_pct:.2}%")); Some(c.clone()) }
+            (Some(c), Some(l)) => {
+                c.shortfall = shortfall;
+                c.utilization_pct = utilization_pct;
+                if l <= c.level { return None; }
+                c.level = l;
+                c.deadline_ms = c.deadline_ms.or(Some(now_ms + grace_ms));
+                c.transition(CallStatus::Escalated, now_ms, format!("utilization {utilization_pct:.2}% crossed the {l:?} threshold, shortfall {shortfall:.2}"));
+                Some(c.clone())
+            }
+            (None, Some(l)) => {
+                let mut c = MarginCall {
+                    id: uuid::Uuid::new_v4().to_string(), account: account.into(), level: l, shortfall, utilization_pct, status: CallStatus::Issued, issued_at_ms: now_ms,
+                    deadline_ms: (l > Level::Warning).then_some(now_ms + grace_ms), acknowledged_by: None, acknowledged_at_ms: None, transitions: Vec::new(),
+                };
+                c.transition(CallStatus::Issued, now_ms, format!("{l:?}: shortfall {shortfall:.2} at {utilization_pct:.2}% utilization"));
+                self.calls.push(c.clone());
+                Some(c)
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Records that someone has taken the call on. The deadline still stands; a call that escalates afterwards can
+    /// be acknowledged again.
+    pub fn acknowledge(&mut self, id: &str, by: &str, note: Option<String>, now_ms: u64) -> Result<MarginCall, AckError> {
+        let c = self.calls.iter_mut().find(|c| c.id == id).ok_or(AckError::NotFound)?;
+        if !matches!(c.status, CallStatus::Issued | CallStatus::Reminded | CallStatus::Escalated) { return Err(AckError::Conflict(format!("margin call is {:?}", c.status))); }
+        c.acknowledged_by = Some(by.into());
+        c.acknowledged_at_ms = Some(now_ms);
+        c.transition(CallStatus::Acknowledged, now_ms, note.map_or_else(|| format!("acknowledged by {by}"), |n| format!("acknowledged by {by}: {n}")));
+        Ok(c.clone())
+    }
+
+    /// Advances every open call with a deadline through reminder, expiry and liquidation-recommended as its grace
+    /// period elapses. Acknowledged calls are not reminded.
+    pub fn escalate(&mut self, now_ms: u64) -> Vec<MarginCall> {
+        let p = &self.policy;
+        let mut changed = Vec::new();
+        for c in self.calls.iter_mut().filter(|c| c.status.is_open()) {
+            let Some(deadline) = c.deadline_ms else { continue };
+            let reminder_at = deadline.saturating_sub((p.grace_ms as f64 * (1.0 - p.reminder_fraction)) as u64);
+            let expired = || (CallStatus::Expired, "grace period expired; account set to reduce-only".to_string());
+            let next = match c.status {
+                CallStatus::Issued | CallStatus::Acknowledged | CallStatus::Reminded | CallStatus::Escalated if now_ms >= deadline => Some(expired()),
+                CallStatus::Issued if now_ms >= reminder_at => Some((CallStatus::Reminded, format!("{}s left to meet the call", (deadline - now_ms) / 1000))),
+                CallStatus::Expired if now_ms >= deadline + p.liquidation_after_ms => Some((CallStatus::LiquidationRecommended, "call unmet after expiry; liquidation recommended".to_string())),
+                _ => None,
+            };
+            if let Some((st, note)) = next { c.transition(st, now_ms, note); changed.push(c.clone()); }
+        }
+        changed
+    }
+}
@@ -0,0 +1,50 @@
+use crate::margin_calls::CallStatus;
+use crate::suite::Trigger;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Intraday re-margining of every open account against the latest cached prices, every `interval_secs`.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CycleConfig { #[serde(default = "enabled")] pub enabled: bool, pub interval_secs: u64 }
+
+fn enabled() -> bool { true }
+
+#[derive(Serialize, Clone)]
+pub struct CycleAccount {
+    pub account: String, pub initial_margin: f64, pub funds: f64, pub margin_utilization_pct: f64, pub shortfall: f64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub call_id: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub call_status: Option<CallStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub unpriced: Vec<String>,
+}
+
+/// `calls_issued` and `calls_met` count calls whose status this cycle changed.
+#[derive(Serialize, Clone)]
+pub struct Cycle {
+    pub id: String, pub trigger: Trigger, pub started_at_ms: u64, pub finished_at_ms: u64, pub accounts_checked: usize, pub accounts_in_shortfall: usize,
+    pub calls_issued: usize, pub calls_met: usize, pub total_shortfall: f64, pub accounts: Vec<CycleAccount>,
+}
+
+pub struct MarginCycles { config: CycleConfig, cycles: VecDeque<Cycle>, max_cycles: usize, last_started_ms: u64 }
+
+impl MarginCycles {
+    /// The first scheduled cycle comes one interval after `now_ms`.
+    pub fn new(config: CycleConfig, max_cycles: usize, now_ms: u64) -> Self { Self { config, cycles: VecDeque::new(), max_cycles: max_cycles.max(1), last_started_ms: now_ms } }
+
+    pub fn config(&self) -> CycleConfig { self.config.clone() }
+    pub fn set_config(&mut self, c: CycleConfig) -> Result<CycleConfig, String> {
+        if c.interval_secs < 60 { return Err("interval_secs must be at least 60".into()); }
+        self.config = c.clone();
+        Ok(c)
+    }
+
+    /// Manual runs restart the interval as well, so a forced cycle is not followed straight away by a scheduled one.
+    pub fn due(&self, now_ms: u64) -> bool { self.config.enabled && now_ms >= self.last_started_ms + self.config.interval_secs * 1000 }
+
+    pub fn record(&mut self, cycle: Cycle) {
+        self.last_started_ms = self.last_started_ms.max(cycle.started_at_ms);
+        self.cycles.push_back(cycle);
+        while self.cycles.len() > self.max_cycles { self.cycles.pop_front(); }
+    }
+
+    pub fn list(&self, limit: usize) -> Vec<Cycle> { self.cycles.iter().rev().take(limit).cloned().collect() }
+    pub fn get(&self, id: &str) -> Option<Cycle> { self.cycles.iter().find(|c| c.id == id).cloned() }
+}
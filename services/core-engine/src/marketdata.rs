@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Weight of the previous estimate in the tick volatility EWMA, as in RiskMetrics.
+const VOL_DECAY: f64 = 0.94;
+/// Returns needed before a volatility estimate is reported; fewer leave it resting on a handful of prints.
+const MIN_VOL_RETURNS: u32 = 20;
+/// A 6.5-hour session, the horizon tick variance is scaled to for a daily figure.
+const TRADING_DAY_MS: f64 = 23_400_000.0;
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Tick { pub instrument: String, pub price: f64, pub bid: Option<f64>, pub ask: Option<f64>, pub ts_ms: Option<u64> }
+
+/// `mid` is set when both sides are quoted; `daily_vol` once enough ticks have arrived to estimate it.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct Quote {
+    pub price: f64, pub bid: Option<f64>, pub ask: Option<f64>, pub at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub mid: Option<f64>, #[serde(default, skip_serializing_if = "Option::is_none")] pub daily_vol: Option<f64>,
+}
+
+/// Variance per millisecond of log returns between successive ticks, exponentially weighted.
+#[derive(Default, Clone, Copy)]
+struct Ewma { variance_per_ms: f64, returns: u32 }
+
+#[derive(Default, Clone)]
+pub struct MarketData { quotes: HashMap<String, Quote>, vol: HashMap<String, Ewma> }
+
+impl MarketData {
+    pub fn quote(&self, instrument: &str) -> Option<Quote> { self.quotes.get(instrument).copied() }
+    pub fn price(&self, instrument: &str) -> Option<f64> { self.quotes.get(instrument).map(|q| q.price) }
+    pub fn all(&self) -> HashMap<String, Quote> { self.quotes.clone() }
+
+    /// Daily volatility estimated from the ticks received, once there are enough of them.
+    pub fn daily_vol(&self, instrument: &str) -> Option<f64> { self.quotes.get(instrument)?.daily_vol }
+
+    /// Ignores ticks older than the cached quote so replays cannot move prices backwards.
+    pub fn apply(&mut self, t: &Tick, now_ms: u64) -> bool {
+        let at_ms = t.ts_ms.unwrap_or(now_ms);
+        let prev = self.quotes.get(&t.instrument).copied();
+        if prev.is_some_and(|q| q.at_ms > at_ms) { return false; }
+        let e = self.vol.entry(t.instrument.clone()).or_default();
+        if let Some(q) = prev.filter(|q| q.at_ms < at_ms && q.price > 0.0) {
+            let r = (t.price / q.price).ln();
+            let sample = r * r / (at_ms - q.at_ms) as f64;
+            e.variance_per_ms = if e.returns == 0 { sample } else { VOL_DECAY * e.variance_per_ms + (1.0 - VOL_DECAY) * sample };
+            e.returns += 1;
+        }
+        let daily_vol = (e.returns >= MIN_VOL_RETURNS).then(|| (e.variance_per_ms * TRADING_DAY_MS).sqrt()).or(prev.and_then(|q| q.daily_vol));
+        let mid = t.bid.zip(t.ask).filter(|(b, a)| *b > 0.0 && a >= b).map(|(b, a)| (b + a) / 2.0);
+        self.quotes.insert(t.instrument.clone(), Quote { price: t.price, bid: t.bid, ask: t.ask, at_ms, mid, daily_vol });
+        true
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Upper bounds in seconds, from half a millisecond to ten seconds.
+const BUCKETS: [f64; 14] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Counts per bucket are kept non-cumulative and summed when rendered.
+#[derive(Clone, Default)]
+pub struct Histogram { buckets: [u64; BUCKETS.len()], count: u64, sum: f64 }
+
+impl Histogram {
+    pub fn observe(&mut self, d: Duration) {
+        let secs = d.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|b| secs <= *b) { self.buckets[i] += 1; }
+        self.count += 1;
+        self.sum += secs;
+    }
+}
+
+/// Process-lifetime metrics; they start from zero on every restart as Prometheus expects of counters.
+/// `requests` is keyed by method, matched route and status class (`2xx`, `4xx`, ...).
+#[derive(Default)]
+pub struct Metrics {
+    requests: BTreeMap<(String, String, String), u64>, latency: BTreeMap<(String, String), Histogram>,
+    checks: BTreeMap<(String, bool), u64>, breaker_trips: BTreeMap<String, u64>, margin: BTreeMap<String, Histogram>,
+}
+
+/// Figures read from elsewhere in the engine at scrape time.
+pub struct Gauges { pub block_rate_pct: f64, pub alerts_total: u64, pub uptime_secs: u64 }
+
+impl Metrics {
+    pub fn request(&mut self, method: &str, route: &str, status: u16, d: Duration) {
+        *self.requests.entry((method.into(), route.into(), format!("{}xx", status / 100))).or_default() += 1;
+        self.latency.entry((method.into(), route.into())).or_default().observe(d);
+    }
+
+    pub fn check(&mut self, kind: &str, approved: bool) { *self.checks.entry((kind.into(), approved)).or_default() += 1; }
+    pub fn breaker_trip(&mut self, level: &str) { *self.breaker_trips.entry(level.into()).or_default() += 1; }
+    pub fn margin_calc(&mut self, source: &str, d: Duration) { self.margin.entry(source.into()).or_default().observe(d); }
+
+    /// The Prometheus text exposition format, version 0.0.4.
+    pub fn render(&self, g: &Gauges) -> String {
+        let mut out = String::new();
+        header(&mut out, "risk_checks_total", "counter", "Pre-trade and basket checks decided, by kind and decision.");
+        for ((kind, approved), n) in &self.checks {
+            let decision = if *approved { "approved" } else { "blocked" };
+            let _ = writeln!(out, "risk_checks_total{{kind=\"{}\",decision=\"{decision}\"}} {n}", escape(kind));
+        }
+        header(&mut out, "risk_block_rate_pct", "gauge", "Share of all checks blocked, in percent.");
+        let _ = writeln!(out, "risk_block_rate_pct {}", g.block_rate_pct);
+        header(&mut out, "risk_alerts_total", "counter", "Alerts raised.");
+        let _ = writeln!(out, "risk_alerts_total {}", g.alerts_total);
+        header(&mut out, "risk_circuit_breaker_triggers_total", "counter", "Circuit breaker halts, by level.");
+        for (level, n) in &self.breaker_trips { let _ = writeln!(out, "risk_circuit_breaker_triggers_total{{level=\"{}\"}} {n}", escape(level)); }
+        header(&mut out, "risk_http_requests_total", "counter", "HTTP requests served, by method, route and status class.");
+        for ((method, route, status), n) in &self.requests {
+            let _ = writeln!(out, "risk_http_requests_total{{method=\"{method}\",route=\"{}\",status=\"{status}\"}} {n}", escape(route));
+        }
+        header(&mut out, "risk_http_request_duration_seconds", "histogram", "HTTP request latency, by method and route.");
+        for ((method, route), h) in &self.latency { histogram(&mut out, "risk_http_request_duration_seconds", &format!("method=\"{method}\",route=\"{}\"", escape(route)), h); }
+        header(&mut out, "risk_margin_calc_duration_seconds", "histogram", "Margin calculation time, by source.");
+        for (source, h) in &self.margin { histogram(&mut out, "risk_margin_calc_duration_seconds", &format!("source=\"{}\"", escape(source)), h); }
+        header(&mut out, "risk_uptime_seconds", "gauge", "Seconds since the engine started.");
+        let _ = writeln!(out, "risk_uptime_seconds {}", g.uptime_secs);
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) { let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}"); }
+
+fn histogram(out: &mut String, name: &str, labels: &str, h: &Histogram) {
+    let mut cumulative = 0;
+    for (b, n) in BUCKETS.iter().zip(h.buckets) { cumulative += n; let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{b}\"}} {cumulative}"); }
+    let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}\n{name}_sum{{{labels}}} {}\n{name}_count{{{labels}}} {}", h.count, h.sum, h.count);
+}
+
+fn escape(v: &str) -> String { v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n") }
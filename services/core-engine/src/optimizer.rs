@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct OptimizeRequest { pub entity: Option<String>, pub accounts: Option<Vec<String>>, pub max_suggestions: Option<usize> }
+
+/// Move `quantity` (signed, as held in `from`) of `instrument` from one account to another. Savings are measured
+/// against the books as left by the suggestions before it.
+#[derive(Serialize)]
+pub struct Suggestion { pub instrument: String, pub from: String, pub to: String, pub quantity: f64, pub margin_before: f64, pub margin_after: f64, pub savings: f64 }
+
+#[derive(Serialize)]
+pub struct OptimizeReport { pub accounts: Vec<String>, pub margin_before: f64, pub margin_after: f64, pub total_savings: f64, pub suggestions: Vec<Suggestion> }
+
+/// One account's positions as `(instrument, signed quantity, price)`, and whether it may receive transfers.
+pub struct Book { pub account: String, pub positions: Vec<(String, f64, f64)>, pub accepts: bool }
+
+fn shift(positions: &mut Vec<(String, f64, f64)>, instrument: &str, q: f64, price: f64) {
+    match positions.iter_mut().find(|p| p.0 == instrument) { Some(p) => p.1 += q, None => positions.push((instrument.into(), q, price)) }
+    positions.retain(|p| p.1.abs() > 1e-9);
+}
+
+/// Greedy descent: each round tries moving every position, in full or just the part that offsets the target's
+/// opposite holding, into every other accepting account, and keeps the move that saves the most margin.
+/// `margin(i, positions)` prices book `i` under its own model.
+pub fn optimize(mut books: Vec<Book>, margin: impl Fn(usize, &[(String, f64, f64)]) -> f64, max_moves: usize) -> (f64, f64, Vec<Suggestion>) {
+    let mut current: Vec<f64> = books.iter().enumerate().map(|(i, b)| margin(i, &b.positions)).collect();
+    let start: f64 = current.iter().sum();
+    let mut out = Vec::new();
+    while out.len() < max_moves {
+        let mut best: Option<(usize, usize, String, f64, f64, f64, f64)> = None;
+        for (from, fb) in books.iter().enumerate() {
+            for (instrument, q, price) in &fb.positions {
+                for (to, tb) in books.iter().enumerate().filter(|(to, tb)| *to != from && tb.accepts) {
+                    let opposite = tb.positions.iter().find(|p| &p.0 == instrument && p.1.signum() == -q.signum()).map(|p| p.1.abs().min(q.abs()) * q.signum());
+                    for mv in std::iter::once(*q).chain(opposite.filter(|o| (o - q).abs() > 1e-9)) {
+                        let (mut f2, mut t2) = (fb.positions.clone(), tb.positions.clone());
+                        shift(&mut f2, instrument, -mv, *price);
+                        shift(&mut t2, instrument, mv, *price);
+                        let (mf, mt) = (margin(from, &f2), margin(to, &t2));
+                        let saving = current[from] + current[to] - mf - mt;
+                        if saving > 1e-6 && best.as_ref().is_none_or(|b| saving > b.6) { best = Some((from, to, instrument.clone(), mv, mf, mt, saving)); }
+                    }
+                }
+            }
+        }
+        let Some((from, to, instrument, q, mf, mt, saving)) = best else { break };
+        let price = books[from].positions.iter().find(|p| p.0 == instrument).map_or(0.0, |p| p.2);
+        let before = current[from] + current[to];
+        shift(&mut books[from].positions, &instrument, -q, price);
+        shift(&mut books[to].positions, &instrument, q, price);
+        (current[from], current[to]) = (mf, mt);
+        out.push(Suggestion { instrument, from: books[from].account.clone(), to: books[to].account.clone(), quantity: q, margin_before: before, margin_after: mf + mt, savings: saving });
+    }
+    (start, current.iter().sum(), out)
+}
@@ -0,0 +1,105 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeStatus { Live, Terminated, SteppedOut }
+
+/// An OTC contract held as a position of `notional` (signed: positive receives fixed / is long) under `instrument`.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct OtcTrade {
+    pub trade_id: String, pub account: String, pub instrument: String, pub counterparty: String, pub notional: f64,
+    pub fixed_rate: Option<f64>, pub floating_rate: Option<f64>, pub maturity: Option<NaiveDate>,
+    #[serde(default = "live")] pub status: TradeStatus, #[serde(default)] pub cash_settled: f64, #[serde(default)] pub events: Vec<String>,
+}
+
+fn live() -> TradeStatus { TradeStatus::Live }
+
+/// `notional` on a termination ends only that much of the trade; `fee` is cash to the account (negative when paid).
+/// A novation either hands the trade to another account of ours, replaces the counterparty, or steps us out of it.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    Reset { rate: f64 },
+    Coupon { amount: f64, pay_date: Option<NaiveDate> },
+    Termination { notional: Option<f64>, #[serde(default)] fee: f64 },
+    Novation { new_counterparty: Option<String>, to_account: Option<String>, #[serde(default)] step_out: bool },
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct LifecycleEvent { pub event_id: String, pub trade_id: String, pub effective_date: NaiveDate, #[serde(flatten)] pub kind: EventKind }
+
+/// What applying an event changed outside the trade record: position deltas and cash due to the account.
+pub struct Effects { pub positions: Vec<(String, String, f64)>, pub cash: Option<(String, NaiveDate, f64)> }
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome { Applied, Duplicate, Rejected }
+
+#[derive(Serialize)]
+pub struct EventResult { pub event_id: String, pub trade_id: String, pub outcome: Outcome, #[serde(skip_serializing_if = "Option::is_none")] pub error: Option<String> }
+
+#[derive(Deserialize)]
+pub struct TradeQuery { pub account: Option<String>, pub status: Option<TradeStatus> }
+
+/// Event ids are remembered so a replayed feed does not apply a coupon or termination twice.
+#[derive(Default)]
+pub struct OtcBook { trades: HashMap<String, OtcTrade>, seen: HashSet<String> }
+
+impl OtcBook {
+    pub fn register(&mut self, mut t: OtcTrade) -> Result<OtcTrade, String> {
+        if self.trades.contains_key(&t.trade_id) { return Err(format!("trade {} already exists", t.trade_id)); }
+        if !t.notional.is_finite() || t.notional == 0.0 { return Err("notional must be a non-zero amount".into()); }
+        t.status = TradeStatus::Live;
+        t.events.clear();
+        self.trades.insert(t.trade_id.clone(), t.clone());
+        Ok(t)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&OtcTrade> { self.trades.get(id) }
+
+    pub fn list(&self, q: &TradeQuery) -> Vec<OtcTrade> {
+        let mut v: Vec<OtcTrade> = self.trades.values().filter(|t| q.account.as_ref().is_none_or(|a| &t.account == a) && q.status.is_none_or(|s| t.status == s)).cloned().collect();
+        v.sort_by(|a, b| a.trade_id.cmp(&b.trade_id));
+        v
+    }
+
+    pub fn seen(&self, event_id: &str) -> bool { self.seen.contains(event_id) }
+
+    /// Applies one event to its trade and returns the side effects for the position and cash books.
+    pub fn apply(&mut self, e: &LifecycleEvent) -> Result<(OtcTrade, Effects), String> {
+        let t = self.trades.get_mut(&e.trade_id).ok_or_else(|| format!("unknown trade {}", e.trade_id))?;
+        if t.status != TradeStatus::Live { return Err(format!("trade is {:?}", t.status).to_lowercase()); }
+        if let EventKind::Novation { new_counterparty: None, to_account: None, step_out: false } = e.kind { return Err("novation needs new_counterparty, to_account or step_out".into()); }
+        let mut fx = Effects { positions: Vec::new(), cash: None };
+        match &e.kind {
+            EventKind::Reset { rate } => t.floating_rate = Some(*rate),
+            EventKind::Coupon { amount, pay_date } => {
+                t.cash_settled += amount;
+                fx.cash = Some((t.account.clone(), pay_date.unwrap_or(e.effective_date), *amount));
+            }
+            EventKind::Termination { notional, fee } => {
+                let cut = notional.map_or(t.notional, |n| n.abs().min(t.notional.abs()) * t.notional.signum());
+                fx.positions.push((t.account.clone(), t.instrument.clone(), -cut));
+                t.notional -= cut;
+                if t.notional.abs() < 1e-9 { t.status = TradeStatus::Terminated; }
+                if *fee != 0.0 { fx.cash = Some((t.account.clone(), e.effective_date, *fee)); }
+            }
+            EventKind::Novation { new_counterparty, to_account, step_out } => {
+                if *step_out {
+                    fx.positions.push((t.account.clone(), t.instrument.clone(), -t.notional));
+                    t.status = TradeStatus::SteppedOut;
+                } else if let Some(to) = to_account.as_ref().filter(|to| **to != t.account) {
+                    fx.positions.push((t.account.clone(), t.instrument.clone(), -t.notional));
+                    fx.positions.push((to.clone(), t.instrument.clone(), t.notional));
+                    t.account = to.clone();
+                }
+                if let Some(cp) = new_counterparty { t.counterparty = cp.clone(); }
+            }
+        }
+        t.events.push(e.event_id.clone());
+        self.seen.insert(e.event_id.clone());
+        Ok((t.clone(), fx))
+    }
+}
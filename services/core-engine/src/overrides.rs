@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonCode { ClientFacilitation, ErrorCorrection, HedgeExecution, LimitIncreasePending, MarketEvent, Other }
+
+/// Waives limit rejections for one account within its validity window. `instrument` and `limit` narrow the
+/// scope; without `limit` every limit breach is waived, never entitlements, account status or a limit that could not
+/// be evaluated.
+#[derive(Serialize, Clone)]
+pub struct Override {
+    pub token: String, pub account: String, pub instrument: Option<String>, pub limit: Option<String>, pub check_id: Option<String>,
+    pub reason_code: ReasonCode, pub comment: Option<String>, pub issued_by: String, pub issued_at_ms: u64, pub expires_at_ms: u64,
+    pub max_uses: u32, pub uses: u32, pub revoked: bool,
+}
+
+#[derive(Deserialize)]
+pub struct IssueOverride {
+    pub account: String, pub instrument: Option<String>, pub limit: Option<String>, pub check_id: Option<String>,
+    pub reason_code: ReasonCode, pub comment: Option<String>, pub ttl_secs: Option<u64>, pub max_uses: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct OverrideQuery { pub account: Option<String>, pub active: Option<bool>, pub limit: Option<usize> }
+
+pub struct Overrides { overrides: Vec<Override>, max_ttl_secs: u64 }
+
+impl Override {
+    fn active(&self, now_ms: u64) -> bool { !self.revoked && now_ms < self.expires_at_ms && self.uses < self.max_uses }
+    pub fn covers(&self, limit_key: &str) -> bool { self.limit.as_ref().is_none_or(|l| l.eq_ignore_ascii_case(limit_key)) }
+}
+
+impl Overrides {
+    pub fn new(max_ttl_secs: u64) -> Self { Self { overrides: Vec::new(), max_ttl_secs } }
+
+    pub fn issue(&mut self, req: IssueOverride, issued_by: &str, now_ms: u64) -> Result<Override, String> {
+        if req.reason_code == ReasonCode::Other && req.comment.as_ref().is_none_or(|c| c.trim().is_empty()) { return Err("reason_code 'other' requires a comment".into()); }
+        let ttl = req.ttl_secs.unwrap_or(900);
+        if ttl == 0 || ttl > self.max_ttl_secs { return Err(format!("ttl_secs must be between 1 and {}", self.max_ttl_secs)); }
+        let o = Override {
+            token: format!("OVR-{}", uuid::Uuid::new_v4().simple()), account: req.account, instrument: req.instrument, limit: req.limit, check_id: req.check_id,
+            reason_code: req.reason_code, comment: req.comment, issued_by: issued_by.into(), issued_at_ms: now_ms, expires_at_ms: now_ms + ttl * 1000,
+            max_uses: req.max_uses.unwrap_or(1).max(1), uses: 0, revoked: false,
+        };
+        self.overrides.push(o.clone());
+        Ok(o)
+    }
+
+    /// The override behind `token` if it is usable for this order, otherwise why not.
+    pub fn check(&self, token: &str, account: &str, instrument: &str, now_ms: u64) -> Result<Override, String> {
+        let o = self.overrides.iter().find(|o| o.token == token).ok_or("unknown override token")?;
+        if o.account != account || o.instrument.as_ref().is_some_and(|i| i != instrument) { return Err("override does not cover this order".into()); }
+        if o.revoked { return Err("override revoked".into()); }
+        if now_ms >= o.expires_at_ms { return Err("override expired".into()); }
+        if o.uses >= o.max_uses { return Err("override already used".into()); }
+        Ok(o.clone())
+    }
+
+    pub fn consume(&mut self, token: &str) { if let Some(o) = self.overrides.iter_mut().find(|o| o.token == token) { o.uses += 1; } }
+
+    pub fn revoke(&mut self, token: &str) -> Option<Override> {
+        let o = self.overrides.iter_mut().find(|o| o.token == token)?;
+        o.revoked = true;
+        Some(o.clone())
+    }
+
+    /// Newest first.
+    pub fn list(&self, q: &OverrideQuery, now_ms: u64) -> Vec<Override> {
+        self.overrides.iter().rev()
+            .filter(|o| q.account.as_ref().is_none_or(|a| &o.account == a) && q.active.is_none_or(|a| o.active(now_ms) == a))
+            .take(q.limit.unwrap_or(100)).cloned().collect()
+    }
+}
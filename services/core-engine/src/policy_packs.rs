@@ -0,0 +1,131 @@
+use crate::circuit_breaker::{BreakerConfig, Level};
+use crate::config_bundle::Settings;
+use crate::erroneous::{ErroneousConfig, Tier};
+use crate::fat_finger::{FatFingerConfig, Reference};
+use crate::limits::{Limit, LimitKind, Scope};
+use serde::Serialize;
+
+/// A firm-wide limit a pack installs. The id is the pack's, so applying the pack again updates the limit in place.
+#[derive(Serialize, Clone)]
+pub struct PackLimit { pub id: String, pub control: &'static str, #[serde(flatten)] pub kind: LimitKind }
+
+/// The pre-trade controls a regulatory regime makes mandatory, preset to values a new client can start from. Settings
+/// a pack leaves out keep whatever is configured.
+#[derive(Serialize, Clone)]
+pub struct Pack {
+    pub id: &'static str, pub name: &'static str, pub regime: &'static str, pub description: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")] pub fat_finger: Option<FatFingerConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub circuit_breaker: Option<BreakerConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub erroneous_orders: Option<ErroneousConfig>,
+    pub limits: Vec<PackLimit>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Status { InForce, Drifted, Missing }
+
+#[derive(Serialize)]
+pub struct Control { pub control: String, pub status: Status }
+
+/// Whether the configuration still holds every control the pack sets. A limit counts as in force while it is at
+/// least as strict as the pack's; a setting only while it is exactly the pack's.
+#[derive(Serialize)]
+pub struct Report { pub pack: &'static str, pub regime: &'static str, pub conformant: bool, pub controls: Vec<Control> }
+
+fn levels(levels: &[(&str, f64, u64)]) -> BreakerConfig {
+    BreakerConfig { levels: levels.iter().map(|(n, t, h)| Level { name: (*n).into(), threshold_pct: *t, halt_secs: *h }).collect(), reference_window_secs: 300 }
+}
+
+fn limit(pack: &str, control: &'static str, kind: LimitKind) -> PackLimit { PackLimit { id: format!("{pack}-{}", control.replace('_', "-")), control, kind } }
+
+pub fn all() -> Vec<Pack> {
+    vec![
+        Pack {
+            id: "us-15c3-5", name: "US equities market access", regime: "SEC Rule 15c3-5",
+            description: "Credit and capital thresholds, erroneous order controls and duplicative order throttling required of broker-dealers with market access.",
+            fat_finger: Some(FatFingerConfig { reference: Reference::Mid, warn_pct: Some(5.0), reject_pct: Some(10.0), max_quote_age_ms: Some(5_000) }),
+            circuit_breaker: Some(levels(&[("L1", 7.0, 900), ("L2", 13.0, 900), ("L3", 20.0, 86_400)])),
+            erroneous_orders: Some(ErroneousConfig { prints: 20, tiers: [(Some(25.0), 10.0), (Some(50.0), 5.0), (None, 3.0)].into_iter().map(|(up_to, band_pct)| Tier { up_to, band_pct }).collect() }),
+            limits: vec![
+                limit("us-15c3-5", "order_notional", LimitKind::OrderNotional { max: 5_000_000.0 }),
+                limit("us-15c3-5", "order_quantity", LimitKind::OrderQuantity { max: 100_000.0 }),
+                limit("us-15c3-5", "order_rate", LimitKind::OrderRate { max_orders: 50, window_secs: 1 }),
+            ],
+        },
+        Pack {
+            id: "eu-mifid-rts6", name: "EU algorithmic trading controls", regime: "MiFID II RTS 6",
+            description: "Article 15 pre-trade controls for algorithmic trading: price collars, maximum order value and volume, and a maximum message rate.",
+            fat_finger: Some(FatFingerConfig { reference: Reference::Mid, warn_pct: Some(2.5), reject_pct: Some(5.0), max_quote_age_ms: Some(2_000) }),
+            circuit_breaker: None, erroneous_orders: None,
+            limits: vec![
+                limit("eu-mifid-rts6", "order_notional", LimitKind::OrderNotional { max: 2_000_000.0 }),
+                limit("eu-mifid-rts6", "order_quantity", LimitKind::OrderQuantity { max: 50_000.0 }),
+                limit("eu-mifid-rts6", "order_rate", LimitKind::OrderRate { max_orders: 100, window_secs: 1 }),
+            ],
+        },
+        Pack {
+            id: "futures-exchange", name: "Futures exchange risk controls", regime: "Exchange pre-trade risk controls (FIA guidance)",
+            description: "Price banding, maximum order size, messaging throttles and velocity logic halts as futures exchanges mandate.",
+            fat_finger: Some(FatFingerConfig { reference: Reference::Last, warn_pct: Some(1.5), reject_pct: Some(3.0), max_quote_age_ms: Some(1_000) }),
+            circuit_breaker: Some(levels(&[("velocity", 3.0, 120), ("daily_7", 7.0, 600), ("daily_13", 13.0, 600), ("daily_20", 20.0, 86_400)])),
+            erroneous_orders: None,
+            limits: vec![
+                limit("futures-exchange", "order_quantity", LimitKind::OrderQuantity { max: 2_000.0 }),
+                limit("futures-exchange", "order_rate", LimitKind::OrderRate { max_orders: 25, window_secs: 1 }),
+                limit("futures-exchange", "open_positions", LimitKind::OpenPositions { max: 200 }),
+            ],
+        },
+    ]
+}
+
+pub fn get(id: &str) -> Option<Pack> { all().into_iter().find(|p| p.id == id) }
+
+impl PackLimit {
+    fn to_limit(&self) -> Limit {
+        Limit { id: self.id.clone(), scope: Scope::Firm, key: None, kind: self.kind.clone(), currency: None, version: 0, created_at_ms: 0, updated_at_ms: 0 }
+    }
+
+    fn held_by(&self, l: &Limit) -> bool {
+        if (l.scope, &l.key, &l.currency) != (Scope::Firm, &None, &None) { return false; }
+        match (&l.kind, &self.kind) {
+            (LimitKind::OrderNotional { max: a }, LimitKind::OrderNotional { max: b }) | (LimitKind::OrderQuantity { max: a }, LimitKind::OrderQuantity { max: b }) => a <= b,
+            (LimitKind::OrderRate { max_orders: a, window_secs: aw }, LimitKind::OrderRate { max_orders: b, window_secs: bw }) => a <= b && aw >= bw,
+            (LimitKind::OpenPositions { max: a }, LimitKind::OpenPositions { max: b }) => a <= b,
+            _ => false,
+        }
+    }
+}
+
+impl Pack {
+    /// `settings` and `limits` with the pack's controls laid over them; limits the pack does not install are kept.
+    pub fn overlay(&self, mut settings: Settings, mut limits: Vec<Limit>) -> (Settings, Vec<Limit>) {
+        if let Some(c) = &self.fat_finger { settings.fat_finger = c.clone(); }
+        if let Some(c) = &self.circuit_breaker { settings.circuit_breaker = c.clone(); }
+        if let Some(c) = &self.erroneous_orders { settings.erroneous_orders = c.clone(); }
+        for p in &self.limits {
+            match limits.iter_mut().find(|l| l.id == p.id) {
+                Some(l) => *l = Limit { version: l.version, created_at_ms: l.created_at_ms, updated_at_ms: l.updated_at_ms, ..p.to_limit() },
+                None => limits.push(p.to_limit()),
+            }
+        }
+        (settings, limits)
+    }
+
+    pub fn report(&self, settings: &Settings, limits: &[Limit]) -> Report {
+        let setting = |name: &str, held: bool| Control { control: name.into(), status: if held { Status::InForce } else { Status::Drifted } };
+        let mut controls = Vec::new();
+        if let Some(c) = &self.fat_finger { controls.push(setting("fat_finger", settings.fat_finger == *c)); }
+        if let Some(c) = &self.circuit_breaker { controls.push(setting("circuit_breaker", settings.circuit_breaker == *c)); }
+        if let Some(c) = &self.erroneous_orders { controls.push(setting("erroneous_orders", settings.erroneous_orders == *c)); }
+        for p in &self.limits {
+            let status = match limits.iter().find(|l| l.id == p.id) {
+                None if limits.iter().any(|l| p.held_by(l)) => Status::InForce,
+                None => Status::Missing,
+                Some(l) if p.held_by(l) => Status::InForce,
+                Some(_) => Status::Drifted,
+            };
+            controls.push(Control { control: format!("limit {}", p.control), status });
+        }
+        Report { pack: self.id, regime: self.regime, conformant: controls.iter().all(|c| c.status == Status::InForce), controls }
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A traded-notional allowance that several accounts draw on together, such as a market-making entity's
+/// sub-accounts sharing one firm-level pool. It resets with the daily session, on top of each account's own
+/// daily allowance.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct PoolSpec { pub description: Option<String>, pub members: Vec<String>, pub notional_limit: f64 }
+
+#[derive(Serialize, Clone)]
+pub struct Pool { pub name: String, #[serde(flatten)] pub spec: PoolSpec, pub created_at_ms: u64, pub updated_at_ms: u64 }
+
+/// One member's draw on its pool this session.
+#[derive(Serialize, Clone, Default)]
+pub struct MemberUsage { pub account: String, pub used: f64, pub reserved: f64, pub pct_of_pool: f64 }
+
+#[derive(Serialize, Clone)]
+pub struct PoolUtilization {
+    pub name: String, pub session: u64, pub notional_limit: f64, pub used: f64, pub reserved: f64, pub remaining: f64, pub utilization_pct: f64, pub members: Vec<MemberUsage>,
+}
+
+#[derive(Default, Clone)]
+struct Usage { session: u64, used: HashMap<String, f64>, reserved: HashMap<String, f64> }
+
+impl Usage {
+    fn roll(&mut self, session: u64) { if session != self.session { self.session = session; self.used.clear(); } }
+    fn total(&self) -> (f64, f64) { (self.used.values().sum(), self.reserved.values().sum()) }
+}
+
+/// Pools by name. An account draws on at most one pool. Checks evaluate and record under the caller's lock on
+/// this book, so concurrent orders from different members cannot spend the same headroom twice.
+#[derive(Default, Clone)]
+pub struct Pools { pools: BTreeMap<String, Pool>, by_account: HashMap<String, String>, usage: HashMap<String, Usage> }
+
+impl Pools {
+    pub fn list(&self) -> Vec<Pool> { self.pools.values().cloned().collect() }
+    pub fn get(&self, name: &str) -> Option<&Pool> { self.pools.get(name) }
+
+    /// The definitions without this session's usage, for a sandbox.
+    pub fn config_copy(&self) -> Self { Self { pools: self.pools.clone(), by_account: self.by_account.clone(), usage: HashMap::new() } }
+
+    /// Creates or replaces the pool, returning it with the definition it replaced. Usage carries over for members
+    /// that stay in the pool.
+    pub fn set(&mut self, name: &str, spec: PoolSpec, now_ms: u64) -> Result<(Pool, Option<Pool>), String> {
+        if name.trim().is_empty() { return Err("pool name is required".into()); }
+        if !(spec.notional_limit.is_finite() && spec.notional_limit > 0.0) { return Err("notional_limit must be positive".into()); }
+        if spec.members.is_empty() || spec.members.iter().any(|m| m.trim().is_empty()) { return Err("a pool needs member accounts".into()); }
+        if spec.members.iter().collect::<BTreeSet<_>>().len() != spec.members.len() { return Err("members must be distinct".into()); }
+        if let Some((m, p)) = spec.members.iter().find_map(|m| self.by_account.get(m).filter(|p| *p != name).map(|p| (m, p))) { return Err(format!("{m} already draws on pool {p}")); }
+        let mut usage = self.usage.remove(name).unwrap_or_default();
+        usage.used.retain(|a, _| spec.members.contains(a));
+        usage.reserved.retain(|a, _| spec.members.contains(a));
+        let previous = self.remove(name);
+        for m in &spec.members { self.by_account.insert(m.clone(), name.into()); }
+        let p = Pool { name: name.into(), spec, created_at_ms: previous.as_ref().map_or(now_ms, |p| p.created_at_ms), updated_at_ms: now_ms };
+        self.pools.insert(name.into(), p.clone());
+        self.usage.insert(name.into(), usage);
+        Ok((p, previous))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Pool> {
+        let p = self.pools.remove(name)?;
+        for m in &p.spec.members { self.by_account.remove(m); }
+        self.usage.remove(name);
+        Some(p)
+    }
+
+    fn usage(&mut self, account: &str, session: u64) -> Option<(&Pool, &mut Usage)> {
+        let name = self.by_account.get(account)?;
+        let u = self.usage.entry(name.clone()).or_default();
+        u.roll(session);
+        Some((self.pools.get(name)?, u))
+    }
+
+    /// Headroom left in the account's pool, if it draws on one.
+    pub fn remaining(&mut self, account: &str, session: u64) -> Option<(String, f64)> {
+        let (p, u) = self.usage(account, session)?;
+        let (used, reserved) = u.total();
+        Some((p.name.clone(), (p.spec.notional_limit - used - reserved).max(0.0)))
+    }
+
+    pub fn evaluate(&mut self, account: &str, notional: f64, session: u64) -> Option<String> {
+        let (name, x) = self.remaining(account, session)?;
+        (notional > x).then(|| format!("Limit pool {name} notional exceeded: {x:.2} remaining"))
+    }
+
+    pub fn record(&mut self, account: &str, notional: f64, session: u64) {
+        if let Some((_, u)) = self.usage(account, session) { *u.used.entry(account.into()).or_default() += notional; }
+    }
+
+    pub fn reserve(&mut self, account: &str, notional: f64, session: u64) {
+        if let Some((_, u)) = self.usage(account, session) { *u.reserved.entry(account.into()).or_default() += notional; }
+    }
+
+    /// Reservations survive a rollover, as in the daily book.
+    pub fn release(&mut self, account: &str, notional: f64, session: u64) {
+        let Some((_, u)) = self.usage(account, session) else { return };
+        if let Some(r) = u.reserved.get_mut(account) { *r -= notional; if *r <= 1e-9 { u.reserved.remove(account); } }
+    }
+
+    pub fn utilization(&mut self, name: &str, session: u64) -> Option<PoolUtilization> {
+        let p = self.pools.get(name)?;
+        let u = self.usage.entry(name.into()).or_default();
+        u.roll(session);
+        let (used, reserved) = u.total();
+        let limit = p.spec.notional_limit;
+        let members = p.spec.members.iter().map(|a| {
+            let (used, reserved) = (u.used.get(a).copied().unwrap_or(0.0), u.reserved.get(a).copied().unwrap_or(0.0));
+            MemberUsage { account: a.clone(), used, reserved, pct_of_pool: (used + reserved) / limit * 100.0 }
+        }).collect();
+        Some(PoolUtilization { name: name.into(), session, notional_limit: limit, used, reserved, remaining: (limit - used - reserved).max(0.0), utilization_pct: (used + reserved) / limit * 100.0, members })
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// `currency` is what the instrument is priced in, filled in when positions are listed.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Position { pub instrument: String, pub quantity: f64, #[serde(default, skip_serializing_if = "Option::is_none")] pub currency: Option<String> }
+
+/// `b`, `s` and `short` are accepted for `buy`, `sell` and `sell_short`, in any case; any other side is refused
+/// rather than read as a buy.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case", try_from = "String")]
+pub enum Side { Buy, Sell, SellShort }
+
+impl TryFrom<String> for Side {
+    type Error = String;
+    fn try_from(side: String) -> Result<Self, String> {
+        match side.to_ascii_lowercase().as_str() {
+            "buy" | "b" => Ok(Side::Buy),
+            "sell" | "s" => Ok(Side::Sell),
+            "sell_short" | "short" => Ok(Side::SellShort),
+            _ => Err(format!("unknown side {side:?}: expected buy, sell or sell_short")),
+        }
+    }
+}
+
+impl Side {
+    pub fn label(self) -> &'static str { match self { Side::Buy => "buy", Side::Sell => "sell", Side::SellShort => "sell_short" } }
+}
+
+/// An execution reported by the OMS. Fills carrying a `fill_id` already booked are acknowledged but not applied again.
+#[derive(Deserialize, Clone)]
+pub struct Fill { pub fill_id: Option<String>, pub account: String, pub instrument: String, pub side: Side, pub quantity: f64, pub price: f64 }
+
+/// `position` is the account's net quantity in the instrument after the fill.
+#[derive(Serialize)]
+pub struct FillAck { pub fill_id: String, pub account: String, pub instrument: String, pub position: f64, pub duplicate: bool }
+
+/// Signed order quantity: buys add, sells and short sells subtract.
+pub fn signed_quantity(side: Side, quantity: f64) -> f64 {
+    match side { Side::Buy => quantity, Side::Sell | Side::SellShort => -quantity }
+}
+
+/// Quantity an order adds to a short position: the part of a sell that goes below flat, beyond any short already held.
+pub fn short_quantity(position: f64, signed_qty: f64) -> f64 { ((-(position + signed_qty)).max(0.0) - (-position).max(0.0)).max(0.0) }
+
+#[derive(Default)]
+pub struct PositionBook { net: HashMap<String, HashMap<String, f64>>, fills: HashSet<String> }
+
+impl PositionBook {
+    pub fn net(&self, account: &str, instrument: &str) -> f64 { self.net.get(account).and_then(|m| m.get(instrument)).copied().unwrap_or(0.0) }
+
+    pub fn list(&self, account: &str) -> Vec<Position> {
+        let mut v: Vec<_> = self.net.get(account).into_iter().flatten().map(|(i, q)| Position { instrument: i.clone(), quantity: *q, currency: None }).collect();
+        v.sort_by(|a, b| a.instrument.cmp(&b.instrument));
+        v
+    }
+
+    /// Accounts holding positions, sorted.
+    pub fn accounts(&self) -> Vec<String> {
+        let mut v: Vec<String> = self.net.iter().filter(|(_, m)| !m.is_empty()).map(|(a, _)| a.clone()).collect();
+        v.sort();
+        v
+    }
+
+    pub fn replace(&mut self, account: &str, positions: Vec<Position>) {
+        self.net.insert(account.into(), positions.into_iter().filter(|p| p.quantity != 0.0).map(|p| (p.instrument, p.quantity)).collect());
+    }
+
+    /// Moves one position by `delta`, dropping it once flat.
+    pub fn adjust(&mut self, account: &str, instrument: &str, delta: f64) {
+        let m = self.net.entry(account.into()).or_default();
+        let q = m.entry(instrument.into()).or_default();
+        *q += delta;
+        if q.abs() < 1e-9 { m.remove(instrument); }
+    }
+
+    pub fn fill(&mut self, f: &Fill) -> FillAck {
+        let fill_id = f.fill_id.clone().unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let duplicate = !self.fills.insert(fill_id.clone());
+        if !duplicate { self.adjust(&f.account, &f.instrument, signed_quantity(f.side, f.quantity)); }
+        FillAck { fill_id, account: f.account.clone(), instrument: f.instrument.clone(), position: self.net(&f.account, &f.instrument), duplicate }
+    }
+
+    /// An order reduces risk when it moves the position towards flat without crossing through it.
+    pub fn is_reducing(&self, account: &str, instrument: &str, signed_qty: f64) -> bool {
+        let pos = self.net(account, instrument);
+        pos != 0.0 && signed_qty.signum() == -pos.signum() && signed_qty.abs() <= pos.abs()
+    }
+}
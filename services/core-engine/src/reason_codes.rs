@@ -0,0 +1,146 @@
+use crate::corporate_actions;
+use crate::entitlements;
+use crate::locale::Locale;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Stable identifiers for why a check refused or flagged an order. Integrations branch on these; the reason text
+/// that comes with them carries the figures and may be reworded.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Code {
+    AccountSuspended, AccountClosed, AccountReduceOnly, KillSwitch, TradingHalt, EventWindowBlock, DependencyUnavailable, DegradedCheck, DeadlineExceeded, EntitlementViolation,
+    ShortSaleNotLocated, ClearlyErroneousPrice, FatFingerPrice, FatFingerWarning, MaxOrderQuantity, ScheduleLimit, ExDividendRisk,
+    AlgoLimit, AlgoNotProjected, PositionLimit, MaxPositionNotional, MaxOrderNotional, ConfiguredLimit, VelocityLimit, DailyNotionalLimit, PoolNotionalLimit,
+    FxRateUnavailable, InsufficientMargin, GreekLimit, GreeksNotEvaluated, BetaLimit, FxSettlementLimit, FxSettlementConcentration, FxSettlementNotAssessed, FxExposureLimit, FxExposureNotAssessed, ConcentrationLimit, ConcentrationWarning, WashTrade, WashTradeWarning,
+    UnfundedSettlement, BasketLinesRejected, BasketLimit, LargeOrder, HardToBorrow, BorrowSpecial, Unclassified,
+}
+
+/// `Block` reasons refuse the order; `Warning` reasons only flag it.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity { Block, Warning }
+
+/// How a code is presented: a fixed human text and the key clients translate it under.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Entry { pub code: Code, pub severity: Severity, pub text: String, pub localization_key: String }
+
+/// One reason on a response: its catalog entry and the reason as the check wrote it.
+#[derive(Serialize, Clone)]
+pub struct Reason { #[serde(flatten)] pub entry: Entry, pub detail: String }
+
+use Code::*;
+use Severity::*;
+
+/// Default severity and text of every code, the text in each locale in `Locale` order.
+const DEFAULTS: [(Code, Severity, [&str; 3]); 47] = [
+    (AccountSuspended, Block, ["Account suspended", "口座停止中", "账户已暂停"]),
+    (AccountClosed, Block, ["Account closed", "口座解約済み", "账户已关闭"]),
+    (AccountReduceOnly, Block, ["Account is reduce-only", "口座は建玉削減のみ可能", "账户仅限减仓"]),
+    (KillSwitch, Block, ["Kill switch engaged", "キルスイッチ作動中", "紧急停止开关已启用"]),
+    (TradingHalt, Block, ["Trading halted", "売買停止中", "交易已暂停"]),
+    (EventWindowBlock, Block, ["Blocked around a scheduled event", "指標発表前後のため発注停止", "重大事件窗口内禁止下单"]),
+    (DependencyUnavailable, Block, ["Risk data unavailable", "リスクデータを利用できません", "风险数据不可用"]),
+    (DegradedCheck, Block, ["Check degraded past its latency budget", "レイテンシ予算超過によりチェックが縮退", "检查超出延迟预算，已降级"]),
+    (DeadlineExceeded, Block, ["Client deadline exceeded", "クライアント指定の期限切れ", "超出客户端截止时间"]),
+    (EntitlementViolation, Block, ["Not entitled to trade", "取引権限がありません", "无交易权限"]),
+    (ShortSaleNotLocated, Block, ["Short sale not located", "空売りの借株手配が未完了", "卖空未落实借券"]),
+    (ClearlyErroneousPrice, Block, ["Clearly erroneous price", "明らかな誤発注価格", "明显错误的价格"]),
+    (FatFingerPrice, Block, ["Price too far from the market", "価格が市場から大きく乖離", "价格偏离市场过大"]),
+    (FatFingerWarning, Warning, ["Price away from the market", "価格が市場から乖離", "价格偏离市场"]),
+    (MaxOrderQuantity, Block, ["Order quantity limit exceeded", "注文数量上限超過", "超出订单数量限额"]),
+    (ScheduleLimit, Block, ["Scheduled trading limit exceeded", "時間帯別取引上限超過", "超出时段交易限额"]),
+    (ExDividendRisk, Warning, ["Ex-dividend risk", "権利落ちリスク", "除息风险"]),
+    (AlgoLimit, Block, ["Algo parameter limit exceeded", "アルゴパラメータ上限超過", "超出算法参数限额"]),
+    (AlgoNotProjected, Warning, ["Algo participation not projected", "アルゴ参加率を推計できません", "无法预估算法参与率"]),
+    (PositionLimit, Block, ["Position limit exceeded", "建玉上限超過", "超出持仓限额"]),
+    (MaxPositionNotional, Block, ["Position notional limit exceeded", "建玉想定元本上限超過", "超出持仓名义金额限额"]),
+    (MaxOrderNotional, Block, ["Order notional limit exceeded", "注文想定元本上限超過", "超出订单名义金额限额"]),
+    (ConfiguredLimit, Block, ["Risk limit exceeded", "リスク限度超過", "超出风险限额"]),
+    (VelocityLimit, Block, ["Order rate limit exceeded", "発注頻度上限超過", "超出下单频率限额"]),
+    (DailyNotionalLimit, Block, ["Daily notional limit exceeded", "日次想定元本上限超過", "超出每日名义金额限额"]),
+    (PoolNotionalLimit, Block, ["Shared limit pool notional exceeded", "共有リミットプールの想定元本上限超過", "超出共享限额池名义金额"]),
+    (FxRateUnavailable, Block, ["No FX rate into the base currency", "基準通貨への為替レートがありません", "无折算为基础货币的汇率"]),
+    (InsufficientMargin, Block, ["Insufficient margin", "証拠金不足", "保证金不足"]),
+    (GreekLimit, Block, ["Greek limit exceeded", "グリークス上限超過", "超出希腊字母限额"]),
+    (GreeksNotEvaluated, Block, ["Greek limits could not be evaluated", "グリークス上限を評価できません", "无法评估希腊字母限额"]),
+    (BetaLimit, Block, ["Beta exposure limit exceeded", "ベータエクスポージャー上限超過", "超出贝塔敞口限额"]),
+    (FxSettlementLimit, Block, ["FX settlement limit exceeded", "為替決済上限超過", "超出外汇结算限额"]),
+    (FxSettlementConcentration, Warning, ["FX settlement concentrated", "為替決済が集中", "外汇结算集中"]),
+    (FxSettlementNotAssessed, Warning, ["FX settlement not assessed", "為替決済を評価できません", "未评估外汇结算"]),
+    (FxExposureLimit, Block, ["FX exposure limit exceeded", "為替エクスポージャー上限超過", "超出外汇敞口限额"]),
+    (FxExposureNotAssessed, Warning, ["FX exposure not assessed", "為替エクスポージャーを評価できません", "未评估外汇敞口"]),
+    (ConcentrationLimit, Block, ["Concentration limit exceeded", "集中度上限超過", "超出集中度限额"]),
+    (ConcentrationWarning, Warning, ["Portfolio concentrated", "ポートフォリオが集中", "投资组合集中"]),
+    (WashTrade, Block, ["Order would trade against the same owner", "自己対当となる注文", "订单将与同一主体自成交"]),
+    (WashTradeWarning, Warning, ["Possible self-match", "自己対当の可能性", "可能自成交"]),
+    (UnfundedSettlement, Block, ["Unfunded settlement", "決済資金不足", "结算资金不足"]),
+    (BasketLinesRejected, Block, ["Basket lines rejected", "バスケット明細が拒否されました", "篮子订单明细被拒绝"]),
+    (BasketLimit, Block, ["Basket limit exceeded", "バスケット上限超過", "超出篮子限额"]),
+    (LargeOrder, Warning, ["Large order", "大口注文", "大额订单"]),
+    (HardToBorrow, Warning, ["Hard to borrow", "借株困難", "难以借券"]),
+    (BorrowSpecial, Warning, ["Borrow special", "特別貸株料銘柄", "特殊借券"]),
+    (Unclassified, Block, ["Other reason", "その他の理由", "其他原因"]),
+];
+
+/// Reason texts by how they start, checked in order, so a longer prefix must come before any shorter one it extends.
+const PREFIXES: [(&str, Code); 50] = [
+    ("Account suspended", AccountSuspended), ("Account closed", AccountClosed), ("Account is reduce-only", AccountReduceOnly), ("kill switch active", KillSwitch), ("Trading halted", TradingHalt), ("Event window blocks", EventWindowBlock),
+    ("Risk data unavailable", DependencyUnavailable), ("Market data unavailable", DependencyUnavailable), ("Degraded check", DegradedCheck), ("Deadline exceeded", DeadlineExceeded),
+    (entitlements::VIOLATION, EntitlementViolation), ("Short sale not located", ShortSaleNotLocated), ("Clearly erroneous price", ClearlyErroneousPrice),
+    ("Fat-finger price", FatFingerPrice), ("Fat-finger warning", FatFingerWarning), ("Account max order quantity", MaxOrderQuantity), ("Scheduled max", ScheduleLimit),
+    (corporate_actions::REASON, ExDividendRisk), ("Algo participation not projected", AlgoNotProjected), ("Algo max", AlgoLimit), ("Position limit exceeded", PositionLimit),
+    ("Account max position notional", MaxPositionNotional), ("Account max order notional", MaxOrderNotional), ("Velocity limit exceeded", VelocityLimit),
+    ("Daily account notional limit", DailyNotionalLimit), ("Daily instrument notional limit", DailyNotionalLimit), ("Limit pool", PoolNotionalLimit), ("No FX rate", FxRateUnavailable), ("Insufficient margin headroom", InsufficientMargin),
+    ("Greek limits not evaluated", GreeksNotEvaluated), ("Account max net", GreekLimit), ("Underlier max net", GreekLimit), ("Portfolio max net", GreekLimit), ("Account max beta exposure", BetaLimit),
+    ("Desk max beta exposure", BetaLimit), ("FX settlement max", FxSettlementLimit), ("FX settlement concentrated", FxSettlementConcentration),
+    ("FX settlement not assessed", FxSettlementNotAssessed), ("FX exposure max", FxExposureLimit), ("FX exposure not assessed", FxExposureNotAssessed), ("Concentration max", ConcentrationLimit), ("Concentration warning", ConcentrationWarning), ("Wash trade warning", WashTradeWarning), ("Wash trade:", WashTrade), ("Unfunded settlement obligation", UnfundedSettlement), ("Basket lines rejected", BasketLinesRejected),
+    ("Account max basket", BasketLimit), ("Large order flag", LargeOrder), ("Hard to borrow", HardToBorrow), ("Borrow special", BorrowSpecial),
+];
+
+/// The code a reason text stands for. Breaches of configured limits name the limit they came from.
+pub fn classify(reason: &str) -> Code {
+    if reason.contains("(limit ") { return ConfiguredLimit; }
+    PREFIXES.iter().find(|(p, _)| reason.starts_with(p)).map_or(Unclassified, |(_, c)| *c)
+}
+
+impl Code {
+    /// A numeric limit the order would breach. Only these open limit breaches and can be waived by an override; a
+    /// limit that could not be evaluated, for want of an FX rate or Greeks, is not one.
+    pub fn is_limit_breach(self) -> bool {
+        matches!(self, MaxOrderQuantity | ScheduleLimit | AlgoLimit | PositionLimit | MaxPositionNotional | MaxOrderNotional | ConfiguredLimit | VelocityLimit
+            | DailyNotionalLimit | PoolNotionalLimit | GreekLimit | BetaLimit | FxSettlementLimit | FxExposureLimit | ConcentrationLimit | BasketLimit)
+    }
+}
+
+fn snake(code: Code) -> String { serde_json::to_value(code).ok().and_then(|v| v.as_str().map(str::to_ascii_lowercase)).unwrap_or_default() }
+
+/// Every code with its default entry, overlaid with the entries an admin has set.
+#[derive(Default, Clone)]
+pub struct Catalog { overrides: BTreeMap<Code, Entry> }
+
+impl Catalog {
+    /// Admin-set entries are shown as written in every locale.
+    pub fn entry(&self, code: Code, locale: Locale) -> Entry {
+        self.overrides.get(&code).cloned().unwrap_or_else(|| {
+            let (_, severity, text) = DEFAULTS.iter().find(|d| d.0 == code).copied().unwrap_or((code, Block, ["Other reason"; 3]));
+            Entry { code, severity, text: text[locale as usize].into(), localization_key: format!("risk.reason.{}", snake(code)) }
+        })
+    }
+
+    pub fn entries(&self, locale: Locale) -> Vec<Entry> { DEFAULTS.iter().map(|d| self.entry(d.0, locale)).collect() }
+
+    pub fn reasons(&self, reasons: &[String], locale: Locale) -> Vec<Reason> { reasons.iter().map(|r| Reason { entry: self.entry(classify(r), locale), detail: r.clone() }).collect() }
+
+    /// Replaces the admin entries; codes left out go back to their defaults. Returns the entries it replaced.
+    pub fn set(&mut self, entries: Vec<Entry>) -> Result<Vec<Entry>, String> {
+        let mut next = BTreeMap::new();
+        for e in entries {
+            if e.text.trim().is_empty() || e.localization_key.trim().is_empty() { return Err(format!("{}: text and localization_key are required", snake(e.code))); }
+            if next.insert(e.code, e.clone()).is_some() { return Err(format!("{} is listed twice", snake(e.code))); }
+        }
+        Ok(std::mem::replace(&mut self.overrides, next).into_values().collect())
+    }
+
+    pub fn overrides(&self) -> Vec<Entry> { self.overrides.values().cloned().collect() }
+}
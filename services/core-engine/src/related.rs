@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Why two accounts are treated as one party: the same beneficial owner, or books run as one strategy.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind { BeneficialOwner, Strategy }
+
+#[derive(Deserialize)]
+pub struct LinkSpec { pub a: String, pub b: String, pub kind: LinkKind, pub note: Option<String> }
+
+#[derive(Serialize, Clone)]
+pub struct Link {
+    pub id: String, pub a: String, pub b: String, pub kind: LinkKind,
+    #[serde(skip_serializing_if = "Option::is_none")] pub note: Option<String>, pub created_at_ms: u64, pub created_by: String,
+}
+
+#[derive(Deserialize)]
+pub struct LinkQuery { pub account: Option<String> }
+
+/// An instrument held across a related group, in the firm's currency.
+#[derive(Serialize)]
+pub struct Exposure { pub instrument: String, pub net_notional: f64, pub gross_notional: f64, pub accounts: usize }
+
+/// Everything linked to `account`, directly or through other linked accounts, and what the group holds together.
+#[derive(Serialize)]
+pub struct RelatedReport {
+    pub account: String, pub accounts: Vec<String>, pub links: Vec<Link>, pub currency: String,
+    pub gross_notional: f64, pub net_notional: f64, pub exposure: Vec<Exposure>, pub unconverted: Vec<String>, pub as_of_ms: u64,
+}
+
+/// Undirected links between accounts. Linked accounts form groups: the connected components of the graph.
+#[derive(Default, Clone)]
+pub struct RelatedAccounts { links: Vec<Link>, next_id: u64 }
+
+impl RelatedAccounts {
+    pub fn list(&self, account: Option<&str>) -> Vec<Link> {
+        self.links.iter().filter(|l| account.is_none_or(|a| l.a == a || l.b == a)).cloned().collect()
+    }
+
+    pub fn add(&mut self, spec: LinkSpec, by: &str, now_ms: u64) -> Result<Link, String> {
+        let (a, b) = (spec.a.trim(), spec.b.trim());
+        if a.is_empty() || b.is_empty() { return Err("a and b are required".into()); }
+        if a == b { return Err("an account cannot be linked to itself".into()); }
+        if self.links.iter().any(|l| l.kind == spec.kind && ((l.a == a && l.b == b) || (l.a == b && l.b == a))) { return Err(format!("{a} and {b} are already linked")); }
+        self.next_id += 1;
+        let link = Link { id: format!("lnk-{}", self.next_id), a: a.into(), b: b.into(), kind: spec.kind, note: spec.note, created_at_ms: now_ms, created_by: by.into() };
+        self.links.push(link.clone());
+        Ok(link)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Link> {
+        let i = self.links.iter().position(|l| l.id == id)?;
+        Some(self.links.remove(i))
+    }
+
+    /// `account` and every account linked to it, sorted; just `account` when it has no links.
+    pub fn group(&self, account: &str) -> Vec<String> {
+        let mut seen = BTreeSet::from([account.to_string()]);
+        let mut queue = VecDeque::from([account.to_string()]);
+        while let Some(next) = queue.pop_front() {
+            for l in &self.links {
+                let other = if l.a == next { &l.b } else if l.b == next { &l.a } else { continue };
+                if seen.insert(other.clone()) { queue.push_back(other.clone()); }
+            }
+        }
+        seen.into_iter().collect()
+    }
+
+    /// Links between members of `group`.
+    pub fn within(&self, group: &[String]) -> Vec<Link> {
+        self.links.iter().filter(|l| group.contains(&l.a)).cloned().collect()
+    }
+
+    /// Groups of two or more accounts.
+    pub fn groups(&self) -> Vec<Vec<String>> {
+        let mut out: Vec<Vec<String>> = Vec::new();
+        for l in &self.links {
+            if !out.iter().any(|g| g.contains(&l.a)) { out.push(self.group(&l.a)); }
+        }
+        out
+    }
+}
+
+/// Gross and net notional and exposure by instrument, largest net first, from each member's signed notionals.
+pub fn aggregate(books: &[Vec<(String, f64)>]) -> (f64, f64, Vec<Exposure>) {
+    let mut by_instrument: BTreeMap<&str, (f64, f64, usize)> = BTreeMap::new();
+    for book in books {
+        for (i, n) in book {
+            let x = by_instrument.entry(i).or_default();
+            (x.0, x.1, x.2) = (x.0 + n, x.1 + n.abs(), x.2 + 1);
+        }
+    }
+    let (gross, net) = by_instrument.values().fold((0.0, 0.0), |(g, n), x| (g + x.1, n + x.0));
+    let mut exposure: Vec<Exposure> = by_instrument.into_iter().map(|(i, (net, gross, accounts))| Exposure { instrument: i.into(), net_notional: net, gross_notional: gross, accounts }).collect();
+    exposure.sort_by(|a, b| b.net_notional.abs().total_cmp(&a.net_notional.abs()).then_with(|| a.instrument.cmp(&b.instrument)));
+    (gross, net, exposure)
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationStatus { Reserved, Committed, Released, Expired }
+
+#[derive(Serialize, Clone)]
+pub struct ReservedLeg { pub instrument: String, pub notional: f64 }
+
+/// Daily-notional and margin headroom held for an approved order until it fills, cancels or times out. `notional`
+/// is what the account allowance holds (net for a package); `legs` is what each instrument allowance holds.
+#[derive(Serialize, Clone)]
+pub struct Reservation {
+    pub id: String, pub check_id: String, pub account: String, pub instrument: String, pub notional: f64, pub margin: f64, pub legs: Vec<ReservedLeg>,
+    pub created_at_ms: u64, pub expires_at_ms: u64, pub status: ReservationStatus, pub filled_notional: Option<f64>, pub closed_at_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct CommitRequest { pub filled_notional: Option<f64> }
+
+#[derive(Deserialize)]
+pub struct ReservationQuery { pub account: Option<String>, pub status: Option<ReservationStatus>, pub limit: Option<usize> }
+
+impl Reservation {
+    /// Instrument allowances in the shape the daily book takes, scaled by `ratio` for partial fills.
+    pub fn leg_notionals(&self, ratio: f64) -> Vec<(&str, f64)> { self.legs.iter().map(|l| (l.instrument.as_str(), l.notional * ratio)).collect() }
+}
+
+pub struct Reservations { items: HashMap<String, Reservation>, pub ttl_ms: u64 }
+
+impl Reservations {
+    pub fn new(ttl_ms: u64) -> Self { Self { items: HashMap::new(), ttl_ms } }
+
+    pub fn reserve(&mut self, check_id: &str, account: &str, notional: f64, margin: f64, legs: &[(&str, f64)], now_ms: u64) -> Reservation {
+        let r = Reservation {
+            id: format!("RSV-{}", uuid::Uuid::new_v4().simple()), check_id: check_id.into(), account: account.into(), instrument: legs.first().map(|l| l.0.into()).unwrap_or_default(), notional, margin,
+            legs: legs.iter().map(|(i, n)| ReservedLeg { instrument: (*i).into(), notional: *n }).collect(),
+            created_at_ms: now_ms, expires_at_ms: now_ms + self.ttl_ms, status: ReservationStatus::Reserved, filled_notional: None, closed_at_ms: None,
+        };
+        self.items.insert(r.id.clone(), r.clone());
+        r
+    }
+
+    /// Open reservations on the account, oldest first.
+    pub fn open(&self, account: &str) -> Vec<Reservation> {
+        let mut v: Vec<Reservation> = self.items.values().filter(|r| r.status == ReservationStatus::Reserved && r.account == account).cloned().collect();
+        v.sort_by_key(|r| r.created_at_ms);
+        v
+    }
+
+    /// Margin held by open reservations on the account.
+    pub fn held_margin(&self, account: &str) -> f64 {
+        self.items.values().filter(|r| r.status == ReservationStatus::Reserved && r.account == account).map(|r| r.margin).sum()
+    }
+
+    /// Closes an open reservation. A commit may fill less than was reserved; the remainder is simply released.
+    pub fn close(&mut self, id: &str, status: ReservationStatus, filled_notional: Option<f64>, now_ms: u64) -> Result<Reservation, String> {
+        let r = self.items.get_mut(id).ok_or("Reservation not found")?;
+        if r.status != ReservationStatus::Reserved { return Err(format!("reservation is already {:?}", r.status).to_lowercase()); }
+        if filled_notional.is_some_and(|f| !(0.0..=r.notional * (1.0 + 1e-9)).contains(&f)) { return Err(format!("filled_notional must be between 0 and {:.2}", r.notional)); }
+        r.status = status;
+        r.filled_notional = filled_notional;
+        r.closed_at_ms = Some(now_ms);
+        Ok(r.clone())
+    }
+
+    /// Expires every open reservation past its deadline and returns them so their headroom can be handed back.
+    pub fn expire(&mut self, now_ms: u64) -> Vec<Reservation> {
+        let ids: Vec<String> = self.items.values().filter(|r| r.status == ReservationStatus::Reserved && now_ms >= r.expires_at_ms).map(|r| r.id.clone()).collect();
+        ids.iter().filter_map(|id| self.close(id, ReservationStatus::Expired, None, now_ms).ok()).collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Reservation> { self.items.get(id) }
+
+    /// Newest first.
+    pub fn list(&self, q: &ReservationQuery) -> Vec<Reservation> {
+        let mut v: Vec<Reservation> = self.items.values()
+            .filter(|r| q.account.as_ref().is_none_or(|a| &r.account == a) && q.status.is_none_or(|s| r.status == s)).cloned().collect();
+        v.sort_by_key(|r| std::cmp::Reverse(r.created_at_ms));
+        v.truncate(q.limit.unwrap_or(100));
+        v
+    }
+}
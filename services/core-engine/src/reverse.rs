@@ -0,0 +1,66 @@
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioKind { Joint, SingleFactor }
+
+#[derive(Serialize)]
+pub struct Shock { pub instrument: String, pub shock_pct: f64, pub z_score: f64 }
+
+/// `distance` is the Mahalanobis norm of the shock vector in daily-vol units; lower is more plausible.
+#[derive(Serialize)]
+pub struct Scenario { pub kind: ScenarioKind, pub shocks: Vec<Shock>, pub loss: f64, pub distance: f64 }
+
+#[derive(Serialize)]
+pub struct ReverseResult { pub loss_threshold: f64, pub feasible: bool, pub scenarios: Vec<Scenario> }
+
+/// Prices cannot fall below zero, so no shock is allowed past -100%.
+const FLOOR: f64 = -1.0;
+const MAX_SCENARIOS: usize = 10;
+
+/// Smallest joint move (independent factors scaled by daily vol) that loses `threshold`, found by minimising
+/// the squared z-score norm subject to the loss constraint. Legs pinned at the price floor drop out of the
+/// free set and the remaining shortfall is re-solved until every free shock is admissible.
+fn joint(legs: &[(String, f64, f64)], vol: &[f64], threshold: f64) -> Option<Vec<f64>> {
+    let exposure: Vec<f64> = legs.iter().map(|(_, q, p)| q * p).collect();
+    let mut shocks = vec![0.0; legs.len()];
+    let mut free: Vec<bool> = exposure.iter().zip(vol).map(|(a, v)| *a != 0.0 && *v > 0.0).collect();
+    loop {
+        let pinned: f64 = (0..legs.len()).filter(|&i| !free[i]).map(|i| -exposure[i] * shocks[i]).sum();
+        let need = threshold - pinned;
+        if need <= 0.0 { return Some(shocks); }
+        let denom: f64 = (0..legs.len()).filter(|&i| free[i]).map(|i| (exposure[i] * vol[i]).powi(2)).sum();
+        if denom == 0.0 { return None; }
+        for i in (0..legs.len()).filter(|&i| free[i]) { shocks[i] = -need * vol[i].powi(2) * exposure[i] / denom; }
+        let over: Vec<usize> = (0..legs.len()).filter(|&i| free[i] && shocks[i] < FLOOR).collect();
+        if over.is_empty() { return Some(shocks); }
+        for i in over { shocks[i] = FLOOR; free[i] = false; }
+    }
+}
+
+fn scenario(kind: ScenarioKind, legs: &[(String, f64, f64)], vol: &[f64], shocks: &[f64]) -> Scenario {
+    let loss = legs.iter().zip(shocks).map(|((_, q, p), r)| -q * p * r).sum();
+    let shocks: Vec<Shock> = legs.iter().zip(vol).zip(shocks).filter(|(_, r)| **r != 0.0)
+        .map(|(((instrument, _, _), v), r)| Shock { instrument: instrument.clone(), shock_pct: r * 100.0, z_score: r / v }).collect();
+    let distance = shocks.iter().map(|s| s.z_score.powi(2)).sum::<f64>().sqrt();
+    Scenario { kind, shocks, loss, distance }
+}
+
+/// Searches the shock space for moves that lose at least `threshold`: the most plausible joint scenario, then every
+/// single-instrument move that breaches on its own, ordered by plausibility.
+pub fn search(legs: &[(String, f64, f64)], vol: impl Fn(&str) -> f64, threshold: f64) -> ReverseResult {
+    let legs: Vec<(String, f64, f64)> = legs.iter().filter(|(_, q, p)| *q != 0.0 && *p > 0.0).cloned().collect();
+    let vol: Vec<f64> = legs.iter().map(|(i, _, _)| vol(i)).collect();
+    let mut scenarios = Vec::new();
+    if threshold <= 0.0 {
+        return ReverseResult { loss_threshold: threshold, feasible: true, scenarios: vec![scenario(ScenarioKind::Joint, &legs, &vol, &vec![0.0; legs.len()])] };
+    }
+    if let Some(shocks) = joint(&legs, &vol, threshold) { scenarios.push(scenario(ScenarioKind::Joint, &legs, &vol, &shocks)); }
+    let mut singles: Vec<Scenario> = legs.iter().enumerate().filter(|(i, _)| vol[*i] > 0.0).filter_map(|(i, (_, q, p))| {
+        let r = -threshold / (q * p);
+        (r >= FLOOR).then(|| { let mut shocks = vec![0.0; legs.len()]; shocks[i] = r; scenario(ScenarioKind::SingleFactor, &legs, &vol, &shocks) })
+    }).collect();
+    singles.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    scenarios.extend(singles.into_iter().take(MAX_SCENARIOS - scenarios.len()));
+    ReverseResult { loss_threshold: threshold, feasible: !scenarios.is_empty(), scenarios }
+}
@@ -0,0 +1,258 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetClass { Equity, Rates, Fx, Commodity, Credit }
+
+/// How an instrument responds to scenario factors. `currency` is the quote currency for FX moves, `duration`
+/// the price sensitivity to a parallel rates shift, `spread_duration` to a credit spread shift (`duration` when
+/// unset) and `vega` the value per unit for a 1% relative vol rise.
+/// `sector` and `beta` drive the exposure checks on basket trades; `option` marks a listed option on `underlying`
+/// and `future` a listed future.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct InstrumentFactors {
+    pub asset_class: AssetClass, pub currency: Option<String>, pub duration: Option<f64>, pub spread_duration: Option<f64>, pub vega: Option<f64>, pub sector: Option<String>, pub beta: Option<f64>,
+    pub option: Option<OptionTerms>, pub future: Option<FutureTerms>,
+}
+
+/// What an instrument is for Greeks: options and futures count towards their underlier, anything held outright
+/// (`cash`) towards itself.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum InstrumentKind { Cash, Future, Option }
+
+impl InstrumentFactors {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.duration.is_some_and(|d| !d.is_finite()) || self.vega.is_some_and(|v| !v.is_finite()) { return Err("duration and vega must be finite".into()); }
+        let positive = |v: Option<f64>| v.is_none_or(|v| v.is_finite() && v > 0.0);
+        match (&self.option, &self.future) {
+            (Some(_), Some(_)) => Err("an instrument is an option or a future, not both".into()),
+            (Some(o), None) if o.underlying.trim().is_empty() => Err("option underlying must not be empty".into()),
+            (Some(o), None) if !(positive(Some(o.strike)) && positive(o.multiplier) && positive(o.implied_vol)) => Err("option strike, multiplier and implied_vol must be positive".into()),
+            (None, Some(f)) if f.underlying.trim().is_empty() => Err("future underlying must not be empty".into()),
+            (None, Some(f)) if !positive(f.multiplier) => Err("future multiplier must be positive".into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionRight { Call, Put }
+
+/// `multiplier` is units of the underlier per contract.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct FutureTerms { pub underlying: String, pub expiry: Option<NaiveDate>, pub multiplier: Option<f64> }
+
+/// `implied_vol` is annualised; without it Greeks use the underlier's daily vol scaled to a year.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct OptionTerms { pub underlying: String, pub right: OptionRight, pub strike: f64, pub expiry: Option<NaiveDate>, pub multiplier: Option<f64>, pub implied_vol: Option<f64> }
+
+/// Price steps hit their asset class unless `instruments` narrows them to a named set. `Market` moves every
+/// price alike and is what a bare `shock_pct` runs.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Step {
+    Equity { shock_pct: f64, #[serde(default)] instruments: Vec<String> },
+    Commodity { shock_pct: f64, #[serde(default)] instruments: Vec<String> },
+    Volatility { shock_pct: f64, #[serde(default)] instruments: Vec<String> },
+    Fx { currency: String, shock_pct: f64 },
+    Rates { shift_bps: f64, currency: Option<String> },
+    Credit { shift_bps: f64, #[serde(default)] instruments: Vec<String> },
+    Market { shock_pct: f64 },
+}
+
+impl Step {
+    fn kind(&self) -> &'static str {
+        match self {
+            Step::Equity { .. } => "equity", Step::Commodity { .. } => "commodity", Step::Volatility { .. } => "volatility", Step::Fx { .. } => "fx", Step::Rates { .. } => "rates",
+            Step::Credit { .. } => "credit", Step::Market { .. } => "market",
+        }
+    }
+
+    /// Price return this step applies to an instrument, if it touches it at all.
+    fn price_return(&self, instrument: &str, f: Option<&InstrumentFactors>) -> Option<f64> {
+        let picks = |names: &Vec<String>, class: AssetClass| if names.is_empty() { f.is_some_and(|f| f.asset_class == class) } else { names.iter().any(|n| n == instrument) };
+        match self {
+            Step::Equity { shock_pct, instruments } => picks(instruments, AssetClass::Equity).then_some(shock_pct / 100.0),
+            Step::Commodity { shock_pct, instruments } => picks(instruments, AssetClass::Commodity).then_some(shock_pct / 100.0),
+            Step::Fx { currency, shock_pct } => f.and_then(|f| f.currency.as_deref()).filter(|c| c.eq_ignore_ascii_case(currency)).map(|_| shock_pct / 100.0),
+            Step::Rates { shift_bps, currency } => f.filter(|f| currency.as_ref().is_none_or(|c| f.currency.as_ref().is_some_and(|fc| fc.eq_ignore_ascii_case(c))))
+                .and_then(|f| f.duration).map(|d| -d * shift_bps / 10_000.0),
+            Step::Credit { shift_bps, instruments } => f.filter(|_| picks(instruments, AssetClass::Credit)).and_then(|f| f.spread_duration.or(f.duration)).map(|d| -d * shift_bps / 10_000.0),
+            Step::Market { shock_pct } => Some(shock_pct / 100.0),
+            Step::Volatility { .. } => None,
+        }
+    }
+}
+
+/// Multiplicative compounds each step on the prices left by the previous ones; additive sums the raw returns.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Compounding { Additive, #[default] Multiplicative }
+
+/// `packaged` marks a scenario shipped with the engine that has not been edited since.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Scenario {
+    #[serde(default)] pub name: String, #[serde(default)] pub version: u32, pub description: Option<String>, #[serde(default)] pub compounding: Compounding, pub steps: Vec<Step>,
+    #[serde(default)] pub packaged: bool,
+}
+
+#[derive(Serialize)]
+pub struct StepResult { pub step: usize, pub kind: &'static str, pub pnl: f64, pub instruments_affected: usize }
+
+#[derive(Serialize)]
+pub struct InstrumentResult { pub instrument: String, pub return_pct: f64, pub vega_pnl: f64, pub pnl: f64 }
+
+/// `linear_options` are options that could not be repriced (no expiry or no underlier price) and fell back to
+/// their own factor mapping.
+#[derive(Serialize)]
+pub struct Outcome {
+    pub compounding: Compounding, pub pnl: f64, pub steps: Vec<StepResult>, pub instruments: Vec<InstrumentResult>, #[serde(skip_serializing_if = "Vec::is_empty")] pub unclassified: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub linear_options: Vec<String>,
+}
+
+/// Market state an option is repriced from: the underlier's price and the annualised vol to use.
+#[derive(Clone, Copy)]
+pub struct Underlier { pub spot: f64, pub vol: f64, pub today: NaiveDate }
+
+/// Committee-defined scenarios plus the factor mapping they are evaluated against.
+#[derive(Default, Clone)]
+pub struct ScenarioLibrary { factors: HashMap<String, InstrumentFactors>, scenarios: BTreeMap<String, Scenario> }
+
+/// Historical episodes shipped with the engine, as peak-to-trough moves in each factor. Equity and vol moves
+/// follow the S&P 500 and VIX; rates and credit the US Treasury curve and US corporate spreads.
+fn packaged() -> Vec<Scenario> {
+    let scenario = |name: &str, description: &str, steps: Vec<Step>| Scenario { name: name.into(), version: 1, description: Some(description.into()), compounding: Compounding::Multiplicative, steps, packaged: true };
+    let equity = |shock_pct| Step::Equity { shock_pct, instruments: Vec::new() };
+    let commodity = |shock_pct| Step::Commodity { shock_pct, instruments: Vec::new() };
+    let vol = |shock_pct| Step::Volatility { shock_pct, instruments: Vec::new() };
+    let credit = |shift_bps| Step::Credit { shift_bps, instruments: Vec::new() };
+    let fx = |currency: &str, shock_pct| Step::Fx { currency: currency.into(), shock_pct };
+    vec![
+        scenario("gfc-2008", "Global financial crisis, September to November 2008", vec![
+            equity(-40.0), vol(150.0), Step::Rates { shift_bps: -150.0, currency: None }, credit(400.0), commodity(-35.0), fx("EUR", -15.0), fx("GBP", -25.0), fx("JPY", 20.0),
+        ]),
+        scenario("covid-crash-2020", "COVID-19 sell-off, 19 February to 23 March 2020", vec![
+            equity(-34.0), vol(250.0), Step::Rates { shift_bps: -110.0, currency: None }, credit(350.0), commodity(-50.0), fx("AUD", -15.0),
+        ]),
+        scenario("flash-crash-2010", "Intraday flash crash of 6 May 2010", vec![equity(-9.0), vol(50.0), Step::Rates { shift_bps: -15.0, currency: None }, credit(25.0)]),
+    ]
+}
+
+impl ScenarioLibrary {
+    /// An empty factor mapping and the packaged scenarios.
+    pub fn with_packaged() -> Self { Self { factors: HashMap::new(), scenarios: packaged().into_iter().map(|s| (s.name.clone(), s)).collect() } }
+
+    pub fn factors(&self) -> HashMap<String, InstrumentFactors> { self.factors.clone() }
+    pub fn factor(&self, instrument: &str) -> Option<&InstrumentFactors> { self.factors.get(instrument) }
+    pub fn set_factors(&mut self, instrument: &str, f: InstrumentFactors) { self.factors.insert(instrument.into(), f); }
+
+    pub fn get(&self, name: &str) -> Option<&Scenario> { self.scenarios.get(name) }
+    pub fn list(&self) -> Vec<Scenario> { self.scenarios.values().cloned().collect() }
+    pub fn remove(&mut self, name: &str) -> bool { self.scenarios.remove(name).is_some() }
+
+    pub fn set(&mut self, name: &str, mut scn: Scenario) -> Result<Scenario, String> {
+        validate(&scn.steps)?;
+        scn.name = name.into();
+        scn.packaged = false;
+        scn.version = self.scenarios.get(name).map_or(1, |s| s.version + 1);
+        self.scenarios.insert(name.into(), scn.clone());
+        Ok(scn)
+    }
+
+    /// Replaces every scenario with `scenarios`, keeping the factor mapping. A scenario whose description,
+    /// compounding and steps are unchanged keeps its version and packaged mark; any other counts as an edit.
+    pub fn replace(&mut self, scenarios: Vec<Scenario>) -> Result<(), String> {
+        let content = |s: &Scenario| serde_json::to_value((&s.description, s.compounding, &s.steps)).unwrap_or_default();
+        let mut next = BTreeMap::new();
+        for mut scn in scenarios {
+            if scn.name.trim().is_empty() { return Err("scenarios need a name".into()); }
+            validate(&scn.steps).map_err(|e| format!("scenario {}: {e}", scn.name))?;
+            match self.scenarios.get(&scn.name) {
+                Some(cur) if content(cur) == content(&scn) => scn = cur.clone(),
+                cur => (scn.version, scn.packaged) = (cur.map_or(1, |c| c.version + 1), false),
+            }
+            if let Some(dup) = next.insert(scn.name.clone(), scn) { return Err(format!("scenario {} appears twice", dup.name)); }
+        }
+        self.scenarios = next;
+        Ok(())
+    }
+
+    /// Applies the steps in order to each leg. Volatility steps only move vega P&L, which is added on top of the price P&L.
+    /// Options are fully revalued off their underlier when `underlier` can price them.
+    pub fn run(&self, steps: &[Step], compounding: Compounding, legs: &[(String, f64, f64)], underlier: impl Fn(&OptionTerms) -> Option<Underlier>) -> Outcome {
+        let mut results: Vec<StepResult> = steps.iter().enumerate().map(|(i, s)| StepResult { step: i + 1, kind: s.kind(), pnl: 0.0, instruments_affected: 0 }).collect();
+        let (mut unclassified, mut linear_options) = (Vec::new(), Vec::new());
+        let instruments = legs.iter().map(|(instrument, q, price)| {
+            let f = self.factors.get(instrument);
+            if f.is_none() { unclassified.push(instrument.clone()); }
+            if let Some(o) = f.and_then(|f| f.option.as_ref()) {
+                match underlier(o).and_then(|u| self.revalue(o, (instrument, *q), steps, compounding, u, &mut results)) {
+                    Some(r) => return r,
+                    None => linear_options.push(instrument.clone()),
+                }
+            }
+            let (mut ret, mut vega_pnl) = (0.0f64, 0.0);
+            for (step, res) in steps.iter().zip(results.iter_mut()) {
+                let before = ret;
+                if let Step::Volatility { shock_pct, instruments } = step {
+                    let hit = if instruments.is_empty() { f.is_some_and(|f| f.vega.is_some()) } else { instruments.iter().any(|n| n == instrument) };
+                    if let Some(v) = f.and_then(|f| f.vega).filter(|_| hit) { let pnl = q * v * shock_pct; vega_pnl += pnl; res.pnl += pnl; res.instruments_affected += 1; }
+                    continue;
+                }
+                let Some(r) = step.price_return(instrument, f) else { continue };
+                ret = match compounding { Compounding::Additive => ret + r, Compounding::Multiplicative => (1.0 + ret) * (1.0 + r) - 1.0 }.max(-1.0);
+                res.pnl += q * price * (ret - before);
+                res.instruments_affected += 1;
+            }
+            InstrumentResult { instrument: instrument.clone(), return_pct: ret * 100.0, vega_pnl, pnl: q * price * ret + vega_pnl }
+        }).collect::<Vec<_>>();
+        Outcome { compounding, pnl: instruments.iter().map(|i| i.pnl).sum(), steps: results, instruments, unclassified, linear_options }
+    }
+
+    /// Reprices an option after every step: price steps move its underlier (they hit it when they would hit the
+    /// underlier, or name the option itself) and volatility steps scale its vol. Repricing each step, rather than
+    /// applying a delta, is what brings gamma and vega into the P&L.
+    fn revalue(&self, o: &OptionTerms, (instrument, q): (&str, f64), steps: &[Step], compounding: Compounding, u: Underlier, results: &mut [StepResult]) -> Option<InstrumentResult> {
+        let units = q * o.multiplier.unwrap_or(1.0);
+        let fu = self.factors.get(&o.underlying);
+        let start = crate::greeks::value(o, u.spot, u.vol, u.today)?;
+        let (mut ret, mut vol, mut value, mut vega_pnl) = (0.0f64, u.vol, start, 0.0);
+        for (step, res) in steps.iter().zip(results.iter_mut()) {
+            match step {
+                Step::Volatility { shock_pct, instruments } => {
+                    if !instruments.is_empty() && !instruments.iter().any(|n| n == instrument || *n == o.underlying) { continue; }
+                    vol = (vol * (1.0 + shock_pct / 100.0)).max(0.0);
+                }
+                _ => {
+                    let Some(r) = step.price_return(&o.underlying, fu).or_else(|| step.price_return(instrument, self.factors.get(instrument))) else { continue };
+                    ret = match compounding { Compounding::Additive => ret + r, Compounding::Multiplicative => (1.0 + ret) * (1.0 + r) - 1.0 }.max(-1.0);
+                }
+            }
+            let next = crate::greeks::value(o, u.spot * (1.0 + ret), vol, u.today)?;
+            let pnl = units * (next - value);
+            if matches!(step, Step::Volatility { .. }) { vega_pnl += pnl; }
+            res.pnl += pnl;
+            res.instruments_affected += 1;
+            value = next;
+        }
+        let return_pct = if start > 0.0 { (value / start - 1.0) * 100.0 } else { 0.0 };
+        Some(InstrumentResult { instrument: instrument.into(), return_pct, vega_pnl, pnl: units * (value - start) })
+    }
+}
+
+pub fn validate(steps: &[Step]) -> Result<(), String> {
+    if steps.is_empty() { return Err("scenario needs at least one step".into()); }
+    for (i, s) in steps.iter().enumerate() {
+        let v = match s {
+            Step::Equity { shock_pct, .. } | Step::Commodity { shock_pct, .. } | Step::Volatility { shock_pct, .. } | Step::Fx { shock_pct, .. } | Step::Market { shock_pct } => *shock_pct,
+            Step::Rates { shift_bps, .. } | Step::Credit { shift_bps, .. } => *shift_bps,
+        };
+        if !v.is_finite() { return Err(format!("step {} must have a finite shock", i + 1)); }
+        if matches!(s, Step::Equity { .. } | Step::Commodity { .. } | Step::Fx { .. } | Step::Market { .. }) && v < -100.0 { return Err(format!("step {} cannot shock prices below -100%", i + 1)); }
+    }
+    Ok(())
+}
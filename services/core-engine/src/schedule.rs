@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DAY_MIN: i64 = 24 * 60;
+
+/// Recurring window in venue-local time ("HH:MM"); `start > end` wraps past midnight for overnight sessions.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SessionWindow { pub name: String, pub start: String, pub end: String, #[serde(flatten)] pub rule: WindowRule }
+
+/// One-off window around a scheduled release, in epoch milliseconds.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct EventWindow { pub name: String, pub start_ms: u64, pub end_ms: u64, #[serde(flatten)] pub rule: WindowRule }
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct WindowRule { pub max_quantity: Option<f64>, pub max_notional: Option<f64>, pub risk_threshold: Option<f64> }
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct GroupSchedule {
+    #[serde(default)] pub group: String,
+    pub instruments: Vec<String>,
+    #[serde(default)] pub utc_offset_minutes: i64,
+    #[serde(default)] pub windows: Vec<SessionWindow>,
+    #[serde(default)] pub events: Vec<EventWindow>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct ActiveRule { pub windows: Vec<String>, #[serde(flatten)] pub rule: WindowRule }
+
+fn parse_hhmm(s: &str) -> Option<i64> {
+    let (h, m) = s.split_once(':')?;
+    let (h, m): (i64, i64) = (h.parse().ok()?, m.parse().ok()?);
+    ((0..24).contains(&h) && (0..60).contains(&m)).then_some(h * 60 + m)
+}
+
+fn tighter(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) { (Some(a), Some(b)) => Some(a.min(b)), (a, b) => a.or(b) }
+}
+
+#[derive(Default, Clone)]
+pub struct Schedules { groups: HashMap<String, GroupSchedule> }
+
+impl Schedules {
+    pub fn list(&self) -> Vec<GroupSchedule> { self.groups.values().cloned().collect() }
+    pub fn remove(&mut self, group: &str) -> bool { self.groups.remove(group).is_some() }
+
+    pub fn set(&mut self, group: &str, mut sched: GroupSchedule) -> Result<GroupSchedule, String> {
+        for w in &sched.windows {
+            if parse_hhmm(&w.start).is_none() || parse_hhmm(&w.end).is_none() { return Err(format!("window '{}' must use HH:MM times", w.name)); }
+        }
+        if let Some(e) = sched.events.iter().find(|e| e.end_ms <= e.start_ms) { return Err(format!("event '{}' ends before it starts", e.name)); }
+        sched.group = group.into();
+        self.groups.insert(group.into(), sched.clone());
+        Ok(sched)
+    }
+
+    /// Replaces every group's schedule with `schedules`.
+    pub fn replace(&mut self, schedules: Vec<GroupSchedule>) -> Result<(), String> {
+        let mut next = Schedules::default();
+        for sched in schedules {
+            let group = sched.group.clone();
+            if group.trim().is_empty() { return Err("schedules need a group".into()); }
+            if next.groups.contains_key(&group) { return Err(format!("schedule {group} appears twice")); }
+            next.set(&group, sched).map_err(|e| format!("schedule {group}: {e}"))?;
+        }
+        *self = next;
+        Ok(())
+    }
+
+    /// Merges every window active for the instrument at `now_ms`, keeping the tightest value of each rule.
+    pub fn active(&self, instrument: &str, now_ms: u64) -> ActiveRule {
+        let mut out = ActiveRule::default();
+        for g in self.groups.values().filter(|g| g.instruments.iter().any(|i| i == instrument)) {
+            let local = ((now_ms / 60_000) as i64 + g.utc_offset_minutes).rem_euclid(DAY_MIN);
+            let session = g.windows.iter().filter(|w| match (parse_hhmm(&w.start), parse_hhmm(&w.end)) {
+                (Some(s), Some(e)) if s <= e => (s..e).contains(&local),
+                (Some(s), Some(e)) => local >= s || local < e,
+                _ => false,
+            }).map(|w| (&w.name, &w.rule));
+            let events = g.events.iter().filter(|e| (e.start_ms..e.end_ms).contains(&now_ms)).map(|e| (&e.name, &e.rule));
+            for (name, r) in session.chain(events) {
+                out.windows.push(format!("{}/{name}", g.group));
+                out.rule.max_quantity = tighter(out.rule.max_quantity, r.max_quantity);
+                out.rule.max_notional = tighter(out.rule.max_notional, r.max_notional);
+                out.rule.risk_threshold = tighter(out.rule.risk_threshold, r.risk_threshold);
+            }
+        }
+        out
+    }
+}
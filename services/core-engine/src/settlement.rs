@@ -0,0 +1,77 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Settlement cycle in business days per market. Orders name their market through `venue`; anything unmapped
+/// settles on `default_days`.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Conventions { pub default_days: u32, #[serde(default)] pub markets: BTreeMap<String, u32> }
+
+/// A cash movement due on `value_date`: negative for purchases, positive for sale proceeds.
+#[derive(Serialize, Clone)]
+pub struct Flow { pub value_date: NaiveDate, pub amount: f64, pub instrument: String, pub check_id: String }
+
+#[derive(Deserialize)]
+pub struct CashUpdate { pub settled: f64 }
+
+#[derive(Serialize)]
+pub struct ValueDate { pub value_date: NaiveDate, pub inflows: f64, pub outflows: f64, pub projected_balance: f64 }
+
+#[derive(Serialize)]
+pub struct Projection { pub account: String, pub as_of: NaiveDate, pub settled: f64, pub unsettled: f64, pub by_value_date: Vec<ValueDate> }
+
+#[derive(Default)]
+struct Ledger { base: f64, flows: Vec<Flow> }
+
+/// Only accounts whose settled cash has been loaded are projected and checked.
+pub struct SettlementBook { pub conventions: Conventions, ledgers: HashMap<String, Ledger> }
+
+/// Adds `days` business days, skipping weekends.
+pub fn add_business_days(mut d: NaiveDate, days: u32) -> NaiveDate {
+    let mut left = days;
+    while left > 0 {
+        d = d.succ_opt().unwrap_or(d);
+        if !matches!(d.weekday(), Weekday::Sat | Weekday::Sun) { left -= 1; }
+    }
+    d
+}
+
+impl SettlementBook {
+    pub fn new(default_days: u32) -> Self { Self { conventions: Conventions { default_days, markets: BTreeMap::new() }, ledgers: HashMap::new() } }
+
+    pub fn value_date(&self, market: Option<&str>, trade_date: NaiveDate) -> NaiveDate {
+        let days = market.and_then(|m| self.conventions.markets.get(m)).copied().unwrap_or(self.conventions.default_days);
+        add_business_days(trade_date, days)
+    }
+
+    pub fn tracked(&self, account: &str) -> bool { self.ledgers.contains_key(account) }
+
+    /// Sets today's settled cash; flows already due by `today` are folded in so they are not counted twice.
+    pub fn set_settled(&mut self, account: &str, settled: f64, today: NaiveDate) {
+        let l = self.ledgers.entry(account.into()).or_default();
+        l.flows.retain(|f| f.value_date > today);
+        l.base = settled;
+    }
+
+    pub fn book(&mut self, account: &str, flows: Vec<Flow>) {
+        if let Some(l) = self.ledgers.get_mut(account) { l.flows.extend(flows); }
+    }
+
+    /// Cash balance on each value date from today on, with `extra` flows included as if already booked.
+    pub fn project(&self, account: &str, today: NaiveDate, extra: &[Flow]) -> Option<Projection> {
+        let l = self.ledgers.get(account)?;
+        let all = l.flows.iter().chain(extra);
+        let settled = l.base + all.clone().filter(|f| f.value_date <= today).map(|f| f.amount).sum::<f64>();
+        let mut dates: BTreeMap<NaiveDate, (f64, f64)> = BTreeMap::new();
+        for f in all.filter(|f| f.value_date > today) {
+            let e = dates.entry(f.value_date).or_default();
+            if f.amount >= 0.0 { e.0 += f.amount } else { e.1 -= f.amount }
+        }
+        let mut balance = settled;
+        let by_value_date = dates.into_iter().map(|(value_date, (inflows, outflows))| {
+            balance += inflows - outflows;
+            ValueDate { value_date, inflows, outflows, projected_balance: balance }
+        }).collect::<Vec<_>>();
+        Some(Projection { account: account.into(), as_of: today, settled, unsettled: balance - settled, by_value_date })
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const VERSION: &str = "simm-like-1.0";
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskClass { InterestRate, CreditQualifying, CreditNonQualifying, Equity, Commodity, Fx }
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Measure { Delta, Vega, Curvature }
+
+/// `bucket` groups correlated factors (currency for rates, sector for equity/credit); `qualifier` names the factor itself.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Sensitivity { pub risk_class: RiskClass, pub measure: Measure, pub bucket: String, pub qualifier: String, pub amount: f64 }
+
+#[derive(Serialize)]
+pub struct ClassMargin { pub risk_class: RiskClass, pub delta: f64, pub vega: f64, pub curvature: f64, pub total: f64 }
+
+#[derive(Serialize)]
+pub struct SimmResult { pub version: &'static str, pub total_im: f64, pub by_risk_class: Vec<ClassMargin> }
+
+struct Params { delta_rw: f64, vega_rw: f64, rho: f64, gamma: f64 }
+
+fn params(rc: RiskClass) -> Params {
+    match rc {
+        RiskClass::InterestRate => Params { delta_rw: 50.0, vega_rw: 0.23, rho: 0.98, gamma: 0.32 },
+        RiskClass::CreditQualifying => Params { delta_rw: 75.0, vega_rw: 0.76, rho: 0.93, gamma: 0.40 },
+        RiskClass::CreditNonQualifying => Params { delta_rw: 280.0, vega_rw: 0.76, rho: 0.82, gamma: 0.43 },
+        RiskClass::Equity => Params { delta_rw: 25.0, vega_rw: 0.45, rho: 0.18, gamma: 0.18 },
+        RiskClass::Commodity => Params { delta_rw: 20.0, vega_rw: 0.55, rho: 0.55, gamma: 0.20 },
+        RiskClass::Fx => Params { delta_rw: 7.4, vega_rw: 0.47, rho: 0.50, gamma: 0.50 },
+    }
+}
+
+/// Cross-risk-class correlation (psi).
+fn psi(a: RiskClass, b: RiskClass) -> f64 {
+    use RiskClass::*;
+    if a == b { return 1.0; }
+    let (a, b) = if a < b { (a, b) } else { (b, a) };
+    match (a, b) {
+        (InterestRate, CreditQualifying) | (InterestRate, CreditNonQualifying) => 0.04,
+        (InterestRate, Equity) => 0.07,
+        (InterestRate, Commodity) => 0.37,
+        (InterestRate, Fx) => 0.14,
+        (CreditQualifying, CreditNonQualifying) => 0.54,
+        (CreditQualifying, Equity) => 0.70,
+        (CreditQualifying, Commodity) => 0.27,
+        (CreditQualifying, Fx) => 0.37,
+        (CreditNonQualifying, Equity) => 0.46,
+        (CreditNonQualifying, Commodity) => 0.24,
+        (CreditNonQualifying, Fx) => 0.15,
+        (Equity, Commodity) => 0.35,
+        (Equity, Fx) => 0.39,
+        _ => 0.35,
+    }
+}
+
+/// Weighted sensitivities netted per qualifier, grouped by bucket.
+fn weighted(sens: &[&Sensitivity], rw: f64) -> BTreeMap<String, BTreeMap<String, f64>> {
+    let mut out: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    for s in sens { *out.entry(s.bucket.clone()).or_default().entry(s.qualifier.clone()).or_default() += s.amount * rw; }
+    out
+}
+
+/// Intra-bucket then inter-bucket aggregation; `square_corr` applies the squared correlations used for curvature.
+fn aggregate(buckets: &BTreeMap<String, BTreeMap<String, f64>>, p: &Params, square_corr: bool) -> f64 {
+    let (rho, gamma) = if square_corr { (p.rho * p.rho, p.gamma * p.gamma) } else { (p.rho, p.gamma) };
+    let ks: Vec<(f64, f64)> = buckets.values().map(|ws| {
+        let w: Vec<f64> = ws.values().copied().collect();
+        let mut k2 = 0.0;
+        for (i, a) in w.iter().enumerate() { for (j, b) in w.iter().enumerate() { k2 += if i == j { a * a } else { rho * a * b }; } }
+        let k = k2.max(0.0).sqrt();
+        (k, w.iter().sum::<f64>().clamp(-k, k))
+    }).collect();
+    let mut total = 0.0;
+    for (i, (ki, si)) in ks.iter().enumerate() { for (j, (_, sj)) in ks.iter().enumerate() { total += if i == j { ki * ki } else { gamma * si * sj }; } }
+    total.max(0.0).sqrt()
+}
+
+pub fn compute(sens: &[Sensitivity]) -> SimmResult {
+    let mut classes: Vec<RiskClass> = sens.iter().map(|s| s.risk_class).collect();
+    classes.sort();
+    classes.dedup();
+    let by_risk_class: Vec<ClassMargin> = classes.into_iter().map(|rc| {
+        let p = params(rc);
+        let pick = |m: Measure| sens.iter().filter(|s| s.risk_class == rc && s.measure == m).collect::<Vec<_>>();
+        let delta = aggregate(&weighted(&pick(Measure::Delta), p.delta_rw), &p, false);
+        let vega = aggregate(&weighted(&pick(Measure::Vega), p.vega_rw), &p, false);
+        let cvr = weighted(&pick(Measure::Curvature), 1.0);
+        let (sum, abs) = cvr.values().flat_map(|b| b.values()).fold((0.0, 0.0), |(s, a), x| (s + x, a + x.abs()));
+        let curvature = if abs == 0.0 { 0.0 } else {
+            let theta = (sum / abs).min(0.0);
+            let lambda = (2.5758f64.powi(2) - 1.0) * (1.0 + theta) - theta;
+            (sum + lambda * aggregate(&cvr, &p, true)).max(0.0)
+        };
+        ClassMargin { risk_class: rc, delta, vega, curvature, total: delta + vega + curvature }
+    }).collect();
+    let mut total = 0.0;
+    for a in &by_risk_class { for b in &by_risk_class { total += psi(a.risk_class, b.risk_class) * a.total * b.total; } }
+    SimmResult { version: VERSION, total_im: total.max(0.0).sqrt(), by_risk_class }
+}
@@ -0,0 +1,178 @@
+use crate::greeks;
+use crate::margin::{Adjustment, Breakdown, PositionMargin};
+use crate::scenarios::{OptionRight, OptionTerms, Underlier};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Price moves scanned as fractions of the price scan range, each under a volatility rise and fall. The two extreme
+/// moves follow at no volatility move, and only `EXTREME_COVER` of their loss counts.
+const MOVES: [f64; 7] = [0.0, 1.0 / 3.0, -1.0 / 3.0, 2.0 / 3.0, -2.0 / 3.0, 1.0, -1.0];
+const EXTREME_MOVE: f64 = 3.0;
+const EXTREME_COVER: f64 = 0.35;
+/// Instruments outside every product group are scanned alone over this many daily sigmas.
+const UNGROUPED_SIGMAS: f64 = 3.0;
+
+/// Futures and options whose underlier is listed in `instruments` are scanned together. `short_option_minimum` is
+/// charged per short option contract, counting short calls or short puts, whichever are more.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct ProductGroup { pub name: String, pub instruments: Vec<String>, pub price_scan_pct: f64, pub vol_scan_pct: f64, #[serde(default)] pub short_option_minimum: f64 }
+
+/// Credits `credit_pct` of each group's scan risk for the share of its net delta offset by the other group's.
+/// Spreads are formed in the order listed, each from the delta earlier ones left over.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct SpreadCredit { pub groups: [String; 2], pub credit_pct: f64 }
+
+/// The SPAN requirement is the initial margin; maintenance is `maintenance_ratio` of it.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct SpanConfig {
+    #[serde(default)] pub groups: Vec<ProductGroup>, #[serde(default)] pub spreads: Vec<SpreadCredit>,
+    #[serde(default = "ungrouped_vol_scan")] pub ungrouped_vol_scan_pct: f64, #[serde(default = "maintenance_ratio")] pub maintenance_ratio: f64,
+}
+
+fn ungrouped_vol_scan() -> f64 { 25.0 }
+fn maintenance_ratio() -> f64 { 0.9 }
+
+impl Default for SpanConfig {
+    fn default() -> Self { Self { groups: Vec::new(), spreads: Vec::new(), ungrouped_vol_scan_pct: ungrouped_vol_scan(), maintenance_ratio: maintenance_ratio() } }
+}
+
+fn pct(x: f64, upper: f64) -> bool { x.is_finite() && x >= 0.0 && x <= upper }
+
+impl SpanConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let (mut names, mut members) = (HashSet::new(), HashSet::new());
+        for g in &self.groups {
+            if g.name.trim().is_empty() || !names.insert(g.name.as_str()) { return Err(format!("product group names must be unique and non-empty: {:?}", g.name)); }
+            if g.instruments.is_empty() { return Err(format!("product group {} lists no instruments", g.name)); }
+            if let Some(i) = g.instruments.iter().find(|i| !members.insert(i.as_str())) { return Err(format!("{i} is in more than one product group")); }
+            if !(pct(g.price_scan_pct, 100.0) && g.price_scan_pct > 0.0) { return Err(format!("product group {} price_scan_pct must be within (0, 100]", g.name)); }
+            if !pct(g.vol_scan_pct, 100.0) || !pct(g.short_option_minimum, f64::MAX) { return Err(format!("product group {} vol_scan_pct and short_option_minimum must be non-negative", g.name)); }
+        }
+        for sp in &self.spreads {
+            if sp.groups[0] == sp.groups[1] || sp.groups.iter().any(|g| !names.contains(g.as_str())) { return Err(format!("spread {} / {} must join two product groups", sp.groups[0], sp.groups[1])); }
+            if !(pct(sp.credit_pct, 100.0) && sp.credit_pct > 0.0) { return Err("credit_pct must be within (0, 100]".into()); }
+        }
+        if !pct(self.ungrouped_vol_scan_pct, 100.0) { return Err("ungrouped_vol_scan_pct must be within [0, 100]".into()); }
+        if !(self.maintenance_ratio > 0.0 && self.maintenance_ratio <= 1.0) { return Err("maintenance_ratio must be within (0, 1]".into()); }
+        Ok(())
+    }
+}
+
+/// `worst_*` name the scenario `scan_risk` comes from; `requirement` is scan risk less spread credit, or the short
+/// option minimum when that is larger.
+#[derive(Serialize)]
+pub struct GroupRequirement {
+    pub group: String, pub instruments: Vec<String>, pub price_scan_pct: f64, pub vol_scan_pct: f64, pub scan_risk: f64, pub worst_price_move_pct: f64, pub worst_vol_move_pct: f64,
+    pub net_delta: f64, pub spread_credit: f64, pub short_option_minimum: f64, pub requirement: f64,
+}
+
+#[derive(Serialize)]
+pub struct SpreadApplied { pub groups: [String; 2], pub credit_pct: f64, pub delta_spread: f64, pub credit: f64 }
+
+#[derive(Serialize)]
+pub struct SpanResult { pub groups: Vec<GroupRequirement>, pub spreads: Vec<SpreadApplied>, pub initial_margin: f64, pub maintenance_margin: f64, #[serde(skip)] positions: Vec<(usize, f64)> }
+
+/// One position as SPAN sees it: its group, option right, cash delta and P&L under every scenario.
+struct Leg { group: usize, quantity: f64, right: Option<OptionRight>, delta: f64, pnl: Vec<f64> }
+
+fn scenarios(price_scan_pct: f64, vol_scan_pct: f64) -> Vec<(f64, f64, f64)> {
+    let (p, v) = (price_scan_pct / 100.0, vol_scan_pct / 100.0);
+    let mut out: Vec<_> = MOVES.iter().flat_map(|m| [(m * p, v, 1.0), (m * p, -v, 1.0)]).collect();
+    out.extend([(EXTREME_MOVE * p, 0.0, EXTREME_COVER), (-EXTREME_MOVE * p, 0.0, EXTREME_COVER)]);
+    out
+}
+
+/// Largest covered loss over the scenarios, with the scenario it came from; zero when every scenario gains.
+fn scan(pnl: impl Fn(usize) -> f64, scenarios: &[(f64, f64, f64)]) -> (f64, Option<usize>) {
+    scenarios.iter().enumerate().map(|(k, (_, _, cover))| (-pnl(k) * cover, Some(k))).fold((0.0, None), |a, b| if b.0 > a.0 { b } else { a })
+}
+
+/// Runs `(instrument, signed quantity, price)` positions through SPAN. `option` gives an instrument's option terms,
+/// `underlier` what an option is repriced from, and `vol` the daily volatility ungrouped instruments are scanned by.
+/// Options that cannot be repriced are refused rather than margined as if they were stock.
+pub fn compute(config: &SpanConfig, positions: &[(String, f64, f64)], option: impl Fn(&str) -> Option<OptionTerms>, underlier: impl Fn(&OptionTerms) -> Option<Underlier>, vol: impl Fn(&str) -> f64) -> Result<SpanResult, String> {
+    let mut groups: Vec<ProductGroup> = config.groups.clone();
+    let mut legs = Vec::new();
+    for (instrument, q, price) in positions {
+        let terms = option(instrument);
+        let name = terms.as_ref().map_or(instrument.as_str(), |o| o.underlying.as_str());
+        let group = match groups.iter().position(|g| g.instruments.iter().any(|i| i == name)) {
+            Some(g) => g,
+            None => {
+                groups.push(ProductGroup { name: name.into(), instruments: vec![name.into()], price_scan_pct: UNGROUPED_SIGMAS * vol(name) * 100.0, vol_scan_pct: config.ungrouped_vol_scan_pct, short_option_minimum: 0.0 });
+                groups.len() - 1
+            }
+        };
+        let g = &groups[group];
+        let sc = scenarios(g.price_scan_pct, g.vol_scan_pct);
+        let leg = match terms {
+            None => Leg { group, quantity: *q, right: None, delta: q * price, pnl: sc.iter().map(|(m, _, _)| q * price * m).collect() },
+            Some(o) => {
+                let u = underlier(&o).ok_or_else(|| format!("no price for {}, the underlier of {instrument}", o.underlying))?;
+                let units = q * o.multiplier.unwrap_or(1.0);
+                let now = greeks::value(&o, u.spot, u.vol, u.today).ok_or_else(|| format!("option {instrument} has no expiry"))?;
+                let pnl = sc.iter().map(|(m, v, _)| units * (greeks::value(&o, u.spot * (1.0 + m), u.vol * (1.0 + v), u.today).unwrap_or(now) - now)).collect();
+                Leg { group, quantity: *q, right: Some(o.right), delta: greeks::option(&o, *q, u.spot, u.vol, u.today)?.delta, pnl }
+            }
+        };
+        legs.push(leg);
+    }
+    let mut out: Vec<GroupRequirement> = groups.iter().enumerate().filter(|(k, _)| legs.iter().any(|l| l.group == *k)).map(|(k, g)| {
+        let sc = scenarios(g.price_scan_pct, g.vol_scan_pct);
+        let members: Vec<&Leg> = legs.iter().filter(|l| l.group == k).collect();
+        let (scan_risk, worst) = scan(|s| members.iter().map(|l| l.pnl[s]).sum(), &sc);
+        let short = |right: OptionRight| members.iter().filter(|l| l.quantity < 0.0 && l.right == Some(right)).map(|l| -l.quantity).fold(0.0, |a, b| a + b);
+        let som = short(OptionRight::Call).max(short(OptionRight::Put)) * g.short_option_minimum;
+        let mut instruments: Vec<String> = positions.iter().zip(&legs).filter(|(_, l)| l.group == k).map(|(p, _)| p.0.clone()).collect();
+        instruments.sort();
+        instruments.dedup();
+        GroupRequirement {
+            group: g.name.clone(), instruments, price_scan_pct: g.price_scan_pct, vol_scan_pct: g.vol_scan_pct, scan_risk,
+            worst_price_move_pct: worst.map_or(0.0, |w| sc[w].0 * 100.0), worst_vol_move_pct: worst.map_or(0.0, |w| sc[w].1 * 100.0),
+            net_delta: members.iter().map(|l| l.delta).sum(), spread_credit: 0.0, short_option_minimum: som, requirement: 0.0,
+        }
+    }).collect();
+    let mut left: BTreeMap<String, f64> = out.iter().map(|g| (g.group.clone(), g.net_delta)).collect();
+    let mut spreads = Vec::new();
+    for sp in &config.spreads {
+        let (Some(a), Some(b)) = (out.iter().position(|g| g.group == sp.groups[0]), out.iter().position(|g| g.group == sp.groups[1])) else { continue };
+        let (da, db) = (left[&sp.groups[0]], left[&sp.groups[1]]);
+        if da * db >= 0.0 { continue; }
+        let spread = da.abs().min(db.abs());
+        let mut credit = 0.0;
+        for (g, d) in [(a, da), (b, db)] {
+            let c = sp.credit_pct / 100.0 * out[g].scan_risk * spread / out[g].net_delta.abs();
+            out[g].spread_credit += c;
+            credit += c;
+            *left.get_mut(&out[g].group).unwrap() = d - spread * d.signum();
+        }
+        spreads.push(SpreadApplied { groups: sp.groups.clone(), credit_pct: sp.credit_pct, delta_spread: spread, credit });
+    }
+    for g in &mut out { g.spread_credit = g.spread_credit.min(g.scan_risk); g.requirement = (g.scan_risk - g.spread_credit).max(g.short_option_minimum); }
+    // Position standalone scan risk, kept to attribute the requirement in the breakdown.
+    let positions = legs.iter().map(|l| {
+        let g = &groups[l.group];
+        (out.iter().position(|r| r.group == g.name).unwrap_or(0), scan(|s| l.pnl[s], &scenarios(g.price_scan_pct, g.vol_scan_pct)).0)
+    }).collect();
+    let initial = out.iter().map(|g| g.requirement).fold(0.0, |a, b| a + b);
+    Ok(SpanResult { groups: out, spreads, initial_margin: initial, maintenance_margin: initial * config.maintenance_ratio, positions })
+}
+
+/// The requirement itemized like the flat models: standalone is a position's own scan risk, and each group's
+/// requirement is split over its positions in proportion to it.
+pub fn breakdown(r: &SpanResult, positions: &[(String, f64, f64)]) -> Breakdown {
+    let standalone_total = r.positions.iter().map(|p| p.1).fold(0.0, |a, b| a + b);
+    let positions = positions.iter().zip(&r.positions).map(|((instrument, quantity, price), (g, standalone))| {
+        let peers: Vec<f64> = r.positions.iter().filter(|p| p.0 == *g).map(|p| p.1).collect();
+        let sum: f64 = peers.iter().sum();
+        let share = if sum > 0.0 { standalone / sum } else { 1.0 / peers.len() as f64 };
+        PositionMargin { instrument: instrument.clone(), quantity: *quantity, price: *price, notional: quantity * price, standalone: *standalone, contribution: r.groups[*g].requirement * share }
+    }).collect();
+    let scan_total = r.groups.iter().map(|g| g.scan_risk).fold(0.0, |a, b| a + b);
+    let credits = r.groups.iter().map(|g| g.spread_credit).fold(0.0, |a, b| a + b);
+    let som = r.groups.iter().map(|g| g.requirement - (g.scan_risk - g.spread_credit)).fold(0.0, |a, b| a + b);
+    let mut offsets = vec![Adjustment { kind: "scan_netting", amount: scan_total - standalone_total }, Adjustment { kind: "inter_commodity_spread", amount: -credits }];
+    offsets.retain(|o| o.amount.abs() > 1e-9);
+    let add_ons = (som > 1e-9).then_some(Adjustment { kind: "short_option_minimum", amount: som }).into_iter().collect();
+    Breakdown { positions, standalone_total, offsets, add_ons, initial_margin: r.initial_margin }
+}
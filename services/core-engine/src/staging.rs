@@ -0,0 +1,119 @@
+use crate::budget::LatencyBudget;
+use crate::calendar::CalendarConfig;
+use crate::circuit_breaker::BreakerConfig;
+use crate::concentration::ConcentrationLimits;
+use crate::config_bundle::ConfigBundle;
+use crate::erroneous::ErroneousConfig;
+use crate::fat_finger::FatFingerConfig;
+use crate::hedge::HedgePolicy;
+use crate::limits::LimitSpec;
+use crate::margin_calls::Thresholds;
+use crate::span::SpanConfig;
+use crate::wash::WashConfig;
+use serde::{Deserialize, Serialize};
+
+/// Finished entries kept for the listing once applied, failed or cancelled.
+const MAX_FINISHED: usize = 500;
+
+/// A change any of the admin endpoints could make now, in the body that endpoint takes. A limit change without
+/// an `id` creates the limit; one with an `id` updates it, and `expected_version` makes it fail rather than
+/// overwrite a limit edited after it was staged.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "target", content = "config", rename_all = "snake_case")]
+pub enum Change {
+    Limit { #[serde(default, skip_serializing_if = "Option::is_none")] id: Option<String>, #[serde(default, skip_serializing_if = "Option::is_none")] expected_version: Option<u64>, #[serde(flatten)] spec: LimitSpec },
+    LimitDelete { id: String, #[serde(default, skip_serializing_if = "Option::is_none")] expected_version: Option<u64> },
+    CircuitBreaker(BreakerConfig), FatFinger(FatFingerConfig), ErroneousOrders(ErroneousConfig), EventWindows(CalendarConfig), MarginCallThresholds(Thresholds),
+    ConcentrationLimits(ConcentrationLimits), WashTrades(WashConfig), Hedging(HedgePolicy), Span(SpanConfig), LatencyBudget(LatencyBudget), Bundle(Box<ConfigBundle>),
+}
+
+impl Change {
+    /// Checks the body as its endpoint would; whether a limit to update still exists is only known at activation.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Change::Limit { spec, .. } => spec.validate(),
+            Change::LimitDelete { .. } | Change::Bundle(_) => Ok(()),
+            Change::CircuitBreaker(c) => c.validate(),
+            Change::FatFinger(c) => c.validate(),
+            Change::ErroneousOrders(c) => c.validate(),
+            Change::EventWindows(c) => c.validate(),
+            Change::MarginCallThresholds(c) => c.validate(),
+            Change::ConcentrationLimits(c) => c.validate(),
+            Change::WashTrades(c) => c.validate(),
+            Change::Hedging(c) => c.validate(),
+            Change::Span(c) => c.validate(),
+            Change::LatencyBudget(c) => c.validate(),
+        }
+    }
+}
+
+/// When a change takes effect: at `activate_at_ms`, or with `at_next_session` when the daily session next rolls over.
+#[derive(Deserialize)]
+pub struct StageRequest { pub change: Change, pub activate_at_ms: Option<u64>, #[serde(default)] pub at_next_session: bool, pub reason: Option<String> }
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Status { Pending, Applied, Failed, Cancelled }
+
+#[derive(Serialize, Clone)]
+pub struct Staged {
+    pub id: String, pub change: Change, pub activate_at_ms: u64, pub status: Status, pub staged_by: String, pub staged_at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub reason: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub finished_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub finished_by: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct StagedQuery { pub status: Option<String> }
+
+/// Changes waiting for their activation time, soonest first, and the most recent finished ones.
+#[derive(Default, Clone)]
+pub struct Staging { entries: Vec<Staged> }
+
+impl Staging {
+    pub fn list(&self, status: Option<&str>) -> Vec<Staged> {
+        let wanted = |e: &Staged| status.is_none_or(|s| serde_json::to_value(e.status).ok().and_then(|v| v.as_str().map(|v| v == s)).unwrap_or(false));
+        self.entries.iter().filter(|e| wanted(e)).cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Staged> { self.entries.iter().find(|e| e.id == id) }
+
+    pub fn stage(&mut self, change: Change, activate_at_ms: u64, staged_by: String, reason: Option<String>, now_ms: u64) -> Staged {
+        let e = Staged { id: uuid::Uuid::new_v4().to_string(), change, activate_at_ms, status: Status::Pending, staged_by, staged_at_ms: now_ms, reason, finished_at_ms: None, finished_by: None, error: None };
+        let at = self.entries.iter().position(|x| x.status == Status::Pending && x.activate_at_ms > activate_at_ms).unwrap_or(self.entries.len());
+        self.entries.insert(at, e.clone());
+        e
+    }
+
+    /// `Err(true)` when the change exists but is no longer pending.
+    pub fn cancel(&mut self, id: &str, by: String, now_ms: u64) -> Result<Staged, bool> {
+        let e = self.entries.iter_mut().find(|e| e.id == id).ok_or(false)?;
+        if e.status != Status::Pending { return Err(true); }
+        (e.status, e.finished_at_ms, e.finished_by) = (Status::Cancelled, Some(now_ms), Some(by));
+        let e = e.clone();
+        self.trim();
+        Ok(e)
+    }
+
+    /// Takes the pending changes due at `now_ms` in activation order, marking them applied; `failed` then marks
+    /// any that could not be.
+    pub fn due(&mut self, now_ms: u64) -> Vec<Staged> {
+        let mut out = Vec::new();
+        for e in self.entries.iter_mut().filter(|e| e.status == Status::Pending && e.activate_at_ms <= now_ms) {
+            (e.status, e.finished_at_ms) = (Status::Applied, Some(now_ms));
+            out.push(e.clone());
+        }
+        out.sort_by_key(|e| e.activate_at_ms);
+        self.trim();
+        out
+    }
+
+    pub fn failed(&mut self, id: &str, error: String) {
+        if let Some(e) = self.entries.iter_mut().find(|e| e.id == id) { (e.status, e.error) = (Status::Failed, Some(error)); }
+    }
+
+    fn trim(&mut self) {
+        let finished = self.entries.iter().filter(|e| e.status != Status::Pending).count();
+        let mut drop = finished.saturating_sub(MAX_FINISHED);
+        self.entries.retain(|e| { let old = drop > 0 && e.status != Status::Pending; if old { drop -= 1; } !old });
+    }
+}
@@ -0,0 +1,207 @@
+use crate::alerts::{Alert, Severity};
+use crate::auth::{ApiKey, KeyScope};
+use crate::checks::{CheckPage, CheckQuery, CheckRecord};
+use serde::Serialize;
+use sqlx::Row;
+use std::future::Future;
+use std::pin::Pin;
+
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct Counters { pub total_checks: u64, pub total_margin_calcs: u64, pub total_alerts: u64, pub trades_blocked: u64 }
+
+impl Counters {
+    pub fn block_rate_pct(&self) -> f64 { if self.total_checks > 0 { self.trades_blocked as f64 / self.total_checks as f64 * 100.0 } else { 0.0 } }
+}
+
+/// `source` is the endpoint or job that computed the requirement.
+#[derive(Serialize, Clone)]
+pub struct MarginRecord { pub id: String, pub account: String, pub source: String, pub initial_margin: f64, pub maintenance_margin: f64, pub at_ms: u64 }
+
+/// One section of the warm-start snapshot; `body` is its JSON. Each save replaces the section's last one.
+#[derive(Clone)]
+pub struct Snapshot { pub section: String, pub body: String, pub at_ms: u64 }
+
+pub enum Record { Check(CheckRecord), Margin(MarginRecord), Alert(Alert), Counters(Counters), Snapshot(Snapshot) }
+
+/// Durable home for what the engine would otherwise lose on restart. Writes arrive in batches from one writer, so
+/// implementations need not order them further.
+pub trait Store: Send + Sync {
+    /// Creates any missing tables.
+    fn migrate(&self) -> StoreFuture<'_, ()>;
+    fn write<'a>(&'a self, batch: &'a [Record]) -> StoreFuture<'a, ()>;
+    fn counters(&self) -> StoreFuture<'_, Option<Counters>>;
+    /// Every alert, oldest first.
+    fn alerts(&self) -> StoreFuture<'_, Vec<Alert>>;
+    fn checks<'a>(&'a self, q: &'a CheckQuery) -> StoreFuture<'a, CheckPage>;
+    fn check<'a>(&'a self, check_id: &'a str) -> StoreFuture<'a, Option<CheckRecord>>;
+    fn snapshot<'a>(&'a self, section: &'a str) -> StoreFuture<'a, Option<Snapshot>>;
+    /// API keys managed in the database, as digests.
+    fn api_keys(&self) -> StoreFuture<'_, Vec<ApiKey>>;
+}
+
+/// Kept in step with `database/migrations/007_engine_store.sql` through `009_engine_api_keys.sql`; the engine applies
+/// it itself so SQLite needs no setup.
+const SCHEMA: [&str; 8] = [
+    "CREATE TABLE IF NOT EXISTS engine_checks (check_id TEXT PRIMARY KEY, kind TEXT NOT NULL, account TEXT NOT NULL, approved BOOLEAN NOT NULL, reasons TEXT NOT NULL, tags TEXT NOT NULL, inputs TEXT NOT NULL, limits_evaluated TEXT NOT NULL, elapsed_us BIGINT NOT NULL, at_ms BIGINT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS engine_margins (id TEXT PRIMARY KEY, account TEXT NOT NULL, source TEXT NOT NULL, initial_margin DOUBLE PRECISION NOT NULL, maintenance_margin DOUBLE PRECISION NOT NULL, at_ms BIGINT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS engine_alerts (id BIGINT PRIMARY KEY, at_ms BIGINT NOT NULL, kind TEXT NOT NULL, severity TEXT NOT NULL, account TEXT, instrument TEXT, message TEXT NOT NULL, tags TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS engine_counters (id INTEGER PRIMARY KEY, total_checks BIGINT NOT NULL, total_margin_calcs BIGINT NOT NULL, total_alerts BIGINT NOT NULL, trades_blocked BIGINT NOT NULL)",
+    "CREATE INDEX IF NOT EXISTS idx_engine_checks_account ON engine_checks(account, at_ms)",
+    "CREATE INDEX IF NOT EXISTS idx_engine_checks_at ON engine_checks(at_ms)",
+    "CREATE TABLE IF NOT EXISTS engine_snapshots (section TEXT PRIMARY KEY, body TEXT NOT NULL, at_ms BIGINT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS engine_api_keys (id TEXT PRIMARY KEY, key_sha256 TEXT NOT NULL, scope TEXT NOT NULL, requests_per_minute BIGINT)",
+];
+
+// Statements are written with Postgres placeholders; SQLite reads `?1` where Postgres reads `$1`.
+const INSERT_CHECK: &str = "INSERT INTO engine_checks (check_id, kind, account, approved, reasons, tags, inputs, limits_evaluated, elapsed_us, at_ms) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) ON CONFLICT (check_id) DO NOTHING";
+const INSERT_MARGIN: &str = "INSERT INTO engine_margins (id, account, source, initial_margin, maintenance_margin, at_ms) VALUES ($1, $2, $3, $4, $5, $6)";
+const INSERT_ALERT: &str = "INSERT INTO engine_alerts (id, at_ms, kind, severity, account, instrument, message, tags) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (id) DO NOTHING";
+const UPSERT_COUNTERS: &str = "INSERT INTO engine_counters (id, total_checks, total_margin_calcs, total_alerts, trades_blocked) VALUES (1, $1, $2, $3, $4) \
+    ON CONFLICT (id) DO UPDATE SET total_checks = excluded.total_checks, total_margin_calcs = excluded.total_margin_calcs, total_alerts = excluded.total_alerts, trades_blocked = excluded.trades_blocked";
+const UPSERT_SNAPSHOT: &str = "INSERT INTO engine_snapshots (section, body, at_ms) VALUES ($1, $2, $3) ON CONFLICT (section) DO UPDATE SET body = excluded.body, at_ms = excluded.at_ms";
+const SELECT_COUNTERS: &str = "SELECT total_checks, total_margin_calcs, total_alerts, trades_blocked FROM engine_counters WHERE id = 1";
+const SELECT_ALERTS: &str = "SELECT id, at_ms, kind, severity, account, instrument, message, tags FROM engine_alerts ORDER BY id";
+const CHECK_COLUMNS: &str = "SELECT check_id, kind, account, approved, reasons, tags, inputs, limits_evaluated, elapsed_us, at_ms FROM engine_checks";
+const CHECK_FILTER: &str = "WHERE ($1 IS NULL OR account = $1) AND ($2 IS NULL OR kind = $2) AND ($3 IS NULL OR approved = $3) AND ($4 IS NULL OR at_ms >= $4) AND ($5 IS NULL OR at_ms <= $5)";
+
+fn severity(a: &Alert) -> String { serde_json::to_value(a.severity).ok().and_then(|v| v.as_str().map(Into::into)).unwrap_or_default() }
+
+/// Both backends run the same statements; only the placeholder syntax and connection type differ.
+macro_rules! sql_store {
+    ($name:ident, $db:ty, $sql:expr) => {
+        pub struct $name { pool: sqlx::Pool<$db> }
+
+        impl Store for $name {
+            fn migrate(&self) -> StoreFuture<'_, ()> {
+                Box::pin(async move {
+                    for stmt in SCHEMA { sqlx::query(stmt).execute(&self.pool).await.map_err(|e| e.to_string())?; }
+                    Ok(())
+                })
+            }
+
+            fn write<'a>(&'a self, batch: &'a [Record]) -> StoreFuture<'a, ()> {
+                Box::pin(async move {
+                    let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+                    for r in batch {
+                        // Each statement runs inside its arm so the rewritten SQL outlives the query that borrows it.
+                        match r {
+                            Record::Check(c) => sqlx::query(&*$sql(INSERT_CHECK)).bind(c.check_id.clone()).bind(c.kind.clone()).bind(c.account.clone()).bind(c.approved)
+                                .bind(serde_json::to_string(&c.reasons).unwrap_or_default()).bind(serde_json::to_string(&c.tags).unwrap_or_default())
+                                .bind(c.inputs.to_string()).bind(serde_json::to_string(&c.limits_evaluated).unwrap_or_default()).bind(c.elapsed_us as i64).bind(c.at_ms as i64).execute(&mut *tx).await,
+                            Record::Margin(m) => sqlx::query(&*$sql(INSERT_MARGIN)).bind(m.id.clone()).bind(m.account.clone()).bind(m.source.clone()).bind(m.initial_margin)
+                                .bind(m.maintenance_margin).bind(m.at_ms as i64).execute(&mut *tx).await,
+                            Record::Alert(a) => sqlx::query(&*$sql(INSERT_ALERT)).bind(a.id as i64).bind(a.at_ms as i64).bind(a.kind.clone()).bind(severity(a))
+                                .bind(a.account.clone()).bind(a.instrument.clone()).bind(a.message.clone()).bind(serde_json::to_string(&a.tags).unwrap_or_default()).execute(&mut *tx).await,
+                            Record::Counters(c) => sqlx::query(&*$sql(UPSERT_COUNTERS)).bind(c.total_checks as i64).bind(c.total_margin_calcs as i64).bind(c.total_alerts as i64)
+                                .bind(c.trades_blocked as i64).execute(&mut *tx).await,
+                            Record::Snapshot(n) => sqlx::query(&*$sql(UPSERT_SNAPSHOT)).bind(n.section.clone()).bind(n.body.clone()).bind(n.at_ms as i64).execute(&mut *tx).await,
+                        }.map_err(|e| e.to_string())?;
+                    }
+                    tx.commit().await.map_err(|e| e.to_string())
+                })
+            }
+
+            fn counters(&self) -> StoreFuture<'_, Option<Counters>> {
+                Box::pin(async move {
+                    let row = sqlx::query(&*$sql(SELECT_COUNTERS)).fetch_optional(&self.pool).await.map_err(|e| e.to_string())?;
+                    row.map(|r| -> Result<Counters, sqlx::Error> {
+                        let n = |i: usize| r.try_get::<i64, _>(i).map(|v| v as u64);
+                        Ok(Counters { total_checks: n(0)?, total_margin_calcs: n(1)?, total_alerts: n(2)?, trades_blocked: n(3)? })
+                    }).transpose().map_err(|e| e.to_string())
+                })
+            }
+
+            fn alerts(&self) -> StoreFuture<'_, Vec<Alert>> {
+                Box::pin(async move {
+                    let rows = sqlx::query(&*$sql(SELECT_ALERTS)).fetch_all(&self.pool).await.map_err(|e| e.to_string())?;
+                    rows.iter().map(|r| -> Result<Alert, sqlx::Error> {
+                        let severity: Severity = serde_json::from_value(serde_json::Value::String(r.try_get("severity")?)).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                        Ok(Alert {
+                            id: r.try_get::<i64, _>("id")? as u64, at_ms: r.try_get::<i64, _>("at_ms")? as u64, kind: r.try_get("kind")?, severity,
+                            account: r.try_get("account")?, instrument: r.try_get("instrument")?, message: r.try_get("message")?,
+                            tags: Self::json(r, "tags")?,
+                        })
+                    }).collect::<Result<_, _>>().map_err(|e| e.to_string())
+                })
+            }
+
+            fn checks<'a>(&'a self, q: &'a CheckQuery) -> StoreFuture<'a, CheckPage> {
+                Box::pin(async move {
+                    let offset = q.offset.unwrap_or(0);
+                    let (from, to) = q.window_ms();
+                    let (from, to) = (from.map(|t| t as i64), to.map(|t| t as i64));
+                    let count = $sql(&format!("SELECT COUNT(*) FROM engine_checks {CHECK_FILTER}"));
+                    let total: i64 = sqlx::query(&count).bind(q.account.clone()).bind(q.kind.clone()).bind(q.approved).bind(from).bind(to)
+                        .fetch_one(&self.pool).await.and_then(|r| r.try_get(0)).map_err(|e| e.to_string())?;
+                    let select = $sql(&format!("{CHECK_COLUMNS} {CHECK_FILTER} ORDER BY at_ms DESC, check_id LIMIT $6 OFFSET $7"));
+                    let rows = sqlx::query(&select).bind(q.account.clone()).bind(q.kind.clone()).bind(q.approved).bind(from).bind(to).bind(q.page_size() as i64).bind(offset as i64)
+                        .fetch_all(&self.pool).await.map_err(|e| e.to_string())?;
+                    let checks = rows.iter().map(Self::check_row).collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+                    Ok(CheckPage::new(checks, total as usize, offset))
+                })
+            }
+
+            fn check<'a>(&'a self, check_id: &'a str) -> StoreFuture<'a, Option<CheckRecord>> {
+                Box::pin(async move {
+                    let row = sqlx::query(&*$sql(&format!("{CHECK_COLUMNS} WHERE check_id = $1"))).bind(check_id).fetch_optional(&self.pool).await.map_err(|e| e.to_string())?;
+                    row.as_ref().map(Self::check_row).transpose().map_err(|e| e.to_string())
+                })
+            }
+
+            fn snapshot<'a>(&'a self, section: &'a str) -> StoreFuture<'a, Option<Snapshot>> {
+                Box::pin(async move {
+                    let row = sqlx::query(&*$sql("SELECT body, at_ms FROM engine_snapshots WHERE section = $1")).bind(section).fetch_optional(&self.pool).await.map_err(|e| e.to_string())?;
+                    row.map(|r| -> Result<Snapshot, sqlx::Error> { Ok(Snapshot { section: section.into(), body: r.try_get("body")?, at_ms: r.try_get::<i64, _>("at_ms")? as u64 }) })
+                        .transpose().map_err(|e| e.to_string())
+                })
+            }
+
+            fn api_keys(&self) -> StoreFuture<'_, Vec<ApiKey>> {
+                Box::pin(async move {
+                    let rows = sqlx::query("SELECT id, key_sha256, scope, requests_per_minute FROM engine_api_keys ORDER BY id").fetch_all(&self.pool).await.map_err(|e| e.to_string())?;
+                    rows.iter().map(|r| -> Result<ApiKey, sqlx::Error> {
+                        let scope: KeyScope = serde_json::from_value(serde_json::Value::String(r.try_get("scope")?)).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                        let rpm: Option<i64> = r.try_get("requests_per_minute")?;
+                        Ok(ApiKey { id: r.try_get("id")?, key_sha256: r.try_get("key_sha256")?, scope, requests_per_minute: rpm.map(|n| n.clamp(0, u32::MAX as i64) as u32) })
+                    }).collect::<Result<_, _>>().map_err(|e| e.to_string())
+                })
+            }
+        }
+
+        impl $name {
+            /// A JSON column; text that no longer parses reads as empty rather than hiding the rest of the row.
+            fn json<T: serde::de::DeserializeOwned + Default>(r: &<$db as sqlx::Database>::Row, column: &str) -> Result<T, sqlx::Error> {
+                r.try_get::<String, _>(column).map(|v| serde_json::from_str(&v).unwrap_or_default())
+            }
+
+            fn check_row(r: &<$db as sqlx::Database>::Row) -> Result<CheckRecord, sqlx::Error> {
+                Ok(CheckRecord {
+                    check_id: r.try_get("check_id")?, kind: r.try_get("kind")?, account: r.try_get("account")?, approved: r.try_get("approved")?, reasons: Self::json(r, "reasons")?,
+                    tags: Self::json(r, "tags")?, inputs: Self::json(r, "inputs")?, limits_evaluated: Self::json(r, "limits_evaluated")?,
+                    elapsed_us: r.try_get::<i64, _>("elapsed_us")? as u64, at_ms: r.try_get::<i64, _>("at_ms")? as u64,
+                })
+            }
+        }
+    };
+}
+
+fn sqlite_sql(stmt: &str) -> String { stmt.replace('$', "?") }
+fn postgres_sql(stmt: &str) -> String { stmt.into() }
+
+sql_store!(SqliteStore, sqlx::Sqlite, sqlite_sql);
+sql_store!(PostgresStore, sqlx::Postgres, postgres_sql);
+
+/// Opens the store `url` names: `sqlite:` URLs create the file when it is missing; `postgres:` and `postgresql:`
+/// URLs connect to an existing database.
+pub async fn connect(url: &str) -> Result<Box<dyn Store>, String> {
+    if url.starts_with("sqlite:") {
+        let opts = url.parse::<sqlx::sqlite::SqliteConnectOptions>().map_err(|e| e.to_string())?.create_if_missing(true);
+        Ok(Box::new(SqliteStore { pool: sqlx::sqlite::SqlitePoolOptions::new().connect_with(opts).await.map_err(|e| e.to_string())? }))
+    } else if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+        Ok(Box::new(PostgresStore { pool: sqlx::postgres::PgPoolOptions::new().connect(url).await.map_err(|e| e.to_string())? }))
+    } else {
+        Err(format!("unsupported database URL {url}; expected sqlite: or postgres:"))
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode { Flat, Historical, Composed }
+
+#[derive(Serialize, Clone)]
+pub struct Contribution { pub instrument: String, pub quantity: f64, pub price: f64, pub pnl: f64 }
+
+#[derive(Serialize, Clone)]
+pub struct AccountStress { pub account: Option<String>, pub pnl: f64, pub breaches: Vec<String>, pub contributions: Vec<Contribution> }
+
+/// `scenario_version` is set when the run used a library scenario; `inputs` is the request as received.
+#[derive(Serialize, Clone)]
+pub struct StressRun {
+    pub id: String, pub at_ms: u64, pub mode: Mode, pub scenario: String, pub scenario_version: Option<u32>,
+    pub suite_run_id: Option<String>, pub inputs: serde_json::Value, pub results: Vec<AccountStress>,
+}
+
+#[derive(Deserialize)]
+pub struct RunQuery { pub scenario: Option<String>, pub account: Option<String>, pub limit: Option<usize> }
+
+/// Change in an instrument's stress P&L split into holding, mark-price and scenario-return effects; the three sum to `pnl_after - pnl_before`.
+#[derive(Serialize)]
+pub struct InstrumentDiff {
+    pub instrument: String, pub quantity_before: f64, pub quantity_after: f64, pub pnl_before: f64, pub pnl_after: f64,
+    pub position_effect: f64, pub price_effect: f64, pub scenario_effect: f64,
+}
+
+/// `loss_increase` is positive when the stress loss grew from the base run to the compared run.
+#[derive(Serialize)]
+pub struct AccountDiff {
+    pub account: Option<String>, pub pnl_before: Option<f64>, pub pnl_after: Option<f64>, pub loss_increase: f64,
+    pub new_breaches: Vec<String>, pub cleared_breaches: Vec<String>, pub instruments: Vec<InstrumentDiff>,
+}
+
+#[derive(Serialize)]
+pub struct RunDiff { pub base: String, pub compare: String, pub scenario_changed: bool, pub accounts: Vec<AccountDiff> }
+
+pub struct StressRuns { runs: VecDeque<StressRun>, max_runs: usize }
+
+fn ret(c: &Contribution) -> f64 { let n = c.quantity * c.price; if n == 0.0 { 0.0 } else { c.pnl / n } }
+
+fn diff_instrument(instrument: &str, a: Option<&Contribution>, b: Option<&Contribution>) -> InstrumentDiff {
+    // A leg missing on one side is treated as a zero holding at the other side's price and return.
+    let (qa, qb) = (a.map_or(0.0, |c| c.quantity), b.map_or(0.0, |c| c.quantity));
+    let (pa, pb) = match (a, b) { (Some(a), Some(b)) => (a.price, b.price), (Some(c), None) | (None, Some(c)) => (c.price, c.price), _ => (0.0, 0.0) };
+    let (ra, rb) = match (a, b) { (Some(a), Some(b)) => (ret(a), ret(b)), (Some(c), None) | (None, Some(c)) => (ret(c), ret(c)), _ => (0.0, 0.0) };
+    InstrumentDiff {
+        instrument: instrument.into(), quantity_before: qa, quantity_after: qb, pnl_before: a.map_or(0.0, |c| c.pnl), pnl_after: b.map_or(0.0, |c| c.pnl),
+        position_effect: (qb - qa) * pa * ra, price_effect: qb * (pb - pa) * ra, scenario_effect: qb * pb * (rb - ra),
+    }
+}
+
+fn diff_account(a: Option<&AccountStress>, b: Option<&AccountStress>) -> AccountDiff {
+    let empty = Vec::new();
+    let (ca, cb) = (a.map_or(&empty, |r| &r.contributions), b.map_or(&empty, |r| &r.contributions));
+    let names: BTreeSet<&str> = ca.iter().chain(cb).map(|c| c.instrument.as_str()).collect();
+    let mut instruments: Vec<InstrumentDiff> = names.into_iter().map(|n| diff_instrument(n, ca.iter().find(|c| c.instrument == n), cb.iter().find(|c| c.instrument == n)))
+        .filter(|d| d.pnl_before != d.pnl_after || d.quantity_before != d.quantity_after).collect();
+    instruments.sort_by(|x, y| (x.pnl_after - x.pnl_before).total_cmp(&(y.pnl_after - y.pnl_before)));
+    let (ba, bb) = (a.map(|r| r.breaches.clone()).unwrap_or_default(), b.map(|r| r.breaches.clone()).unwrap_or_default());
+    let (pa, pb) = (a.map(|r| r.pnl), b.map(|r| r.pnl));
+    AccountDiff {
+        account: a.or(b).and_then(|r| r.account.clone()), pnl_before: pa, pnl_after: pb, loss_increase: pa.unwrap_or(0.0) - pb.unwrap_or(0.0),
+        new_breaches: bb.iter().filter(|x| !ba.contains(x)).cloned().collect(), cleared_breaches: ba.iter().filter(|x| !bb.contains(x)).cloned().collect(), instruments,
+    }
+}
+
+impl StressRuns {
+    pub fn new(max_runs: usize) -> Self { Self { runs: VecDeque::new(), max_runs: max_runs.max(1) } }
+
+    pub fn record(&mut self, run: StressRun) {
+        self.runs.push_back(run);
+        while self.runs.len() > self.max_runs { self.runs.pop_front(); }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&StressRun> { self.runs.iter().find(|r| r.id == id) }
+
+    pub fn list(&self, q: &RunQuery) -> Vec<StressRun> {
+        self.runs.iter().rev()
+            .filter(|r| q.scenario.as_ref().is_none_or(|s| &r.scenario == s))
+            .filter(|r| q.account.as_ref().is_none_or(|a| r.results.iter().any(|x| x.account.as_ref() == Some(a))))
+            .take(q.limit.unwrap_or(50)).cloned().collect()
+    }
+
+    /// Per-account comparison of two runs, largest loss increase first.
+    pub fn diff(&self, base: &str, compare: &str) -> Option<RunDiff> {
+        let (a, b) = (self.get(base)?, self.get(compare)?);
+        let accounts: BTreeSet<Option<&String>> = a.results.iter().chain(&b.results).map(|r| r.account.as_ref()).collect();
+        let mut out: Vec<AccountDiff> = accounts.into_iter().map(|acc| diff_account(a.results.iter().find(|r| r.account.as_ref() == acc), b.results.iter().find(|r| r.account.as_ref() == acc))).collect();
+        out.sort_by(|x, y| y.loss_increase.total_cmp(&x.loss_increase));
+        Some(RunDiff { base: base.into(), compare: compare.into(), scenario_changed: a.scenario != b.scenario || a.scenario_version != b.scenario_version || a.mode != b.mode, accounts: out })
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+/// Named library scenarios to run against every open account once a day at `run_at` (HH:MM, UTC).
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SuiteConfig { pub scenarios: Vec<String>, pub run_at: String, #[serde(default = "enabled")] pub enabled: bool }
+
+fn enabled() -> bool { true }
+
+impl Default for SuiteConfig {
+    fn default() -> Self { Self { scenarios: Vec::new(), run_at: "02:00".into(), enabled: true } }
+}
+
+#[derive(Serialize, Clone)]
+pub struct AccountResult { pub account: String, pub scenario: String, pub pnl: f64, pub breaches: Vec<String> }
+
+#[derive(Serialize, Clone)]
+pub struct NewBreach { pub account: String, pub scenario: String, pub breach: String }
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger { Scheduled, Manual }
+
+#[derive(Serialize, Clone)]
+pub struct SuiteRun {
+    pub id: String, pub run_date: NaiveDate, pub started_at_ms: u64, pub finished_at_ms: u64, pub trigger: Trigger,
+    pub results: Vec<AccountResult>, pub stress_run_ids: Vec<String>, pub missing_scenarios: Vec<String>, pub new_breaches: Vec<NewBreach>,
+}
+
+pub struct StressSuite { config: SuiteConfig, runs: VecDeque<SuiteRun>, max_runs: usize }
+
+fn run_at(s: &str) -> Option<NaiveTime> { NaiveTime::parse_from_str(s, "%H:%M").ok() }
+
+impl StressSuite {
+    pub fn new(max_runs: usize) -> Self { Self { config: SuiteConfig::default(), runs: VecDeque::new(), max_runs: max_runs.max(1) } }
+
+    pub fn config(&self) -> SuiteConfig { self.config.clone() }
+    pub fn set_config(&mut self, c: SuiteConfig) -> Result<SuiteConfig, String> {
+        if run_at(&c.run_at).is_none() { return Err("run_at must use HH:MM".into()); }
+        self.config = c.clone();
+        Ok(c)
+    }
+
+    /// True once per UTC day, after `run_at`, unless a scheduled run already happened that day.
+    pub fn due(&self, now: DateTime<Utc>) -> bool {
+        let Some(at) = run_at(&self.config.run_at) else { return false };
+        let today = now.date_naive();
+        self.config.enabled && !self.config.scenarios.is_empty() && now.time() >= at
+            && !self.runs.iter().any(|r| r.run_date == today && matches!(r.trigger, Trigger::Scheduled))
+    }
+
+    /// Stores the run, flagging breaches the previous run did not have, and returns it.
+    pub fn record(&mut self, mut run: SuiteRun) -> SuiteRun {
+        let prev: HashSet<(&str, &str, &str)> = self.runs.back().map(|r| r.results.iter().flat_map(|x| x.breaches.iter().map(move |b| (x.account.as_str(), x.scenario.as_str(), b.as_str()))).collect()).unwrap_or_default();
+        run.new_breaches = run.results.iter().flat_map(|x| x.breaches.iter().map(move |b| (x, b)))
+            .filter(|(x, b)| !prev.contains(&(x.account.as_str(), x.scenario.as_str(), b.as_str())))
+            .map(|(x, b)| NewBreach { account: x.account.clone(), scenario: x.scenario.clone(), breach: b.clone() }).collect();
+        self.runs.push_back(run.clone());
+        while self.runs.len() > self.max_runs { self.runs.pop_front(); }
+        run
+    }
+
+    pub fn runs(&self, limit: usize) -> Vec<SuiteRun> { self.runs.iter().rev().take(limit).cloned().collect() }
+    pub fn get(&self, id: &str) -> Option<SuiteRun> { self.runs.iter().find(|r| r.id == id).cloned() }
+}
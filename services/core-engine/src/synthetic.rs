@@ -0,0 +1,127 @@
+use crate::correlation::Pair;
+use crate::marketdata::Tick;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A year is 252 days of 24 hours, so daily steps move by the daily vol used everywhere else in the engine.
+const MS_PER_YEAR: f64 = 252.0 * 86_400_000.0;
+const MAX_TICKS: usize = 100_000;
+
+/// Geometric Brownian motion with Poisson jumps. `drift` and `annual_vol` are annualised; jumps arrive
+/// `jumps_per_year` on average, each moving the price by `jump_mean_pct` on average with `jump_vol_pct` dispersion
+/// in log terms. The drift is not compensated for jumps. `start_price` defaults to the instrument's last price.
+#[derive(Deserialize)]
+pub struct SyntheticInstrument {
+    pub instrument: String, pub start_price: Option<f64>, pub annual_vol: f64, #[serde(default)] pub drift: f64,
+    #[serde(default)] pub jumps_per_year: f64, #[serde(default)] pub jump_mean_pct: f64, #[serde(default)] pub jump_vol_pct: f64,
+}
+
+/// `correlation` applies to every pair of diffusions unless `pairs` sets that pair; jumps are independent. The same
+/// `seed` gives the same paths. Generated ticks are fed through the normal tick pipeline unless `ingest` is false,
+/// in which case they are returned instead.
+#[derive(Deserialize)]
+pub struct GeneratorSpec {
+    pub instruments: Vec<SyntheticInstrument>, #[serde(default)] pub correlation: f64, #[serde(default)] pub pairs: Vec<Pair>,
+    pub steps: usize, #[serde(default = "step_ms")] pub step_ms: u64, pub seed: Option<u64>, pub spread_bps: Option<f64>, #[serde(default = "ingest")] pub ingest: bool,
+}
+
+fn step_ms() -> u64 { 1000 }
+fn ingest() -> bool { true }
+
+/// `realized_vol` is annualised from the path's own log returns.
+#[derive(Serialize)]
+pub struct PathSummary { pub instrument: String, pub start_price: f64, pub end_price: f64, pub min_price: f64, pub max_price: f64, pub jumps: usize, pub realized_vol: f64 }
+
+#[derive(Serialize)]
+pub struct Generated { pub seed: u64, pub steps: usize, pub step_ms: u64, pub from_ms: u64, pub to_ms: u64, pub paths: Vec<PathSummary>, #[serde(skip)] pub ticks: Vec<Tick> }
+
+/// SplitMix64; good enough for test paths and reproducible from its seed without an extra dependency.
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let z = (self.0 ^ (self.0 >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        let z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Open interval (0, 1), so logs stay finite.
+    pub fn uniform(&mut self) -> f64 { ((self.next() >> 11) as f64 + 0.5) / (1u64 << 53) as f64 }
+    pub fn normal(&mut self) -> f64 { (-2.0 * self.uniform().ln()).sqrt() * (2.0 * std::f64::consts::PI * self.uniform()).cos() }
+
+    fn poisson(&mut self, lambda: f64) -> usize {
+        let (limit, mut k, mut p) = ((-lambda).exp(), 0, 1.0);
+        loop { p *= self.uniform(); if p <= limit { return k; } k += 1; }
+    }
+}
+
+/// Lower-triangular factor of a correlation matrix, or `None` when it is not positive definite.
+pub fn cholesky(m: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = m.len();
+    let mut l = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let s: f64 = (0..j).map(|k| l[i][k] * l[j][k]).sum();
+            if i == j { let d = m[i][i] - s; if d <= 1e-12 { return None; } l[i][i] = d.sqrt(); } else { l[i][j] = (m[i][j] - s) / l[j][j]; }
+        }
+    }
+    Some(l)
+}
+
+fn validate(spec: &GeneratorSpec) -> Result<(), String> {
+    if spec.instruments.is_empty() { return Err("instruments must not be empty".into()); }
+    if spec.steps == 0 || spec.step_ms == 0 { return Err("steps and step_ms must be positive".into()); }
+    if spec.steps.saturating_mul(spec.instruments.len()) > MAX_TICKS { return Err(format!("at most {MAX_TICKS} ticks can be generated at once")); }
+    let mut seen = HashSet::new();
+    for i in &spec.instruments {
+        if !seen.insert(i.instrument.as_str()) { return Err(format!("{} is listed twice", i.instrument)); }
+        let finite = [i.annual_vol, i.drift, i.jumps_per_year, i.jump_mean_pct, i.jump_vol_pct].iter().all(|x| x.is_finite());
+        if !finite || i.annual_vol < 0.0 || i.jumps_per_year < 0.0 || i.jump_vol_pct < 0.0 || i.jump_mean_pct <= -100.0 { return Err(format!("{}: vol, jump rate and jump vol must be non-negative and jumps above -100%", i.instrument)); }
+        if i.start_price.is_some_and(|p| !(p.is_finite() && p > 0.0)) { return Err(format!("{}: start_price must be positive", i.instrument)); }
+    }
+    if !(spec.correlation > -1.0 && spec.correlation < 1.0) { return Err("correlation must be within (-1, 1)".into()); }
+    if let Some(p) = spec.pairs.iter().find(|p| !(p.correlation > -1.0 && p.correlation < 1.0 && seen.contains(p.a.as_str()) && seen.contains(p.b.as_str()) && p.a != p.b)) {
+        return Err(format!("pair {} / {} must join two listed instruments with a correlation within (-1, 1)", p.a, p.b));
+    }
+    if spec.spread_bps.is_some_and(|b| !(b.is_finite() && b >= 0.0)) { return Err("spread_bps must be non-negative".into()); }
+    Ok(())
+}
+
+/// Generates `steps` ticks per instrument, one every `step_ms`, the last stamped `end_ms`. `last` gives the price an
+/// instrument starts from when the spec has none.
+pub fn generate(spec: &GeneratorSpec, seed: u64, end_ms: u64, last: impl Fn(&str) -> Option<f64>) -> Result<Generated, String> {
+    validate(spec)?;
+    let n = spec.instruments.len();
+    let mut corr = vec![vec![spec.correlation; n]; n];
+    for (k, row) in corr.iter_mut().enumerate() { row[k] = 1.0; }
+    let index = |name: &str| spec.instruments.iter().position(|i| i.instrument == name).unwrap_or(0);
+    for p in &spec.pairs { let (a, b) = (index(&p.a), index(&p.b)); corr[a][b] = p.correlation; corr[b][a] = p.correlation; }
+    let l = cholesky(&corr).ok_or("correlation matrix is not positive definite")?;
+    let mut prices = spec.instruments.iter().map(|i| i.start_price.or_else(|| last(&i.instrument)).ok_or_else(|| format!("{} has no start_price and no last price", i.instrument))).collect::<Result<Vec<f64>, String>>()?;
+    let starts = prices.clone();
+    let (dt, mut rng) = (spec.step_ms as f64 / MS_PER_YEAR, Rng(seed));
+    let from_ms = end_ms.saturating_sub((spec.steps as u64 - 1) * spec.step_ms);
+    let (mut ticks, mut jumps, mut lows, mut highs) = (Vec::with_capacity(spec.steps * n), vec![0; n], prices.clone(), prices.clone());
+    let mut returns: Vec<Vec<f64>> = vec![Vec::with_capacity(spec.steps); n];
+    for step in 0..spec.steps {
+        let z: Vec<f64> = (0..n).map(|_| rng.normal()).collect();
+        for (k, i) in spec.instruments.iter().enumerate() {
+            let w: f64 = (0..=k).map(|j| l[k][j] * z[j]).sum();
+            let mut r = (i.drift - i.annual_vol.powi(2) / 2.0) * dt + i.annual_vol * dt.sqrt() * w;
+            for _ in 0..rng.poisson(i.jumps_per_year * dt) { r += (1.0 + i.jump_mean_pct / 100.0).ln() + i.jump_vol_pct / 100.0 * rng.normal(); jumps[k] += 1; }
+            prices[k] *= r.exp();
+            (lows[k], highs[k]) = (lows[k].min(prices[k]), highs[k].max(prices[k]));
+            returns[k].push(r);
+            let half = spec.spread_bps.map(|b| prices[k] * b / 20_000.0);
+            ticks.push(Tick { instrument: i.instrument.clone(), price: prices[k], bid: half.map(|h| prices[k] - h), ask: half.map(|h| prices[k] + h), ts_ms: Some(from_ms + step as u64 * spec.step_ms) });
+        }
+    }
+    let paths = spec.instruments.iter().enumerate().map(|(k, i)| {
+        let r = &returns[k];
+        let mean = r.iter().sum::<f64>() / r.len() as f64;
+        let var = if r.len() > 1 { r.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (r.len() - 1) as f64 } else { 0.0 };
+        PathSummary { instrument: i.instrument.clone(), start_price: starts[k], end_price: prices[k], min_price: lows[k], max_price: highs[k], jumps: jumps[k], realized_vol: (var / dt).sqrt() }
+    }).collect();
+    Ok(Generated { seed, steps: spec.steps, step_ms: spec.step_ms, from_ms, to_ms: end_ms, paths, ticks })
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What sent an order below the account: the strategy, the algo instance and the trader. Each tag is optional.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug)]
+pub struct Tags {
+    #[serde(skip_serializing_if = "Option::is_none")] pub strategy: Option<String>, #[serde(skip_serializing_if = "Option::is_none")] pub algo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub trader: Option<String>,
+}
+
+impl Tags {
+    pub fn is_empty(&self) -> bool { self.strategy.is_none() && self.algo.is_none() && self.trader.is_none() }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if [&self.strategy, &self.algo, &self.trader].iter().any(|t| t.as_deref().is_some_and(|t| t.trim().is_empty())) { return Err("tags must not be blank".into()); }
+        Ok(())
+    }
+
+    /// Every tag `filter` sets must match; an empty filter matches everything.
+    pub fn within(&self, filter: &Tags) -> bool {
+        [(&filter.strategy, &self.strategy), (&filter.algo, &self.algo), (&filter.trader, &self.trader)].iter().all(|(f, t)| f.is_none() || f == t)
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct TagCounts { pub checks: u64, pub blocked: u64 }
+
+/// Checks and blocks per tag value; untagged checks are only in the totals.
+#[derive(Serialize, Clone, Default)]
+pub struct TagStats { pub strategy: BTreeMap<String, TagCounts>, pub algo: BTreeMap<String, TagCounts>, pub trader: BTreeMap<String, TagCounts> }
+
+impl TagStats {
+    pub fn record(&mut self, t: &Tags, approved: bool) {
+        for (book, tag) in [(&mut self.strategy, &t.strategy), (&mut self.algo, &t.algo), (&mut self.trader, &t.trader)] {
+            let Some(tag) = tag else { continue };
+            let c = book.entry(tag.clone()).or_default();
+            c.checks += 1;
+            if !approved { c.blocked += 1; }
+        }
+    }
+}
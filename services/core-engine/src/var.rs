@@ -0,0 +1,157 @@
+use crate::correlation::{Z_95, Z_99};
+use crate::synthetic::{cholesky, Rng};
+use chrono::NaiveDate;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Paths simulated per task, each from its own generator, so a seed gives the same result however many threads run.
+const PATHS_PER_CHUNK: usize = 4096;
+pub const MAX_PATHS: usize = 1_000_000;
+
+/// How VaR is estimated: replaying stored daily returns, from the normal variance of the book, or by simulating
+/// correlated lognormal moves.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Model { Historical, Parametric, MonteCarlo }
+
+/// One-day historical-simulation VaR and expected shortfall: today's holdings revalued under each of the last
+/// `scenarios` daily returns. Losses are positive; a book that gains in every scenario reports zero.
+#[derive(Serialize)]
+pub struct HistoricalVar {
+    pub lookback_days: usize, pub scenarios: usize, pub var_95: f64, pub var_99: f64, pub expected_shortfall_95: f64, pub expected_shortfall_99: f64, pub worst_loss: f64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub from: Option<NaiveDate>, #[serde(skip_serializing_if = "Option::is_none")] pub to: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub missing_history: Vec<String>,
+}
+
+/// Daily returns per instrument on the dates every one of them has a return for, from stored closes.
+fn history_returns(instruments: &[&str], closes: &impl Fn(&str) -> Vec<(NaiveDate, f64)>) -> (Vec<NaiveDate>, BTreeMap<String, Vec<f64>>) {
+    let series: Vec<BTreeMap<NaiveDate, f64>> = instruments.iter().map(|i| closes(i).windows(2).map(|w| (w[1].0, w[1].1 / w[0].1 - 1.0)).collect()).collect();
+    let Some(first) = series.first() else { return (Vec::new(), BTreeMap::new()) };
+    let dates: Vec<NaiveDate> = first.keys().filter(|d| series.iter().all(|s| s.contains_key(d))).copied().collect();
+    let returns = instruments.iter().zip(&series).map(|(i, s)| (i.to_string(), dates.iter().map(|d| s[d]).collect())).collect();
+    (dates, returns)
+}
+
+/// Runs `(instrument, signed quantity, price)` holdings over up to `lookback` days. Returns in `supplied` (oldest
+/// first) take precedence over stored history and are aligned with it on the most recent day; instruments with
+/// neither are left out and listed as missing.
+pub fn historical(positions: &[(String, f64, f64)], supplied: &BTreeMap<String, Vec<f64>>, closes: impl Fn(&str) -> Vec<(NaiveDate, f64)>, lookback: usize) -> HistoricalVar {
+    let held: BTreeSet<&str> = positions.iter().map(|(i, _, _)| i.as_str()).collect();
+    let stored: Vec<&str> = held.iter().copied().filter(|i| !supplied.contains_key(*i) && closes(i).len() >= 2).collect();
+    let (dates, mut returns) = history_returns(&stored, &closes);
+    let n = held.iter().filter_map(|i| supplied.get(*i).map(Vec::len)).chain((!stored.is_empty()).then_some(dates.len())).min().unwrap_or(0).min(lookback);
+    for i in &held { if let Some(r) = supplied.get(*i) { returns.insert(i.to_string(), r.clone()); } }
+    let missing = held.iter().filter(|i| !returns.contains_key(**i)).map(|i| i.to_string()).collect();
+    let pnl: Vec<f64> = (0..n).map(|k| positions.iter().filter_map(|(i, q, p)| { let r = returns.get(i)?; Some(q * p * r[r.len() - n + k]) }).sum()).collect();
+    let window = (!stored.is_empty() && n > 0).then(|| (dates[dates.len() - n], dates[dates.len() - 1]));
+    summarize(lookback, pnl, window, missing)
+}
+
+/// P&L of the holdings on each of the last `lookback` dates every held instrument has a stored return for, and the
+/// instruments left out for want of history. Series from separate books can be summed date by date.
+pub fn dated_pnl(positions: &[(String, f64, f64)], closes: impl Fn(&str) -> Vec<(NaiveDate, f64)>, lookback: usize) -> (BTreeMap<NaiveDate, f64>, Vec<String>) {
+    let held: BTreeSet<&str> = positions.iter().map(|(i, _, _)| i.as_str()).collect();
+    let stored: Vec<&str> = held.iter().copied().filter(|i| closes(i).len() >= 2).collect();
+    let (dates, returns) = history_returns(&stored, &closes);
+    let skip = dates.len().saturating_sub(lookback);
+    let pnl = dates.iter().enumerate().skip(skip).map(|(k, d)| (*d, positions.iter().filter_map(|(i, q, p)| Some(q * p * returns.get(i)?[k])).sum())).collect();
+    (pnl, held.into_iter().filter(|i| !returns.contains_key(*i)).map(String::from).collect())
+}
+
+/// VaR and expected shortfall of a P&L series, reported as `historical` reports them.
+pub fn summarize(lookback: usize, mut pnl: Vec<f64>, window: Option<(NaiveDate, NaiveDate)>, missing: Vec<String>) -> HistoricalVar {
+    let n = pnl.len();
+    pnl.sort_by(f64::total_cmp);
+    let tail = |conf: f64| {
+        let k = ((n as f64 * (1.0 - conf)).ceil() as usize).clamp(1, n.max(1));
+        if n == 0 { return (0.0, 0.0); }
+        ((-pnl[k - 1]).max(0.0), (-pnl[..k].iter().sum::<f64>() / k as f64).max(0.0))
+    };
+    let ((var_95, expected_shortfall_95), (var_99, expected_shortfall_99)) = (tail(0.95), tail(0.99));
+    HistoricalVar {
+        lookback_days: lookback, scenarios: n, var_95, var_99, expected_shortfall_95, expected_shortfall_99, worst_loss: pnl.first().map_or(0.0, |p| (-p).max(0.0)),
+        from: window.map(|w| w.0), to: window.map(|w| w.1), missing_history: missing,
+    }
+}
+
+/// Correlations between the instruments listed, row by row in the same order. Pairs it does not cover keep the
+/// stored correlations.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CorrelationMatrix { pub instruments: Vec<String>, pub matrix: Vec<Vec<f64>> }
+
+impl CorrelationMatrix {
+    pub fn validate(&self) -> Result<(), String> {
+        let n = self.instruments.len();
+        if self.instruments.iter().collect::<BTreeSet<_>>().len() != n { return Err("correlation instruments must be distinct".into()); }
+        if self.matrix.len() != n || self.matrix.iter().any(|r| r.len() != n) { return Err(format!("correlation matrix must be {n}x{n}")); }
+        for (i, r) in self.matrix.iter().enumerate() {
+            if r[i] != 1.0 { return Err(format!("correlation of {} with itself must be 1", self.instruments[i])); }
+            for (j, c) in r.iter().enumerate() {
+                if !(-1.0..=1.0).contains(c) { return Err(format!("correlation between {} and {} must be in [-1, 1]", self.instruments[i], self.instruments[j])); }
+                if (c - self.matrix[j][i]).abs() > 1e-9 { return Err(format!("correlation matrix is not symmetric at {}, {}", self.instruments[i], self.instruments[j])); }
+            }
+        }
+        Ok(())
+    }
+
+    /// The full matrix for `instruments`, taking pairs from `self` where it has them and from `stored` otherwise.
+    pub fn resolve(this: Option<&Self>, instruments: &[String], stored: impl Fn(&str, &str) -> f64) -> Vec<Vec<f64>> {
+        let at = |i: &str| this.and_then(|m| m.instruments.iter().position(|x| x == i));
+        instruments.iter().map(|a| instruments.iter().map(|b| match (this, at(a), at(b)) {
+            (Some(m), Some(i), Some(j)) => m.matrix[i][j],
+            _ => stored(a, b),
+        }).collect()).collect()
+    }
+}
+
+/// One-day VaR and expected shortfall from a parametric or simulated model. `paths` and `seed` are set for Monte
+/// Carlo runs, which can be repeated exactly by sending the seed back.
+#[derive(Serialize)]
+pub struct ModelVar {
+    pub instruments: usize, pub portfolio_vol: f64, pub var_95: f64, pub var_99: f64, pub expected_shortfall_95: f64, pub expected_shortfall_99: f64,
+    #[serde(skip_serializing_if = "Option::is_none")] pub worst_loss: Option<f64>, #[serde(skip_serializing_if = "Option::is_none")] pub paths: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub seed: Option<u64>,
+}
+
+fn portfolio_vol(w: &[f64], corr: &[Vec<f64>]) -> f64 {
+    w.iter().enumerate().map(|(i, a)| w.iter().enumerate().map(|(j, b)| corr[i][j] * a * b).sum::<f64>()).sum::<f64>().max(0.0).sqrt()
+}
+
+/// Variance-covariance VaR of `(instrument, net notional)` exposures with daily `vols`: normal P&L with standard
+/// deviation from the correlation-weighted exposures.
+pub fn parametric(exposures: &[(String, f64)], vols: &[f64], corr: &[Vec<f64>]) -> ModelVar {
+    let w: Vec<f64> = exposures.iter().zip(vols).map(|((_, n), v)| n * v).collect();
+    let sigma = portfolio_vol(&w, corr);
+    let density = |z: f64| (-z * z / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt();
+    ModelVar {
+        instruments: exposures.len(), portfolio_vol: sigma, var_95: Z_95 * sigma, var_99: Z_99 * sigma,
+        expected_shortfall_95: sigma * density(Z_95) / 0.05, expected_shortfall_99: sigma * density(Z_99) / 0.01, worst_loss: None, paths: None, seed: None,
+    }
+}
+
+/// Monte Carlo VaR over `paths` one-day scenarios: each instrument moves lognormally with its daily vol, the moves
+/// correlated through the Cholesky factor of `corr`, and the book is revalued in full on every path. Paths are
+/// generated in parallel.
+pub fn monte_carlo(exposures: &[(String, f64)], vols: &[f64], corr: &[Vec<f64>], paths: usize, seed: u64) -> Result<ModelVar, String> {
+    let l = cholesky(corr).ok_or("correlation matrix is not positive definite")?;
+    let n = exposures.len();
+    let pnl: Vec<f64> = (0..paths.div_ceil(PATHS_PER_CHUNK)).into_par_iter().flat_map_iter(|c| {
+        let mut rng = Rng(seed ^ (c as u64).wrapping_mul(0xD1B5_4A32_D192_ED03));
+        let (l, mut e, mut out) = (&l, vec![0.0; n], Vec::with_capacity(PATHS_PER_CHUNK));
+        for _ in c * PATHS_PER_CHUNK..((c + 1) * PATHS_PER_CHUNK).min(paths) {
+            e.iter_mut().for_each(|x| *x = rng.normal());
+            out.push((0..n).map(|i| {
+                let z: f64 = (0..=i).map(|k| l[i][k] * e[k]).sum();
+                exposures[i].1 * ((vols[i] * z - vols[i] * vols[i] / 2.0).exp() - 1.0)
+            }).sum::<f64>());
+        }
+        out
+    }).collect();
+    let w: Vec<f64> = exposures.iter().zip(vols).map(|((_, x), v)| x * v).collect();
+    let v = summarize(0, pnl, None, Vec::new());
+    Ok(ModelVar {
+        instruments: n, portfolio_vol: portfolio_vol(&w, corr), var_95: v.var_95, var_99: v.var_99, expected_shortfall_95: v.expected_shortfall_95,
+        expected_shortfall_99: v.expected_shortfall_99, worst_loss: Some(v.worst_loss), paths: Some(paths), seed: Some(seed),
+    })
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+const HOUR_MS: u64 = 3_600_000;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct WindowLimit { pub max_orders: u64, pub max_notional: f64 }
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct VelocityLimits { pub per_second: WindowLimit, pub per_minute: WindowLimit, pub per_hour: WindowLimit }
+
+impl Default for VelocityLimits {
+    fn default() -> Self {
+        Self {
+            per_second: WindowLimit { max_orders: 50, max_notional: 5_000_000.0 },
+            per_minute: WindowLimit { max_orders: 600, max_notional: 50_000_000.0 },
+            per_hour: WindowLimit { max_orders: 10_000, max_notional: 500_000_000.0 },
+        }
+    }
+}
+
+impl VelocityLimits {
+    /// Every window needs room for at least one order and a positive notional.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, _, l) in self.windows() {
+            if l.max_orders == 0 { return Err(format!("max_orders for {name} must be positive")); }
+            if !(l.max_notional.is_finite() && l.max_notional > 0.0) { return Err(format!("max_notional for {name} must be positive")); }
+        }
+        Ok(())
+    }
+
+    fn windows(&self) -> [(&'static str, u64, WindowLimit); 3] {
+        [("1s", 1_000, self.per_second), ("1m", 60_000, self.per_minute), ("1h", HOUR_MS, self.per_hour)]
+    }
+}
+
+#[derive(Serialize)]
+pub struct WindowState { pub window: String, pub orders: u64, pub notional: f64, pub max_orders: u64, pub max_notional: f64 }
+
+#[derive(Default)]
+pub struct VelocityBook { limits: HashMap<String, VelocityLimits>, orders: HashMap<String, VecDeque<(u64, f64)>> }
+
+impl VelocityBook {
+    pub fn limits(&self, account: &str) -> VelocityLimits { self.limits.get(account).copied().unwrap_or_default() }
+    pub fn set_limits(&mut self, account: &str, limits: VelocityLimits) { self.limits.insert(account.into(), limits); }
+
+    /// Returns one reason per window the order would push over its count or notional cap.
+    pub fn evaluate(&mut self, account: &str, notional: f64, now_ms: u64) -> Vec<String> {
+        let limits = self.limits(account);
+        self.windows(account, now_ms).into_iter().zip(limits.windows()).flat_map(|(w, (name, _, _))| {
+            let mut r = Vec::new();
+            if w.orders + 1 > w.max_orders { r.push(format!("Velocity limit exceeded: {} orders in {name}", w.orders + 1)); }
+            if w.notional + notional.abs() > w.max_notional { r.push(format!("Velocity limit exceeded: notional {:.2} in {name}", w.notional + notional.abs())); }
+            r
+        }).collect()
+    }
+
+    pub fn record(&mut self, account: &str, notional: f64, now_ms: u64) {
+        self.orders.entry(account.into()).or_default().push_back((now_ms, notional.abs()));
+    }
+
+    pub fn windows(&mut self, account: &str, now_ms: u64) -> Vec<WindowState> {
+        let limits = self.limits(account);
+        let q = self.orders.entry(account.into()).or_default();
+        while q.front().is_some_and(|(t, _)| now_ms.saturating_sub(*t) >= HOUR_MS) { q.pop_front(); }
+        limits.windows().into_iter().map(|(name, len, l)| {
+            let (orders, notional) = q.iter().rev().take_while(|(t, _)| now_ms.saturating_sub(*t) < len).fold((0, 0.0), |(c, n), (_, x)| (c + 1, n + x));
+            WindowState { window: name.into(), orders, notional, max_orders: l.max_orders, max_notional: l.max_notional }
+        }).collect()
+    }
+}
@@ -0,0 +1,57 @@
+use crate::correlation::Pair;
+use crate::liquidity::LiquidityParams;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Snapshot sections in the order they are loaded: accounts and limits before the positions checked against them,
+/// and prices last so quotes arrive for instruments the engine already knows about.
+pub const SECTIONS: [&str; 5] = ["accounts", "limits", "positions", "reference", "prices"];
+
+/// Instrument reference data: liquidity parameters and pairwise correlations.
+#[derive(Deserialize, Serialize, Default)]
+pub struct Reference { pub liquidity: HashMap<String, LiquidityParams>, pub correlations: Vec<Pair> }
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum StageStatus { Pending, Loading, Loaded, Failed }
+
+/// `items` counts what the section put in memory; `saved_at_ms` is when the snapshot it came from was taken.
+#[derive(Serialize, Clone)]
+pub struct Stage {
+    pub section: &'static str, pub status: StageStatus, pub items: usize, #[serde(skip_serializing_if = "Option::is_none")] pub saved_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub elapsed_ms: Option<u64>, #[serde(skip_serializing_if = "Option::is_none")] pub error: Option<String>,
+}
+
+/// Progress priming memory from the last snapshot. An engine without a store has nothing to load and is ready at
+/// once; one with a store is ready when every section has loaded, and stays unready if any fails.
+#[derive(Serialize, Clone)]
+pub struct Warmup { pub ready: bool, pub progress_pct: f64, pub started_at_ms: u64, #[serde(skip_serializing_if = "Option::is_none")] pub finished_at_ms: Option<u64>, pub stages: Vec<Stage> }
+
+impl Warmup {
+    pub fn done(now_ms: u64) -> Self { Self { ready: true, progress_pct: 100.0, started_at_ms: now_ms, finished_at_ms: Some(now_ms), stages: Vec::new() } }
+
+    pub fn pending(now_ms: u64) -> Self {
+        let stages = SECTIONS.iter().map(|s| Stage { section: s, status: StageStatus::Pending, items: 0, saved_at_ms: None, elapsed_ms: None, error: None }).collect();
+        Self { ready: false, progress_pct: 0.0, started_at_ms: now_ms, finished_at_ms: None, stages }
+    }
+
+    pub fn start(&mut self, section: &str) { if let Some(s) = self.stage(section) { s.status = StageStatus::Loading; } }
+
+    /// Records how a section went. `Ok` carries the items loaded and the snapshot time, if there was a snapshot.
+    pub fn finish(&mut self, section: &str, outcome: Result<(usize, Option<u64>), String>, elapsed_ms: u64, now_ms: u64) {
+        let Some(s) = self.stage(section) else { return };
+        s.elapsed_ms = Some(elapsed_ms);
+        match outcome {
+            Ok((items, saved_at_ms)) => (s.status, s.items, s.saved_at_ms) = (StageStatus::Loaded, items, saved_at_ms),
+            Err(e) => (s.status, s.error) = (StageStatus::Failed, Some(e)),
+        }
+        let loaded = self.stages.iter().filter(|s| s.status == StageStatus::Loaded).count();
+        self.progress_pct = loaded as f64 / self.stages.len() as f64 * 100.0;
+        if self.stages.iter().all(|s| matches!(s.status, StageStatus::Loaded | StageStatus::Failed)) {
+            self.finished_at_ms = Some(now_ms);
+            self.ready = loaded == self.stages.len();
+        }
+    }
+
+    fn stage(&mut self, section: &str) -> Option<&mut Stage> { self.stages.iter_mut().find(|s| s.section == section) }
+}
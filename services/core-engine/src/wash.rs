@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// `Warn` flags a self-match instead of refusing the order.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode { #[default] Reject, Warn }
+
+/// Whose orders must not trade with each other: one account's, one trader's across every account they send for, or
+/// those of every account in a related-party group. Trader scope falls back to the account for orders without a
+/// trader tag.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope { #[default] Account, Trader, Related }
+
+/// An order self-matches when an approved opposite-side order from the same owner in the same instrument, sent within
+/// the last `window_secs`, would cross it: a buy at or above a recent sell, or a sell at or below a recent buy.
+/// Unpriced orders cross anything.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct WashConfig { pub enabled: bool, pub window_secs: u64, #[serde(default)] pub mode: Mode, #[serde(default)] pub scope: Scope }
+
+impl Default for WashConfig {
+    fn default() -> Self { Self { enabled: false, window_secs: 60, mode: Mode::Reject, scope: Scope::Account } }
+}
+
+impl WashConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_secs == 0 { return Err("window_secs must be positive".into()); }
+        Ok(())
+    }
+}
+
+/// Checks refused or flagged as self-matches since the engine started.
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct WashStats { pub blocked: u64, pub flagged: u64 }
+
+/// An order leg as the check sees it: instrument, whether it buys, and its price.
+pub type Leg<'a> = (&'a str, bool, f64);
+
+struct Sent { buy: bool, price: f64, account: String, at_ms: u64 }
+
+/// Recently approved orders by owner and instrument, oldest first.
+#[derive(Default)]
+pub struct WashTrades { pub config: WashConfig, orders: HashMap<(String, String), VecDeque<Sent>>, pub stats: WashStats }
+
+impl WashTrades {
+    /// `group` names the account's related-party group, the same for every account in it; none for an account
+    /// linked to no other.
+    fn owner(&self, account: &str, trader: Option<&str>, group: Option<&str>) -> String {
+        match (self.config.scope, trader, group) {
+            (Scope::Trader, Some(t), _) => format!("trader {t}"),
+            (Scope::Related, _, Some(g)) => format!("related group {g}"),
+            _ => format!("account {account}"),
+        }
+    }
+
+    /// Reasons (`Reject`) or flags (`Warn`), one per leg that would self-match.
+    pub fn evaluate(&mut self, account: &str, trader: Option<&str>, group: Option<&str>, legs: &[Leg], now_ms: u64) -> (Vec<String>, Vec<String>) {
+        let (mut reasons, mut flags) = (Vec::new(), Vec::new());
+        if !self.config.enabled { return (reasons, flags); }
+        let (owner, window_ms) = (self.owner(account, trader, group), self.config.window_secs * 1000);
+        for (instrument, buy, price) in legs {
+            let Some(q) = self.orders.get_mut(&(owner.clone(), instrument.to_string())) else { continue };
+            while q.front().is_some_and(|o| now_ms.saturating_sub(o.at_ms) >= window_ms) { q.pop_front(); }
+            let crosses = |o: &&Sent| o.buy != *buy && (*price <= 0.0 || o.price <= 0.0 || if *buy { *price >= o.price } else { *price <= o.price });
+            let Some(o) = q.iter().rev().find(crosses) else { continue };
+            let (side, other) = if *buy { ("buy", "sell") } else { ("sell", "buy") };
+            let from = if o.account == account { String::new() } else { format!(" for {}", o.account) };
+            let what = format!("{side} {instrument} at {price:.4} would match {owner}'s {other} at {:.4}{from} from {}s ago", o.price, now_ms.saturating_sub(o.at_ms) / 1000);
+            match self.config.mode {
+                Mode::Reject => reasons.push(format!("Wash trade: {what}")),
+                Mode::Warn => flags.push(format!("Wash trade warning: {what}")),
+            }
+        }
+        if !reasons.is_empty() { self.stats.blocked += 1; } else if !flags.is_empty() { self.stats.flagged += 1; }
+        (reasons, flags)
+    }
+
+    pub fn record(&mut self, account: &str, trader: Option<&str>, group: Option<&str>, legs: &[Leg], now_ms: u64) {
+        if !self.config.enabled { return; }
+        let owner = self.owner(account, trader, group);
+        for (instrument, buy, price) in legs {
+            self.orders.entry((owner.clone(), instrument.to_string())).or_default().push_back(Sent { buy: *buy, price: *price, account: account.into(), at_ms: now_ms });
+        }
+    }
+}